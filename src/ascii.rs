@@ -0,0 +1,153 @@
+use super::{
+    error::{Error, Result},
+    instrument::Instrument,
+};
+
+/// The value SCPI sends for positive infinity, its negation for negative infinity.
+const SCPI_INFINITY: f64 = 9.9e37;
+/// The value SCPI sends for not a number.
+const SCPI_NAN: f64 = 9.91e37;
+
+/// How a list of ASCII values is laid out in a response.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AsciiFormat {
+    /// Separator between values, `,` by default.
+    pub separator: char,
+    /// Whether the response starts with a header to drop, such as `:CURV` in `:CURV 1,2,3`,
+    /// as sent with `SYSTem:HEADer ON`.
+    pub strip_header: bool,
+}
+
+impl Default for AsciiFormat {
+    fn default() -> Self {
+        Self {
+            separator: ',',
+            strip_header: false,
+        }
+    }
+}
+
+/// Values an ASCII response can be parsed into.
+pub trait AsciiValue: Sized {
+    /// Parses a single value, surrounding whitespace already trimmed.
+    fn parse_ascii(text: &str) -> Option<Self>;
+}
+
+macro_rules! ascii_integers {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl AsciiValue for $type {
+                fn parse_ascii(text: &str) -> Option<Self> {
+                    // IEEE 488.2 non-decimal numerics, #H1F, #Q17 and #B11111
+                    let radix = match text.get(..2).map(str::to_ascii_uppercase).as_deref() {
+                        Some("#H") => 16,
+                        Some("#Q") => 8,
+                        Some("#B") => 2,
+                        _ => return text.parse().ok(),
+                    };
+                    <$type>::from_str_radix(&text[2..], radix).ok()
+                }
+            }
+        )*
+    };
+}
+
+ascii_integers!(u8, i8, u16, i16, u32, i32, u64, i64, usize, isize);
+
+macro_rules! ascii_floats {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl AsciiValue for $type {
+                fn parse_ascii(text: &str) -> Option<Self> {
+                    match text.to_ascii_uppercase().as_str() {
+                        "INF" | "+INF" => return Some(<$type>::INFINITY),
+                        "NINF" | "-INF" => return Some(<$type>::NEG_INFINITY),
+                        "NAN" => return Some(<$type>::NAN),
+                        _ => (),
+                    }
+                    let value: f64 = text.parse().ok()?;
+                    let value = if value == SCPI_INFINITY {
+                        f64::INFINITY
+                    } else if value == -SCPI_INFINITY {
+                        f64::NEG_INFINITY
+                    } else if value == SCPI_NAN {
+                        f64::NAN
+                    } else {
+                        value
+                    };
+                    Some(value as $type)
+                }
+            }
+        )*
+    };
+}
+
+ascii_floats!(f32, f64);
+
+impl AsciiValue for bool {
+    fn parse_ascii(text: &str) -> Option<Self> {
+        match text.to_ascii_uppercase().as_str() {
+            "1" | "ON" | "TRUE" => Some(true),
+            "0" | "OFF" | "FALSE" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the values of `response`, an empty response holds none.
+pub fn parse_ascii_values<T: AsciiValue>(response: &str, format: &AsciiFormat) -> Result<Vec<T>> {
+    let mut values = response.trim();
+    if format.strip_header
+        && let Some((_, rest)) = values.split_once(char::is_whitespace)
+    {
+        values = rest.trim_start();
+    }
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    values
+        .split(format.separator)
+        .map(str::trim)
+        .map(|value| {
+            T::parse_ascii(value)
+                .ok_or_else(|| Error::InvalidValue(value.to_owned(), std::any::type_name::<T>()))
+        })
+        .collect()
+}
+
+impl Instrument {
+    /// Reads a response made of `,` separated values, such as `+1.234E+00,+1.235E+00`.
+    ///
+    /// Floats also accept the SCPI special values, `9.9E37` is infinity and `9.91E37` not a
+    /// number, integers the `#H`, `#Q` and `#B` prefixed ones and booleans `ON` and `OFF`.
+    pub fn read_ascii_values<T: AsciiValue>(&self) -> Result<Vec<T>> {
+        self.read_ascii_values_with(&AsciiFormat::default())
+    }
+
+    pub fn read_ascii_values_with<T: AsciiValue>(&self, format: &AsciiFormat) -> Result<Vec<T>> {
+        parse_ascii_values(&self.read()?, format)
+    }
+
+    /// Writes `buf` and reads the `,` separated values of the response.
+    ///
+    /// ```no_run
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// let readings = instrument.query_ascii_values::<f64>("READ?\n")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_ascii_values<T: AsciiValue>(&mut self, buf: impl AsRef<[u8]>) -> Result<Vec<T>> {
+        self.query_ascii_values_with(buf, &AsciiFormat::default())
+    }
+
+    pub fn query_ascii_values_with<T: AsciiValue>(
+        &mut self,
+        buf: impl AsRef<[u8]>,
+        format: &AsciiFormat,
+    ) -> Result<Vec<T>> {
+        self.query_with(buf.as_ref(), |instrument| {
+            instrument.read_ascii_values_with(format)
+        })
+    }
+}
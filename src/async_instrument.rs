@@ -0,0 +1,263 @@
+use super::{
+    block::{BlockLength, BlockValue, ByteOrder},
+    error::{Error, Result, VisaError},
+    event::{Event, EventType},
+    instrument::Instrument,
+};
+use std::{
+    future::Future,
+    panic,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, ready},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    task::{JoinError, JoinHandle, spawn_blocking},
+};
+
+/// Longest a service request is waited for before checking whether the wait was dropped.
+const SERVICE_REQUEST_SLICE: Duration = Duration::from_millis(100);
+
+/// An [`Instrument`] for tokio, whose operations run on the blocking pool.
+///
+/// Dropping the future of an operation doesn't cancel it. The operation runs to its end on the
+/// blocking pool, holding the instrument and bounded by its timeout, and its result is discarded.
+/// Nothing is aborted with `viTerminate`, so the session isn't left in the middle of a message,
+/// but a dropped query still waits for its response.
+///
+/// ```no_run
+/// # async fn example(instrument: visa::Instrument) -> visa::Result<()> {
+/// let instrument = visa::AsyncInstrument::from(instrument);
+/// let voltage = instrument.query("MEAS:VOLT?\n").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncInstrument {
+    inner: Arc<Mutex<Instrument>>,
+    /// Bytes read for [`AsyncRead`] that didn't fit in the caller's buffer.
+    pending: Vec<u8>,
+    reading: Option<JoinHandle<std::io::Result<Vec<u8>>>>,
+    /// The write [`AsyncWrite`] started last, its error is reported by the next write or flush.
+    writing: Option<JoinHandle<std::io::Result<()>>>,
+    flushing: Option<JoinHandle<std::io::Result<()>>>,
+}
+
+impl From<Instrument> for AsyncInstrument {
+    fn from(value: Instrument) -> Self {
+        Self::from(Arc::new(Mutex::new(value)))
+    }
+}
+
+/// Shares an instrument opened by a [`ResourceManager`](crate::ResourceManager).
+impl From<Arc<Mutex<Instrument>>> for AsyncInstrument {
+    fn from(value: Arc<Mutex<Instrument>>) -> Self {
+        Self {
+            inner: value,
+            pending: Vec::new(),
+            reading: None,
+            writing: None,
+            flushing: None,
+        }
+    }
+}
+
+impl AsyncInstrument {
+    pub fn instrument(&self) -> &Arc<Mutex<Instrument>> {
+        &self.inner
+    }
+
+    /// Runs `operation` on the blocking pool, for anything without an async counterpart.
+    pub async fn run_blocking<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&mut Instrument) -> T + Send + 'static,
+    ) -> T {
+        let instrument = self.inner.clone();
+        join(spawn_blocking(move || operation(&mut instrument.lock().unwrap())).await)
+    }
+
+    pub async fn write(&self, buf: impl Into<Vec<u8>>) -> Result<()> {
+        let buf = buf.into();
+        self.run_blocking(move |instrument| instrument.write(buf))
+            .await
+    }
+
+    pub async fn read(&self) -> Result<String> {
+        self.run_blocking(|instrument| Instrument::read(instrument))
+            .await
+    }
+
+    /// Writes `buf` and reads the response, without any other operation in between.
+    pub async fn query(&self, buf: impl Into<Vec<u8>>) -> Result<String> {
+        let buf = buf.into();
+        self.run_blocking(move |instrument| instrument.query(buf))
+            .await
+    }
+
+    pub async fn read_bytes(&self) -> Result<Vec<u8>> {
+        self.run_blocking(|instrument| instrument.read_bytes())
+            .await
+    }
+
+    pub async fn query_bytes(&self, buf: impl Into<Vec<u8>>) -> Result<Vec<u8>> {
+        let buf = buf.into();
+        self.run_blocking(move |instrument| instrument.query_bytes(buf))
+            .await
+    }
+
+    /// Reads an IEEE 488.2 arbitrary block, see [`Instrument::read_binary_block`].
+    pub async fn read_binary_block<T: BlockValue + Send + 'static>(
+        &self,
+        order: ByteOrder,
+    ) -> Result<Vec<T>> {
+        self.run_blocking(move |instrument| instrument.read_binary_block(order))
+            .await
+    }
+
+    pub async fn write_binary_block<T: BlockValue + Send + 'static>(
+        &self,
+        command: impl Into<Vec<u8>>,
+        values: impl Into<Vec<T>>,
+        order: ByteOrder,
+        length: BlockLength,
+    ) -> Result<()> {
+        let command = command.into();
+        let values = values.into();
+        self.run_blocking(move |instrument| {
+            instrument.write_binary_block(command, &values, order, length)
+        })
+        .await
+    }
+
+    /// Waits for a service request, which must be enabled for the queue. The wait doesn't hold
+    /// the instrument, so other operations can run meanwhile, and ends shortly after its future
+    /// is dropped.
+    pub async fn wait_for_service_request(&self, timeout: Option<Duration>) -> Result<Event> {
+        let backend = self
+            .run_blocking(|instrument| instrument.shared_backend())
+            .await;
+        let cancel = DropFlag::default();
+        let cancelled = cancel.0.clone();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let task = spawn_blocking(move || {
+            loop {
+                let slice = match deadline {
+                    Some(deadline) => deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(SERVICE_REQUEST_SLICE),
+                    None => SERVICE_REQUEST_SLICE,
+                };
+                match backend.wait_on_event(EventType::ServiceRequest, Some(slice)) {
+                    Err(Error::Visa(VisaError::Timeout))
+                        if !cancelled.load(Ordering::Relaxed)
+                            && deadline.is_none_or(|deadline| Instant::now() < deadline) =>
+                    {
+                        continue;
+                    }
+                    result => return result,
+                }
+            }
+        });
+        join(task.await)
+    }
+
+    /// Waits for the write started last, returning its result.
+    fn poll_written(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let Some(task) = &mut self.writing else {
+            return Poll::Ready(Ok(()));
+        };
+        let result = join(ready!(Pin::new(task).poll(cx)));
+        self.writing = None;
+        Poll::Ready(result)
+    }
+}
+
+/// Set when dropped, with the future holding it.
+#[derive(Debug, Default)]
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns the result of a blocking task, resuming its panic if it had one.
+fn join<T>(result: std::result::Result<T, JoinError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => panic::resume_unwind(error.into_panic()),
+    }
+}
+
+impl AsyncRead for AsyncInstrument {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        if this.pending.is_empty() {
+            let capacity = buf.remaining();
+            let task = this.reading.get_or_insert_with(|| {
+                let instrument = this.inner.clone();
+                spawn_blocking(move || {
+                    let mut data = vec![0; capacity];
+                    let count = std::io::Read::read(&mut &*instrument.lock().unwrap(), &mut data)?;
+                    data.truncate(count);
+                    Ok(data)
+                })
+            });
+            let data = join(ready!(Pin::new(task).poll(cx)));
+            this.reading = None;
+            this.pending = data?;
+        }
+
+        let count = this.pending.len().min(buf.remaining());
+        buf.put_slice(&this.pending[..count]);
+        this.pending.drain(..count);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for AsyncInstrument {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        // Like `tokio::fs::File`, `buf` is copied and taken at once, once the previous write is done
+        let this = self.get_mut();
+        ready!(this.poll_written(cx))?;
+        let instrument = this.inner.clone();
+        let data = buf.to_vec();
+        this.writing = Some(spawn_blocking(move || {
+            std::io::Write::write_all(&mut &*instrument.lock().unwrap(), &data)
+        }));
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_written(cx))?;
+        let task = this.flushing.get_or_insert_with(|| {
+            let instrument = this.inner.clone();
+            spawn_blocking(move || std::io::Write::flush(&mut &*instrument.lock().unwrap()))
+        });
+        let result = join(ready!(Pin::new(task).poll(cx)));
+        this.flushing = None;
+        Poll::Ready(result)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,411 @@
+//! Typed VISA attributes, read with [`Session::get`](crate::Session::get) and written with
+//! [`Session::set`](crate::Session::set).
+//!
+//! Every attribute is a newtype over its value, attributes implementing [`WritableAttribute`]
+//! can be set, the others are read only.
+//!
+//! ```no_run
+//! use visa::attribute::{TermChar, Timeout};
+//! # fn example(instrument: &visa::Instrument) -> visa::Result<()> {
+//! let Timeout(timeout) = instrument.get::<Timeout>()?;
+//! instrument.set(TermChar(b'\n'))?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::{
+    bindings::*,
+    error::{Error, Result, VisaError},
+    resource_manager::AccessMode,
+};
+use std::{fmt, time::Duration};
+
+/// Type of an attribute value, which decides the size of the buffer VISA writes it into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    I16,
+    I32,
+    Bool,
+    String,
+}
+
+impl fmt::Display for AttributeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::I16 => "i16",
+            Self::I32 => "i32",
+            Self::Bool => "bool",
+            Self::String => "string",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Value of an attribute, as read from or written to a backend.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AttributeValue {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I16(i16),
+    I32(i32),
+    Bool(bool),
+    String(String),
+}
+
+impl AttributeValue {
+    pub fn kind(&self) -> AttributeKind {
+        match self {
+            Self::U8(_) => AttributeKind::U8,
+            Self::U16(_) => AttributeKind::U16,
+            Self::U32(_) => AttributeKind::U32,
+            Self::U64(_) => AttributeKind::U64,
+            Self::I16(_) => AttributeKind::I16,
+            Self::I32(_) => AttributeKind::I32,
+            Self::Bool(_) => AttributeKind::Bool,
+            Self::String(_) => AttributeKind::String,
+        }
+    }
+}
+
+/// An attribute with a known identifier and type.
+pub trait Attribute: Sized {
+    /// The `VI_ATTR_*` identifier.
+    const ID: u32;
+    const KIND: AttributeKind;
+
+    /// Converts a value read from a backend, failing if it isn't of [`KIND`](Self::KIND).
+    fn from_value(value: AttributeValue) -> Result<Self>;
+}
+
+/// An attribute that can be set.
+pub trait WritableAttribute: Attribute {
+    fn to_value(&self) -> AttributeValue;
+}
+
+/// Plain types attribute values convert from and to.
+trait Value: Sized {
+    const KIND: AttributeKind;
+
+    /// Converts `value`, giving it back if it's of another type.
+    fn from_value(value: AttributeValue) -> std::result::Result<Self, AttributeValue>;
+
+    fn to_value(&self) -> AttributeValue;
+}
+
+macro_rules! values {
+    ($($type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl Value for $type {
+                const KIND: AttributeKind = AttributeKind::$variant;
+
+                fn from_value(value: AttributeValue) -> std::result::Result<Self, AttributeValue> {
+                    match value {
+                        AttributeValue::$variant(value) => Ok(value),
+                        value => Err(value),
+                    }
+                }
+
+                fn to_value(&self) -> AttributeValue {
+                    AttributeValue::$variant(self.to_owned())
+                }
+            }
+        )*
+    };
+}
+
+values! {
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    i16 => I16,
+    i32 => I32,
+    bool => Bool,
+    String => String,
+}
+
+/// Error for the attribute `id`, of type `expected`, used as a value of type `actual`.
+fn mismatch(id: u32, expected: AttributeKind, actual: AttributeKind) -> Error {
+    Error::AttributeKindMismatch(id, expected, actual)
+}
+
+/// Checks `kind` is the type of the attribute `id`, attributes without a typed counterpart
+/// can be read as any type.
+pub(crate) fn check_kind(id: u32, kind: AttributeKind) -> Result<()> {
+    match kind_of(id) {
+        Some(expected) if expected != kind => Err(mismatch(id, expected, kind)),
+        _ => Ok(()),
+    }
+}
+
+/// Checks `value` can be written to the attribute `id`, typed attributes being read only or
+/// taking values of their own type.
+pub(crate) fn check_writable(id: u32, value: &AttributeValue) -> Result<()> {
+    if is_read_only(id) {
+        return Err(Error::Visa(VisaError::AttributeReadOnly));
+    }
+    check_kind(id, value.kind())
+}
+
+macro_rules! attributes {
+    ($($(#[$meta:meta])* $name:ident($type:ty) = $id:ident, $access:ident;)*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+            pub struct $name(pub $type);
+
+            impl Attribute for $name {
+                const ID: u32 = $id as _;
+                const KIND: AttributeKind = <$type as Value>::KIND;
+
+                fn from_value(value: AttributeValue) -> Result<Self> {
+                    <$type as Value>::from_value(value)
+                        .map(Self)
+                        .map_err(|value| mismatch(Self::ID, Self::KIND, value.kind()))
+                }
+            }
+
+            attributes!(@$access $name);
+        )*
+
+        /// Type of the attribute `id`, if it has a typed counterpart.
+        fn kind_of(id: u32) -> Option<AttributeKind> {
+            match id {
+                Timeout::ID | ResourceLockState::ID => Some(AttributeKind::U32),
+                $($name::ID => Some($name::KIND),)*
+                _ => None,
+            }
+        }
+
+        /// Whether the attribute `id` is read only, as far as its typed counterpart tells.
+        fn is_read_only(id: u32) -> bool {
+            match id {
+                ResourceLockState::ID => true,
+                $($name::ID => attributes!(@is_read_only $access),)*
+                _ => false,
+            }
+        }
+    };
+    (@is_read_only read_only) => {
+        true
+    };
+    (@is_read_only read_write) => {
+        false
+    };
+    (@read_only $name:ident) => {};
+    (@read_write $name:ident) => {
+        impl WritableAttribute for $name {
+            fn to_value(&self) -> AttributeValue {
+                self.0.to_value()
+            }
+        }
+    };
+}
+
+/// Timeout of I/O operations, `None` waits forever. `VI_ATTR_TMO_VALUE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timeout(pub Option<Duration>);
+
+impl Attribute for Timeout {
+    const ID: u32 = VI_ATTR_TMO_VALUE as _;
+    const KIND: AttributeKind = AttributeKind::U32;
+
+    fn from_value(value: AttributeValue) -> Result<Self> {
+        match value {
+            AttributeValue::U32(VI_TMO_INFINITE) => Ok(Self(None)),
+            AttributeValue::U32(millis) => Ok(Self(Some(Duration::from_millis(millis as _)))),
+            value => Err(mismatch(Self::ID, Self::KIND, value.kind())),
+        }
+    }
+}
+
+impl WritableAttribute for Timeout {
+    fn to_value(&self) -> AttributeValue {
+        match self.0 {
+            // Timeouts too long to represent are as good as infinite
+            Some(timeout) => {
+                AttributeValue::U32(timeout.as_millis().min(VI_TMO_INFINITE as _) as _)
+            }
+            None => AttributeValue::U32(VI_TMO_INFINITE),
+        }
+    }
+}
+
+/// Lock currently held on the resource. `VI_ATTR_RSRC_LOCK_STATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceLockState(pub AccessMode);
+
+impl Attribute for ResourceLockState {
+    const ID: u32 = VI_ATTR_RSRC_LOCK_STATE as _;
+    const KIND: AttributeKind = AttributeKind::U32;
+
+    fn from_value(value: AttributeValue) -> Result<Self> {
+        match value {
+            AttributeValue::U32(mode) => Ok(Self(AccessMode::from_bits_retain(mode as _))),
+            value => Err(mismatch(Self::ID, Self::KIND, value.kind())),
+        }
+    }
+}
+
+attributes! {
+    /// `VI_ATTR_RSRC_CLASS`, such as `INSTR` or `SOCKET`.
+    ResourceClass(String) = VI_ATTR_RSRC_CLASS, read_only;
+    /// `VI_ATTR_RSRC_NAME`.
+    ResourceName(String) = VI_ATTR_RSRC_NAME, read_only;
+    /// `VI_ATTR_RSRC_IMPL_VERSION`.
+    ResourceImplVersion(u32) = VI_ATTR_RSRC_IMPL_VERSION, read_only;
+    /// `VI_ATTR_RSRC_SPEC_VERSION`.
+    ResourceSpecVersion(u32) = VI_ATTR_RSRC_SPEC_VERSION, read_only;
+    /// Manufacturer of the VISA implementation. `VI_ATTR_RSRC_MANF_NAME`.
+    ResourceManufacturerName(String) = VI_ATTR_RSRC_MANF_NAME, read_only;
+    /// `VI_ATTR_RSRC_MANF_ID`.
+    ResourceManufacturerId(u16) = VI_ATTR_RSRC_MANF_ID, read_only;
+    /// Maximum number of events queued at once. `VI_ATTR_MAX_QUEUE_LENGTH`.
+    MaxQueueLength(u32) = VI_ATTR_MAX_QUEUE_LENGTH, read_write;
+
+    /// Character ending reads when [`TermCharEnabled`]. `VI_ATTR_TERMCHAR`.
+    TermChar(u8) = VI_ATTR_TERMCHAR, read_write;
+    /// `VI_ATTR_TERMCHAR_EN`.
+    TermCharEnabled(bool) = VI_ATTR_TERMCHAR_EN, read_write;
+    /// Whether END is sent with the last byte of each write. `VI_ATTR_SEND_END_EN`.
+    SendEndEnabled(bool) = VI_ATTR_SEND_END_EN, read_write;
+    /// Whether END doesn't end reads. `VI_ATTR_SUPPRESS_END_EN`.
+    SuppressEndEnabled(bool) = VI_ATTR_SUPPRESS_END_EN, read_write;
+    /// `VI_ATTR_IO_PROT`.
+    IoProtocol(u16) = VI_ATTR_IO_PROT, read_write;
+    /// `VI_ATTR_DMA_ALLOW_EN`.
+    DmaAllowed(bool) = VI_ATTR_DMA_ALLOW_EN, read_write;
+    /// `VI_ATTR_FILE_APPEND_EN`.
+    FileAppendEnabled(bool) = VI_ATTR_FILE_APPEND_EN, read_write;
+    /// `VI_ATTR_RD_BUF_OPER_MODE`.
+    ReadBufferOperationMode(u16) = VI_ATTR_RD_BUF_OPER_MODE, read_write;
+    /// `VI_ATTR_RD_BUF_SIZE`.
+    ReadBufferSize(u32) = VI_ATTR_RD_BUF_SIZE, read_only;
+    /// `VI_ATTR_WR_BUF_OPER_MODE`.
+    WriteBufferOperationMode(u16) = VI_ATTR_WR_BUF_OPER_MODE, read_write;
+    /// `VI_ATTR_WR_BUF_SIZE`.
+    WriteBufferSize(u32) = VI_ATTR_WR_BUF_SIZE, read_only;
+    /// `VI_ATTR_TRIG_ID`.
+    TriggerId(i16) = VI_ATTR_TRIG_ID, read_write;
+    /// Whether the device is IEEE 488.2 compliant. `VI_ATTR_4882_COMPLIANT`.
+    Is4882Compliant(bool) = VI_ATTR_4882_COMPLIANT, read_only;
+
+    /// `VI_ATTR_INTF_TYPE`.
+    InterfaceType(u16) = VI_ATTR_INTF_TYPE, read_only;
+    /// `VI_ATTR_INTF_NUM`.
+    InterfaceNumber(u16) = VI_ATTR_INTF_NUM, read_only;
+    /// `VI_ATTR_INTF_INST_NAME`.
+    InterfaceName(String) = VI_ATTR_INTF_INST_NAME, read_only;
+    /// `VI_ATTR_MANF_ID`.
+    ManufacturerId(u16) = VI_ATTR_MANF_ID, read_only;
+    /// `VI_ATTR_MANF_NAME`.
+    ManufacturerName(String) = VI_ATTR_MANF_NAME, read_only;
+    /// `VI_ATTR_MODEL_CODE`.
+    ModelCode(u16) = VI_ATTR_MODEL_CODE, read_only;
+    /// `VI_ATTR_MODEL_NAME`.
+    ModelName(String) = VI_ATTR_MODEL_NAME, read_only;
+
+    /// `VI_ATTR_MEM_BASE_64`.
+    MemoryBase(u64) = VI_ATTR_MEM_BASE_64, read_only;
+    /// `VI_ATTR_MEM_SIZE_64`.
+    MemorySize(u64) = VI_ATTR_MEM_SIZE_64, read_only;
+    /// `VI_ATTR_WIN_BASE_ADDR_64`.
+    WindowBase(u64) = VI_ATTR_WIN_BASE_ADDR_64, read_only;
+    /// `VI_ATTR_WIN_SIZE_64`.
+    WindowSize(u64) = VI_ATTR_WIN_SIZE_64, read_only;
+
+    /// `VI_ATTR_GPIB_PRIMARY_ADDR`.
+    GpibPrimaryAddress(u16) = VI_ATTR_GPIB_PRIMARY_ADDR, read_only;
+    /// `VI_ATTR_GPIB_SECONDARY_ADDR`, `VI_NO_SEC_ADDR` without one.
+    GpibSecondaryAddress(u16) = VI_ATTR_GPIB_SECONDARY_ADDR, read_only;
+    /// `VI_ATTR_GPIB_READDR_EN`.
+    GpibReaddressing(bool) = VI_ATTR_GPIB_READDR_EN, read_write;
+    /// `VI_ATTR_GPIB_UNADDR_EN`.
+    GpibUnaddressing(bool) = VI_ATTR_GPIB_UNADDR_EN, read_write;
+    /// `VI_ATTR_GPIB_REN_STATE`.
+    GpibRenState(i16) = VI_ATTR_GPIB_REN_STATE, read_only;
+
+    /// `VI_ATTR_ASRL_BAUD`.
+    AsrlBaud(u32) = VI_ATTR_ASRL_BAUD, read_write;
+    /// `VI_ATTR_ASRL_DATA_BITS`.
+    AsrlDataBits(u16) = VI_ATTR_ASRL_DATA_BITS, read_write;
+    /// `VI_ATTR_ASRL_PARITY`.
+    AsrlParity(u16) = VI_ATTR_ASRL_PARITY, read_write;
+    /// `VI_ATTR_ASRL_STOP_BITS`, in tenths of a bit.
+    AsrlStopBits(u16) = VI_ATTR_ASRL_STOP_BITS, read_write;
+    /// `VI_ATTR_ASRL_FLOW_CNTRL`.
+    AsrlFlowControl(u16) = VI_ATTR_ASRL_FLOW_CNTRL, read_write;
+    /// `VI_ATTR_ASRL_END_IN`.
+    AsrlEndIn(u16) = VI_ATTR_ASRL_END_IN, read_write;
+    /// `VI_ATTR_ASRL_END_OUT`.
+    AsrlEndOut(u16) = VI_ATTR_ASRL_END_OUT, read_write;
+    /// `VI_ATTR_ASRL_XON_CHAR`.
+    AsrlXonChar(u8) = VI_ATTR_ASRL_XON_CHAR, read_write;
+    /// `VI_ATTR_ASRL_XOFF_CHAR`.
+    AsrlXoffChar(u8) = VI_ATTR_ASRL_XOFF_CHAR, read_write;
+    /// `VI_ATTR_ASRL_REPLACE_CHAR`.
+    AsrlReplaceChar(u8) = VI_ATTR_ASRL_REPLACE_CHAR, read_write;
+    /// `VI_ATTR_ASRL_DISCARD_NULL`.
+    AsrlDiscardNull(bool) = VI_ATTR_ASRL_DISCARD_NULL, read_write;
+    /// Number of bytes waiting in the input buffer. `VI_ATTR_ASRL_AVAIL_NUM`.
+    AsrlAvailable(u32) = VI_ATTR_ASRL_AVAIL_NUM, read_only;
+    /// `VI_ATTR_ASRL_BREAK_LEN`, in milliseconds.
+    AsrlBreakLength(i16) = VI_ATTR_ASRL_BREAK_LEN, read_write;
+    /// `VI_ATTR_ASRL_CONNECTED`.
+    AsrlConnected(bool) = VI_ATTR_ASRL_CONNECTED, read_only;
+
+    /// `VI_ATTR_TCPIP_ADDR`.
+    TcpipAddress(String) = VI_ATTR_TCPIP_ADDR, read_only;
+    /// `VI_ATTR_TCPIP_HOSTNAME`.
+    TcpipHostname(String) = VI_ATTR_TCPIP_HOSTNAME, read_only;
+    /// `VI_ATTR_TCPIP_PORT`.
+    TcpipPort(u16) = VI_ATTR_TCPIP_PORT, read_only;
+    /// `VI_ATTR_TCPIP_DEVICE_NAME`.
+    TcpipDeviceName(String) = VI_ATTR_TCPIP_DEVICE_NAME, read_only;
+    /// `VI_ATTR_TCPIP_NODELAY`.
+    TcpipNoDelay(bool) = VI_ATTR_TCPIP_NODELAY, read_write;
+    /// `VI_ATTR_TCPIP_KEEPALIVE`.
+    TcpipKeepAlive(bool) = VI_ATTR_TCPIP_KEEPALIVE, read_write;
+    /// `VI_ATTR_TCPIP_IS_HISLIP`.
+    TcpipIsHislip(bool) = VI_ATTR_TCPIP_IS_HISLIP, read_only;
+    /// `VI_ATTR_TCPIP_HISLIP_VERSION`.
+    TcpipHislipVersion(u32) = VI_ATTR_TCPIP_HISLIP_VERSION, read_only;
+    /// `VI_ATTR_TCPIP_HISLIP_OVERLAP_EN`.
+    TcpipHislipOverlap(bool) = VI_ATTR_TCPIP_HISLIP_OVERLAP_EN, read_write;
+    /// `VI_ATTR_TCPIP_HISLIP_MAX_MESSAGE_KB`.
+    TcpipHislipMaxMessageKb(u32) = VI_ATTR_TCPIP_HISLIP_MAX_MESSAGE_KB, read_write;
+
+    /// `VI_ATTR_USB_SERIAL_NUM`.
+    UsbSerialNumber(String) = VI_ATTR_USB_SERIAL_NUM, read_only;
+    /// `VI_ATTR_USB_INTFC_NUM`.
+    UsbInterfaceNumber(i16) = VI_ATTR_USB_INTFC_NUM, read_only;
+    /// `VI_ATTR_USB_PROTOCOL`.
+    UsbProtocol(i16) = VI_ATTR_USB_PROTOCOL, read_only;
+    /// `VI_ATTR_USB_MAX_INTR_SIZE`.
+    UsbMaxInterruptSize(u16) = VI_ATTR_USB_MAX_INTR_SIZE, read_write;
+
+    /// `VI_ATTR_STATUS` of an event, the completion code of the operation it reports.
+    EventStatus(i32) = VI_ATTR_STATUS, read_only;
+    /// `VI_ATTR_JOB_ID` of an event.
+    JobId(u32) = VI_ATTR_JOB_ID, read_only;
+    /// `VI_ATTR_RET_COUNT_64` of an event, the number of bytes transferred.
+    ReturnCount(u64) = VI_ATTR_RET_COUNT_64, read_only;
+    /// `VI_ATTR_OPER_NAME` of an event.
+    OperationName(String) = VI_ATTR_OPER_NAME, read_only;
+    /// `VI_ATTR_RECV_TRIG_ID` of an event.
+    ReceivedTriggerId(i16) = VI_ATTR_RECV_TRIG_ID, read_only;
+    /// `VI_ATTR_USB_RECV_INTR_SIZE` of an event.
+    UsbReceivedInterruptSize(u16) = VI_ATTR_USB_RECV_INTR_SIZE, read_only;
+    /// `VI_ATTR_GPIB_RECV_CIC_STATE` of an event.
+    GpibReceivedCicState(bool) = VI_ATTR_GPIB_RECV_CIC_STATE, read_only;
+}
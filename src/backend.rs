@@ -0,0 +1,444 @@
+mod fake;
+#[cfg(target_os = "linux")]
+mod gpib;
+mod hislip;
+#[cfg(unix)]
+mod serial;
+#[cfg(feature = "sim")]
+mod sim;
+mod socket;
+mod transcript;
+mod usbtmc;
+mod visa;
+mod vxi11;
+
+use super::{
+    attribute::{
+        Attribute, AttributeKind, AttributeValue, SendEndEnabled, SuppressEndEnabled, TermChar,
+        TermCharEnabled, Timeout, WritableAttribute,
+    },
+    bindings::ViSession,
+    error::{Error, Result, Status, VisaError},
+    event::{Event, EventHandler, EventMechanism, EventType, HandlerId},
+    instrument::{FlushMode, TriggerProtocol},
+    resource::{ResourceName, expression_regex},
+    resource_manager::AccessMode,
+    session::Session,
+};
+use std::{
+    fmt::Debug,
+    io::ErrorKind,
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+use tracing::trace;
+
+/// Timeout used when connecting if the open timeout is zero.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Shortest I/O timeout, what a zero timeout waits for.
+const MIN_IO_TIMEOUT: Duration = Duration::from_millis(1);
+
+pub use fake::*;
+#[cfg(target_os = "linux")]
+pub use gpib::*;
+pub use hislip::*;
+#[cfg(unix)]
+pub use serial::*;
+#[cfg(feature = "sim")]
+pub use sim::*;
+pub(crate) use socket::*;
+pub use transcript::*;
+pub use usbtmc::*;
+pub(crate) use visa::*;
+pub use vxi11::*;
+
+/// Remote enable (REN) and local control requests, numbered like VISA's `VI_GPIB_REN_*` modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum RemoteLocal {
+    DisableRemote = 0,
+    EnableRemote = 1,
+    DisableRemoteGoToLocal = 2,
+    EnableRemoteGoToRemote = 3,
+    EnableRemoteLocalLockout = 4,
+    EnableRemoteGoToRemoteLocalLockout = 5,
+    GoToLocal = 6,
+}
+
+/// I/O operations a session forwards to the implementation serving its resource.
+///
+/// Sessions are shared between threads the same way VISA sessions are, so every
+/// operation takes `&self` and implementations synchronize their own state.
+///
+/// The VISA library and the native backends implement it, other implementations can be
+/// wrapped in a [`Session`] with [`Session::from_backend`] and served by a resource manager
+/// through [`ResourceManager::register_backend`](crate::ResourceManager::register_backend).
+pub trait Backend: Debug + Send + Sync {
+    /// Writes `buf` to the device, returning the number of bytes transferred.
+    fn write(&self, buf: &[u8]) -> Result<usize>;
+
+    /// Reads into `buf`, returning the number of bytes transferred and the condition that ended the read.
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)>;
+
+    /// Flushes or discards the buffers selected by `mode`.
+    fn flush(&self, mode: FlushMode) -> Result<()>;
+
+    /// Writes `buf` to the formatted I/O write buffer, sent to the device once it's full or
+    /// flushed with [`FlushMode::WRITE_BUF`]. Backends without one write `buf` at once.
+    fn buffered_write(&self, buf: &[u8]) -> Result<usize> {
+        self.write(buf)
+    }
+
+    /// Reads from the formatted I/O read buffer, refilled from the device once it's empty.
+    fn buffered_read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        self.read(buf)
+    }
+
+    /// Sets the size of the buffers selected by `mask`, [`FlushMode::READ_BUF`],
+    /// [`FlushMode::WRITE_BUF`], [`FlushMode::IO_IN_BUF`] or [`FlushMode::IO_OUT_BUF`].
+    fn set_buffer_size(&self, _mask: FlushMode, _size: usize) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Clears the device, discarding any pending input and output.
+    fn clear(&self) -> Result<()>;
+
+    /// Serial polls the device for its status byte.
+    fn read_status_byte(&self) -> Result<u8> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Triggers the device with `protocol`.
+    fn assert_trigger(&self, _protocol: TriggerProtocol) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Locks the resource, returning the access key of shared locks.
+    fn lock(
+        &self,
+        _mode: AccessMode,
+        _timeout: Duration,
+        _key: Option<&str>,
+    ) -> Result<Option<String>> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Reads the attribute `attribute`, whose values are of type `kind`.
+    fn get_attribute(&self, _attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        Err(Error::Visa(VisaError::AttributeNotSupported))
+    }
+
+    /// Sets the attribute `attribute` to `value`.
+    fn set_attribute(&self, _attribute: u32, _value: &AttributeValue) -> Result<()> {
+        Err(Error::Visa(VisaError::AttributeNotSupported))
+    }
+
+    /// Enables delivering events of `event_type` through `mechanism`.
+    fn enable_event(&self, _event_type: EventType, _mechanism: EventMechanism) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    fn disable_event(&self, _event_type: EventType, _mechanism: EventMechanism) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Discards the pending events of `event_type` delivered through `mechanism`.
+    fn discard_events(&self, _event_type: EventType, _mechanism: EventMechanism) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Waits for a queued event of `event_type`, `None` waits forever.
+    fn wait_on_event(&self, _event_type: EventType, _timeout: Option<Duration>) -> Result<Event> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Installs `handler` for events of `event_type`, it's called once the handler mechanism is
+    /// enabled for them.
+    fn install_handler(&self, _event_type: EventType, _handler: EventHandler) -> Result<HandlerId> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Uninstalls a handler, dropping it once it isn't running anymore.
+    fn uninstall_handler(&self, _event_type: EventType, _id: HandlerId) -> Result<()> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Closes the resource, it's called at most once by the session but may also be called on drop.
+    fn close(&self) -> Result<()>;
+
+    /// The underlying VISA session, if the resource is served by the VISA library.
+    fn as_vi_session(&self) -> Option<ViSession> {
+        None
+    }
+}
+
+/// Default I/O timeout of native backends, the same as `VI_ATTR_TMO_VALUE`'s default.
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// I/O settings native backends keep for their message based sessions, exposed as the
+/// VISA attributes with the same meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IoSettings {
+    /// `None` waits forever.
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) term_char: u8,
+    /// Whether reads end on `term_char`, besides on END.
+    pub(crate) term_char_enabled: bool,
+    /// Whether END is sent with the last byte of every write.
+    pub(crate) send_end: bool,
+}
+
+impl IoSettings {
+    /// VISA's defaults, `\n` as a disabled termination character and END sent with writes.
+    pub(crate) fn new(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            term_char: b'\n',
+            term_char_enabled: false,
+            send_end: true,
+        }
+    }
+
+    pub(crate) fn get(&self, attribute: u32) -> Result<AttributeValue> {
+        let value = match attribute {
+            Timeout::ID => Timeout(self.timeout).to_value(),
+            TermChar::ID => TermChar(self.term_char).to_value(),
+            TermCharEnabled::ID => TermCharEnabled(self.term_char_enabled).to_value(),
+            SendEndEnabled::ID => SendEndEnabled(self.send_end).to_value(),
+            SuppressEndEnabled::ID => SuppressEndEnabled(false).to_value(),
+            _ => return Err(Error::Visa(VisaError::AttributeNotSupported)),
+        };
+        Ok(value)
+    }
+
+    pub(crate) fn set(&mut self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        let value = value.clone();
+        match attribute {
+            Timeout::ID => self.timeout = Timeout::from_value(value)?.0,
+            TermChar::ID => self.term_char = TermChar::from_value(value)?.0,
+            TermCharEnabled::ID => self.term_char_enabled = TermCharEnabled::from_value(value)?.0,
+            SendEndEnabled::ID => self.send_end = SendEndEnabled::from_value(value)?.0,
+            // END always ends reads
+            SuppressEndEnabled::ID => {
+                if SuppressEndEnabled::from_value(value)?.0 {
+                    return Err(Error::Visa(VisaError::AttributeStateNotSupported));
+                }
+            }
+            _ => return Err(Error::Visa(VisaError::AttributeNotSupported)),
+        }
+        Ok(())
+    }
+}
+
+/// The timeout to use while opening a resource, a zero open timeout means the default one.
+pub(crate) fn open_timeout(timeout: Duration) -> Duration {
+    match timeout.is_zero() {
+        true => DEFAULT_CONNECT_TIMEOUT,
+        false => timeout,
+    }
+}
+
+/// Maps socket timeouts to VISA timeouts and closed connections to lost ones, everything else is an I/O error.
+pub(crate) fn map_io_error(error: std::io::Error) -> Error {
+    match error.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => Error::Visa(VisaError::Timeout),
+        ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset | ErrorKind::BrokenPipe => {
+            Error::Visa(VisaError::ConnectionLost)
+        }
+        _ => Error::Io(error),
+    }
+}
+
+/// The timeout given to sockets for a VISA timeout. Sockets reject a zero timeout, so a zero
+/// VISA timeout gets the shortest one, only succeeding with data that's already there.
+pub(crate) fn io_timeout(timeout: Option<Duration>) -> Option<Duration> {
+    timeout.map(|timeout| timeout.max(MIN_IO_TIMEOUT))
+}
+
+/// When an operation with the VISA timeout `timeout` expires, `None` never does.
+pub(crate) fn deadline(timeout: Option<Duration>) -> Option<Instant> {
+    io_timeout(timeout).map(|timeout| Instant::now() + timeout)
+}
+
+/// Time left until `deadline`, failing with a timeout once it has passed. `None` waits forever.
+pub(crate) fn remaining(deadline: Option<Instant>) -> Result<Option<Duration>> {
+    match deadline {
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+            _ => Err(Error::Visa(VisaError::Timeout)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Writes `buf` through `send` in chunks of at most `size` bytes, returning how many were written.
+///
+/// `send` is told whether the chunk ends `buf` and returns how much of it the device accepted, the
+/// rest is sent again. An empty `buf` is still sent as one empty chunk, to deliver the END indicator.
+pub(crate) fn write_chunks(
+    buf: &[u8],
+    size: usize,
+    mut send: impl FnMut(&[u8], bool) -> Result<usize>,
+) -> Result<usize> {
+    let mut written = 0;
+    loop {
+        let len = (buf.len() - written).min(size);
+        let sent = send(&buf[written..written + len], written + len == buf.len())?;
+        // A device accepting nothing would never let the write finish
+        if sent > len || (sent == 0 && len > 0) {
+            return Err(Error::Visa(VisaError::Io));
+        }
+        written += sent;
+        if written == buf.len() {
+            return Ok(written);
+        }
+    }
+}
+
+/// Connects to `host` on `port`, trying every address it resolves to.
+pub(crate) fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream> {
+    let timeout = open_timeout(timeout);
+
+    let mut last_error = None;
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(stream) => {
+                trace!("connected to {}", address);
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    Err(last_error
+        .map(Error::Io)
+        .unwrap_or(Error::Visa(VisaError::ResourceNotFound)))
+}
+
+/// Opens `resource` with one of the native backends, without going through the VISA library.
+pub(crate) fn open(resource: &str, access_mode: AccessMode, timeout: Duration) -> Result<Session> {
+    let session = match resource.parse()? {
+        ResourceName::TcpipSocket { host, port, .. } => {
+            Session::from_backend(TcpSocket::connect(&host, port, timeout)?)
+        }
+        ResourceName::TcpipInstr {
+            host, device_name, ..
+        } if device_name.to_ascii_lowercase().starts_with("hislip") => {
+            // HiSLIP device names can carry the server port, as in `hislip0,4880`
+            let (sub_address, port) = match device_name.split_once(',') {
+                Some((sub_address, port)) => (
+                    sub_address,
+                    port.parse()
+                        .map_err(|_| Error::Visa(VisaError::InvalidResourceName))?,
+                ),
+                None => (device_name.as_str(), HISLIP_PORT),
+            };
+            Session::from_backend(HiSlip::connect(&host, port, sub_address, timeout)?)
+        }
+        ResourceName::TcpipInstr {
+            host, device_name, ..
+        } => Session::from_backend(Vxi11::connect(&host, &device_name, timeout)?),
+        #[cfg(target_os = "linux")]
+        ResourceName::Gpib {
+            board,
+            primary_address,
+            secondary_address,
+        } => Session::from_backend(Gpib::open(board, primary_address, secondary_address)?),
+        #[cfg(unix)]
+        ResourceName::Asrl { port } => {
+            Session::from_backend(Serial::open(port_path(&port)?, SerialSettings::default())?)
+        }
+        #[cfg(target_os = "linux")]
+        ResourceName::Usb {
+            vendor_id,
+            product_id,
+            serial_number,
+            interface_number,
+            ..
+        } => {
+            let transport = UsbFs::open(vendor_id, product_id, &serial_number, interface_number)?;
+            Session::from_backend(UsbTmc::new(transport)?)
+        }
+        resource => return Err(Error::UnsupportedResource(resource.to_string())),
+    };
+
+    if !(access_mode & (AccessMode::EXCLUSIVE_LOCK | AccessMode::SHARED_LOCK)).is_empty() {
+        session.backend().lock(access_mode, timeout, None)?;
+    }
+
+    Ok(session)
+}
+
+/// Lists the resources the native backends can discover that match the VISA `expression`.
+pub(crate) fn find(expression: &str) -> Result<Vec<String>> {
+    let expression = expression_regex(expression)?;
+
+    let mut resources: Vec<String> = vec![];
+    #[cfg(target_os = "linux")]
+    {
+        resources.extend(find_ports());
+        resources.extend(
+            find_interfaces()
+                .iter()
+                .map(|interface| interface.resource_name()),
+        );
+    }
+
+    resources.retain(|resource| expression.is_match(resource));
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_settings_expose_their_attributes() {
+        let mut io = IoSettings::new(DEFAULT_TIMEOUT);
+        assert_eq!(io.get(TermChar::ID).unwrap(), AttributeValue::U8(b'\n'));
+
+        io.set(Timeout::ID, &Timeout(None).to_value()).unwrap();
+        io.set(TermCharEnabled::ID, &AttributeValue::Bool(true))
+            .unwrap();
+        assert_eq!(io.timeout, None);
+        assert!(io.term_char_enabled);
+
+        assert!(matches!(
+            io.set(TermChar::ID, &AttributeValue::Bool(true)),
+            Err(Error::AttributeKindMismatch(
+                _,
+                AttributeKind::U8,
+                AttributeKind::Bool
+            ))
+        ));
+        assert!(matches!(
+            io.set(SuppressEndEnabled::ID, &AttributeValue::Bool(true)),
+            Err(Error::Visa(VisaError::AttributeStateNotSupported))
+        ));
+        assert!(matches!(
+            io.get(crate::attribute::ResourceName::ID),
+            Err(Error::Visa(VisaError::AttributeNotSupported))
+        ));
+    }
+
+    #[test]
+    fn zero_timeouts_still_wait_a_little() {
+        assert_eq!(io_timeout(Some(Duration::ZERO)), Some(MIN_IO_TIMEOUT));
+        assert_eq!(io_timeout(None), None);
+        assert!(
+            remaining(deadline(Some(Duration::from_secs(1))))
+                .unwrap()
+                .is_some()
+        );
+        assert!(matches!(
+            remaining(Some(Instant::now())),
+            Err(Error::Visa(VisaError::Timeout))
+        ));
+        assert_eq!(remaining(None).unwrap(), None);
+    }
+}
@@ -0,0 +1,412 @@
+use super::Backend;
+use crate::{
+    attribute::{
+        Attribute, AttributeKind, AttributeValue, SendEndEnabled, SuppressEndEnabled, TermChar,
+        TermCharEnabled, Timeout, WritableAttribute,
+    },
+    error::{Error, Result, Status, VisaError},
+    event::{Event, EventHandler, EventMechanism, EventType, HandlerId},
+    instrument::{FlushMode, TriggerProtocol},
+    resource_manager::AccessMode,
+};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Identification the fake answers `*IDN?` with unless told otherwise.
+const IDENTIFICATION: &str = "Fake,Instrument,0,0\n";
+
+#[derive(Debug)]
+struct State {
+    /// Responses by query, without trailing whitespace.
+    responses: HashMap<Vec<u8>, Vec<u8>>,
+    written: Vec<Vec<u8>>,
+    /// Responses not yet read, each ends with END.
+    output: VecDeque<Vec<u8>>,
+    /// Attributes by identifier, any attribute set is stored.
+    attributes: HashMap<u32, AttributeValue>,
+    /// Event types enabled for the queue.
+    enabled_events: HashSet<EventType>,
+    events: VecDeque<EventType>,
+    /// Event types enabled for handlers.
+    handled_events: HashSet<EventType>,
+    handlers: Vec<Handler>,
+    next_handler: u64,
+    /// Status byte returned by serial polls.
+    status_byte: u8,
+    triggers: usize,
+    closed: bool,
+}
+
+/// An installed handler, shared with the pushes running it.
+#[derive(Clone)]
+struct Handler {
+    id: HandlerId,
+    event_type: EventType,
+    handler: Arc<Mutex<EventHandler>>,
+}
+
+impl Debug for Handler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handler")
+            .field("id", &self.id)
+            .field("event_type", &self.event_type)
+            .finish_non_exhaustive()
+    }
+}
+
+/// In-memory backend answering queries from a table, for tests without an instrument.
+///
+/// Every write is a complete message, a message matching a query of the table queues its
+/// response and reading with nothing queued times out. Attributes only store their values,
+/// starting from VISA's defaults for the I/O ones. Pushed events are delivered like VISA
+/// would, to the queue or to handlers called by the push. Clones share the same state, so a
+/// test can keep one to inspect what was written after handing another to a [`Session`](crate::Session).
+///
+/// ```
+/// use visa::{Fake, Instrument, Session};
+///
+/// let fake = Fake::new().with_response("MEAS:VOLT?", "1.5\n");
+/// let mut instrument = Instrument::new(Session::from(fake.clone()))?;
+/// assert_eq!(instrument.query("MEAS:VOLT?\n")?, "1.5\n");
+/// assert_eq!(fake.written().last().unwrap(), b"MEAS:VOLT?\n");
+/// # Ok::<(), visa::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Fake {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for Fake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fake {
+    /// Creates a fake that only answers `*IDN?`.
+    pub fn new() -> Self {
+        let fake = Self {
+            state: Arc::new(Mutex::new(State {
+                responses: HashMap::new(),
+                written: Vec::new(),
+                output: VecDeque::new(),
+                attributes: HashMap::from([
+                    (
+                        Timeout::ID,
+                        Timeout(Some(Duration::from_millis(2000))).to_value(),
+                    ),
+                    (TermChar::ID, TermChar(b'\n').to_value()),
+                    (TermCharEnabled::ID, TermCharEnabled(false).to_value()),
+                    (SendEndEnabled::ID, SendEndEnabled(true).to_value()),
+                    (SuppressEndEnabled::ID, SuppressEndEnabled(false).to_value()),
+                ]),
+                enabled_events: HashSet::new(),
+                events: VecDeque::new(),
+                handled_events: HashSet::new(),
+                handlers: Vec::new(),
+                next_handler: 0,
+                status_byte: 0,
+                triggers: 0,
+                closed: false,
+            })),
+        };
+        fake.set_response("*IDN?", IDENTIFICATION);
+        fake
+    }
+
+    pub fn with_response(self, query: impl AsRef<[u8]>, response: impl AsRef<[u8]>) -> Self {
+        self.set_response(query, response);
+        self
+    }
+
+    /// Answers `query` with `response`, trailing whitespace of the query is ignored.
+    pub fn set_response(&self, query: impl AsRef<[u8]>, response: impl AsRef<[u8]>) {
+        self.state.lock().unwrap().responses.insert(
+            query.as_ref().trim_ascii_end().to_vec(),
+            response.as_ref().to_vec(),
+        );
+    }
+
+    /// Queues `message` to be read, as if the device had sent it unprompted.
+    pub fn push_output(&self, message: impl AsRef<[u8]>) {
+        self.state
+            .lock()
+            .unwrap()
+            .output
+            .push_back(message.as_ref().to_vec());
+    }
+
+    /// Delivers an event of `event_type`, queued if it's enabled for the queue and passed to its
+    /// handlers, most recently installed first, if it's enabled for them. Otherwise it's dropped.
+    pub fn push_event(&self, event_type: EventType) {
+        let handlers = {
+            let mut state = self.state.lock().unwrap();
+            if state.enabled_events.contains(&event_type) {
+                state.events.push_back(event_type);
+            }
+            match state.handled_events.contains(&event_type) {
+                true => state
+                    .handlers
+                    .iter()
+                    .rev()
+                    .filter(|handler| handler.event_type == event_type)
+                    .cloned()
+                    .collect(),
+                false => Vec::new(),
+            }
+        };
+        // Called unlocked, handlers may use the fake
+        let event = Event::new(event_type);
+        for Handler { handler, .. } in handlers {
+            (handler.lock().unwrap())(&event);
+        }
+    }
+
+    /// Sets the status byte serial polls return.
+    pub fn set_status_byte(&self, status_byte: u8) {
+        self.state.lock().unwrap().status_byte = status_byte;
+    }
+
+    /// Number of software triggers asserted so far.
+    pub fn triggers(&self) -> usize {
+        self.state.lock().unwrap().triggers
+    }
+
+    /// Every message written so far, in order.
+    pub fn written(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().written.clone()
+    }
+
+    /// The value the attribute `attribute` was last set to.
+    pub fn attribute(&self, attribute: u32) -> Option<AttributeValue> {
+        self.state
+            .lock()
+            .unwrap()
+            .attributes
+            .get(&attribute)
+            .cloned()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+}
+
+impl State {
+    fn check_open(&self) -> Result<()> {
+        match self.closed {
+            true => Err(Error::Visa(VisaError::InvalidObject)),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Backend for Fake {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.written.push(buf.to_vec());
+        if let Some(response) = state.responses.get(buf.trim_ascii_end()).cloned() {
+            state.output.push_back(response);
+        }
+        Ok(buf.len())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        let message = state
+            .output
+            .front_mut()
+            .ok_or(Error::Visa(VisaError::Timeout))?;
+
+        let count = message.len().min(buf.len());
+        buf[..count].copy_from_slice(&message[..count]);
+        message.drain(..count);
+        match message.is_empty() {
+            true => {
+                state.output.pop_front();
+                Ok((count, Status::OperationCompleted))
+            }
+            false => Ok((count, Status::BytesReadEqualInput)),
+        }
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        if mode.intersects(FlushMode::READ_BUF_DISCARD | FlushMode::IO_IN_BUF_DISCARD) {
+            state.output.clear();
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.output.clear();
+        Ok(())
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        let state = self.state.lock().unwrap();
+        state.check_open()?;
+        Ok(state.status_byte)
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        match protocol {
+            TriggerProtocol::Default => {
+                state.triggers += 1;
+                Ok(())
+            }
+            _ => Err(Error::Visa(VisaError::InvalidProtocol)),
+        }
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => Ok(None),
+            false => Ok(Some(key.unwrap_or("fake").to_owned())),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        let state = self.state.lock().unwrap();
+        state.check_open()?;
+        state
+            .attributes
+            .get(&attribute)
+            .cloned()
+            .ok_or(Error::Visa(VisaError::AttributeNotSupported))
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.attributes.insert(attribute, value.clone());
+        Ok(())
+    }
+
+    fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        if event_type == EventType::AllEnabled {
+            return Err(Error::Visa(VisaError::InvalidEvent));
+        }
+        if mechanism.is_empty()
+            || !(EventMechanism::QUEUE | EventMechanism::HANDLER).contains(mechanism)
+        {
+            return Err(Error::Visa(VisaError::MechanismNotSupported));
+        }
+        if mechanism.contains(EventMechanism::HANDLER) {
+            if !state
+                .handlers
+                .iter()
+                .any(|handler| handler.event_type == event_type)
+            {
+                return Err(Error::Visa(VisaError::HandlerNotInstalled));
+            }
+            state.handled_events.insert(event_type);
+        }
+        if mechanism.contains(EventMechanism::QUEUE) {
+            state.enabled_events.insert(event_type);
+        }
+        Ok(())
+    }
+
+    fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        let State {
+            enabled_events,
+            handled_events,
+            ..
+        } = &mut *state;
+        for (enabled, events) in [
+            (EventMechanism::QUEUE, enabled_events),
+            (EventMechanism::HANDLER, handled_events),
+        ] {
+            if mechanism.contains(enabled) {
+                match event_type {
+                    EventType::AllEnabled => events.clear(),
+                    event_type => {
+                        events.remove(&event_type);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        if mechanism.contains(EventMechanism::QUEUE) {
+            state
+                .events
+                .retain(|queued| event_type != EventType::AllEnabled && *queued != event_type);
+        }
+        Ok(())
+    }
+
+    fn wait_on_event(&self, event_type: EventType, _timeout: Option<Duration>) -> Result<Event> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        if event_type != EventType::AllEnabled && !state.enabled_events.contains(&event_type) {
+            return Err(Error::Visa(VisaError::SessionNotEnabled));
+        }
+        // Like reads, waits never block and time out at once on an empty queue
+        let position = state
+            .events
+            .iter()
+            .position(|queued| event_type == EventType::AllEnabled || *queued == event_type)
+            .ok_or(Error::Visa(VisaError::Timeout))?;
+        let event_type = state.events.remove(position).unwrap();
+        Ok(Event::new(event_type))
+    }
+
+    fn install_handler(&self, event_type: EventType, handler: EventHandler) -> Result<HandlerId> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        let id = HandlerId(state.next_handler);
+        state.next_handler += 1;
+        state.handlers.push(Handler {
+            id,
+            event_type,
+            handler: Arc::new(Mutex::new(handler)),
+        });
+        Ok(id)
+    }
+
+    fn uninstall_handler(&self, event_type: EventType, id: HandlerId) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let position = state
+            .handlers
+            .iter()
+            .position(|handler| handler.id == id && handler.event_type == event_type)
+            .ok_or(Error::Visa(VisaError::HandlerNotInstalled))?;
+        state.handlers.remove(position);
+        Ok(())
+    }
+
+    fn close(&self) -> Result<()> {
+        self.state.lock().unwrap().closed = true;
+        Ok(())
+    }
+}
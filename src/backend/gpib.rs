@@ -0,0 +1,467 @@
+use super::{Backend, DEFAULT_TIMEOUT, IoSettings, RemoteLocal};
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    error::{Error, Result, Status, VisaError},
+    instrument::{FlushMode, TriggerProtocol},
+    resource_manager::AccessMode,
+};
+use libloading::Library;
+use std::{
+    ffi::{c_char, c_int, c_long, c_void},
+    io,
+    sync::Mutex,
+    time::Duration,
+};
+use tracing::{debug, trace};
+
+/// Names the linux-gpib user space library is searched under.
+const LIBRARY_LOCATIONS: &[&str] = &["libgpib.so.0", "libgpib.so"];
+
+// ibsta bits
+const ERR: c_int = 0x8000;
+const TIMO: c_int = 0x4000;
+const END: c_int = 0x2000;
+
+// iberr codes
+const EDVR: c_int = 0;
+const ECIC: c_int = 1;
+const ENOL: c_int = 2;
+const EADR: c_int = 3;
+const EARG: c_int = 4;
+const ESAC: c_int = 5;
+const EABO: c_int = 6;
+const ENEB: c_int = 7;
+const EOIP: c_int = 10;
+const ECAP: c_int = 11;
+const EBUS: c_int = 14;
+
+// ibeos flags
+const REOS: c_int = 0x400;
+const BIN: c_int = 0x1000;
+
+/// Secondary addresses are sent as `0x60 + address`.
+const SECONDARY_ADDRESS_BASE: u8 = 0x60;
+/// Terminates the address lists of the IEEE 488.2 calls.
+const NOADDR: u16 = 0xffff;
+
+/// Durations of the `T10us` to `T1000s` timeout codes, `TNONE` (0) waits forever.
+const TIMEOUTS: [Duration; 17] = [
+    Duration::from_micros(10),
+    Duration::from_micros(30),
+    Duration::from_micros(100),
+    Duration::from_micros(300),
+    Duration::from_millis(1),
+    Duration::from_millis(3),
+    Duration::from_millis(10),
+    Duration::from_millis(30),
+    Duration::from_millis(100),
+    Duration::from_millis(300),
+    Duration::from_secs(1),
+    Duration::from_secs(3),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+    Duration::from_secs(100),
+    Duration::from_secs(300),
+    Duration::from_secs(1000),
+];
+
+/// The shortest timeout code that waits at least `timeout`.
+fn timeout_code(timeout: Option<Duration>) -> c_int {
+    match timeout {
+        Some(timeout) => {
+            TIMEOUTS
+                .iter()
+                .position(|code| *code >= timeout)
+                .unwrap_or(TIMEOUTS.len() - 1) as c_int
+                + 1
+        }
+        None => 0,
+    }
+}
+
+/// The calls used from linux-gpib's `libgpib`, resolved when it's first needed.
+#[derive(Debug)]
+#[allow(non_snake_case)]
+struct LinuxGpib {
+    _library: Library,
+    ibdev: unsafe extern "C" fn(c_int, c_int, c_int, c_int, c_int, c_int) -> c_int,
+    ibonl: unsafe extern "C" fn(c_int, c_int) -> c_int,
+    ibwrt: unsafe extern "C" fn(c_int, *const c_void, c_long) -> c_int,
+    ibrd: unsafe extern "C" fn(c_int, *mut c_void, c_long) -> c_int,
+    ibclr: unsafe extern "C" fn(c_int) -> c_int,
+    ibrsp: unsafe extern "C" fn(c_int, *mut c_char) -> c_int,
+    ibtrg: unsafe extern "C" fn(c_int) -> c_int,
+    ibloc: unsafe extern "C" fn(c_int) -> c_int,
+    ibsre: unsafe extern "C" fn(c_int, c_int) -> c_int,
+    ibtmo: unsafe extern "C" fn(c_int, c_int) -> c_int,
+    ibeos: unsafe extern "C" fn(c_int, c_int) -> c_int,
+    ibeot: unsafe extern "C" fn(c_int, c_int) -> c_int,
+    EnableRemote: unsafe extern "C" fn(c_int, *const u16),
+    SendLLO: unsafe extern "C" fn(c_int),
+    SetRWLS: unsafe extern "C" fn(c_int, *const u16),
+    ThreadIbsta: unsafe extern "C" fn() -> c_int,
+    ThreadIberr: unsafe extern "C" fn() -> c_int,
+    ThreadIbcntl: unsafe extern "C" fn() -> c_long,
+}
+
+static LIBRARY: Mutex<Option<&'static LinuxGpib>> = Mutex::new(None);
+
+impl LinuxGpib {
+    fn open(name: &str) -> std::result::Result<Self, libloading::Error> {
+        unsafe {
+            let library = Library::new(name)?;
+            Ok(Self {
+                ibdev: *library.get(b"ibdev\0")?,
+                ibonl: *library.get(b"ibonl\0")?,
+                ibwrt: *library.get(b"ibwrt\0")?,
+                ibrd: *library.get(b"ibrd\0")?,
+                ibclr: *library.get(b"ibclr\0")?,
+                ibrsp: *library.get(b"ibrsp\0")?,
+                ibtrg: *library.get(b"ibtrg\0")?,
+                ibloc: *library.get(b"ibloc\0")?,
+                ibsre: *library.get(b"ibsre\0")?,
+                ibtmo: *library.get(b"ibtmo\0")?,
+                ibeos: *library.get(b"ibeos\0")?,
+                ibeot: *library.get(b"ibeot\0")?,
+                EnableRemote: *library.get(b"EnableRemote\0")?,
+                SendLLO: *library.get(b"SendLLO\0")?,
+                SetRWLS: *library.get(b"SetRWLS\0")?,
+                ThreadIbsta: *library.get(b"ThreadIbsta\0")?,
+                ThreadIberr: *library.get(b"ThreadIberr\0")?,
+                ThreadIbcntl: *library.get(b"ThreadIbcntl\0")?,
+                _library: library,
+            })
+        }
+    }
+
+    /// Returns `status` if the call succeeded, the error of the calling thread's last call otherwise.
+    fn check(&self, status: c_int) -> Result<c_int> {
+        if status & ERR == 0 {
+            return Ok(status);
+        }
+        if status & TIMO != 0 {
+            return Err(Error::Visa(VisaError::Timeout));
+        }
+
+        let error = match unsafe { (self.ThreadIberr)() } {
+            // The driver failed, ibcntl holds errno
+            EDVR => {
+                let errno = unsafe { (self.ThreadIbcntl)() };
+                return Err(Error::Io(io::Error::from_raw_os_error(errno as _)));
+            }
+            ECIC => VisaError::NotControllerInCharge,
+            ENOL => VisaError::NoListeners,
+            EADR | EARG => VisaError::InvalidParameter,
+            ESAC => VisaError::NotSystemController,
+            EABO => VisaError::Timeout,
+            ENEB => VisaError::ResourceNotFound,
+            EOIP => VisaError::ResourceBusy,
+            ECAP => VisaError::OperationNotSupported,
+            EBUS => VisaError::Io,
+            _ => VisaError::Unknown,
+        };
+        Err(Error::Visa(error))
+    }
+
+    /// Checks the outcome of the IEEE 488.2 calls, which don't return a status.
+    fn check_last(&self) -> Result<c_int> {
+        self.check(unsafe { (self.ThreadIbsta)() })
+    }
+
+    fn count(&self) -> usize {
+        unsafe { (self.ThreadIbcntl)() as usize }
+    }
+}
+
+/// Returns linux-gpib, loading it on first use.
+fn library() -> Result<&'static LinuxGpib> {
+    let mut library = LIBRARY.lock().unwrap();
+    if let Some(library) = *library {
+        return Ok(library);
+    }
+
+    for location in LIBRARY_LOCATIONS {
+        match LinuxGpib::open(location) {
+            Ok(gpib) => {
+                debug!("loaded linux-gpib from {}", location);
+                let gpib = Box::leak(Box::new(gpib));
+                *library = Some(gpib);
+                return Ok(gpib);
+            }
+            Err(error) => trace!("skipping {}: {}", location, error),
+        }
+    }
+
+    Err(Error::Visa(VisaError::LibraryNotFound))
+}
+
+#[derive(Debug)]
+struct State {
+    io: IoSettings,
+    closed: bool,
+}
+
+/// Native backend for `GPIB` resources, through the linux-gpib driver.
+///
+/// The library is loaded at runtime, so it's only needed on machines actually talking to a GPIB board.
+#[derive(Debug)]
+pub struct Gpib {
+    library: &'static LinuxGpib,
+    board: c_int,
+    /// The device's `Addr4882_t`, the primary address with the secondary one in the high byte.
+    address: u16,
+    device: c_int,
+    state: Mutex<State>,
+}
+
+impl Gpib {
+    /// Opens the device at `primary_address` and optional `secondary_address`, both from 0 to 30, on GPIB `board`.
+    ///
+    /// Writes assert EOI with the last byte and reads end on EOI only, as with VISA's defaults.
+    pub fn open(board: u16, primary_address: u8, secondary_address: Option<u8>) -> Result<Self> {
+        if primary_address > 30 || secondary_address.is_some_and(|address| address > 30) {
+            return Err(Error::Visa(VisaError::InvalidResourceName));
+        }
+
+        let library = library()?;
+        let secondary_address = secondary_address
+            .map(|address| address + SECONDARY_ADDRESS_BASE)
+            .unwrap_or(0);
+        let device = unsafe {
+            (library.ibdev)(
+                board as _,
+                primary_address as _,
+                secondary_address as _,
+                timeout_code(Some(DEFAULT_TIMEOUT)),
+                1,
+                0,
+            )
+        };
+        if device < 0 {
+            library.check_last()?;
+            return Err(Error::Visa(VisaError::ResourceNotFound));
+        }
+        debug!(
+            "opened GPIB{}::{} as descriptor {}",
+            board, primary_address, device
+        );
+
+        Ok(Self {
+            library,
+            board: board as _,
+            address: primary_address as u16 | (secondary_address as u16) << 8,
+            device,
+            state: Mutex::new(State {
+                io: IoSettings::new(DEFAULT_TIMEOUT),
+                closed: false,
+            }),
+        })
+    }
+
+    /// Sets the I/O timeout, rounded up to the next timeout the driver supports. `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.library
+            .check(unsafe { (self.library.ibtmo)(self.device, timeout_code(timeout)) })?;
+        state.io.timeout = timeout;
+        Ok(())
+    }
+
+    /// Sets the EOS character and whether reads end when it's received, besides on EOI.
+    pub fn set_term_char(&self, term_char: u8, enabled: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let eos = match enabled {
+            true => term_char as c_int | REOS | BIN,
+            false => 0,
+        };
+        self.library
+            .check(unsafe { (self.library.ibeos)(self.device, eos) })?;
+        state.io.term_char = term_char;
+        state.io.term_char_enabled = enabled;
+        Ok(())
+    }
+
+    /// Sets whether EOI is asserted with the last byte of every write.
+    pub fn set_send_end(&self, send_end: bool) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.library
+            .check(unsafe { (self.library.ibeot)(self.device, send_end as _) })?;
+        state.io.send_end = send_end;
+        Ok(())
+    }
+
+    /// Serial polls the device for its status byte.
+    pub fn read_status_byte(&self) -> Result<u8> {
+        let _state = self.state.lock().unwrap();
+        let mut status_byte: c_char = 0;
+        self.library
+            .check(unsafe { (self.library.ibrsp)(self.device, &mut status_byte) })?;
+        Ok(status_byte as u8)
+    }
+
+    /// Sends the group execute trigger (GET) to the device.
+    pub fn trigger(&self) -> Result<()> {
+        let _state = self.state.lock().unwrap();
+        self.library
+            .check(unsafe { (self.library.ibtrg)(self.device) })
+            .map(|_| ())
+    }
+
+    /// Changes the REN line of the board and the remote or local state of the device.
+    pub fn remote_local(&self, mode: RemoteLocal) -> Result<()> {
+        let _state = self.state.lock().unwrap();
+        let library = self.library;
+        let addresses = [self.address, NOADDR];
+        unsafe {
+            match mode {
+                RemoteLocal::DisableRemote | RemoteLocal::DisableRemoteGoToLocal => {
+                    library.check((library.ibsre)(self.board, 0))?;
+                }
+                RemoteLocal::EnableRemote => {
+                    library.check((library.ibsre)(self.board, 1))?;
+                }
+                RemoteLocal::EnableRemoteGoToRemote => {
+                    (library.EnableRemote)(self.board, addresses.as_ptr());
+                    library.check_last()?;
+                }
+                RemoteLocal::EnableRemoteLocalLockout => {
+                    library.check((library.ibsre)(self.board, 1))?;
+                    (library.SendLLO)(self.board);
+                    library.check_last()?;
+                }
+                RemoteLocal::EnableRemoteGoToRemoteLocalLockout => {
+                    (library.SetRWLS)(self.board, addresses.as_ptr());
+                    library.check_last()?;
+                }
+                RemoteLocal::GoToLocal => {
+                    library.check((library.ibloc)(self.device))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Backend for Gpib {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let _state = self.state.lock().unwrap();
+        self.library.check(unsafe {
+            (self.library.ibwrt)(self.device, buf.as_ptr() as _, buf.len() as _)
+        })?;
+        Ok(self.library.count())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let state = self.state.lock().unwrap();
+        let status = self.library.check(unsafe {
+            (self.library.ibrd)(self.device, buf.as_mut_ptr() as _, buf.len() as _)
+        })?;
+        let count = self.library.count().min(buf.len());
+
+        // END is set for both EOI and the EOS character
+        let status = match status & END != 0 {
+            true if state.io.term_char_enabled
+                && buf[..count].last() == Some(&state.io.term_char) =>
+            {
+                Status::TerminationCharacterRead
+            }
+            true => Status::OperationCompleted,
+            false => Status::BytesReadEqualInput,
+        };
+        Ok((count, status))
+    }
+
+    fn flush(&self, _mode: FlushMode) -> Result<()> {
+        // Transfers aren't buffered on this side of the driver
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let _state = self.state.lock().unwrap();
+        self.library
+            .check(unsafe { (self.library.ibclr)(self.device) })
+            .map(|_| ())
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        Gpib::read_status_byte(self)
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        match protocol {
+            TriggerProtocol::Default => self.trigger(),
+            _ => Err(Error::Visa(VisaError::InvalidProtocol)),
+        }
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => Ok(None),
+            false => Ok(Some(key.unwrap_or("gpib").to_owned())),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        self.state.lock().unwrap().io.get(attribute)
+    }
+
+    /// Settings are applied by the driver, only those actually changed are sent to it.
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        let current = self.state.lock().unwrap().io;
+        let mut io = current;
+        io.set(attribute, value)?;
+
+        if io.timeout != current.timeout {
+            self.set_timeout(io.timeout)?;
+        }
+        if (io.term_char, io.term_char_enabled) != (current.term_char, current.term_char_enabled) {
+            self.set_term_char(io.term_char, io.term_char_enabled)?;
+        }
+        if io.send_end != current.send_end {
+            self.set_send_end(io.send_end)?;
+        }
+        Ok(())
+    }
+
+    fn close(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Ok(());
+        }
+        state.closed = true;
+        self.library
+            .check(unsafe { (self.library.ibonl)(self.device, 0) })
+            .map(|_| ())
+    }
+}
+
+impl Drop for Gpib {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_timeouts_up_to_the_next_code() {
+        assert_eq!(timeout_code(None), 0);
+        assert_eq!(timeout_code(Some(Duration::ZERO)), 1);
+        assert_eq!(timeout_code(Some(Duration::from_micros(10))), 1);
+        assert_eq!(timeout_code(Some(Duration::from_micros(11))), 2);
+        assert_eq!(timeout_code(Some(DEFAULT_TIMEOUT)), 12);
+        assert_eq!(timeout_code(Some(Duration::from_secs(1000))), 17);
+        assert_eq!(timeout_code(Some(Duration::from_secs(5000))), 17);
+    }
+}
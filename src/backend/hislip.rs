@@ -0,0 +1,708 @@
+#[cfg(feature = "test-servers")]
+mod server;
+
+use super::{
+    Backend, DEFAULT_TIMEOUT, IoSettings, RemoteLocal, connect, deadline, io_timeout, map_io_error,
+    open_timeout, remaining, write_chunks,
+};
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    error::{Error, Result, Status, VisaError},
+    event::{Event, EventMechanism, EventType, check_service_request},
+    instrument::{FlushMode, TriggerProtocol},
+    resource_manager::AccessMode,
+};
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+#[cfg(feature = "test-servers")]
+pub use server::*;
+
+/// Default port of HiSLIP servers.
+pub const HISLIP_PORT: u16 = 4880;
+
+pub(crate) const PROTOCOL_VERSION: u16 = 0x0100;
+pub(crate) const VENDOR_ID: [u8; 2] = *b"RV";
+pub(crate) const FIRST_MESSAGE_ID: u32 = 0xFFFF_FF00;
+const PROLOGUE: [u8; 2] = *b"HS";
+const HEADER_LEN: usize = 16;
+/// Largest message this client accepts, announced to the server.
+const MAX_MESSAGE_SIZE: u64 = 1 << 20;
+/// Longest the asynchronous channel is held at a time while waiting for a service request.
+const SERVICE_REQUEST_SLICE: Duration = Duration::from_millis(10);
+
+pub(crate) const INITIALIZE: u8 = 0;
+pub(crate) const INITIALIZE_RESPONSE: u8 = 1;
+pub(crate) const FATAL_ERROR: u8 = 2;
+pub(crate) const ERROR: u8 = 3;
+pub(crate) const ASYNC_LOCK: u8 = 4;
+pub(crate) const ASYNC_LOCK_RESPONSE: u8 = 5;
+pub(crate) const DATA: u8 = 6;
+pub(crate) const DATA_END: u8 = 7;
+pub(crate) const DEVICE_CLEAR_COMPLETE: u8 = 8;
+pub(crate) const DEVICE_CLEAR_ACKNOWLEDGE: u8 = 9;
+pub(crate) const ASYNC_REMOTE_LOCAL_CONTROL: u8 = 10;
+pub(crate) const ASYNC_REMOTE_LOCAL_RESPONSE: u8 = 11;
+pub(crate) const TRIGGER: u8 = 12;
+pub(crate) const INTERRUPTED: u8 = 13;
+pub(crate) const ASYNC_INTERRUPTED: u8 = 14;
+pub(crate) const ASYNC_MAXIMUM_MESSAGE_SIZE: u8 = 15;
+pub(crate) const ASYNC_MAXIMUM_MESSAGE_SIZE_RESPONSE: u8 = 16;
+pub(crate) const ASYNC_INITIALIZE: u8 = 17;
+pub(crate) const ASYNC_INITIALIZE_RESPONSE: u8 = 18;
+pub(crate) const ASYNC_DEVICE_CLEAR: u8 = 19;
+pub(crate) const ASYNC_SERVICE_REQUEST: u8 = 20;
+pub(crate) const ASYNC_STATUS_QUERY: u8 = 21;
+pub(crate) const ASYNC_STATUS_RESPONSE: u8 = 22;
+pub(crate) const ASYNC_DEVICE_CLEAR_ACKNOWLEDGE: u8 = 23;
+
+pub(crate) const LOCK_RELEASE: u8 = 0;
+pub(crate) const LOCK_REQUEST: u8 = 1;
+pub(crate) const LOCK_FAILURE: u8 = 0;
+pub(crate) const LOCK_SUCCESS: u8 = 1;
+pub(crate) const LOCK_SUCCESS_SHARED: u8 = 2;
+#[cfg(feature = "test-servers")]
+pub(crate) const LOCK_ERROR: u8 = 3;
+
+/// Feature bit of the control code selecting overlapped mode.
+pub(crate) const OVERLAPPED: u8 = 0x01;
+/// Control code bit telling the server the response to the previous message was delivered.
+pub(crate) const RMT_DELIVERED: u8 = 0x01;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Message {
+    pub(crate) message_type: u8,
+    pub(crate) control_code: u8,
+    pub(crate) parameter: u32,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl Message {
+    pub(crate) fn new(message_type: u8, control_code: u8, parameter: u32) -> Self {
+        Self {
+            message_type,
+            control_code,
+            parameter,
+            payload: vec![],
+        }
+    }
+
+    pub(crate) fn with_payload(mut self, payload: impl Into<Vec<u8>>) -> Self {
+        self.payload = payload.into();
+        self
+    }
+
+    pub(crate) fn write_to(&self, stream: &mut TcpStream) -> std::io::Result<()> {
+        let mut message = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        message.extend_from_slice(&PROLOGUE);
+        message.push(self.message_type);
+        message.push(self.control_code);
+        message.extend_from_slice(&self.parameter.to_be_bytes());
+        message.extend_from_slice(&(self.payload.len() as u64).to_be_bytes());
+        message.extend_from_slice(&self.payload);
+        stream.write_all(&message)
+    }
+
+    /// Reads a message, refusing payloads larger than `max_size`.
+    pub(crate) fn read_from(stream: &mut TcpStream, max_size: u64) -> Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        stream.read_exact(&mut header).map_err(map_io_error)?;
+        if header[..2] != PROLOGUE {
+            return Err(Error::Visa(VisaError::Io));
+        }
+
+        let len = u64::from_be_bytes(header[8..16].try_into().unwrap());
+        if len > max_size {
+            return Err(Error::Visa(VisaError::Io));
+        }
+        let mut payload = vec![0; len as usize];
+        stream.read_exact(&mut payload).map_err(map_io_error)?;
+
+        Ok(Self {
+            message_type: header[2],
+            control_code: header[3],
+            parameter: u32::from_be_bytes(header[4..8].try_into().unwrap()),
+            payload,
+        })
+    }
+}
+
+/// Writes `message` to one of the channels, a write that fails partway breaks it.
+fn send_message(
+    stream: &mut TcpStream,
+    broken: &mut bool,
+    message: &Message,
+    timeout: Option<Duration>,
+) -> Result<()> {
+    if *broken {
+        return Err(Error::Visa(VisaError::ConnectionLost));
+    }
+    stream.set_write_timeout(io_timeout(timeout))?;
+    message.write_to(stream).map_err(|error| {
+        *broken = true;
+        map_io_error(error)
+    })
+}
+
+/// Reads a message from one of the channels, failing if none starts before `start_deadline` or it
+/// isn't complete by `deadline`.
+///
+/// Nothing is lost when no message starts in time, failing partway through one breaks the
+/// channel since the next message can't be found anymore.
+fn receive_message(
+    stream: &mut TcpStream,
+    broken: &mut bool,
+    start_deadline: Option<Instant>,
+    deadline: Option<Instant>,
+) -> Result<Message> {
+    if *broken {
+        return Err(Error::Visa(VisaError::ConnectionLost));
+    }
+    stream.set_read_timeout(remaining(start_deadline)?)?;
+    match stream.peek(&mut [0]) {
+        Ok(0) => return Err(Error::Visa(VisaError::ConnectionLost)),
+        Ok(_) => {}
+        Err(error) => return Err(map_io_error(error)),
+    }
+
+    let message = remaining(deadline)
+        .and_then(|timeout| Ok(stream.set_read_timeout(timeout)?))
+        .and_then(|()| Message::read_from(stream, MAX_MESSAGE_SIZE));
+    if message.is_err() {
+        *broken = true;
+    }
+    message
+}
+
+/// Maps `Error` and `FatalError` messages, anything else is a protocol violation.
+fn unexpected(message: Message) -> Error {
+    match message.message_type {
+        FATAL_ERROR => {
+            debug!(
+                "fatal error {}: {}",
+                message.control_code,
+                String::from_utf8_lossy(&message.payload)
+            );
+            Error::Visa(VisaError::ConnectionLost)
+        }
+        ERROR => {
+            debug!(
+                "error {}: {}",
+                message.control_code,
+                String::from_utf8_lossy(&message.payload)
+            );
+            Error::Visa(VisaError::Io)
+        }
+        _ => Error::Visa(VisaError::Io),
+    }
+}
+
+#[derive(Debug)]
+struct Synchronous {
+    stream: TcpStream,
+    message_id: u32,
+    /// Message ID of the last `DataEnd` sent, responses carry it back.
+    last_end_id: u32,
+    rmt_delivered: bool,
+    /// Data received but not yet read.
+    buffer: VecDeque<u8>,
+    /// Whether the buffer holds the end of a response.
+    end: bool,
+    /// Whether a message was only partly sent or received, leaving the channel out of step.
+    broken: bool,
+    io: IoSettings,
+}
+
+impl Synchronous {
+    fn send(&mut self, message_type: u8, payload: &[u8]) -> Result<()> {
+        let control_code = match std::mem::take(&mut self.rmt_delivered) {
+            true => RMT_DELIVERED,
+            false => 0,
+        };
+        if message_type == DATA_END {
+            self.last_end_id = self.message_id;
+        }
+        let message =
+            Message::new(message_type, control_code, self.message_id).with_payload(payload);
+        self.message_id = self.message_id.wrapping_add(2);
+
+        let timeout = self.io.timeout;
+        send_message(&mut self.stream, &mut self.broken, &message, timeout)
+    }
+
+    fn receive(&mut self, deadline: Option<Instant>) -> Result<Message> {
+        receive_message(&mut self.stream, &mut self.broken, deadline, deadline)
+    }
+}
+
+#[derive(Debug)]
+struct Asynchronous {
+    stream: TcpStream,
+    /// Service requests received while waiting for other responses.
+    service_requests: VecDeque<u8>,
+    /// Whether service requests are queued as events.
+    service_requests_enabled: bool,
+    /// Whether a message was only partly sent or received, leaving the channel out of step.
+    broken: bool,
+}
+
+impl Asynchronous {
+    fn request(
+        &mut self,
+        message: Message,
+        response_type: u8,
+        timeout: Option<Duration>,
+    ) -> Result<Message> {
+        send_message(&mut self.stream, &mut self.broken, &message, timeout)?;
+
+        let deadline = deadline(timeout);
+        loop {
+            let message = self.receive(deadline)?;
+            match message.message_type {
+                message_type if message_type == response_type => return Ok(message),
+                ASYNC_SERVICE_REQUEST => self.service_requests.push_back(message.control_code),
+                ASYNC_INTERRUPTED => continue,
+                _ => return Err(unexpected(message)),
+            }
+        }
+    }
+
+    fn receive(&mut self, deadline: Option<Instant>) -> Result<Message> {
+        receive_message(&mut self.stream, &mut self.broken, deadline, deadline)
+    }
+}
+
+/// Native HiSLIP client for `TCPIP::host::hislip0[,port]::INSTR` resources.
+#[derive(Debug)]
+pub struct HiSlip {
+    synchronous: Mutex<Synchronous>,
+    asynchronous: Mutex<Asynchronous>,
+    session_id: u16,
+    server_version: u16,
+    /// Largest message the server accepts.
+    max_message_size: u64,
+    overlapped: AtomicBool,
+    closed: AtomicBool,
+}
+
+impl HiSlip {
+    /// Connects to `sub_address` (for example `hislip0`) of the server at `host` on `port`.
+    pub fn connect(host: &str, port: u16, sub_address: &str, timeout: Duration) -> Result<Self> {
+        let timeout = open_timeout(timeout);
+
+        let mut stream = connect(host, port, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        let parameter = (PROTOCOL_VERSION as u32) << 16 | u16::from_be_bytes(VENDOR_ID) as u32;
+        Message::new(INITIALIZE, 0, parameter)
+            .with_payload(sub_address.as_bytes())
+            .write_to(&mut stream)?;
+        let response = Message::read_from(&mut stream, MAX_MESSAGE_SIZE)?;
+        if response.message_type != INITIALIZE_RESPONSE {
+            return Err(unexpected(response));
+        }
+        let overlapped = response.control_code & OVERLAPPED != 0;
+        let server_version = (response.parameter >> 16) as u16;
+        let session_id = response.parameter as u16;
+
+        let mut asynchronous = Asynchronous {
+            stream: connect(host, port, timeout)?,
+            service_requests: VecDeque::new(),
+            service_requests_enabled: false,
+            broken: false,
+        };
+        asynchronous.request(
+            Message::new(ASYNC_INITIALIZE, 0, session_id as u32),
+            ASYNC_INITIALIZE_RESPONSE,
+            Some(timeout),
+        )?;
+        let response = asynchronous.request(
+            Message::new(ASYNC_MAXIMUM_MESSAGE_SIZE, 0, 0)
+                .with_payload(MAX_MESSAGE_SIZE.to_be_bytes()),
+            ASYNC_MAXIMUM_MESSAGE_SIZE_RESPONSE,
+            Some(timeout),
+        )?;
+        let max_message_size = response
+            .payload
+            .try_into()
+            .map(u64::from_be_bytes)
+            .map_err(|_| Error::Visa(VisaError::Io))?;
+        debug!(
+            "opened HiSLIP session {} to {} on {}, overlapped: {}",
+            session_id, sub_address, host, overlapped
+        );
+
+        Ok(Self {
+            synchronous: Mutex::new(Synchronous {
+                stream,
+                message_id: FIRST_MESSAGE_ID,
+                last_end_id: FIRST_MESSAGE_ID,
+                rmt_delivered: false,
+                buffer: VecDeque::new(),
+                end: false,
+                broken: false,
+                io: IoSettings::new(DEFAULT_TIMEOUT),
+            }),
+            asynchronous: Mutex::new(asynchronous),
+            session_id,
+            server_version,
+            max_message_size: max_message_size.max(HEADER_LEN as u64 + 1),
+            overlapped: AtomicBool::new(overlapped),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn session_id(&self) -> u16 {
+        self.session_id
+    }
+
+    /// Protocol version reported by the server, major version in the high byte.
+    pub fn server_version(&self) -> u16 {
+        self.server_version
+    }
+
+    /// Whether the session runs in overlapped mode rather than synchronized mode.
+    pub fn is_overlapped(&self) -> bool {
+        self.overlapped.load(Ordering::Acquire)
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.synchronous.lock().unwrap().io.timeout
+    }
+
+    /// Sets the I/O timeout, `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.synchronous.lock().unwrap().io.timeout = timeout;
+    }
+
+    /// Clears the device, requesting overlapped or synchronized mode for the rest of the session.
+    pub fn clear_with_mode(&self, overlapped: bool) -> Result<()> {
+        let timeout = self.timeout();
+        let mut synchronous = self.synchronous.lock().unwrap();
+        self.asynchronous.lock().unwrap().request(
+            Message::new(ASYNC_DEVICE_CLEAR, 0, 0),
+            ASYNC_DEVICE_CLEAR_ACKNOWLEDGE,
+            timeout,
+        )?;
+
+        let feature = match overlapped {
+            true => OVERLAPPED,
+            false => 0,
+        };
+        let synchronous = &mut *synchronous;
+        send_message(
+            &mut synchronous.stream,
+            &mut synchronous.broken,
+            &Message::new(DEVICE_CLEAR_COMPLETE, feature, 0),
+            timeout,
+        )?;
+
+        // Anything still in flight on the synchronous channel belongs to the cleared operations
+        let deadline = deadline(timeout);
+        let acknowledge = loop {
+            let message = synchronous.receive(deadline)?;
+            match message.message_type {
+                DEVICE_CLEAR_ACKNOWLEDGE => break message,
+                DATA | DATA_END | INTERRUPTED => continue,
+                _ => return Err(unexpected(message)),
+            }
+        };
+
+        synchronous.message_id = FIRST_MESSAGE_ID;
+        synchronous.last_end_id = FIRST_MESSAGE_ID;
+        synchronous.rmt_delivered = false;
+        synchronous.buffer.clear();
+        synchronous.end = false;
+        self.overlapped.store(
+            acknowledge.control_code & OVERLAPPED != 0,
+            Ordering::Release,
+        );
+        Ok(())
+    }
+
+    /// Sends a trigger message, the equivalent of a GPIB group execute trigger.
+    pub fn trigger(&self) -> Result<()> {
+        self.synchronous.lock().unwrap().send(TRIGGER, &[])
+    }
+
+    /// Reads the status byte through the asynchronous channel.
+    pub fn read_status_byte(&self) -> Result<u8> {
+        let mut synchronous = self.synchronous.lock().unwrap();
+        let control_code = match std::mem::take(&mut synchronous.rmt_delivered) {
+            true => RMT_DELIVERED,
+            false => 0,
+        };
+        let message_id = synchronous.message_id.wrapping_sub(2);
+        let timeout = synchronous.io.timeout;
+        drop(synchronous);
+
+        let response = self.asynchronous.lock().unwrap().request(
+            Message::new(ASYNC_STATUS_QUERY, control_code, message_id),
+            ASYNC_STATUS_RESPONSE,
+            timeout,
+        )?;
+        Ok(response.control_code)
+    }
+
+    /// Sends a remote/local control request.
+    pub fn remote_local(&self, request: RemoteLocal) -> Result<()> {
+        let synchronous = self.synchronous.lock().unwrap();
+        let message_id = synchronous.message_id.wrapping_sub(2);
+        let timeout = synchronous.io.timeout;
+        drop(synchronous);
+
+        let response = self.asynchronous.lock().unwrap().request(
+            Message::new(ASYNC_REMOTE_LOCAL_CONTROL, request as u8, message_id),
+            ASYNC_REMOTE_LOCAL_RESPONSE,
+            timeout,
+        );
+        response.map(|_| ())
+    }
+
+    /// Waits for a service request from the device, returning the status byte that came with it.
+    ///
+    /// `None` waits forever. The asynchronous channel is only held for short slices of the wait,
+    /// so other operations can still use it.
+    pub fn wait_for_service_request(&self, timeout: Option<Duration>) -> Result<u8> {
+        let deadline = deadline(timeout);
+        loop {
+            let mut asynchronous = self.asynchronous.lock().unwrap();
+            if let Some(status_byte) = asynchronous.service_requests.pop_front() {
+                return Ok(status_byte);
+            }
+
+            let slice = Instant::now() + SERVICE_REQUEST_SLICE;
+            let start_deadline = deadline.map_or(slice, |deadline| deadline.min(slice));
+            let asynchronous = &mut *asynchronous;
+            let message = match receive_message(
+                &mut asynchronous.stream,
+                &mut asynchronous.broken,
+                Some(start_deadline),
+                deadline,
+            ) {
+                Ok(message) => message,
+                Err(Error::Visa(VisaError::Timeout))
+                    if deadline.is_none_or(|deadline| Instant::now() < deadline) =>
+                {
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            match message.message_type {
+                ASYNC_SERVICE_REQUEST => return Ok(message.control_code),
+                ASYNC_INTERRUPTED => continue,
+                _ => return Err(unexpected(message)),
+            }
+        }
+    }
+}
+
+impl Backend for HiSlip {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut synchronous = self.synchronous.lock().unwrap();
+        let chunk_size = (self.max_message_size as usize)
+            .saturating_sub(HEADER_LEN)
+            .max(1);
+
+        write_chunks(buf, chunk_size, |chunk, last| {
+            let message_type = match last && synchronous.io.send_end {
+                true => DATA_END,
+                false => DATA,
+            };
+            synchronous.send(message_type, chunk)?;
+            Ok(chunk.len())
+        })
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut synchronous = self.synchronous.lock().unwrap();
+        let deadline = deadline(synchronous.io.timeout);
+        let overlapped = self.is_overlapped();
+        let mut count = 0;
+
+        loop {
+            while count < buf.len() {
+                let Some(byte) = synchronous.buffer.pop_front() else {
+                    break;
+                };
+                buf[count] = byte;
+                count += 1;
+                if synchronous.io.term_char_enabled && byte == synchronous.io.term_char {
+                    if synchronous.buffer.is_empty() && synchronous.end {
+                        synchronous.end = false;
+                        synchronous.rmt_delivered = true;
+                    }
+                    return Ok((count, Status::TerminationCharacterRead));
+                }
+            }
+
+            if synchronous.buffer.is_empty() && synchronous.end {
+                synchronous.end = false;
+                synchronous.rmt_delivered = true;
+                return Ok((count, Status::OperationCompleted));
+            } else if count == buf.len() {
+                return Ok((count, Status::BytesReadEqualInput));
+            }
+
+            let message = synchronous.receive(deadline)?;
+            match message.message_type {
+                // In synchronized mode only the response to the latest query is wanted
+                DATA | DATA_END if !overlapped && message.parameter != synchronous.last_end_id => {
+                    continue;
+                }
+                DATA => synchronous.buffer.extend(message.payload),
+                DATA_END => {
+                    synchronous.buffer.extend(message.payload);
+                    synchronous.end = true;
+                }
+                INTERRUPTED => {
+                    synchronous.buffer.clear();
+                    count = 0;
+                }
+                _ => return Err(unexpected(message)),
+            }
+        }
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        if mode.intersects(FlushMode::READ_BUF_DISCARD | FlushMode::IO_IN_BUF_DISCARD) {
+            let mut synchronous = self.synchronous.lock().unwrap();
+            synchronous.buffer.clear();
+            synchronous.end = false;
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.clear_with_mode(self.is_overlapped())
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        HiSlip::read_status_byte(self)
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        match protocol {
+            TriggerProtocol::Default => self.trigger(),
+            _ => Err(Error::Visa(VisaError::InvalidProtocol)),
+        }
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        // An empty lock string requests an exclusive lock, any other string a shared one
+        let key = match mode.contains(AccessMode::SHARED_LOCK) {
+            true => Some(key.filter(|key| !key.is_empty()).unwrap_or("visa")),
+            false => None,
+        };
+        let lock_timeout = timeout.as_millis().min(u32::MAX as _) as u32;
+        let io_timeout = self.timeout().map(|io_timeout| io_timeout.max(timeout));
+
+        let response = self.asynchronous.lock().unwrap().request(
+            Message::new(ASYNC_LOCK, LOCK_REQUEST, lock_timeout)
+                .with_payload(key.unwrap_or_default().as_bytes()),
+            ASYNC_LOCK_RESPONSE,
+            io_timeout,
+        )?;
+        match response.control_code {
+            LOCK_SUCCESS => Ok(key.map(str::to_owned)),
+            LOCK_FAILURE => Err(Error::Visa(VisaError::ResourceLocked)),
+            _ => Err(Error::Visa(VisaError::InvalidAccessKey)),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        let synchronous = self.synchronous.lock().unwrap();
+        let message_id = synchronous.message_id.wrapping_sub(2);
+        let timeout = synchronous.io.timeout;
+        drop(synchronous);
+
+        let response = self.asynchronous.lock().unwrap().request(
+            Message::new(ASYNC_LOCK, LOCK_RELEASE, message_id),
+            ASYNC_LOCK_RESPONSE,
+            timeout,
+        )?;
+        match response.control_code {
+            LOCK_SUCCESS | LOCK_SUCCESS_SHARED => Ok(()),
+            _ => Err(Error::Visa(VisaError::SessionNotLocked)),
+        }
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        self.synchronous.lock().unwrap().io.get(attribute)
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        self.synchronous.lock().unwrap().io.set(attribute, value)
+    }
+
+    fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        if event_type == EventType::AllEnabled {
+            return Err(Error::Visa(VisaError::InvalidEvent));
+        }
+        check_service_request(event_type, mechanism)?;
+        let mut asynchronous = self.asynchronous.lock().unwrap();
+        // Only requests received from now on are delivered, as with VISA
+        asynchronous.service_requests.clear();
+        asynchronous.service_requests_enabled = true;
+        Ok(())
+    }
+
+    fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        check_service_request(event_type, mechanism)?;
+        self.asynchronous.lock().unwrap().service_requests_enabled = false;
+        Ok(())
+    }
+
+    fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        check_service_request(event_type, mechanism)?;
+        self.asynchronous.lock().unwrap().service_requests.clear();
+        Ok(())
+    }
+
+    fn wait_on_event(&self, event_type: EventType, timeout: Option<Duration>) -> Result<Event> {
+        check_service_request(event_type, EventMechanism::QUEUE)?;
+        if !self.asynchronous.lock().unwrap().service_requests_enabled {
+            return Err(Error::Visa(VisaError::SessionNotEnabled));
+        }
+        self.wait_for_service_request(timeout)?;
+        Ok(Event::new(EventType::ServiceRequest))
+    }
+
+    fn close(&self) -> Result<()> {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let _ = self
+            .synchronous
+            .lock()
+            .unwrap()
+            .stream
+            .shutdown(Shutdown::Both);
+        let _ = self
+            .asynchronous
+            .lock()
+            .unwrap()
+            .stream
+            .shutdown(Shutdown::Both);
+        Ok(())
+    }
+}
+
+impl Drop for HiSlip {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
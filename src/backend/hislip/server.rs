@@ -0,0 +1,399 @@
+use super::{
+    ASYNC_DEVICE_CLEAR, ASYNC_DEVICE_CLEAR_ACKNOWLEDGE, ASYNC_INITIALIZE,
+    ASYNC_INITIALIZE_RESPONSE, ASYNC_LOCK, ASYNC_LOCK_RESPONSE, ASYNC_MAXIMUM_MESSAGE_SIZE,
+    ASYNC_MAXIMUM_MESSAGE_SIZE_RESPONSE, ASYNC_REMOTE_LOCAL_CONTROL, ASYNC_REMOTE_LOCAL_RESPONSE,
+    ASYNC_SERVICE_REQUEST, ASYNC_STATUS_QUERY, ASYNC_STATUS_RESPONSE, DATA, DATA_END,
+    DEVICE_CLEAR_ACKNOWLEDGE, DEVICE_CLEAR_COMPLETE, ERROR, FATAL_ERROR, INITIALIZE,
+    INITIALIZE_RESPONSE, LOCK_ERROR, LOCK_FAILURE, LOCK_RELEASE, LOCK_SUCCESS, LOCK_SUCCESS_SHARED,
+    Message, OVERLAPPED, PROTOCOL_VERSION, RMT_DELIVERED, TRIGGER, VENDOR_ID,
+};
+use crate::error::Result;
+use std::{
+    collections::{HashMap, HashSet},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Largest message accepted, header included.
+const MAX_MESSAGE_SIZE: u64 = 4096;
+/// Status byte bit set while a response has not been delivered.
+const MESSAGE_AVAILABLE: u8 = 0x10;
+const ERROR_UNRECOGNIZED_MESSAGE_TYPE: u8 = 1;
+const FATAL_ERROR_INVALID_INITIALIZATION: u8 = 3;
+
+type Handler = Box<dyn FnMut(&[u8]) -> Vec<u8> + Send>;
+
+#[derive(Default)]
+struct Connection {
+    /// Asynchronous channel, once the client established it.
+    asynchronous: Option<Arc<Mutex<TcpStream>>>,
+    /// Whether a response was sent that the client did not confirm with RMT-delivered yet.
+    response_pending: bool,
+}
+
+struct Device {
+    handler: Handler,
+    sessions: HashMap<u16, Connection>,
+    next_session_id: u16,
+    exclusive_lock: Option<u16>,
+    /// Key of the shared lock and the sessions holding it.
+    shared_lock: Option<(Vec<u8>, HashSet<u16>)>,
+    overlapped: bool,
+    status_byte: u8,
+    triggers: usize,
+    clears: usize,
+}
+
+impl Device {
+    fn can_lock(&self, session_id: u16, key: &[u8]) -> bool {
+        let exclusive = self.exclusive_lock.is_none_or(|owner| owner == session_id);
+        let shared = match &self.shared_lock {
+            None => true,
+            Some((shared_key, owners)) => match key.is_empty() {
+                true => owners.iter().all(|owner| *owner == session_id),
+                false => shared_key == key,
+            },
+        };
+        exclusive && shared
+    }
+
+    fn release_locks(&mut self, session_id: u16) -> u8 {
+        let mut released = LOCK_ERROR;
+        if let Some((_, owners)) = &mut self.shared_lock
+            && owners.remove(&session_id)
+        {
+            released = LOCK_SUCCESS_SHARED;
+            if owners.is_empty() {
+                self.shared_lock = None;
+            }
+        }
+        if self.exclusive_lock == Some(session_id) {
+            self.exclusive_lock = None;
+            released = LOCK_SUCCESS;
+        }
+        released
+    }
+}
+
+struct Shared {
+    device: Mutex<Device>,
+    changed: Condvar,
+}
+
+/// In-process HiSLIP server stand-in, for exercising clients without an instrument.
+///
+/// Every message the client ends with `DataEnd` is passed to the handler, whose
+/// non-empty return value is sent back as the response. Locks are arbitrated between
+/// sessions but not enforced on data transfers.
+///
+/// ```no_run
+/// # use visa::*;
+/// # use std::time::Duration;
+/// let server = HiSlipServer::bind("127.0.0.1:0", |message| match message {
+///     b"*IDN?\n" => b"ACME,Model 1,1234,1.0\n".to_vec(),
+///     _ => vec![],
+/// })
+/// .unwrap();
+///
+/// let hislip = HiSlip::connect("127.0.0.1", server.port(), "hislip0", Duration::ZERO).unwrap();
+/// let instrument = Instrument::new(hislip.into()).unwrap();
+/// ```
+pub struct HiSlipServer {
+    shared: Arc<Shared>,
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for HiSlipServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HiSlipServer")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HiSlipServer {
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        handler: impl FnMut(&[u8]) -> Vec<u8> + Send + 'static,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            device: Mutex::new(Device {
+                handler: Box::new(handler),
+                sessions: HashMap::new(),
+                next_session_id: 1,
+                exclusive_lock: None,
+                shared_lock: None,
+                overlapped: false,
+                status_byte: 0,
+                triggers: 0,
+                clears: 0,
+            }),
+            changed: Condvar::new(),
+        });
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        {
+            let shared = shared.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::Acquire) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let _ = stream.set_nodelay(true);
+                        let shared = shared.clone();
+                        thread::spawn(move || serve(stream, &shared));
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            shared,
+            address,
+            stopped,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// Sets whether new sessions start in overlapped mode, the default is synchronized mode.
+    pub fn set_overlapped(&self, overlapped: bool) {
+        self.shared.device.lock().unwrap().overlapped = overlapped;
+    }
+
+    /// Sets the status byte returned by status queries, the MAV bit is managed by the server.
+    pub fn set_status_byte(&self, status_byte: u8) {
+        self.shared.device.lock().unwrap().status_byte = status_byte;
+    }
+
+    /// Sends a service request with `status_byte` to every connected session.
+    pub fn request_service(&self, status_byte: u8) {
+        let device = self.shared.device.lock().unwrap();
+        for connection in device.sessions.values() {
+            if let Some(stream) = &connection.asynchronous {
+                let _ = Message::new(ASYNC_SERVICE_REQUEST, status_byte, 0)
+                    .write_to(&mut stream.lock().unwrap());
+            }
+        }
+    }
+
+    /// Number of trigger messages received.
+    pub fn triggers(&self) -> usize {
+        self.shared.device.lock().unwrap().triggers
+    }
+
+    /// Number of device clears received.
+    pub fn clears(&self) -> usize {
+        self.shared.device.lock().unwrap().clears
+    }
+}
+
+impl Drop for HiSlipServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        // Wake up the accept loop so it notices it has to stop
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+/// Serves a connection, which the first message makes either a synchronous or an asynchronous channel.
+fn serve(mut stream: TcpStream, shared: &Shared) {
+    let Ok(message) = Message::read_from(&mut stream, MAX_MESSAGE_SIZE) else {
+        return;
+    };
+    match message.message_type {
+        INITIALIZE => serve_synchronous(stream, shared),
+        ASYNC_INITIALIZE => serve_asynchronous(stream, shared, message.parameter as u16),
+        _ => {
+            let _ = Message::new(FATAL_ERROR, FATAL_ERROR_INVALID_INITIALIZATION, 0)
+                .write_to(&mut stream);
+        }
+    }
+}
+
+fn serve_synchronous(mut stream: TcpStream, shared: &Shared) {
+    let mut device = shared.device.lock().unwrap();
+    let session_id = device.next_session_id;
+    device.next_session_id = device.next_session_id.wrapping_add(1).max(1);
+    device.sessions.insert(session_id, Connection::default());
+    let overlapped = device.overlapped;
+    drop(device);
+
+    let control_code = match overlapped {
+        true => OVERLAPPED,
+        false => 0,
+    };
+    let parameter = (PROTOCOL_VERSION as u32) << 16 | session_id as u32;
+    let mut result =
+        Message::new(INITIALIZE_RESPONSE, control_code, parameter).write_to(&mut stream);
+
+    let mut input = Vec::new();
+    while result.is_ok() {
+        let Ok(message) = Message::read_from(&mut stream, MAX_MESSAGE_SIZE) else {
+            break;
+        };
+        let mut device = shared.device.lock().unwrap();
+        if matches!(message.message_type, DATA | DATA_END | TRIGGER)
+            && message.control_code & RMT_DELIVERED != 0
+            && let Some(connection) = device.sessions.get_mut(&session_id)
+        {
+            connection.response_pending = false;
+        }
+
+        result = match message.message_type {
+            DATA => {
+                input.extend_from_slice(&message.payload);
+                Ok(())
+            }
+            DATA_END => {
+                input.extend_from_slice(&message.payload);
+                let response = (device.handler)(&std::mem::take(&mut input));
+                match response.is_empty() {
+                    true => Ok(()),
+                    false => {
+                        if let Some(connection) = device.sessions.get_mut(&session_id) {
+                            connection.response_pending = true;
+                        }
+                        Message::new(DATA_END, 0, message.parameter)
+                            .with_payload(response)
+                            .write_to(&mut stream)
+                    }
+                }
+            }
+            TRIGGER => {
+                device.triggers += 1;
+                Ok(())
+            }
+            DEVICE_CLEAR_COMPLETE => {
+                input.clear();
+                if let Some(connection) = device.sessions.get_mut(&session_id) {
+                    connection.response_pending = false;
+                }
+                Message::new(
+                    DEVICE_CLEAR_ACKNOWLEDGE,
+                    message.control_code & OVERLAPPED,
+                    0,
+                )
+                .write_to(&mut stream)
+            }
+            _ => Message::new(ERROR, ERROR_UNRECOGNIZED_MESSAGE_TYPE, 0).write_to(&mut stream),
+        };
+    }
+
+    // The session ends with its synchronous channel
+    let mut device = shared.device.lock().unwrap();
+    device.release_locks(session_id);
+    if let Some(Connection {
+        asynchronous: Some(stream),
+        ..
+    }) = device.sessions.remove(&session_id)
+    {
+        let _ = stream.lock().unwrap().shutdown(std::net::Shutdown::Both);
+    }
+    shared.changed.notify_all();
+}
+
+fn serve_asynchronous(stream: TcpStream, shared: &Shared, session_id: u16) {
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut reader = reader;
+    let stream = Arc::new(Mutex::new(stream));
+
+    let mut device = shared.device.lock().unwrap();
+    let Some(connection) = device.sessions.get_mut(&session_id) else {
+        let _ = Message::new(FATAL_ERROR, FATAL_ERROR_INVALID_INITIALIZATION, 0)
+            .write_to(&mut stream.lock().unwrap());
+        return;
+    };
+    connection.asynchronous = Some(stream.clone());
+    drop(device);
+
+    let parameter = u16::from_be_bytes(VENDOR_ID) as u32;
+    let mut result =
+        Message::new(ASYNC_INITIALIZE_RESPONSE, 0, parameter).write_to(&mut stream.lock().unwrap());
+
+    while result.is_ok() {
+        let Ok(message) = Message::read_from(&mut reader, MAX_MESSAGE_SIZE) else {
+            break;
+        };
+        let response = match message.message_type {
+            ASYNC_MAXIMUM_MESSAGE_SIZE => Message::new(ASYNC_MAXIMUM_MESSAGE_SIZE_RESPONSE, 0, 0)
+                .with_payload(MAX_MESSAGE_SIZE.to_be_bytes()),
+            ASYNC_LOCK => Message::new(ASYNC_LOCK_RESPONSE, lock(shared, session_id, &message), 0),
+            ASYNC_REMOTE_LOCAL_CONTROL => Message::new(ASYNC_REMOTE_LOCAL_RESPONSE, 0, 0),
+            ASYNC_STATUS_QUERY => {
+                let mut device = shared.device.lock().unwrap();
+                let mut status_byte = device.status_byte;
+                if let Some(connection) = device.sessions.get_mut(&session_id) {
+                    if message.control_code & RMT_DELIVERED != 0 {
+                        connection.response_pending = false;
+                    }
+                    if connection.response_pending {
+                        status_byte |= MESSAGE_AVAILABLE;
+                    }
+                }
+                Message::new(ASYNC_STATUS_RESPONSE, status_byte, 0)
+            }
+            ASYNC_DEVICE_CLEAR => {
+                let mut device = shared.device.lock().unwrap();
+                device.clears += 1;
+                let feature = match device.overlapped {
+                    true => OVERLAPPED,
+                    false => 0,
+                };
+                Message::new(ASYNC_DEVICE_CLEAR_ACKNOWLEDGE, feature, 0)
+            }
+            _ => Message::new(ERROR, ERROR_UNRECOGNIZED_MESSAGE_TYPE, 0),
+        };
+        result = response.write_to(&mut stream.lock().unwrap());
+    }
+}
+
+/// Handles an `AsyncLock` request or release, returning the response control code.
+fn lock(shared: &Shared, session_id: u16, message: &Message) -> u8 {
+    let mut device = shared.device.lock().unwrap();
+    if message.control_code == LOCK_RELEASE {
+        let released = device.release_locks(session_id);
+        shared.changed.notify_all();
+        return released;
+    }
+
+    let key = &message.payload;
+    let deadline = Instant::now() + Duration::from_millis(message.parameter as _);
+    while !device.can_lock(session_id, key) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return LOCK_FAILURE;
+        }
+        device = shared.changed.wait_timeout(device, remaining).unwrap().0;
+    }
+
+    match key.is_empty() {
+        true => device.exclusive_lock = Some(session_id),
+        false => {
+            device
+                .shared_lock
+                .get_or_insert_with(|| (key.clone(), HashSet::new()))
+                .1
+                .insert(session_id);
+        }
+    }
+    LOCK_SUCCESS
+}
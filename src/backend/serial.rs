@@ -0,0 +1,623 @@
+use super::{Backend, DEFAULT_TIMEOUT, IoSettings, deadline, remaining};
+use crate::{
+    attribute::{
+        AsrlBaud, AsrlDataBits, AsrlEndIn, AsrlEndOut, AsrlFlowControl, AsrlParity, AsrlStopBits,
+        AsrlXoffChar, AsrlXonChar, Attribute, AttributeKind, AttributeValue, WritableAttribute,
+    },
+    bindings::*,
+    error::{Error, Result, Status, VisaError},
+    instrument::FlushMode,
+    resource_manager::AccessMode,
+};
+use bitflags::bitflags;
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::fs::OpenOptionsExt,
+    },
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+const CHUNK_SIZE: usize = 4096;
+
+/// `VI_ATTR_ASRL_PARITY`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum Parity {
+    #[default]
+    None = VI_ASRL_PAR_NONE,
+    Odd = VI_ASRL_PAR_ODD,
+    Even = VI_ASRL_PAR_EVEN,
+    Mark = VI_ASRL_PAR_MARK,
+    Space = VI_ASRL_PAR_SPACE,
+}
+
+/// `VI_ATTR_ASRL_STOP_BITS`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum StopBits {
+    #[default]
+    One = VI_ASRL_STOP_ONE,
+    OneAndHalf = VI_ASRL_STOP_ONE5,
+    Two = VI_ASRL_STOP_TWO,
+}
+
+impl TryFrom<u32> for Parity {
+    type Error = VisaError;
+
+    fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
+        match value {
+            VI_ASRL_PAR_NONE => Ok(Self::None),
+            VI_ASRL_PAR_ODD => Ok(Self::Odd),
+            VI_ASRL_PAR_EVEN => Ok(Self::Even),
+            VI_ASRL_PAR_MARK => Ok(Self::Mark),
+            VI_ASRL_PAR_SPACE => Ok(Self::Space),
+            _ => Err(VisaError::AttributeStateNotSupported),
+        }
+    }
+}
+
+impl TryFrom<u32> for StopBits {
+    type Error = VisaError;
+
+    fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
+        match value {
+            VI_ASRL_STOP_ONE => Ok(Self::One),
+            VI_ASRL_STOP_ONE5 => Ok(Self::OneAndHalf),
+            VI_ASRL_STOP_TWO => Ok(Self::Two),
+            _ => Err(VisaError::AttributeStateNotSupported),
+        }
+    }
+}
+
+bitflags! {
+    /// `VI_ATTR_ASRL_FLOW_CNTRL`
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct FlowControl: u32 {
+        const NONE = VI_ASRL_FLOW_NONE;
+        const XON_XOFF = VI_ASRL_FLOW_XON_XOFF;
+        const RTS_CTS = VI_ASRL_FLOW_RTS_CTS;
+        const DTR_DSR = VI_ASRL_FLOW_DTR_DSR;
+    }
+}
+
+/// How the end of a message is signalled on a serial line, `VI_ATTR_ASRL_END_IN` and `VI_ATTR_ASRL_END_OUT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum SerialEnd {
+    None = VI_ASRL_END_NONE,
+    /// The highest data bit marks the last character.
+    LastBit = VI_ASRL_END_LAST_BIT,
+    TermChar = VI_ASRL_END_TERMCHAR,
+    /// A break follows the message, only valid for output.
+    Break = VI_ASRL_END_BREAK,
+}
+
+impl TryFrom<u32> for SerialEnd {
+    type Error = VisaError;
+
+    fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
+        match value {
+            VI_ASRL_END_NONE => Ok(Self::None),
+            VI_ASRL_END_LAST_BIT => Ok(Self::LastBit),
+            VI_ASRL_END_TERMCHAR => Ok(Self::TermChar),
+            VI_ASRL_END_BREAK => Ok(Self::Break),
+            _ => Err(VisaError::AttributeStateNotSupported),
+        }
+    }
+}
+
+/// Line settings of a serial port, with the same defaults as VISA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SerialSettings {
+    /// `VI_ATTR_ASRL_BAUD`
+    pub baud_rate: u32,
+    /// `VI_ATTR_ASRL_DATA_BITS`, from 5 to 8.
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+    pub end_in: SerialEnd,
+    pub end_out: SerialEnd,
+    /// `VI_ATTR_ASRL_XON_CHAR`
+    pub xon_char: u8,
+    /// `VI_ATTR_ASRL_XOFF_CHAR`
+    pub xoff_char: u8,
+}
+
+impl Default for SerialSettings {
+    fn default() -> Self {
+        Self {
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::NONE,
+            end_in: SerialEnd::TermChar,
+            end_out: SerialEnd::None,
+            xon_char: 0x11,
+            xoff_char: 0x13,
+        }
+    }
+}
+
+/// The device a `ASRL[board]::INSTR` resource refers to, numbered ports start at `/dev/ttyS0` for `ASRL1`.
+pub(crate) fn port_path(port: &str) -> Result<PathBuf> {
+    match port.parse::<u16>() {
+        Ok(0) => Err(Error::Visa(VisaError::InvalidResourceName)),
+        Ok(number) => Ok(PathBuf::from(format!("/dev/ttyS{}", number - 1))),
+        Err(_) => Ok(PathBuf::from(port)),
+    }
+}
+
+/// Lists the serial ports backed by a device.
+#[cfg(target_os = "linux")]
+pub(crate) fn find_ports() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/tty") else {
+        return vec![];
+    };
+
+    let mut ports: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().join("device").exists())
+        // Legacy serial ports are always registered, unknown ones have no UART behind them
+        .filter(|entry| {
+            std::fs::read_to_string(entry.path().join("type"))
+                .is_ok_and(|port_type| port_type.trim() != "0")
+                || !entry.file_name().to_string_lossy().starts_with("ttyS")
+        })
+        .map(|entry| format!("ASRL/dev/{}::INSTR", entry.file_name().to_string_lossy()))
+        .collect();
+    ports.sort();
+    ports
+}
+
+fn speed(baud_rate: u32) -> Result<libc::speed_t> {
+    #[cfg(target_os = "linux")]
+    let speed = match baud_rate {
+        50 => libc::B50,
+        75 => libc::B75,
+        110 => libc::B110,
+        134 => libc::B134,
+        150 => libc::B150,
+        200 => libc::B200,
+        300 => libc::B300,
+        600 => libc::B600,
+        1200 => libc::B1200,
+        1800 => libc::B1800,
+        2400 => libc::B2400,
+        4800 => libc::B4800,
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        230400 => libc::B230400,
+        460800 => libc::B460800,
+        500000 => libc::B500000,
+        576000 => libc::B576000,
+        921600 => libc::B921600,
+        1000000 => libc::B1000000,
+        1152000 => libc::B1152000,
+        1500000 => libc::B1500000,
+        2000000 => libc::B2000000,
+        2500000 => libc::B2500000,
+        3000000 => libc::B3000000,
+        3500000 => libc::B3500000,
+        4000000 => libc::B4000000,
+        _ => return Err(Error::Visa(VisaError::AttributeStateNotSupported)),
+    };
+    // Elsewhere speeds are the baud rates themselves
+    #[cfg(not(target_os = "linux"))]
+    let speed = baud_rate as libc::speed_t;
+    Ok(speed)
+}
+
+fn check(result: libc::c_int) -> Result<libc::c_int> {
+    match result {
+        -1 => Err(Error::Io(io::Error::last_os_error())),
+        result => Ok(result),
+    }
+}
+
+fn configure(fd: RawFd, settings: &SerialSettings) -> Result<()> {
+    if settings.end_in == SerialEnd::Break {
+        return Err(Error::Visa(VisaError::AttributeStateNotSupported));
+    }
+
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    check(unsafe { libc::tcgetattr(fd, &mut termios) })?;
+    unsafe { libc::cfmakeraw(&mut termios) };
+
+    termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+    termios.c_cflag &= !libc::CSIZE;
+    termios.c_cflag |= match settings.data_bits {
+        5 => libc::CS5,
+        6 => libc::CS6,
+        7 => libc::CS7,
+        8 => libc::CS8,
+        _ => return Err(Error::Visa(VisaError::AttributeStateNotSupported)),
+    };
+
+    termios.c_cflag &= !(libc::PARENB | libc::PARODD);
+    #[cfg(target_os = "linux")]
+    {
+        termios.c_cflag &= !libc::CMSPAR;
+    }
+    termios.c_cflag |= match settings.parity {
+        Parity::None => 0,
+        Parity::Odd => libc::PARENB | libc::PARODD,
+        Parity::Even => libc::PARENB,
+        #[cfg(target_os = "linux")]
+        Parity::Mark => libc::PARENB | libc::PARODD | libc::CMSPAR,
+        #[cfg(target_os = "linux")]
+        Parity::Space => libc::PARENB | libc::CMSPAR,
+        #[cfg(not(target_os = "linux"))]
+        Parity::Mark | Parity::Space => {
+            return Err(Error::Visa(VisaError::AttributeStateNotSupported));
+        }
+    };
+
+    // POSIX only knows one or two stop bits, UARTs use 1.5 for two with 5 data bits
+    termios.c_cflag &= !libc::CSTOPB;
+    match (settings.stop_bits, settings.data_bits) {
+        (StopBits::One, _) => {}
+        (StopBits::Two, _) | (StopBits::OneAndHalf, 5) => termios.c_cflag |= libc::CSTOPB,
+        (StopBits::OneAndHalf, _) => {
+            return Err(Error::Visa(VisaError::AttributeStateNotSupported));
+        }
+    }
+
+    if settings.flow_control.contains(FlowControl::DTR_DSR) {
+        return Err(Error::Visa(VisaError::AttributeStateNotSupported));
+    }
+    termios.c_cflag &= !libc::CRTSCTS;
+    if settings.flow_control.contains(FlowControl::RTS_CTS) {
+        termios.c_cflag |= libc::CRTSCTS;
+    }
+    termios.c_iflag &= !(libc::IXON | libc::IXOFF | libc::IXANY);
+    if settings.flow_control.contains(FlowControl::XON_XOFF) {
+        termios.c_iflag |= libc::IXON | libc::IXOFF;
+    }
+    termios.c_cc[libc::VSTART] = settings.xon_char;
+    termios.c_cc[libc::VSTOP] = settings.xoff_char;
+    termios.c_cc[libc::VMIN] = 0;
+    termios.c_cc[libc::VTIME] = 0;
+
+    let speed = speed(settings.baud_rate)?;
+    check(unsafe { libc::cfsetispeed(&mut termios, speed) })?;
+    check(unsafe { libc::cfsetospeed(&mut termios, speed) })?;
+    check(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) })?;
+    Ok(())
+}
+
+#[derive(Debug)]
+struct State {
+    /// `None` once the session is closed.
+    file: Option<File>,
+    settings: SerialSettings,
+    /// Bytes received but not yet returned by a read.
+    buffer: Vec<u8>,
+    io: IoSettings,
+}
+
+impl State {
+    /// The port, failing once the session is closed.
+    fn file(&self) -> Result<&File> {
+        self.file
+            .as_ref()
+            .ok_or(Error::Visa(VisaError::InvalidObject))
+    }
+
+    fn fd(&self) -> Result<RawFd> {
+        self.file().map(File::as_raw_fd)
+    }
+
+    /// Waits until the port is ready for `events`, failing if it isn't before `deadline`.
+    fn wait(&self, events: libc::c_short, deadline: Option<Instant>) -> Result<()> {
+        let timeout = match remaining(deadline)? {
+            Some(remaining) => remaining.as_millis().clamp(1, libc::c_int::MAX as _) as libc::c_int,
+            None => -1,
+        };
+        let mut poll = libc::pollfd {
+            fd: self.fd()?,
+            events,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut poll, 1, timeout) } {
+            -1 => match io::Error::last_os_error() {
+                error if error.kind() == ErrorKind::Interrupted => Ok(()),
+                error => Err(Error::Io(error)),
+            },
+            0 => Err(Error::Visa(VisaError::Timeout)),
+            _ if poll.revents & libc::POLLHUP != 0 && poll.revents & libc::POLLIN == 0 => {
+                Err(Error::Visa(VisaError::ConnectionLost))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Receives more data into the buffer, failing if nothing arrives before `deadline`.
+    fn receive(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.wait(libc::POLLIN, deadline)?;
+        let mut chunk = [0; CHUNK_SIZE];
+        match self.file()?.read(&mut chunk) {
+            Ok(0) => Err(Error::Visa(VisaError::ConnectionLost)),
+            Ok(count) => {
+                self.buffer.extend_from_slice(&chunk[..count]);
+                Ok(())
+            }
+            Err(error)
+                if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) =>
+            {
+                Ok(())
+            }
+            Err(error) => Err(Error::Io(error)),
+        }
+    }
+
+    fn transmit(&mut self, mut buf: &[u8]) -> Result<()> {
+        let deadline = deadline(self.io.timeout);
+        while !buf.is_empty() {
+            self.wait(libc::POLLOUT, deadline)?;
+            match self.file()?.write(buf) {
+                Ok(count) => buf = &buf[count..],
+                Err(error)
+                    if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {}
+                Err(error) => return Err(Error::Io(error)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Mask of the highest data bit, used by `SerialEnd::LastBit`.
+    fn last_bit(&self) -> u8 {
+        1 << (self.settings.data_bits - 1)
+    }
+
+    /// Where a read ends within the first `available` buffered bytes, and why.
+    fn end(&self, available: usize) -> Option<(usize, Status)> {
+        let term_char = self.io.term_char_enabled || self.settings.end_in == SerialEnd::TermChar;
+        self.buffer[..available]
+            .iter()
+            .enumerate()
+            .find_map(|(index, byte)| match self.settings.end_in {
+                SerialEnd::LastBit if byte & self.last_bit() != 0 => {
+                    Some((index + 1, Status::OperationCompleted))
+                }
+                _ if term_char && *byte == self.io.term_char => {
+                    Some((index + 1, Status::TerminationCharacterRead))
+                }
+                _ => None,
+            })
+    }
+
+    fn discard_input(&mut self) -> Result<()> {
+        self.buffer.clear();
+        check(unsafe { libc::tcflush(self.fd()?, libc::TCIFLUSH) }).map(|_| ())
+    }
+}
+
+/// Native backend for `ASRL` resources, driving the serial port through termios.
+///
+/// Reads end on the termination character, `\n` by default, as set by [`SerialSettings::end_in`].
+#[derive(Debug)]
+pub struct Serial {
+    state: Mutex<State>,
+}
+
+impl Serial {
+    /// Opens the serial port at `path`, such as `/dev/ttyUSB0`, with `settings`.
+    pub fn open(path: impl AsRef<Path>, settings: SerialSettings) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY | libc::O_NONBLOCK)
+            .open(path)
+            .map_err(|error| match error.kind() {
+                ErrorKind::NotFound => Error::Visa(VisaError::ResourceNotFound),
+                _ => Error::Io(error),
+            })?;
+        configure(file.as_raw_fd(), &settings)?;
+        debug!("opened {} with {:?}", path.display(), settings);
+
+        Ok(Self {
+            state: Mutex::new(State {
+                file: Some(file),
+                settings,
+                buffer: Vec::new(),
+                io: IoSettings::new(DEFAULT_TIMEOUT),
+            }),
+        })
+    }
+
+    pub fn settings(&self) -> SerialSettings {
+        self.state.lock().unwrap().settings
+    }
+
+    /// Applies new line settings, keeping the old ones if the port doesn't support them.
+    pub fn set_settings(&self, settings: SerialSettings) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        configure(state.fd()?, &settings)?;
+        state.settings = settings;
+        Ok(())
+    }
+
+    /// Sets the I/O timeout, `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.state.lock().unwrap().io.timeout = timeout;
+    }
+
+    /// Sends a break once everything written so far has been transmitted.
+    pub fn send_break(&self) -> Result<()> {
+        let fd = self.state.lock().unwrap().fd()?;
+        check(unsafe { libc::tcdrain(fd) })?;
+        check(unsafe { libc::tcsendbreak(fd, 0) }).map(|_| ())
+    }
+}
+
+impl Backend for Serial {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let end = match state.io.send_end {
+            true => state.settings.end_out,
+            false => SerialEnd::None,
+        };
+
+        match end {
+            SerialEnd::None | SerialEnd::Break => state.transmit(buf)?,
+            SerialEnd::TermChar => {
+                let mut message = buf.to_vec();
+                message.push(state.io.term_char);
+                state.transmit(&message)?;
+            }
+            SerialEnd::LastBit => {
+                let last_bit = state.last_bit();
+                let mut message: Vec<u8> = buf.iter().map(|byte| byte & !last_bit).collect();
+                if let Some(last) = message.last_mut() {
+                    *last |= last_bit;
+                }
+                state.transmit(&message)?;
+            }
+        }
+
+        if end == SerialEnd::Break {
+            let fd = state.fd()?;
+            check(unsafe { libc::tcdrain(fd) })?;
+            check(unsafe { libc::tcsendbreak(fd, 0) })?;
+        }
+        Ok(buf.len())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut state = self.state.lock().unwrap();
+        let deadline = deadline(state.io.timeout);
+
+        loop {
+            let available = state.buffer.len().min(buf.len());
+            let (count, status) = match state.end(available) {
+                Some(end) => end,
+                None if available == buf.len() => (available, Status::BytesReadEqualInput),
+                None => {
+                    state.receive(deadline)?;
+                    continue;
+                }
+            };
+
+            buf[..count].copy_from_slice(&state.buffer[..count]);
+            state.buffer.drain(..count);
+            return Ok((count, status));
+        }
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let fd = state.fd()?;
+        if mode.intersects(FlushMode::READ_BUF_DISCARD | FlushMode::IO_IN_BUF_DISCARD) {
+            state.discard_input()?;
+        }
+        if mode.intersects(FlushMode::WRITE_BUF_DISCARD | FlushMode::IO_OUT_BUF_DISCARD) {
+            check(unsafe { libc::tcflush(fd, libc::TCOFLUSH) })?;
+        }
+        if mode.intersects(FlushMode::WRITE_BUF | FlushMode::IO_OUT_BUF) {
+            check(unsafe { libc::tcdrain(fd) })?;
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.discard_input()?;
+        check(unsafe { libc::tcflush(state.fd()?, libc::TCOFLUSH) }).map(|_| ())
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => Ok(None),
+            false => Ok(Some(key.unwrap_or("serial").to_owned())),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        let state = self.state.lock().unwrap();
+        state.file()?;
+        let settings = state.settings;
+        let value = match attribute {
+            AsrlBaud::ID => AsrlBaud(settings.baud_rate).to_value(),
+            AsrlDataBits::ID => AsrlDataBits(settings.data_bits as u16).to_value(),
+            AsrlParity::ID => AsrlParity(settings.parity as u16).to_value(),
+            AsrlStopBits::ID => AsrlStopBits(settings.stop_bits as u16).to_value(),
+            AsrlFlowControl::ID => AsrlFlowControl(settings.flow_control.bits() as u16).to_value(),
+            AsrlEndIn::ID => AsrlEndIn(settings.end_in as u16).to_value(),
+            AsrlEndOut::ID => AsrlEndOut(settings.end_out as u16).to_value(),
+            AsrlXonChar::ID => AsrlXonChar(settings.xon_char).to_value(),
+            AsrlXoffChar::ID => AsrlXoffChar(settings.xoff_char).to_value(),
+            _ => return state.io.get(attribute),
+        };
+        Ok(value)
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let fd = state.fd()?;
+        let mut settings = state.settings;
+        let value = value.clone();
+        match attribute {
+            AsrlBaud::ID => settings.baud_rate = AsrlBaud::from_value(value)?.0,
+            AsrlDataBits::ID => {
+                settings.data_bits = u8::try_from(AsrlDataBits::from_value(value)?.0)
+                    .map_err(|_| Error::Visa(VisaError::AttributeStateNotSupported))?
+            }
+            AsrlParity::ID => {
+                settings.parity = Parity::try_from(AsrlParity::from_value(value)?.0 as u32)
+                    .map_err(Error::Visa)?
+            }
+            AsrlStopBits::ID => {
+                settings.stop_bits = StopBits::try_from(AsrlStopBits::from_value(value)?.0 as u32)
+                    .map_err(Error::Visa)?
+            }
+            AsrlFlowControl::ID => {
+                settings.flow_control =
+                    FlowControl::from_bits(AsrlFlowControl::from_value(value)?.0 as u32)
+                        .ok_or(Error::Visa(VisaError::AttributeStateNotSupported))?
+            }
+            AsrlEndIn::ID => {
+                settings.end_in = SerialEnd::try_from(AsrlEndIn::from_value(value)?.0 as u32)
+                    .map_err(Error::Visa)?
+            }
+            AsrlEndOut::ID => {
+                settings.end_out = SerialEnd::try_from(AsrlEndOut::from_value(value)?.0 as u32)
+                    .map_err(Error::Visa)?
+            }
+            AsrlXonChar::ID => settings.xon_char = AsrlXonChar::from_value(value)?.0,
+            AsrlXoffChar::ID => settings.xoff_char = AsrlXoffChar::from_value(value)?.0,
+            _ => return state.io.set(attribute, &value),
+        }
+
+        // The port keeps its old settings if it doesn't support the new ones
+        configure(fd, &settings)?;
+        state.settings = settings;
+        Ok(())
+    }
+
+    fn close(&self) -> Result<()> {
+        // Dropping the file closes the port
+        self.state.lock().unwrap().file = None;
+        Ok(())
+    }
+}
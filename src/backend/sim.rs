@@ -0,0 +1,714 @@
+use super::{Backend, DEFAULT_TIMEOUT};
+use crate::{
+    attribute::{Attribute, AttributeKind, AttributeValue, Timeout, WritableAttribute},
+    error::{Error, Result, Status, VisaError},
+    format::exponent,
+    instrument::FlushMode,
+    resource::normalize,
+    resource_manager::AccessMode,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::trace;
+
+fn newline() -> String {
+    "\n".into()
+}
+
+fn error_query() -> String {
+    "SYST:ERR?".into()
+}
+
+fn no_error() -> String {
+    "0,\"No error\"".into()
+}
+
+fn command_error() -> String {
+    "-100,\"Command error\"".into()
+}
+
+fn out_of_range() -> String {
+    "-222,\"Data out of range\"".into()
+}
+
+/// Terminations of the messages the device receives (`q`) and sends (`r`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Eom {
+    #[serde(default = "newline")]
+    q: String,
+    #[serde(default = "newline")]
+    r: String,
+}
+
+impl Default for Eom {
+    fn default() -> Self {
+        Self {
+            q: newline(),
+            r: newline(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Dialogue {
+    q: String,
+    /// Commands without a response leave it out.
+    r: Option<String>,
+    /// Seconds before the response can be read.
+    #[serde(default)]
+    delay: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Float,
+    Int,
+    Str,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Specs {
+    #[serde(rename = "type")]
+    kind: Option<Kind>,
+    min: Option<f64>,
+    max: Option<f64>,
+    valid: Option<Vec<toml::Value>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Getter {
+    q: String,
+    r: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Setter {
+    q: String,
+    r: Option<String>,
+    /// Response to a value outside of the specs.
+    e: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PropertyDefinition {
+    default: toml::Value,
+    getter: Option<Getter>,
+    setter: Option<Setter>,
+    #[serde(default)]
+    specs: Specs,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ErrorQueue {
+    #[serde(default = "error_query")]
+    q: String,
+    /// Response once the queue is empty.
+    #[serde(default = "no_error")]
+    default: String,
+    #[serde(default = "command_error")]
+    command_error: String,
+    #[serde(default = "out_of_range")]
+    out_of_range: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ErrorBehaviour {
+    /// Response to unknown commands and rejected values without their own.
+    response: Option<String>,
+    queue: Option<ErrorQueue>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeviceDefinition {
+    #[serde(default)]
+    eom: Eom,
+    #[serde(default)]
+    dialogues: Vec<Dialogue>,
+    #[serde(default)]
+    properties: BTreeMap<String, PropertyDefinition>,
+    #[serde(default)]
+    error: ErrorBehaviour,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ResourceDefinition {
+    device: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeviceFile {
+    #[serde(default)]
+    devices: HashMap<String, DeviceDefinition>,
+    #[serde(default)]
+    resources: BTreeMap<String, ResourceDefinition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Float(f64),
+    Int(i64),
+    Str(String),
+}
+
+impl Value {
+    fn from_toml(value: &toml::Value, kind: Kind) -> Option<Self> {
+        match (kind, value) {
+            (Kind::Float, toml::Value::Float(value)) => Some(Self::Float(*value)),
+            (Kind::Float, toml::Value::Integer(value)) => Some(Self::Float(*value as _)),
+            (Kind::Int, toml::Value::Integer(value)) => Some(Self::Int(*value)),
+            (Kind::Str, toml::Value::String(value)) => Some(Self::Str(value.clone())),
+            _ => None,
+        }
+    }
+
+    fn parse(text: &str, kind: Kind) -> Option<Self> {
+        match kind {
+            Kind::Float => text.parse().ok().map(Self::Float),
+            Kind::Int => text.parse().ok().map(Self::Int),
+            Kind::Str => Some(Self::Str(text.to_owned())),
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            Self::Int(value) => Some(*value as _),
+            Self::Str(_) => None,
+        }
+    }
+
+    /// Formats the value following the Python style `spec` of a placeholder, such as `.3f` or `d`.
+    fn format(&self, spec: &str) -> String {
+        let precision = spec
+            .strip_prefix('.')
+            .and_then(|spec| spec.strip_suffix(['f', 'e']))
+            .and_then(|precision| precision.parse::<usize>().ok());
+
+        match (self.as_f64(), precision, spec.ends_with('e')) {
+            (Some(value), Some(precision), false) => format!("{:.*}", precision, value),
+            (Some(value), Some(precision), true) if value.is_finite() => {
+                exponent(value, precision, false)
+            }
+            _ => match self {
+                Self::Float(value) => value.to_string(),
+                Self::Int(value) => value.to_string(),
+                Self::Str(value) => value.clone(),
+            },
+        }
+    }
+}
+
+/// Splits a template such as `VOLT {:.2f}` into the text before its placeholder, the format spec and the text after.
+fn placeholder(template: &str) -> Option<(&str, &str, &str)> {
+    let start = template.find('{')?;
+    let end = start + template[start..].find('}')?;
+    let spec = &template[start + 1..end];
+    Some((
+        &template[..start],
+        spec.strip_prefix(':').unwrap_or(spec),
+        &template[end + 1..],
+    ))
+}
+
+fn render(template: &str, value: &Value) -> String {
+    match placeholder(template) {
+        Some((prefix, spec, suffix)) => format!("{}{}{}", prefix, value.format(spec), suffix),
+        None => template.to_owned(),
+    }
+}
+
+/// The text of `message` in place of the placeholder of `template`, if it matches.
+fn extract<'a>(template: &str, message: &'a str) -> Option<&'a str> {
+    let (prefix, _, suffix) = placeholder(template)?;
+    message
+        .strip_prefix(prefix)?
+        .strip_suffix(suffix)
+        .map(str::trim)
+}
+
+#[derive(Debug)]
+struct Property {
+    default: Value,
+    kind: Kind,
+    getter: Option<Getter>,
+    setter: Option<Setter>,
+    min: Option<f64>,
+    max: Option<f64>,
+    valid: Option<Vec<Value>>,
+}
+
+impl Property {
+    fn new(device: &str, name: &str, definition: PropertyDefinition) -> Result<Self> {
+        let invalid = |reason: &str| {
+            Error::InvalidSimulation(format!("device {}, property {}: {}", device, name, reason))
+        };
+
+        let kind = match (definition.specs.kind, &definition.default) {
+            (Some(kind), _) => kind,
+            (None, toml::Value::Float(_)) => Kind::Float,
+            (None, toml::Value::Integer(_)) => Kind::Int,
+            (None, _) => Kind::Str,
+        };
+        let default = Value::from_toml(&definition.default, kind)
+            .ok_or_else(|| invalid("the default doesn't match the type"))?;
+        let valid = definition
+            .specs
+            .valid
+            .map(|valid| {
+                valid
+                    .iter()
+                    .map(|value| Value::from_toml(value, kind))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| invalid("a valid value doesn't match the type"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            default,
+            kind,
+            getter: definition.getter,
+            setter: definition.setter,
+            min: definition.specs.min,
+            max: definition.specs.max,
+            valid,
+        })
+    }
+
+    fn accepts(&self, value: &Value) -> bool {
+        let in_range = match value.as_f64() {
+            Some(number) => {
+                self.min.is_none_or(|min| number >= min) && self.max.is_none_or(|max| number <= max)
+            }
+            None => true,
+        };
+        in_range
+            && self
+                .valid
+                .as_ref()
+                .is_none_or(|valid| valid.contains(value))
+    }
+}
+
+#[derive(Debug)]
+struct Device {
+    eom: Eom,
+    dialogues: Vec<Dialogue>,
+    properties: Vec<Property>,
+    error: ErrorBehaviour,
+}
+
+impl Device {
+    fn new(name: &str, definition: DeviceDefinition) -> Result<Self> {
+        if let Some(dialogue) = definition
+            .dialogues
+            .iter()
+            .find(|dialogue| Duration::try_from_secs_f64(dialogue.delay).is_err())
+        {
+            return Err(Error::InvalidSimulation(format!(
+                "device {}, dialogue {:?}: the delay isn't a valid number of seconds",
+                name, dialogue.q
+            )));
+        }
+
+        let properties = definition
+            .properties
+            .into_iter()
+            .map(|(property, definition)| Property::new(name, &property, definition))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            eom: definition.eom,
+            dialogues: definition.dialogues,
+            properties,
+            error: definition.error,
+        })
+    }
+}
+
+/// Simulated instruments described by a TOML device file, in the spirit of pyvisa-sim.
+///
+/// Devices answer fixed dialogues, expose properties through getter and setter commands
+/// checked against their specs, and can keep a SCPI error queue:
+///
+/// ```toml
+/// [devices.dmm]
+/// eom = { q = "\n", r = "\n" }
+/// dialogues = [
+///     { q = "*IDN?", r = "ACME,DMM 1,1234,1.0" },
+///     { q = "*RST" },
+///     { q = "MEAS?", r = "+1.234E+00", delay = 0.1 },
+/// ]
+/// error = { response = "ERROR", queue = { q = "SYST:ERR?" } }
+///
+/// [devices.dmm.properties.range]
+/// default = 10.0
+/// getter = { q = "RANG?", r = "{:.1f}" }
+/// setter = { q = "RANG {}" }
+/// specs = { min = 0.1, max = 1000.0 }
+///
+/// [resources."TCPIP::sim::INSTR"]
+/// device = "dmm"
+/// ```
+///
+/// Unknown commands and rejected values push `-100,"Command error"` and `-222,"Data out of range"`
+/// to the queue, these and the empty queue response can be changed in the `queue` table.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// The devices by normalized resource name, with the name as written in the file.
+    resources: BTreeMap<String, (String, Arc<Device>)>,
+}
+
+impl FromStr for Simulation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let file: DeviceFile =
+            toml::from_str(s).map_err(|error| Error::InvalidSimulation(error.to_string()))?;
+
+        let mut devices = HashMap::new();
+        for (name, definition) in file.devices {
+            let device = Device::new(&name, definition)?;
+            devices.insert(name, Arc::new(device));
+        }
+
+        let resources = file
+            .resources
+            .into_iter()
+            .map(|(resource, definition)| {
+                let device = devices.get(&definition.device).ok_or_else(|| {
+                    Error::InvalidSimulation(format!(
+                        "resource {}: unknown device {}",
+                        resource, definition.device
+                    ))
+                })?;
+                Ok((normalize(&resource), (resource, device.clone())))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { resources })
+    }
+}
+
+impl Simulation {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// The simulated resources, named as in the device file.
+    pub fn resources(&self) -> Vec<String> {
+        self.resources
+            .values()
+            .map(|(resource, _)| resource.clone())
+            .collect()
+    }
+
+    pub fn contains(&self, resource: &str) -> bool {
+        self.resources.contains_key(&normalize(resource))
+    }
+
+    /// Opens a new instance of the device behind `resource`, with its properties at their defaults.
+    pub fn open(&self, resource: &str) -> Result<SimulatedDevice> {
+        let (_, device) = self
+            .resources
+            .get(&normalize(resource))
+            .ok_or(Error::Visa(VisaError::ResourceNotFound))?;
+
+        Ok(SimulatedDevice {
+            device: device.clone(),
+            state: Mutex::new(State {
+                values: device
+                    .properties
+                    .iter()
+                    .map(|property| property.default.clone())
+                    .collect(),
+                errors: VecDeque::new(),
+                output: VecDeque::new(),
+                timeout: Some(DEFAULT_TIMEOUT),
+                closed: false,
+            }),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    /// When the response can be read, after the delay of its dialogue.
+    ready: Instant,
+    data: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Current values of the device's properties, in the same order.
+    values: Vec<Value>,
+    errors: VecDeque<String>,
+    output: VecDeque<Response>,
+    timeout: Option<Duration>,
+    closed: bool,
+}
+
+impl State {
+    fn check_open(&self) -> Result<()> {
+        match self.closed {
+            true => Err(Error::Visa(VisaError::InvalidObject)),
+            false => Ok(()),
+        }
+    }
+
+    fn respond(&mut self, device: &Device, response: &str, delay: Duration) {
+        self.output.push_back(Response {
+            ready: Instant::now() + delay,
+            data: format!("{}{}", response, device.eom.r).into_bytes(),
+        });
+    }
+
+    fn push_error(&mut self, device: &Device, error: impl FnOnce(&ErrorQueue) -> &String) {
+        if let Some(queue) = &device.error.queue {
+            self.errors.push_back(error(queue).clone());
+        }
+    }
+
+    fn handle(&mut self, device: &Device, message: &str) {
+        trace!("simulated device received {:?}", message);
+
+        if let Some(dialogue) = device
+            .dialogues
+            .iter()
+            .find(|dialogue| dialogue.q == message)
+        {
+            if let Some(response) = &dialogue.r {
+                self.respond(device, response, Duration::from_secs_f64(dialogue.delay));
+            }
+            return;
+        }
+
+        if let Some(queue) = &device.error.queue
+            && queue.q == message
+        {
+            let error = self
+                .errors
+                .pop_front()
+                .unwrap_or_else(|| queue.default.clone());
+            self.respond(device, &error, Duration::ZERO);
+            return;
+        }
+
+        for (index, property) in device.properties.iter().enumerate() {
+            if let Some(getter) = &property.getter
+                && getter.q == message
+            {
+                let response = render(&getter.r, &self.values[index]);
+                self.respond(device, &response, Duration::ZERO);
+                return;
+            }
+
+            if let Some(setter) = &property.setter
+                && let Some(text) = extract(&setter.q, message)
+            {
+                match Value::parse(text, property.kind).filter(|value| property.accepts(value)) {
+                    Some(value) => {
+                        self.values[index] = value;
+                        if let Some(response) = &setter.r {
+                            self.respond(device, response, Duration::ZERO);
+                        }
+                    }
+                    None => {
+                        self.push_error(device, |queue| &queue.out_of_range);
+                        if let Some(response) = setter.e.as_ref().or(device.error.response.as_ref())
+                        {
+                            self.respond(device, response, Duration::ZERO);
+                        }
+                    }
+                }
+                return;
+            }
+        }
+
+        self.push_error(device, |queue| &queue.command_error);
+        if let Some(response) = &device.error.response {
+            self.respond(device, response, Duration::ZERO);
+        }
+    }
+}
+
+/// Native backend serving a device of a [`Simulation`].
+///
+/// Every write can carry several messages separated by the device's input termination,
+/// reading with no response pending times out right away.
+#[derive(Debug)]
+pub struct SimulatedDevice {
+    device: Arc<Device>,
+    state: Mutex<State>,
+}
+
+impl SimulatedDevice {
+    /// Sets how long reads wait for delayed responses, `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.state.lock().unwrap().timeout = timeout;
+    }
+}
+
+impl Backend for SimulatedDevice {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+
+        let text = String::from_utf8_lossy(buf);
+        let messages: Vec<&str> = match self.device.eom.q.is_empty() {
+            true => vec![&text],
+            false => text.split(self.device.eom.q.as_str()).collect(),
+        };
+        for message in messages.into_iter().map(str::trim) {
+            if !message.is_empty() {
+                state.handle(&self.device, message);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let deadline = self
+            .state
+            .lock()
+            .unwrap()
+            .timeout
+            .map(|timeout| Instant::now() + timeout);
+
+        // The state isn't held while waiting for a delayed response, so it can still be written,
+        // cleared or closed meanwhile
+        let mut state = loop {
+            let state = self.state.lock().unwrap();
+            state.check_open()?;
+            let ready = state
+                .output
+                .front()
+                .ok_or(Error::Visa(VisaError::Timeout))?
+                .ready;
+            let now = Instant::now();
+            if ready <= now {
+                break state;
+            }
+            drop(state);
+
+            match deadline {
+                Some(deadline) if deadline < ready => {
+                    thread::sleep(deadline.saturating_duration_since(now));
+                    return Err(Error::Visa(VisaError::Timeout));
+                }
+                _ => thread::sleep(ready - now),
+            }
+        };
+
+        let response = state.output.front_mut().unwrap();
+        let count = response.data.len().min(buf.len());
+        buf[..count].copy_from_slice(&response.data[..count]);
+        response.data.drain(..count);
+        match response.data.is_empty() {
+            true => {
+                state.output.pop_front();
+                Ok((count, Status::OperationCompleted))
+            }
+            false => Ok((count, Status::BytesReadEqualInput)),
+        }
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        if mode.intersects(FlushMode::READ_BUF_DISCARD | FlushMode::IO_IN_BUF_DISCARD) {
+            state.output.clear();
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.output.clear();
+        Ok(())
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => Ok(None),
+            false => Ok(Some(key.unwrap_or("sim").to_owned())),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Only the timeout can be changed, the termination of messages is the device's.
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        match attribute {
+            Timeout::ID => Ok(Timeout(self.state.lock().unwrap().timeout).to_value()),
+            _ => Err(Error::Visa(VisaError::AttributeNotSupported)),
+        }
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        match attribute {
+            Timeout::ID => {
+                self.set_timeout(Timeout::from_value(value.clone())?.0);
+                Ok(())
+            }
+            _ => Err(Error::Visa(VisaError::AttributeNotSupported)),
+        }
+    }
+
+    fn close(&self) -> Result<()> {
+        self.state.lock().unwrap().closed = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_values_with_python_specs() {
+        assert_eq!(Value::Float(1.0).format(".3f"), "1.000");
+        assert_eq!(Value::Int(2).format(".1f"), "2.0");
+        assert_eq!(Value::Float(1234.5).format(".2e"), "1.23e+03");
+        assert_eq!(Value::Float(-0.00012).format(".1e"), "-1.2e-04");
+        assert_eq!(Value::Float(0.5).format(""), "0.5");
+        assert_eq!(Value::Str("AC".into()).format(".2f"), "AC");
+    }
+
+    #[test]
+    fn renders_and_extracts_placeholders() {
+        assert_eq!(placeholder("VOLT {:.2f} V"), Some(("VOLT ", ".2f", " V")));
+        assert_eq!(placeholder("MODE {}"), Some(("MODE ", "", "")));
+        assert_eq!(placeholder("*RST"), None);
+
+        assert_eq!(render("VOLT {:.2f}", &Value::Float(1.5)), "VOLT 1.50");
+        assert_eq!(extract("VOLT {}", "VOLT  2.5"), Some("2.5"));
+        assert_eq!(extract("VOLT {}", "CURR 2.5"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use super::{Backend, DEFAULT_TIMEOUT, IoSettings, connect, deadline, io_timeout, remaining};
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    error::{Error, Result, Status, VisaError},
+    instrument::FlushMode,
+    resource_manager::AccessMode,
+};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug)]
+struct State {
+    stream: TcpStream,
+    /// Bytes received but not yet returned by a read.
+    buffer: Vec<u8>,
+    io: IoSettings,
+}
+
+/// Native backend for `TCPIP::host::port::SOCKET` resources, over a plain TCP connection.
+///
+/// Reads end on the termination character, `\n` by default, or when the buffer is full.
+#[derive(Debug)]
+pub(crate) struct TcpSocket {
+    state: Mutex<State>,
+}
+
+impl TcpSocket {
+    pub(crate) fn connect(host: &str, port: u16, timeout: Duration) -> Result<Self> {
+        let stream = connect(host, port, timeout)?;
+        Ok(Self::from_stream(stream))
+    }
+
+    pub(crate) fn from_stream(stream: TcpStream) -> Self {
+        Self {
+            state: Mutex::new(State {
+                stream,
+                buffer: Vec::new(),
+                io: IoSettings {
+                    term_char_enabled: true,
+                    ..IoSettings::new(DEFAULT_TIMEOUT)
+                },
+            }),
+        }
+    }
+}
+
+impl State {
+    /// Receives more data into the buffer, failing if nothing arrives before `deadline`.
+    fn receive(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.stream.set_read_timeout(remaining(deadline)?)?;
+
+        let mut chunk = [0; CHUNK_SIZE];
+        match self.stream.read(&mut chunk) {
+            Ok(0) => Err(Error::Visa(VisaError::ConnectionLost)),
+            Ok(count) => {
+                self.buffer.extend_from_slice(&chunk[..count]);
+                Ok(())
+            }
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Err(Error::Visa(VisaError::Timeout))
+            }
+            Err(error) if error.kind() == ErrorKind::Interrupted => Ok(()),
+            Err(error) => Err(Error::Io(error)),
+        }
+    }
+
+    /// Discards the buffered input and anything already waiting on the socket.
+    fn discard_input(&mut self) -> Result<()> {
+        self.buffer.clear();
+        self.stream.set_nonblocking(true)?;
+        let mut chunk = [0; CHUNK_SIZE];
+        let result = loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => break Err(Error::Visa(VisaError::ConnectionLost)),
+                Ok(_) => continue,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => break Err(Error::Io(error)),
+            }
+        };
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+}
+
+impl Backend for TcpSocket {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let timeout = state.io.timeout;
+        state.stream.set_write_timeout(io_timeout(timeout))?;
+        match state.stream.write_all(buf) {
+            Ok(()) => Ok(buf.len()),
+            Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Err(Error::Visa(VisaError::Timeout))
+            }
+            Err(error) => Err(Error::Io(error)),
+        }
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut state = self.state.lock().unwrap();
+        let deadline = deadline(state.io.timeout);
+
+        loop {
+            let available = state.buffer.len().min(buf.len());
+            let term_char = state
+                .io
+                .term_char_enabled
+                .then(|| {
+                    let term_char = state.io.term_char;
+                    state.buffer[..available]
+                        .iter()
+                        .position(|byte| *byte == term_char)
+                })
+                .flatten();
+
+            let (count, status) = match term_char {
+                Some(position) => (position + 1, Status::TerminationCharacterRead),
+                None if available == buf.len() => (available, Status::BytesReadEqualInput),
+                None => {
+                    state.receive(deadline)?;
+                    continue;
+                }
+            };
+
+            buf[..count].copy_from_slice(&state.buffer[..count]);
+            state.buffer.drain(..count);
+            return Ok((count, status));
+        }
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        if mode.intersects(FlushMode::READ_BUF_DISCARD | FlushMode::IO_IN_BUF_DISCARD) {
+            self.state.lock().unwrap().buffer.clear();
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.state.lock().unwrap().discard_input()
+    }
+
+    /// The connection is private to the session, so locks always succeed.
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => Ok(None),
+            false => Ok(Some(key.unwrap_or("socket").to_owned())),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        self.state.lock().unwrap().io.get(attribute)
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        self.state.lock().unwrap().io.set(attribute, value)
+    }
+
+    fn close(&self) -> Result<()> {
+        let state = self.state.lock().unwrap();
+        match state.stream.shutdown(std::net::Shutdown::Both) {
+            Err(error) if error.kind() != ErrorKind::NotConnected => Err(Error::Io(error)),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,928 @@
+use super::Backend;
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    bindings::{ViEventType, ViSession, ViStatus},
+    error::{Error, Result, Status, VisaError},
+    event::{Event, EventHandler, EventMechanism, EventType, HandlerId},
+    instrument::{FlushMode, TriggerProtocol},
+    resource_manager::AccessMode,
+    session::Session,
+};
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tracing::warn;
+
+const HEADER: &str = "# visa transcript";
+const RESOURCE: &str = "# resource ";
+
+/// Quotes `data`, escaping anything but printable ASCII.
+fn quote(data: &[u8]) -> String {
+    let mut quoted = String::with_capacity(data.len() + 2);
+    quoted.push('"');
+    for byte in data {
+        match byte {
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            0x20..=0x7e => quoted.push(*byte as char),
+            _ => {
+                let _ = write!(quoted, "\\x{:02x}", byte);
+            }
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn format_error(error: &Error) -> String {
+    match error {
+        Error::Visa(error) => format!("visa 0x{:08X}", *error as i32 as u32),
+        error => format!("other {}", quote(error.to_string().as_bytes())),
+    }
+}
+
+fn format_key(key: Option<&str>) -> String {
+    key.map(|key| quote(key.as_bytes()))
+        .unwrap_or_else(|| "-".into())
+}
+
+fn format_value(value: &AttributeValue) -> String {
+    match value {
+        AttributeValue::U8(value) => value.to_string(),
+        AttributeValue::U16(value) => value.to_string(),
+        AttributeValue::U32(value) => value.to_string(),
+        AttributeValue::U64(value) => value.to_string(),
+        AttributeValue::I16(value) => value.to_string(),
+        AttributeValue::I32(value) => value.to_string(),
+        AttributeValue::Bool(value) => value.to_string(),
+        AttributeValue::String(value) => quote(value.as_bytes()),
+    }
+}
+
+/// Backend wrapper writing every operation on a session, its outcome and when it happened to a transcript.
+///
+/// Transcripts are plain text, one operation per line, and can be served back by [`Replay`].
+#[derive(Debug)]
+pub struct Recorder {
+    inner: Session,
+    start: Instant,
+    file: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    /// Records the operations on `session`, opened to `resource`, to a new transcript at `path`.
+    pub fn new(session: Session, resource: &str, path: impl AsRef<Path>) -> Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        writeln!(file, "{}", HEADER)?;
+        writeln!(file, "{}{}", RESOURCE, resource)?;
+        writeln!(
+            file,
+            "# started {}.{:06} s after the Unix epoch",
+            started.as_secs(),
+            started.subsec_micros()
+        )?;
+        file.flush()?;
+
+        Ok(Self {
+            inner: session,
+            start: Instant::now(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Appends `operation` and its `outcome` to the transcript, a failure to do so doesn't fail the operation.
+    fn record<T>(
+        &self,
+        operation: String,
+        result: Result<T>,
+        outcome: impl FnOnce(&T) -> Option<String>,
+    ) -> Result<T> {
+        let elapsed = self.start.elapsed();
+        let mut line = format!(
+            "{}.{:06} {}",
+            elapsed.as_secs(),
+            elapsed.subsec_micros(),
+            operation
+        );
+        match &result {
+            Ok(value) => {
+                if let Some(outcome) = outcome(value) {
+                    line.push_str(" -> ");
+                    line.push_str(&outcome);
+                }
+            }
+            Err(error) => {
+                line.push_str(" !! ");
+                line.push_str(&format_error(error));
+            }
+        }
+
+        let mut file = self.file.lock().unwrap();
+        if let Err(error) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+            warn!("failed to record {:?}: {}", line, error);
+        }
+        result
+    }
+}
+
+impl Backend for Recorder {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        self.record(
+            format!("write {}", quote(buf)),
+            self.inner.backend().write(buf),
+            |count| Some(count.to_string()),
+        )
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let result = self.inner.backend().read(buf);
+        self.record("read".into(), result, |(count, status)| {
+            Some(format!(
+                "{} 0x{:08X}",
+                quote(&buf[..*count]),
+                *status as u32
+            ))
+        })
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        self.record(
+            format!("flush 0x{:04X}", mode.bits()),
+            self.inner.backend().flush(mode),
+            |_| None,
+        )
+    }
+
+    fn buffered_write(&self, buf: &[u8]) -> Result<usize> {
+        self.record(
+            format!("buffered_write {}", quote(buf)),
+            self.inner.backend().buffered_write(buf),
+            |count| Some(count.to_string()),
+        )
+    }
+
+    fn buffered_read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let result = self.inner.backend().buffered_read(buf);
+        self.record("buffered_read".into(), result, |(count, status)| {
+            Some(format!(
+                "{} 0x{:08X}",
+                quote(&buf[..*count]),
+                *status as u32
+            ))
+        })
+    }
+
+    fn set_buffer_size(&self, mask: FlushMode, size: usize) -> Result<()> {
+        self.record(
+            format!("set_buffer 0x{:04X} {}", mask.bits(), size),
+            self.inner.backend().set_buffer_size(mask, size),
+            |_| None,
+        )
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.record("clear".into(), self.inner.backend().clear(), |_| None)
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        self.record(
+            "read_stb".into(),
+            self.inner.backend().read_status_byte(),
+            |status_byte| Some(format!("0x{:02X}", status_byte)),
+        )
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        self.record(
+            format!("trigger 0x{:X}", protocol as u16),
+            self.inner.backend().assert_trigger(protocol),
+            |_| None,
+        )
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        self.record(
+            format!("lock 0x{:X} {}", mode.bits(), format_key(key)),
+            self.inner.backend().lock(mode, timeout, key),
+            |key| Some(format_key(key.as_deref())),
+        )
+    }
+
+    fn unlock(&self) -> Result<()> {
+        self.record("unlock".into(), self.inner.backend().unlock(), |_| None)
+    }
+
+    fn get_attribute(&self, attribute: u32, kind: AttributeKind) -> Result<AttributeValue> {
+        self.record(
+            format!("get 0x{:08X} {}", attribute, kind),
+            self.inner.backend().get_attribute(attribute, kind),
+            |value| Some(format!("{} {}", value.kind(), format_value(value))),
+        )
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        self.record(
+            format!(
+                "set 0x{:08X} {} {}",
+                attribute,
+                value.kind(),
+                format_value(value)
+            ),
+            self.inner.backend().set_attribute(attribute, value),
+            |_| None,
+        )
+    }
+
+    fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.record(
+            format!(
+                "enable_event 0x{:08X} 0x{:X}",
+                event_type as u32,
+                mechanism.bits()
+            ),
+            self.inner.backend().enable_event(event_type, mechanism),
+            |_| None,
+        )
+    }
+
+    fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.record(
+            format!(
+                "disable_event 0x{:08X} 0x{:X}",
+                event_type as u32,
+                mechanism.bits()
+            ),
+            self.inner.backend().disable_event(event_type, mechanism),
+            |_| None,
+        )
+    }
+
+    fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.record(
+            format!(
+                "discard_events 0x{:08X} 0x{:X}",
+                event_type as u32,
+                mechanism.bits()
+            ),
+            self.inner.backend().discard_events(event_type, mechanism),
+            |_| None,
+        )
+    }
+
+    fn wait_on_event(&self, event_type: EventType, timeout: Option<Duration>) -> Result<Event> {
+        self.record(
+            format!("wait_event 0x{:08X}", event_type as u32),
+            self.inner.backend().wait_on_event(event_type, timeout),
+            |event| Some(format!("0x{:08X}", event.event_type() as u32)),
+        )
+    }
+
+    fn install_handler(&self, event_type: EventType, handler: EventHandler) -> Result<HandlerId> {
+        self.record(
+            format!("install_handler 0x{:08X}", event_type as u32),
+            self.inner.backend().install_handler(event_type, handler),
+            |id| Some(id.0.to_string()),
+        )
+    }
+
+    fn uninstall_handler(&self, event_type: EventType, id: HandlerId) -> Result<()> {
+        self.record(
+            format!("uninstall_handler 0x{:08X} {}", event_type as u32, id.0),
+            self.inner.backend().uninstall_handler(event_type, id),
+            |_| None,
+        )
+    }
+
+    fn close(&self) -> Result<()> {
+        self.record("close".into(), self.inner.close(), |_| None)
+    }
+
+    fn as_vi_session(&self) -> Option<ViSession> {
+        self.inner.as_vi_session()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operation {
+    Write(Vec<u8>),
+    Read,
+    Flush(u16),
+    BufferedWrite(Vec<u8>),
+    BufferedRead,
+    SetBuffer(u16, usize),
+    Clear,
+    ReadStatusByte,
+    Trigger(u16),
+    Lock(u32, Option<String>),
+    Unlock,
+    Get(u32, AttributeKind),
+    Set(u32, AttributeValue),
+    EnableEvent(u32, u16),
+    DisableEvent(u32, u16),
+    DiscardEvents(u32, u16),
+    WaitEvent(u32),
+    InstallHandler(u32),
+    UninstallHandler(u32, u64),
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    None,
+    Written(usize),
+    Read(Vec<u8>, Status),
+    StatusByte(u8),
+    Key(Option<String>),
+    Value(AttributeValue),
+    Event(EventType),
+    Handler(HandlerId),
+}
+
+#[derive(Debug, Clone)]
+enum RecordedError {
+    Visa(VisaError),
+    Other(String),
+}
+
+impl RecordedError {
+    fn to_error(&self) -> Error {
+        match self {
+            Self::Visa(error) => Error::Visa(*error),
+            Self::Other(message) => Error::Io(io::Error::other(message.clone())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    line: usize,
+    operation: Operation,
+    outcome: std::result::Result<Outcome, RecordedError>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(Vec<u8>),
+}
+
+fn tokenize(line: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' => {}
+            '"' => {
+                let mut data = vec![];
+                loop {
+                    match chars.next().ok_or("unterminated string")? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or("unterminated escape")? {
+                            'n' => data.push(b'\n'),
+                            'r' => data.push(b'\r'),
+                            't' => data.push(b'\t'),
+                            'x' => {
+                                let hex: String = chars.by_ref().take(2).collect();
+                                let byte = u8::from_str_radix(&hex, 16)
+                                    .map_err(|_| format!("invalid escape \\x{}", hex))?;
+                                data.push(byte);
+                            }
+                            c => data.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                        },
+                        c => data.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                    }
+                }
+                tokens.push(Token::Quoted(data));
+            }
+            c => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| *c != ' ') {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_hex(token: Option<&Token>) -> std::result::Result<u32, String> {
+    match token {
+        Some(Token::Word(word)) => word
+            .strip_prefix("0x")
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| format!("invalid code {}", word)),
+        _ => Err("missing code".into()),
+    }
+}
+
+fn parse_data(token: Option<&Token>) -> std::result::Result<Vec<u8>, String> {
+    match token {
+        Some(Token::Quoted(data)) => Ok(data.clone()),
+        _ => Err("missing data".into()),
+    }
+}
+
+fn parse_key(token: Option<&Token>) -> std::result::Result<Option<String>, String> {
+    match token {
+        Some(Token::Word(word)) if word == "-" => Ok(None),
+        Some(Token::Quoted(key)) => Ok(Some(String::from_utf8_lossy(key).into_owned())),
+        _ => Err("missing key".into()),
+    }
+}
+
+fn parse_kind(token: Option<&Token>) -> std::result::Result<AttributeKind, String> {
+    let kind = match token {
+        Some(Token::Word(word)) => match word.as_str() {
+            "u8" => AttributeKind::U8,
+            "u16" => AttributeKind::U16,
+            "u32" => AttributeKind::U32,
+            "u64" => AttributeKind::U64,
+            "i16" => AttributeKind::I16,
+            "i32" => AttributeKind::I32,
+            "bool" => AttributeKind::Bool,
+            "string" => AttributeKind::String,
+            word => return Err(format!("unknown attribute type {}", word)),
+        },
+        _ => return Err("missing attribute type".into()),
+    };
+    Ok(kind)
+}
+
+fn parse_value(
+    kind: AttributeKind,
+    token: Option<&Token>,
+) -> std::result::Result<AttributeValue, String> {
+    let word = match (kind, token) {
+        (AttributeKind::String, token) => {
+            return Ok(AttributeValue::String(
+                String::from_utf8_lossy(&parse_data(token)?).into_owned(),
+            ));
+        }
+        (_, Some(Token::Word(word))) => word,
+        _ => return Err("missing attribute value".into()),
+    };
+
+    let invalid = |_| format!("invalid {} {}", kind, word);
+    let value = match kind {
+        AttributeKind::U8 => AttributeValue::U8(word.parse().map_err(invalid)?),
+        AttributeKind::U16 => AttributeValue::U16(word.parse().map_err(invalid)?),
+        AttributeKind::U32 => AttributeValue::U32(word.parse().map_err(invalid)?),
+        AttributeKind::U64 => AttributeValue::U64(word.parse().map_err(invalid)?),
+        AttributeKind::I16 => AttributeValue::I16(word.parse().map_err(invalid)?),
+        AttributeKind::I32 => AttributeValue::I32(word.parse().map_err(invalid)?),
+        AttributeKind::Bool => match word.as_str() {
+            "true" => AttributeValue::Bool(true),
+            "false" => AttributeValue::Bool(false),
+            _ => return Err(format!("invalid bool {}", word)),
+        },
+        AttributeKind::String => unreachable!(),
+    };
+    Ok(value)
+}
+
+fn parse_error(tokens: &[Token]) -> std::result::Result<RecordedError, String> {
+    match tokens.first() {
+        Some(Token::Word(kind)) if kind == "visa" => {
+            let code = parse_hex(tokens.get(1))?;
+            VisaError::try_from(code as i32 as ViStatus)
+                .map(RecordedError::Visa)
+                .map_err(|_| format!("unknown error 0x{:08X}", code))
+        }
+        Some(Token::Word(kind)) if kind == "other" => Ok(RecordedError::Other(
+            String::from_utf8_lossy(&parse_data(tokens.get(1))?).into_owned(),
+        )),
+        _ => Err("invalid error".into()),
+    }
+}
+
+fn parse_outcome(operation: &Operation, tokens: &[Token]) -> std::result::Result<Outcome, String> {
+    match operation {
+        Operation::Write(_) | Operation::BufferedWrite(_) => match tokens.first() {
+            Some(Token::Word(count)) => count
+                .parse()
+                .map(Outcome::Written)
+                .map_err(|_| format!("invalid count {}", count)),
+            _ => Err("missing count".into()),
+        },
+        Operation::Read | Operation::BufferedRead => {
+            let code = parse_hex(tokens.get(1))?;
+            let status = Status::try_from(code as ViStatus)
+                .map_err(|_| format!("unknown status 0x{:08X}", code))?;
+            Ok(Outcome::Read(parse_data(tokens.first())?, status))
+        }
+        Operation::ReadStatusByte => Ok(Outcome::StatusByte(parse_hex(tokens.first())? as _)),
+        Operation::Lock(..) => Ok(Outcome::Key(parse_key(tokens.first())?)),
+        Operation::WaitEvent(_) => {
+            let code = parse_hex(tokens.first())?;
+            EventType::try_from(code as ViEventType)
+                .map(Outcome::Event)
+                .map_err(|_| format!("unknown event 0x{:08X}", code))
+        }
+        Operation::InstallHandler(_) => match tokens.first() {
+            Some(Token::Word(id)) => id
+                .parse()
+                .map(|id| Outcome::Handler(HandlerId(id)))
+                .map_err(|_| format!("invalid handler {}", id)),
+            _ => Err("missing handler".into()),
+        },
+        Operation::Get(..) => {
+            let kind = parse_kind(tokens.first())?;
+            Ok(Outcome::Value(parse_value(kind, tokens.get(1))?))
+        }
+        _ => Ok(Outcome::None),
+    }
+}
+
+fn parse_entry(line: &str) -> std::result::Result<Entry, String> {
+    let tokens = tokenize(line)?;
+    let split = tokens
+        .iter()
+        .position(|token| matches!(token, Token::Word(word) if word == "->" || word == "!!"));
+    let (operation, outcome) = match split {
+        Some(split) => (
+            &tokens[1..split],
+            Some((&tokens[split], &tokens[split + 1..])),
+        ),
+        None => (tokens.get(1..).unwrap_or_default(), None),
+    };
+
+    let operation = match operation.first() {
+        Some(Token::Word(word)) => match word.as_str() {
+            "write" => Operation::Write(parse_data(operation.get(1))?),
+            "read" => Operation::Read,
+            "flush" => Operation::Flush(parse_hex(operation.get(1))? as _),
+            "buffered_write" => Operation::BufferedWrite(parse_data(operation.get(1))?),
+            "buffered_read" => Operation::BufferedRead,
+            "set_buffer" => match operation.get(2) {
+                Some(Token::Word(size)) => Operation::SetBuffer(
+                    parse_hex(operation.get(1))? as _,
+                    size.parse().map_err(|_| format!("invalid size {}", size))?,
+                ),
+                _ => return Err("missing size".into()),
+            },
+            "clear" => Operation::Clear,
+            "read_stb" => Operation::ReadStatusByte,
+            "trigger" => Operation::Trigger(parse_hex(operation.get(1))? as _),
+            "lock" => Operation::Lock(parse_hex(operation.get(1))?, parse_key(operation.get(2))?),
+            "unlock" => Operation::Unlock,
+            "get" => Operation::Get(parse_hex(operation.get(1))?, parse_kind(operation.get(2))?),
+            "set" => {
+                let kind = parse_kind(operation.get(2))?;
+                Operation::Set(
+                    parse_hex(operation.get(1))?,
+                    parse_value(kind, operation.get(3))?,
+                )
+            }
+            "enable_event" => Operation::EnableEvent(
+                parse_hex(operation.get(1))?,
+                parse_hex(operation.get(2))? as _,
+            ),
+            "disable_event" => Operation::DisableEvent(
+                parse_hex(operation.get(1))?,
+                parse_hex(operation.get(2))? as _,
+            ),
+            "discard_events" => Operation::DiscardEvents(
+                parse_hex(operation.get(1))?,
+                parse_hex(operation.get(2))? as _,
+            ),
+            "wait_event" => Operation::WaitEvent(parse_hex(operation.get(1))?),
+            "install_handler" => Operation::InstallHandler(parse_hex(operation.get(1))?),
+            "uninstall_handler" => match operation.get(2) {
+                Some(Token::Word(id)) => Operation::UninstallHandler(
+                    parse_hex(operation.get(1))?,
+                    id.parse().map_err(|_| format!("invalid handler {}", id))?,
+                ),
+                _ => return Err("missing handler".into()),
+            },
+            "close" => Operation::Close,
+            word => return Err(format!("unknown operation {}", word)),
+        },
+        _ => return Err("missing operation".into()),
+    };
+
+    let outcome = match outcome {
+        None => Ok(Outcome::None),
+        Some((Token::Word(marker), error)) if marker == "!!" => Err(parse_error(error)?),
+        Some((_, result)) => Ok(parse_outcome(&operation, result)?),
+    };
+
+    Ok(Entry {
+        line: 0,
+        operation,
+        outcome,
+    })
+}
+
+fn describe(operation: &Operation) -> String {
+    match operation {
+        Operation::Write(data) => format!("write {}", quote(data)),
+        Operation::Read => "read".into(),
+        Operation::Flush(mode) => format!("flush 0x{:04X}", mode),
+        Operation::BufferedWrite(data) => format!("buffered_write {}", quote(data)),
+        Operation::BufferedRead => "buffered_read".into(),
+        Operation::SetBuffer(mask, size) => format!("set_buffer 0x{:04X} {}", mask, size),
+        Operation::Clear => "clear".into(),
+        Operation::ReadStatusByte => "read_stb".into(),
+        Operation::Trigger(protocol) => format!("trigger 0x{:X}", protocol),
+        Operation::Lock(mode, key) => format!("lock 0x{:X} {}", mode, format_key(key.as_deref())),
+        Operation::Unlock => "unlock".into(),
+        Operation::Get(attribute, kind) => format!("get 0x{:08X} {}", attribute, kind),
+        Operation::Set(attribute, value) => format!(
+            "set 0x{:08X} {} {}",
+            attribute,
+            value.kind(),
+            format_value(value)
+        ),
+        Operation::EnableEvent(event_type, mechanism) => {
+            format!("enable_event 0x{:08X} 0x{:X}", event_type, mechanism)
+        }
+        Operation::DisableEvent(event_type, mechanism) => {
+            format!("disable_event 0x{:08X} 0x{:X}", event_type, mechanism)
+        }
+        Operation::DiscardEvents(event_type, mechanism) => {
+            format!("discard_events 0x{:08X} 0x{:X}", event_type, mechanism)
+        }
+        Operation::WaitEvent(event_type) => format!("wait_event 0x{:08X}", event_type),
+        Operation::InstallHandler(event_type) => format!("install_handler 0x{:08X}", event_type),
+        Operation::UninstallHandler(event_type, id) => {
+            format!("uninstall_handler 0x{:08X} {}", event_type, id)
+        }
+        Operation::Close => "close".into(),
+    }
+}
+
+/// Backend serving a transcript written by a [`Recorder`] back, in order.
+///
+/// Every operation must be the one recorded next, with the same data, and gets the recorded
+/// outcome, anything else fails with [`Error::ReplayDiverged`]. Clones share their position
+/// in the transcript, so a test can keep one to check it was [`finish`](Self::finish)ed.
+/// Handlers are installed as recorded but never called, the events they got aren't recorded.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    resource: Option<String>,
+    entries: Arc<Mutex<VecDeque<Entry>>>,
+}
+
+impl FromStr for Replay {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut resource = None;
+        let mut entries = VecDeque::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim_end();
+            if let Some(name) = line.strip_prefix(RESOURCE) {
+                resource = Some(name.to_owned());
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut entry = parse_entry(line).map_err(|error| {
+                Error::InvalidTranscript(format!("line {}: {}", index + 1, error))
+            })?;
+            entry.line = index + 1;
+            entries.push_back(entry);
+        }
+
+        Ok(Self {
+            resource,
+            entries: Arc::new(Mutex::new(entries)),
+        })
+    }
+}
+
+impl Replay {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// The resource the transcript was recorded from.
+    pub fn resource(&self) -> Option<&str> {
+        self.resource.as_deref()
+    }
+
+    /// Number of recorded operations not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Fails if part of the transcript wasn't replayed.
+    pub fn finish(&self) -> Result<()> {
+        match self.entries.lock().unwrap().front() {
+            Some(entry) => Err(Error::ReplayDiverged(format!(
+                "line {}: {} was never performed",
+                entry.line,
+                describe(&entry.operation)
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Replays the next entry if it's `operation`.
+    fn next(&self, operation: Operation) -> Result<Outcome> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.front() else {
+            return Err(Error::ReplayDiverged(format!(
+                "{} after the end of the transcript",
+                describe(&operation)
+            )));
+        };
+
+        if entry.operation != operation {
+            return Err(Error::ReplayDiverged(format!(
+                "line {}: expected {}, got {}",
+                entry.line,
+                describe(&entry.operation),
+                describe(&operation)
+            )));
+        }
+
+        let entry = entries.pop_front().unwrap();
+        entry.outcome.map_err(|error| error.to_error())
+    }
+
+    /// Replays a read of `operation`, copying the recorded data into `buf`.
+    fn read_into(&self, operation: Operation, buf: &mut [u8]) -> Result<(usize, Status)> {
+        match self.next(operation)? {
+            Outcome::Read(data, status) if data.len() <= buf.len() => {
+                buf[..data.len()].copy_from_slice(&data);
+                Ok((data.len(), status))
+            }
+            Outcome::Read(data, _) => Err(Error::ReplayDiverged(format!(
+                "{} bytes were read, the buffer only holds {}",
+                data.len(),
+                buf.len()
+            ))),
+            _ => Err(Error::InvalidTranscript("read without data".into())),
+        }
+    }
+}
+
+impl Backend for Replay {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        match self.next(Operation::Write(buf.to_vec()))? {
+            Outcome::Written(count) => Ok(count),
+            _ => Ok(buf.len()),
+        }
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        self.read_into(Operation::Read, buf)
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        self.next(Operation::Flush(mode.bits())).map(|_| ())
+    }
+
+    fn buffered_write(&self, buf: &[u8]) -> Result<usize> {
+        match self.next(Operation::BufferedWrite(buf.to_vec()))? {
+            Outcome::Written(count) => Ok(count),
+            _ => Ok(buf.len()),
+        }
+    }
+
+    fn buffered_read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        self.read_into(Operation::BufferedRead, buf)
+    }
+
+    fn set_buffer_size(&self, mask: FlushMode, size: usize) -> Result<()> {
+        self.next(Operation::SetBuffer(mask.bits(), size))
+            .map(|_| ())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.next(Operation::Clear).map(|_| ())
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        match self.next(Operation::ReadStatusByte)? {
+            Outcome::StatusByte(status_byte) => Ok(status_byte),
+            _ => Err(Error::InvalidTranscript(
+                "serial poll without status byte".into(),
+            )),
+        }
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        self.next(Operation::Trigger(protocol as _)).map(|_| ())
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        match self.next(Operation::Lock(mode.bits(), key.map(str::to_owned)))? {
+            Outcome::Key(key) => Ok(key),
+            _ => Ok(None),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        self.next(Operation::Unlock).map(|_| ())
+    }
+
+    fn get_attribute(&self, attribute: u32, kind: AttributeKind) -> Result<AttributeValue> {
+        match self.next(Operation::Get(attribute, kind))? {
+            Outcome::Value(value) => Ok(value),
+            _ => Err(Error::InvalidTranscript(
+                "attribute read without value".into(),
+            )),
+        }
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        self.next(Operation::Set(attribute, value.clone()))
+            .map(|_| ())
+    }
+
+    fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.next(Operation::EnableEvent(event_type as _, mechanism.bits()))
+            .map(|_| ())
+    }
+
+    fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.next(Operation::DisableEvent(event_type as _, mechanism.bits()))
+            .map(|_| ())
+    }
+
+    fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.next(Operation::DiscardEvents(event_type as _, mechanism.bits()))
+            .map(|_| ())
+    }
+
+    fn wait_on_event(&self, event_type: EventType, _timeout: Option<Duration>) -> Result<Event> {
+        match self.next(Operation::WaitEvent(event_type as _))? {
+            Outcome::Event(event_type) => Ok(Event::new(event_type)),
+            _ => Err(Error::InvalidTranscript("event wait without event".into())),
+        }
+    }
+
+    fn install_handler(&self, event_type: EventType, _handler: EventHandler) -> Result<HandlerId> {
+        match self.next(Operation::InstallHandler(event_type as _))? {
+            Outcome::Handler(id) => Ok(id),
+            _ => Err(Error::InvalidTranscript(
+                "handler without identifier".into(),
+            )),
+        }
+    }
+
+    fn uninstall_handler(&self, event_type: EventType, id: HandlerId) -> Result<()> {
+        self.next(Operation::UninstallHandler(event_type as _, id.0))
+            .map(|_| ())
+    }
+
+    fn close(&self) -> Result<()> {
+        self.next(Operation::Close).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_any_byte_reversibly() {
+        let data: Vec<u8> = (0..=255).collect();
+        let quoted = quote(&data);
+        assert!(quoted.bytes().all(|byte| (0x20..=0x7e).contains(&byte)));
+        assert_eq!(tokenize(&quoted).unwrap(), [Token::Quoted(data)]);
+    }
+
+    #[test]
+    fn splits_lines_into_words_and_strings() {
+        assert_eq!(
+            tokenize("0.5 write \"a b\" -> 3").unwrap(),
+            [
+                Token::Word("0.5".into()),
+                Token::Word("write".into()),
+                Token::Quoted(b"a b".to_vec()),
+                Token::Word("->".into()),
+                Token::Word("3".into()),
+            ]
+        );
+        assert!(tokenize("\"\\xzz\"").is_err());
+        assert!(tokenize("\"open").is_err());
+    }
+}
@@ -0,0 +1,649 @@
+#[cfg(target_os = "linux")]
+mod usbfs;
+
+use super::{Backend, DEFAULT_TIMEOUT, IoSettings, RemoteLocal, deadline, write_chunks};
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    error::{Error, Result, Status, VisaError},
+    event::{Event, EventMechanism, EventType, check_service_request},
+    instrument::{FlushMode, TriggerProtocol},
+    resource_manager::AccessMode,
+};
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::debug;
+
+#[cfg(target_os = "linux")]
+pub use usbfs::*;
+
+const HEADER_LEN: usize = 12;
+/// Largest amount of data sent or requested in a single transfer.
+const CHUNK_SIZE: usize = 1 << 16;
+/// Bulk-IN transfers are read in multiples of the largest packet size of any bus speed.
+const PACKET_SIZE: usize = 512;
+/// Delay between polls of a pending clear or abort.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+/// Longest the interface is held at a time while waiting for a service request.
+const SERVICE_REQUEST_SLICE: Duration = Duration::from_millis(10);
+
+const DEV_DEP_MSG_OUT: u8 = 1;
+const REQUEST_DEV_DEP_MSG_IN: u8 = 2;
+const DEV_DEP_MSG_IN: u8 = 2;
+const USB488_TRIGGER: u8 = 128;
+
+const ATTRIBUTE_EOM: u8 = 0x01;
+const ATTRIBUTE_TERM_CHAR: u8 = 0x02;
+
+const INITIATE_ABORT_BULK_OUT: u8 = 1;
+const CHECK_ABORT_BULK_OUT_STATUS: u8 = 2;
+const INITIATE_ABORT_BULK_IN: u8 = 3;
+const CHECK_ABORT_BULK_IN_STATUS: u8 = 4;
+const INITIATE_CLEAR: u8 = 5;
+const CHECK_CLEAR_STATUS: u8 = 6;
+const GET_CAPABILITIES: u8 = 7;
+const INDICATOR_PULSE: u8 = 64;
+const READ_STATUS_BYTE: u8 = 128;
+const REN_CONTROL: u8 = 160;
+const GO_TO_LOCAL: u8 = 161;
+const LOCAL_LOCKOUT: u8 = 162;
+
+const STATUS_SUCCESS: u8 = 0x01;
+const STATUS_PENDING: u8 = 0x02;
+
+/// `bNotify1` of interrupt-IN packets carrying a service request.
+const NOTIFY_SERVICE_REQUEST: u8 = 0x81;
+
+/// Endpoints of a USBTMC interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    BulkOut,
+    BulkIn,
+    InterruptIn,
+}
+
+/// Recipient of a class specific control request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Recipient {
+    Interface,
+    Endpoint(Endpoint),
+}
+
+/// USB transfers a USBTMC interface is driven with.
+///
+/// Timeouts of `None` wait forever. Implemented by [`UsbFs`] on Linux, and by
+/// stand-ins to exercise the USBTMC framing without a device.
+pub trait UsbTransport: Debug + Send {
+    fn bulk_out(&mut self, data: &[u8], timeout: Option<Duration>) -> Result<usize>;
+
+    fn bulk_in(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize>;
+
+    /// Whether the interface has an interrupt-IN endpoint.
+    fn has_interrupt_in(&self) -> bool {
+        false
+    }
+
+    fn interrupt_in(&mut self, _buf: &mut [u8], _timeout: Option<Duration>) -> Result<usize> {
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    }
+
+    /// Sends a class specific device-to-host control request, returning the number of bytes received.
+    fn control_in(
+        &mut self,
+        recipient: Recipient,
+        request: u8,
+        value: u16,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<usize>;
+
+    fn clear_halt(&mut self, endpoint: Endpoint) -> Result<()>;
+}
+
+/// What a USBTMC interface reports to `GET_CAPABILITIES`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    pub indicator_pulse: bool,
+    pub talk_only: bool,
+    pub listen_only: bool,
+    /// Whether reads can end on a termination character.
+    pub term_char: bool,
+    /// Whether the interface follows USB488.
+    pub usb488: bool,
+    /// Whether `REN_CONTROL`, `GO_TO_LOCAL` and `LOCAL_LOCKOUT` are supported.
+    pub remote_local: bool,
+    pub trigger: bool,
+    pub scpi: bool,
+    pub service_request: bool,
+}
+
+impl Capabilities {
+    fn parse(response: &[u8]) -> Self {
+        let bit = |index: usize, mask: u8| response.get(index).is_some_and(|byte| byte & mask != 0);
+        Self {
+            indicator_pulse: bit(4, 0x04),
+            talk_only: bit(4, 0x02),
+            listen_only: bit(4, 0x01),
+            term_char: bit(5, 0x01),
+            usb488: bit(14, 0x04),
+            remote_local: bit(14, 0x02),
+            trigger: bit(14, 0x01),
+            scpi: bit(15, 0x08),
+            service_request: bit(15, 0x04),
+        }
+    }
+}
+
+/// Waits before polling a pending clear or abort again, failing once `deadline` has passed.
+fn pause(deadline: Option<Instant>) -> Result<()> {
+    super::remaining(deadline)?;
+    thread::sleep(POLL_INTERVAL);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct State<T> {
+    /// `None` once the session is closed, releasing the interface.
+    transport: Option<T>,
+    /// `bTag` of the last bulk transfer.
+    tag: u8,
+    /// `bTag` of the last `READ_STATUS_BYTE`, which uses its own range.
+    status_tag: u8,
+    /// Service requests received while waiting for a status byte.
+    service_requests: VecDeque<u8>,
+    /// Whether service requests are queued as events.
+    service_requests_enabled: bool,
+    io: IoSettings,
+}
+
+impl<T: UsbTransport> State<T> {
+    /// The transport, failing once the session is closed.
+    fn transport(&mut self) -> Result<&mut T> {
+        self.transport
+            .as_mut()
+            .ok_or(Error::Visa(VisaError::InvalidObject))
+    }
+
+    fn check_open(&self) -> Result<()> {
+        match self.transport {
+            Some(_) => Ok(()),
+            None => Err(Error::Visa(VisaError::InvalidObject)),
+        }
+    }
+
+    fn next_tag(&mut self) -> u8 {
+        self.tag = self.tag % 255 + 1;
+        self.tag
+    }
+
+    fn next_status_tag(&mut self) -> u8 {
+        self.status_tag = match self.status_tag {
+            2..=126 => self.status_tag + 1,
+            _ => 2,
+        };
+        self.status_tag
+    }
+
+    /// Sends a control request whose response starts with a USBTMC status.
+    fn request(
+        &mut self,
+        recipient: Recipient,
+        request: u8,
+        value: u16,
+        len: usize,
+    ) -> Result<Vec<u8>> {
+        let mut response = vec![0; len];
+        let timeout = self.io.timeout;
+        let count =
+            self.transport()?
+                .control_in(recipient, request, value, &mut response, timeout)?;
+        response.truncate(count);
+        match response.first() {
+            Some(_) => Ok(response),
+            None => Err(Error::Visa(VisaError::Io)),
+        }
+    }
+
+    /// Sends a control request that has to succeed.
+    fn command(
+        &mut self,
+        recipient: Recipient,
+        request: u8,
+        value: u16,
+        len: usize,
+    ) -> Result<Vec<u8>> {
+        let response = self.request(recipient, request, value, len)?;
+        match response[0] {
+            STATUS_SUCCESS => Ok(response),
+            status => {
+                debug!(
+                    "USBTMC request {} failed with status {:#04x}",
+                    request, status
+                );
+                Err(Error::Visa(VisaError::Io))
+            }
+        }
+    }
+
+    fn send(&mut self, message_id: u8, header: [u8; 8], data: &[u8]) -> Result<u8> {
+        let tag = self.next_tag();
+        let mut message = Vec::with_capacity(HEADER_LEN + data.len() + 3);
+        message.extend_from_slice(&[message_id, tag, !tag, 0]);
+        message.extend_from_slice(&header);
+        message.extend_from_slice(data);
+        message.resize(message.len().next_multiple_of(4), 0);
+
+        let timeout = self.io.timeout;
+        match self.transport()?.bulk_out(&message, timeout) {
+            Ok(_) => Ok(tag),
+            Err(Error::Visa(VisaError::Timeout)) => {
+                self.abort_bulk_out(tag)?;
+                Err(Error::Visa(VisaError::Timeout))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Requests up to `len` bytes, returning the data and the transfer attributes.
+    fn receive(&mut self, len: usize) -> Result<(Vec<u8>, u8)> {
+        let mut attributes = 0;
+        if self.io.term_char_enabled {
+            attributes |= ATTRIBUTE_TERM_CHAR;
+        }
+        let mut header = [0; 8];
+        header[..4].copy_from_slice(&(len as u32).to_le_bytes());
+        header[4] = attributes;
+        header[5] = self.io.term_char;
+        let tag = self.send(REQUEST_DEV_DEP_MSG_IN, header, &[])?;
+
+        match self.receive_response(tag, len) {
+            Err(Error::Visa(VisaError::Timeout)) => {
+                self.abort_bulk_in(tag)?;
+                Err(Error::Visa(VisaError::Timeout))
+            }
+            result => result,
+        }
+    }
+
+    fn receive_response(&mut self, tag: u8, len: usize) -> Result<(Vec<u8>, u8)> {
+        let timeout = self.io.timeout;
+        let deadline = deadline(timeout);
+        let mut transfer = vec![0; (HEADER_LEN + len + 3).next_multiple_of(PACKET_SIZE)];
+        let mut received = self.transport()?.bulk_in(&mut transfer, timeout)?;
+
+        if received < HEADER_LEN
+            || transfer[0] != DEV_DEP_MSG_IN
+            || transfer[1] != tag
+            || transfer[2] != !tag
+        {
+            return Err(Error::Visa(VisaError::Io));
+        }
+        let size = u32::from_le_bytes(transfer[4..8].try_into().unwrap()) as usize;
+        let attributes = transfer[8];
+        if size > len {
+            return Err(Error::Visa(VisaError::Io));
+        }
+
+        // Responses spanning several packets arrive in more than one transfer
+        while received < HEADER_LEN + size {
+            let remaining = super::remaining(deadline)?;
+            let count = self
+                .transport()?
+                .bulk_in(&mut transfer[received..], remaining)?;
+            if count == 0 {
+                return Err(Error::Visa(VisaError::Io));
+            }
+            received += count;
+        }
+
+        transfer.truncate(HEADER_LEN + size);
+        transfer.drain(..HEADER_LEN);
+        Ok((transfer, attributes))
+    }
+
+    fn abort_bulk_out(&mut self, tag: u8) -> Result<()> {
+        let recipient = Recipient::Endpoint(Endpoint::BulkOut);
+        let response = self.request(recipient, INITIATE_ABORT_BULK_OUT, tag as u16, 2)?;
+        if response[0] == STATUS_SUCCESS {
+            let deadline = deadline(self.io.timeout);
+            while self.request(recipient, CHECK_ABORT_BULK_OUT_STATUS, 0, 8)?[0] == STATUS_PENDING {
+                pause(deadline)?;
+            }
+        }
+        self.transport()?.clear_halt(Endpoint::BulkOut)
+    }
+
+    fn abort_bulk_in(&mut self, tag: u8) -> Result<()> {
+        let recipient = Recipient::Endpoint(Endpoint::BulkIn);
+        let response = self.request(recipient, INITIATE_ABORT_BULK_IN, tag as u16, 2)?;
+        if response[0] != STATUS_SUCCESS {
+            return Ok(());
+        }
+
+        let deadline = deadline(self.io.timeout);
+        loop {
+            let status = self.request(recipient, CHECK_ABORT_BULK_IN_STATUS, 0, 8)?;
+            match (
+                status[0],
+                status.get(1).is_some_and(|fifo| fifo & 0x01 != 0),
+            ) {
+                (STATUS_PENDING, true) => self.discard_bulk_in()?,
+                (STATUS_PENDING, false) => pause(deadline)?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn discard_bulk_in(&mut self) -> Result<()> {
+        let mut discard = vec![0; PACKET_SIZE];
+        let timeout = self.io.timeout;
+        self.transport()?.bulk_in(&mut discard, timeout).map(|_| ())
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.command(Recipient::Interface, INITIATE_CLEAR, 0, 1)?;
+        let deadline = deadline(self.io.timeout);
+        loop {
+            let status = self.request(Recipient::Interface, CHECK_CLEAR_STATUS, 0, 2)?;
+            match (
+                status[0],
+                status.get(1).is_some_and(|fifo| fifo & 0x01 != 0),
+            ) {
+                (STATUS_PENDING, true) => self.discard_bulk_in()?,
+                (STATUS_PENDING, false) => pause(deadline)?,
+                (STATUS_SUCCESS, _) => break,
+                _ => return Err(Error::Visa(VisaError::Io)),
+            }
+        }
+        self.transport()?.clear_halt(Endpoint::BulkOut)
+    }
+
+    /// Waits for an interrupt-IN packet, returning its two bytes.
+    fn interrupt(&mut self, deadline: Option<Instant>) -> Result<[u8; 2]> {
+        let mut packet = [0; 2];
+        let remaining = super::remaining(deadline)?;
+        match self.transport()?.interrupt_in(&mut packet, remaining)? {
+            2 => Ok(packet),
+            _ => Err(Error::Visa(VisaError::Io)),
+        }
+    }
+}
+
+/// Native USBTMC client with USB488 extensions, for `USB::vendor::product::serial::INSTR` resources.
+///
+/// Generic over the transport so the framing can be driven by anything implementing
+/// [`UsbTransport`], on Linux [`UsbFs`] talks to devices through usbfs.
+#[derive(Debug)]
+pub struct UsbTmc<T> {
+    state: Mutex<State<T>>,
+    capabilities: Capabilities,
+}
+
+impl<T: UsbTransport> UsbTmc<T> {
+    pub fn new(transport: T) -> Result<Self> {
+        let mut state = State {
+            transport: Some(transport),
+            tag: 0,
+            status_tag: 1,
+            service_requests: VecDeque::new(),
+            service_requests_enabled: false,
+            io: IoSettings::new(DEFAULT_TIMEOUT),
+        };
+        let capabilities = state
+            .command(Recipient::Interface, GET_CAPABILITIES, 0, 0x18)
+            .map(|response| Capabilities::parse(&response))?;
+        debug!("opened USBTMC interface with {:?}", capabilities);
+
+        Ok(Self {
+            state: Mutex::new(state),
+            capabilities,
+        })
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Sets the I/O timeout, `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.state.lock().unwrap().io.timeout = timeout;
+    }
+
+    /// Reads the status byte, through the interrupt-IN endpoint if the interface has one.
+    pub fn read_status_byte(&self) -> Result<u8> {
+        if !self.capabilities.usb488 {
+            return Err(Error::Visa(VisaError::OperationNotSupported));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let tag = state.next_status_tag();
+        let response = state.command(Recipient::Interface, READ_STATUS_BYTE, tag as u16, 3)?;
+        if !state.transport()?.has_interrupt_in() {
+            return response.get(2).copied().ok_or(Error::Visa(VisaError::Io));
+        }
+
+        let deadline = deadline(state.io.timeout);
+        loop {
+            match state.interrupt(deadline)? {
+                [notify, status_byte] if notify == 0x80 | tag => return Ok(status_byte),
+                [NOTIFY_SERVICE_REQUEST, status_byte] => {
+                    state.service_requests.push_back(status_byte)
+                }
+                _ => continue,
+            }
+        }
+    }
+
+    /// Waits for a service request notification, returning the status byte that came with it.
+    ///
+    /// `None` waits forever. The interface is only held for short slices of the wait, so other
+    /// operations can still use it.
+    pub fn wait_for_service_request(&self, timeout: Option<Duration>) -> Result<u8> {
+        let deadline = deadline(timeout);
+        loop {
+            let mut state = self.state.lock().unwrap();
+            if !state.transport()?.has_interrupt_in() {
+                return Err(Error::Visa(VisaError::OperationNotSupported));
+            }
+            if let Some(status_byte) = state.service_requests.pop_front() {
+                return Ok(status_byte);
+            }
+
+            let slice = Instant::now() + SERVICE_REQUEST_SLICE;
+            match state.interrupt(Some(deadline.map_or(slice, |deadline| deadline.min(slice)))) {
+                Ok([NOTIFY_SERVICE_REQUEST, status_byte]) => return Ok(status_byte),
+                Ok(_) => continue,
+                Err(Error::Visa(VisaError::Timeout))
+                    if deadline.is_none_or(|deadline| Instant::now() < deadline) =>
+                {
+                    continue;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Sends a USB488 trigger message, the equivalent of a GPIB group execute trigger.
+    pub fn trigger(&self) -> Result<()> {
+        if !self.capabilities.trigger {
+            return Err(Error::Visa(VisaError::OperationNotSupported));
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .send(USB488_TRIGGER, [0; 8], &[])
+            .map(|_| ())
+    }
+
+    /// Controls the remote enable state through the USB488 `REN_CONTROL`, `GO_TO_LOCAL` and `LOCAL_LOCKOUT` requests.
+    pub fn remote_local(&self, request: RemoteLocal) -> Result<()> {
+        if !self.capabilities.remote_local {
+            return Err(Error::Visa(VisaError::OperationNotSupported));
+        }
+
+        // USB488 has no requests to address devices, they go remote with the next message
+        let requests: &[(u8, u16)] = match request {
+            RemoteLocal::DisableRemote | RemoteLocal::DisableRemoteGoToLocal => &[(REN_CONTROL, 0)],
+            RemoteLocal::EnableRemote | RemoteLocal::EnableRemoteGoToRemote => &[(REN_CONTROL, 1)],
+            RemoteLocal::EnableRemoteLocalLockout
+            | RemoteLocal::EnableRemoteGoToRemoteLocalLockout => {
+                &[(REN_CONTROL, 1), (LOCAL_LOCKOUT, 0)]
+            }
+            RemoteLocal::GoToLocal => &[(GO_TO_LOCAL, 0)],
+        };
+
+        let mut state = self.state.lock().unwrap();
+        for (request, value) in requests {
+            state.command(Recipient::Interface, *request, *value, 1)?;
+        }
+        Ok(())
+    }
+
+    /// Blinks the device's activity indicator, if it has one.
+    pub fn indicator_pulse(&self) -> Result<()> {
+        self.state
+            .lock()
+            .unwrap()
+            .command(Recipient::Interface, INDICATOR_PULSE, 0, 1)
+            .map(|_| ())
+    }
+}
+
+impl<T: UsbTransport> Backend for UsbTmc<T> {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut state = self.state.lock().unwrap();
+
+        write_chunks(buf, CHUNK_SIZE, |chunk, last| {
+            let mut header = [0; 8];
+            header[..4].copy_from_slice(&(chunk.len() as u32).to_le_bytes());
+            if last && state.io.send_end {
+                header[4] = ATTRIBUTE_EOM;
+            }
+            state.send(DEV_DEP_MSG_OUT, header, chunk)?;
+            Ok(chunk.len())
+        })
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut state = self.state.lock().unwrap();
+        let mut count = 0;
+
+        loop {
+            let len = (buf.len() - count).min(CHUNK_SIZE);
+            let (data, attributes) = state.receive(len)?;
+            buf[count..count + data.len()].copy_from_slice(&data);
+            count += data.len();
+
+            if attributes & ATTRIBUTE_EOM != 0 {
+                return Ok((count, Status::OperationCompleted));
+            } else if attributes & ATTRIBUTE_TERM_CHAR != 0 {
+                return Ok((count, Status::TerminationCharacterRead));
+            } else if count == buf.len() {
+                return Ok((count, Status::BytesReadEqualInput));
+            }
+        }
+    }
+
+    fn flush(&self, _mode: FlushMode) -> Result<()> {
+        // Nothing is buffered on the host side
+        self.state.lock().unwrap().check_open()
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.state.lock().unwrap().clear()
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        UsbTmc::read_status_byte(self)
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        match protocol {
+            TriggerProtocol::Default => self.trigger(),
+            _ => Err(Error::Visa(VisaError::InvalidProtocol)),
+        }
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        _timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        // Claiming the interface already keeps other processes away from the device
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => Ok(None),
+            false => Ok(Some(key.unwrap_or("usb").to_owned())),
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        let state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.io.get(attribute)
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.io.set(attribute, value)
+    }
+
+    fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        if event_type == EventType::AllEnabled {
+            return Err(Error::Visa(VisaError::InvalidEvent));
+        }
+        check_service_request(event_type, mechanism)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.transport()?.has_interrupt_in() {
+            return Err(Error::Visa(VisaError::OperationNotSupported));
+        }
+        // Only requests received from now on are delivered, as with VISA
+        state.service_requests.clear();
+        state.service_requests_enabled = true;
+        Ok(())
+    }
+
+    fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        check_service_request(event_type, mechanism)?;
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.service_requests_enabled = false;
+        Ok(())
+    }
+
+    fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        check_service_request(event_type, mechanism)?;
+        let mut state = self.state.lock().unwrap();
+        state.check_open()?;
+        state.service_requests.clear();
+        Ok(())
+    }
+
+    fn wait_on_event(&self, event_type: EventType, timeout: Option<Duration>) -> Result<Event> {
+        check_service_request(event_type, EventMechanism::QUEUE)?;
+        {
+            let state = self.state.lock().unwrap();
+            state.check_open()?;
+            if !state.service_requests_enabled {
+                return Err(Error::Visa(VisaError::SessionNotEnabled));
+            }
+        }
+        self.wait_for_service_request(timeout)?;
+        Ok(Event::new(EventType::ServiceRequest))
+    }
+
+    fn close(&self) -> Result<()> {
+        // Dropping the transport releases the interface
+        self.state.lock().unwrap().transport = None;
+        Ok(())
+    }
+}
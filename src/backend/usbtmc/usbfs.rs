@@ -0,0 +1,377 @@
+//! USBTMC transport over Linux usbfs, with discovery through sysfs.
+
+use super::{Endpoint, Recipient, UsbTransport};
+use crate::error::{Error, Result, VisaError};
+use std::{
+    ffi::c_void,
+    fs::{self, File},
+    io,
+    mem::size_of,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::debug;
+
+const SYSFS_DEVICES: &str = "/sys/bus/usb/devices";
+const USBTMC_CLASS: u8 = 0xFE;
+const USBTMC_SUBCLASS: u8 = 0x03;
+
+const REQUEST_TYPE_INTERFACE: u8 = 0xA1;
+const REQUEST_TYPE_ENDPOINT: u8 = 0xA2;
+
+#[repr(C)]
+struct ControlTransfer {
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    length: u16,
+    timeout: u32,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+struct BulkTransfer {
+    endpoint: u32,
+    length: u32,
+    timeout: u32,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+struct Ioctl {
+    interface: i32,
+    code: i32,
+    data: *mut c_void,
+}
+
+/// Encodes an ioctl request number like the kernel's `_IOC` macro.
+const fn ioc(direction: u32, number: u32, size: usize) -> u32 {
+    direction << 30 | (size as u32) << 16 | (b'U' as u32) << 8 | number
+}
+
+const IOC_NONE: u32 = 0;
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+const USBDEVFS_CONTROL: u32 = ioc(IOC_READ | IOC_WRITE, 0, size_of::<ControlTransfer>());
+const USBDEVFS_BULK: u32 = ioc(IOC_READ | IOC_WRITE, 2, size_of::<BulkTransfer>());
+const USBDEVFS_CLAIMINTERFACE: u32 = ioc(IOC_READ, 15, size_of::<u32>());
+const USBDEVFS_RELEASEINTERFACE: u32 = ioc(IOC_READ, 16, size_of::<u32>());
+const USBDEVFS_IOCTL: u32 = ioc(IOC_READ | IOC_WRITE, 18, size_of::<Ioctl>());
+const USBDEVFS_CLEAR_HALT: u32 = ioc(IOC_READ, 21, size_of::<u32>());
+const USBDEVFS_DISCONNECT: u32 = ioc(IOC_NONE, 22, 0);
+const USBDEVFS_CONNECT: u32 = ioc(IOC_NONE, 23, 0);
+
+fn timeout_ms(timeout: Option<Duration>) -> u32 {
+    match timeout {
+        // usbfs waits forever on a zero timeout
+        Some(timeout) => timeout.as_millis().clamp(1, u32::MAX as _) as u32,
+        None => 0,
+    }
+}
+
+fn map_usb_error(error: io::Error) -> Error {
+    match error.raw_os_error() {
+        Some(libc::ETIMEDOUT) => Error::Visa(VisaError::Timeout),
+        Some(libc::ENODEV) => Error::Visa(VisaError::ConnectionLost),
+        Some(libc::EBUSY) => Error::Visa(VisaError::ResourceBusy),
+        _ => Error::Io(error),
+    }
+}
+
+/// A USBTMC interface found in sysfs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UsbTmcInterface {
+    bus: u8,
+    address: u8,
+    vendor_id: u16,
+    product_id: u16,
+    serial_number: String,
+    interface_number: u8,
+    bulk_out: u8,
+    bulk_in: u8,
+    interrupt_in: Option<u8>,
+}
+
+impl UsbTmcInterface {
+    /// Canonical VISA resource name, the interface number only appears when it is not the first one.
+    pub(crate) fn resource_name(&self) -> String {
+        let interface = match self.interface_number {
+            0 => String::new(),
+            number => format!("::{}", number),
+        };
+        format!(
+            "USB0::0x{:04X}::0x{:04X}::{}{}::INSTR",
+            self.vendor_id, self.product_id, self.serial_number, interface
+        )
+    }
+}
+
+fn read_attribute(path: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(path.join(name))
+        .ok()
+        .map(|value| value.trim().to_owned())
+}
+
+fn read_hex<T: TryFrom<u32>>(path: &Path, name: &str) -> Option<T> {
+    let value = u32::from_str_radix(&read_attribute(path, name)?, 16).ok()?;
+    T::try_from(value).ok()
+}
+
+fn read_decimal<T: std::str::FromStr>(path: &Path, name: &str) -> Option<T> {
+    read_attribute(path, name)?.parse().ok()
+}
+
+fn parse_interface(path: &Path) -> Option<UsbTmcInterface> {
+    if read_hex::<u8>(path, "bInterfaceClass")? != USBTMC_CLASS
+        || read_hex::<u8>(path, "bInterfaceSubClass")? != USBTMC_SUBCLASS
+    {
+        return None;
+    }
+    let device = path
+        .parent()?
+        .join(path.file_name()?.to_str()?.split(':').next()?);
+
+    let mut bulk_out = None;
+    let mut bulk_in = None;
+    let mut interrupt_in = None;
+    for entry in fs::read_dir(path).ok()?.flatten() {
+        let endpoint = entry.path();
+        if !entry.file_name().to_string_lossy().starts_with("ep_") {
+            continue;
+        }
+        let address = read_hex::<u8>(&endpoint, "bEndpointAddress")?;
+        let kind = read_attribute(&endpoint, "type")?;
+        match (kind.as_str(), address & 0x80 != 0) {
+            ("Bulk", false) => bulk_out = Some(address),
+            ("Bulk", true) => bulk_in = Some(address),
+            ("Interrupt", true) => interrupt_in = Some(address),
+            _ => {}
+        }
+    }
+
+    Some(UsbTmcInterface {
+        bus: read_decimal(&device, "busnum")?,
+        address: read_decimal(&device, "devnum")?,
+        vendor_id: read_hex(&device, "idVendor")?,
+        product_id: read_hex(&device, "idProduct")?,
+        serial_number: read_attribute(&device, "serial").unwrap_or_default(),
+        interface_number: read_hex(path, "bInterfaceNumber")?,
+        bulk_out: bulk_out?,
+        bulk_in: bulk_in?,
+        interrupt_in,
+    })
+}
+
+/// Lists the USBTMC interfaces of the connected devices.
+pub(crate) fn find_interfaces() -> Vec<UsbTmcInterface> {
+    let Ok(entries) = fs::read_dir(SYSFS_DEVICES) else {
+        return vec![];
+    };
+
+    // Interfaces are named `bus-port[.port]:configuration.interface`
+    let mut interfaces: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().contains(':'))
+        .filter_map(|entry| parse_interface(&entry.path()))
+        .collect();
+    interfaces
+        .sort_by_key(|interface| (interface.bus, interface.address, interface.interface_number));
+    interfaces
+}
+
+/// USBTMC transport through Linux usbfs, claiming the interface from the kernel's usbtmc driver.
+///
+/// The user needs read and write access to the device node under `/dev/bus/usb`,
+/// usually granted by a udev rule.
+#[derive(Debug)]
+pub struct UsbFs {
+    file: File,
+    path: PathBuf,
+    interface_number: u8,
+    bulk_out: u8,
+    bulk_in: u8,
+    interrupt_in: Option<u8>,
+    /// Whether a kernel driver was detached and has to be reattached when done.
+    detached: bool,
+}
+
+impl UsbFs {
+    /// Opens the USBTMC interface of the device with the given IDs.
+    ///
+    /// An empty serial number matches any device, and without an interface number the
+    /// first USBTMC interface of the device is used.
+    pub fn open(
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: &str,
+        interface_number: Option<u8>,
+    ) -> Result<Self> {
+        let interface = find_interfaces()
+            .into_iter()
+            .find(|interface| {
+                interface.vendor_id == vendor_id
+                    && interface.product_id == product_id
+                    && (serial_number.is_empty() || interface.serial_number == serial_number)
+                    && interface_number.is_none_or(|number| number == interface.interface_number)
+            })
+            .ok_or(Error::Visa(VisaError::ResourceNotFound))?;
+        Self::from_interface(&interface)
+    }
+
+    pub(crate) fn from_interface(interface: &UsbTmcInterface) -> Result<Self> {
+        let path = PathBuf::from(format!(
+            "/dev/bus/usb/{:03}/{:03}",
+            interface.bus, interface.address
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(map_usb_error)?;
+
+        let mut usbfs = Self {
+            file,
+            path,
+            interface_number: interface.interface_number,
+            bulk_out: interface.bulk_out,
+            bulk_in: interface.bulk_in,
+            interrupt_in: interface.interrupt_in,
+            detached: false,
+        };
+
+        // Fails when no driver is bound, which is fine
+        let mut disconnect = Ioctl {
+            interface: interface.interface_number as i32,
+            code: USBDEVFS_DISCONNECT as i32,
+            data: std::ptr::null_mut(),
+        };
+        usbfs.detached = usbfs.ioctl(USBDEVFS_IOCTL, &mut disconnect).is_ok();
+
+        let mut number = interface.interface_number as u32;
+        usbfs.ioctl(USBDEVFS_CLAIMINTERFACE, &mut number)?;
+        debug!(
+            "claimed interface {} of {}",
+            interface.interface_number,
+            usbfs.path.display()
+        );
+        Ok(usbfs)
+    }
+
+    fn ioctl<T>(&self, request: u32, argument: &mut T) -> Result<i32> {
+        let result = unsafe {
+            libc::ioctl(
+                self.file.as_raw_fd(),
+                request as _,
+                argument as *mut T as *mut c_void,
+            )
+        };
+        match result {
+            -1 => Err(map_usb_error(io::Error::last_os_error())),
+            count => Ok(count),
+        }
+    }
+
+    fn bulk(
+        &mut self,
+        endpoint: u8,
+        buf: *mut c_void,
+        len: usize,
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let mut transfer = BulkTransfer {
+            endpoint: endpoint as u32,
+            length: len as u32,
+            timeout: timeout_ms(timeout),
+            data: buf,
+        };
+        self.ioctl(USBDEVFS_BULK, &mut transfer)
+            .map(|count| count as usize)
+    }
+
+    fn endpoint_address(&self, endpoint: Endpoint) -> Result<u8> {
+        match endpoint {
+            Endpoint::BulkOut => Ok(self.bulk_out),
+            Endpoint::BulkIn => Ok(self.bulk_in),
+            Endpoint::InterruptIn => self
+                .interrupt_in
+                .ok_or(Error::Visa(VisaError::OperationNotSupported)),
+        }
+    }
+}
+
+impl UsbTransport for UsbFs {
+    fn bulk_out(&mut self, data: &[u8], timeout: Option<Duration>) -> Result<usize> {
+        // usbfs only reads from the buffer of an OUT transfer
+        self.bulk(
+            self.bulk_out,
+            data.as_ptr() as *mut c_void,
+            data.len(),
+            timeout,
+        )
+    }
+
+    fn bulk_in(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize> {
+        self.bulk(self.bulk_in, buf.as_mut_ptr() as _, buf.len(), timeout)
+    }
+
+    fn has_interrupt_in(&self) -> bool {
+        self.interrupt_in.is_some()
+    }
+
+    fn interrupt_in(&mut self, buf: &mut [u8], timeout: Option<Duration>) -> Result<usize> {
+        let endpoint = self.endpoint_address(Endpoint::InterruptIn)?;
+        // usbfs turns bulk requests on interrupt endpoints into interrupt transfers
+        self.bulk(endpoint, buf.as_mut_ptr() as _, buf.len(), timeout)
+    }
+
+    fn control_in(
+        &mut self,
+        recipient: Recipient,
+        request: u8,
+        value: u16,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let (request_type, index) = match recipient {
+            Recipient::Interface => (REQUEST_TYPE_INTERFACE, self.interface_number as u16),
+            Recipient::Endpoint(endpoint) => (
+                REQUEST_TYPE_ENDPOINT,
+                self.endpoint_address(endpoint)? as u16,
+            ),
+        };
+        let mut transfer = ControlTransfer {
+            request_type,
+            request,
+            value,
+            index,
+            length: buf.len() as u16,
+            timeout: timeout_ms(timeout),
+            data: buf.as_mut_ptr() as _,
+        };
+        self.ioctl(USBDEVFS_CONTROL, &mut transfer)
+            .map(|count| count as usize)
+    }
+
+    fn clear_halt(&mut self, endpoint: Endpoint) -> Result<()> {
+        let mut address = self.endpoint_address(endpoint)? as u32;
+        self.ioctl(USBDEVFS_CLEAR_HALT, &mut address).map(|_| ())
+    }
+}
+
+impl Drop for UsbFs {
+    fn drop(&mut self) {
+        let mut number = self.interface_number as u32;
+        let _ = self.ioctl(USBDEVFS_RELEASEINTERFACE, &mut number);
+        if self.detached {
+            let mut connect = Ioctl {
+                interface: self.interface_number as i32,
+                code: USBDEVFS_CONNECT as i32,
+                data: std::ptr::null_mut(),
+            };
+            let _ = self.ioctl(USBDEVFS_IOCTL, &mut connect);
+        }
+    }
+}
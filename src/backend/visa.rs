@@ -0,0 +1,516 @@
+use super::Backend;
+use crate::{
+    attribute::{AttributeKind, AttributeValue, check_kind},
+    bindings::*,
+    error::{Error, Result, Status, VisaError, parse_vi_status},
+    event::{Event, EventHandler, EventMechanism, EventType, HandlerId},
+    instrument::{FlushMode, TriggerProtocol},
+    library::{library, loaded_library, require},
+    resource_manager::AccessMode,
+};
+use std::{
+    collections::BTreeMap,
+    ffi::{CStr, CString},
+    fmt::Debug,
+    mem::ManuallyDrop,
+    panic::{self, AssertUnwindSafe},
+    str::FromStr,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tracing::warn;
+
+/// A session opened through the VISA library, closed on drop.
+#[derive(Debug)]
+pub(crate) struct VisaSession {
+    inner: AtomicU32,
+}
+
+/// A handler shared with the calls running it, so it outlives its uninstallation until they return.
+type SharedHandler = Arc<Mutex<EventHandler>>;
+
+/// A handler installed on a session.
+struct Installed {
+    session: ViSession,
+    event_type: EventType,
+    handler: SharedHandler,
+}
+
+/// Handlers installed on every session, by the identifier passed to VISA as their user handle.
+///
+/// [`dispatch`] only finds handlers through this table, never through a pointer into a session,
+/// so a call racing with the session being closed and dropped finds nothing rather than freed memory.
+static HANDLERS: Mutex<BTreeMap<u64, Installed>> = Mutex::new(BTreeMap::new());
+static NEXT_HANDLER_ID: AtomicU64 = AtomicU64::new(0);
+
+impl VisaSession {
+    pub(crate) fn new(session: ViSession) -> Self {
+        Self {
+            inner: AtomicU32::new(session as _),
+        }
+    }
+
+    fn session(&self) -> ViSession {
+        self.inner.load(Ordering::Acquire) as _
+    }
+}
+
+impl Backend for VisaSession {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let visa = library()?;
+        let mut ret_cnt: ViUInt32 = 0;
+        unsafe {
+            let status = visa.viWrite(
+                self.session(),
+                buf.as_ptr(),
+                buf.len() as _,
+                &mut ret_cnt as _,
+            );
+            parse_vi_status(status)?;
+        }
+        Ok(ret_cnt as _)
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let visa = library()?;
+        let mut ret_cnt: ViUInt32 = 0;
+        let status = unsafe {
+            let status = visa.viRead(
+                self.session(),
+                buf.as_mut_ptr(),
+                buf.len() as _,
+                &mut ret_cnt as _,
+            );
+            parse_vi_status(status)?
+        };
+        Ok((ret_cnt as _, status))
+    }
+
+    fn flush(&self, mode: FlushMode) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viFlush)?;
+        unsafe {
+            let status = visa.viFlush(self.session(), mode.bits());
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn buffered_write(&self, buf: &[u8]) -> Result<usize> {
+        let visa = library()?;
+        require(&visa.viBufWrite)?;
+        let mut ret_cnt: ViUInt32 = 0;
+        unsafe {
+            let status = visa.viBufWrite(
+                self.session(),
+                buf.as_ptr(),
+                buf.len() as _,
+                &mut ret_cnt as _,
+            );
+            parse_vi_status(status)?;
+        }
+        Ok(ret_cnt as _)
+    }
+
+    fn buffered_read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let visa = library()?;
+        require(&visa.viBufRead)?;
+        let mut ret_cnt: ViUInt32 = 0;
+        let status = unsafe {
+            let status = visa.viBufRead(
+                self.session(),
+                buf.as_mut_ptr(),
+                buf.len() as _,
+                &mut ret_cnt as _,
+            );
+            parse_vi_status(status)?
+        };
+        Ok((ret_cnt as _, status))
+    }
+
+    fn set_buffer_size(&self, mask: FlushMode, size: usize) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viSetBuf)?;
+        unsafe {
+            let status = visa.viSetBuf(self.session(), mask.bits(), size as _);
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viClear)?;
+        unsafe {
+            let status = visa.viClear(self.session());
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        let visa = library()?;
+        require(&visa.viReadSTB)?;
+        let mut status_byte: ViUInt16 = 0;
+        unsafe {
+            let status = visa.viReadSTB(self.session(), &mut status_byte as _);
+            parse_vi_status(status)?;
+        }
+        Ok(status_byte as _)
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viAssertTrigger)?;
+        unsafe {
+            let status = visa.viAssertTrigger(self.session(), protocol as _);
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        timeout: Duration,
+        key: Option<&str>,
+    ) -> Result<Option<String>> {
+        let visa = library()?;
+        require(&visa.viLock)?;
+        match (mode & AccessMode::SHARED_LOCK).is_empty() {
+            true => unsafe {
+                let status = visa.viLock(
+                    self.session(),
+                    mode.bits(),
+                    timeout.as_millis() as _,
+                    VI_NULL as _,
+                    VI_NULL as _,
+                );
+                parse_vi_status(status)?;
+                Ok(None)
+            },
+            false => {
+                let key = match key {
+                    Some(key) => {
+                        let key = CString::from_str(key).map_err(|_| Error::InvalidString)?;
+                        Some(key)
+                    }
+                    None => None,
+                };
+
+                let mut access_key = [0; VI_FIND_BUFLEN as _];
+                unsafe {
+                    let status = visa.viLock(
+                        self.session(),
+                        mode.bits(),
+                        timeout.as_millis() as _,
+                        key.as_ref().map(|key| key.as_ptr()).unwrap_or(VI_NULL as _),
+                        access_key.as_mut_ptr() as _,
+                    );
+                    parse_vi_status(status)?;
+                }
+
+                let access_key = CStr::from_bytes_until_nul(&access_key)
+                    .map_err(|_| Error::InvalidString)?
+                    .to_str()
+                    .map_err(|_| Error::InvalidString)?;
+                Ok(Some(access_key.to_owned()))
+            }
+        }
+    }
+
+    fn unlock(&self) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viUnlock)?;
+        unsafe {
+            let status = visa.viUnlock(self.session());
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, kind: AttributeKind) -> Result<AttributeValue> {
+        get_vi_attribute(self.session(), attribute, kind)
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        set_vi_attribute(self.session(), attribute, value)
+    }
+
+    fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viEnableEvent)?;
+        unsafe {
+            let status = visa.viEnableEvent(
+                self.session(),
+                event_type as _,
+                mechanism.bits(),
+                VI_NULL as _,
+            );
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viDisableEvent)?;
+        unsafe {
+            let status = visa.viDisableEvent(self.session(), event_type as _, mechanism.bits());
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viDiscardEvents)?;
+        unsafe {
+            let status = visa.viDiscardEvents(self.session(), event_type as _, mechanism.bits());
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn wait_on_event(&self, event_type: EventType, timeout: Option<Duration>) -> Result<Event> {
+        let visa = library()?;
+        require(&visa.viWaitOnEvent)?;
+        let timeout = match timeout {
+            Some(timeout) => timeout.as_millis().min(VI_TMO_INFINITE as u128 - 1) as _,
+            None => VI_TMO_INFINITE as _,
+        };
+        let mut out_event_type: ViEventType = 0;
+        let mut context: ViEvent = 0;
+        unsafe {
+            let status = visa.viWaitOnEvent(
+                self.session(),
+                event_type as _,
+                timeout,
+                &mut out_event_type as _,
+                &mut context as _,
+            );
+            parse_vi_status(status)?;
+        }
+        match EventType::try_from(out_event_type) {
+            Ok(event_type) => Ok(Event::from_context(event_type, context)),
+            Err(error) => {
+                unsafe { visa.viClose(context) };
+                Err(Error::Visa(error))
+            }
+        }
+    }
+
+    fn install_handler(&self, event_type: EventType, handler: EventHandler) -> Result<HandlerId> {
+        let visa = library()?;
+        require(&visa.viInstallHandler)?;
+        require(&visa.viUninstallHandler)?;
+        let session = self.session();
+
+        // Registered first, the handler may be called before viInstallHandler returns
+        let id = NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed);
+        HANDLERS.lock().unwrap().insert(
+            id,
+            Installed {
+                session,
+                event_type,
+                handler: Arc::new(Mutex::new(handler)),
+            },
+        );
+        unsafe {
+            let status = visa.viInstallHandler(
+                session,
+                event_type as _,
+                Some(dispatch),
+                id as usize as ViAddr,
+            );
+            if let Err(error) = parse_vi_status(status) {
+                HANDLERS.lock().unwrap().remove(&id);
+                return Err(error);
+            }
+        }
+        Ok(HandlerId(id))
+    }
+
+    fn uninstall_handler(&self, event_type: EventType, id: HandlerId) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viUninstallHandler)?;
+        unsafe {
+            let status = visa.viUninstallHandler(
+                self.session(),
+                event_type as _,
+                Some(dispatch),
+                id.0 as usize as ViAddr,
+            );
+            parse_vi_status(status)?;
+        }
+        HANDLERS.lock().unwrap().remove(&id.0);
+        Ok(())
+    }
+
+    fn close(&self) -> Result<()> {
+        let session = self.inner.swap(VI_NULL, Ordering::AcqRel);
+        if session == VI_NULL {
+            return Ok(());
+        }
+
+        let visa = library()?;
+        // Taken out of the table first, calls still running keep their handler alive
+        let installed: Vec<_> = {
+            let mut handlers = HANDLERS.lock().unwrap();
+            let ids: Vec<u64> = handlers
+                .iter()
+                .filter(|(_, installed)| installed.session == session as ViSession)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.into_iter()
+                .filter_map(|id| {
+                    handlers
+                        .remove(&id)
+                        .map(|installed| (id, installed.event_type))
+                })
+                .collect()
+        };
+        // Handlers are only installed where they can be uninstalled
+        for (id, event_type) in installed {
+            unsafe {
+                visa.viUninstallHandler(
+                    session as _,
+                    event_type as _,
+                    Some(dispatch),
+                    id as usize as ViAddr,
+                );
+            }
+        }
+        unsafe {
+            let status = visa.viClose(session as _);
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn as_vi_session(&self) -> Option<ViSession> {
+        Some(self.session())
+    }
+}
+
+impl Drop for VisaSession {
+    fn drop(&mut self) {
+        // A session can only have been opened through a loaded library
+        if loaded_library().is_some() {
+            let _ = self.close();
+        }
+    }
+}
+
+/// Calls the handler `user_handle` identifies in [`HANDLERS`], if it's still installed.
+/// Panics are caught, unwinding into the VISA library would abort.
+unsafe extern "C" fn dispatch(
+    _session: ViSession,
+    event_type: ViEventType,
+    context: ViEvent,
+    user_handle: ViAddr,
+) -> ViStatus {
+    let handler = HANDLERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&(user_handle as usize as u64))
+        .map(|installed| installed.handler.clone());
+    let (Some(handler), Ok(event_type)) = (handler, EventType::try_from(event_type)) else {
+        return VI_SUCCESS as _;
+    };
+
+    // The library closes the context once the handler returns
+    let event = ManuallyDrop::new(Event::from_context(event_type, context));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut handler = handler.lock().unwrap_or_else(PoisonError::into_inner);
+        handler(&event)
+    }));
+    if result.is_err() {
+        warn!("handler of {:?} events panicked", event_type);
+    }
+    VI_SUCCESS as _
+}
+
+/// Reads the attribute `attribute` of a session or event context, whose values are of type `kind`.
+///
+/// Typed attributes must be read as their own type. The value is read into a buffer as large as
+/// the largest attribute type whatever `kind` is, so VISA never writes past it.
+pub(crate) fn get_vi_attribute(
+    object: ViObject,
+    attribute: u32,
+    kind: AttributeKind,
+) -> Result<AttributeValue> {
+    check_kind(attribute, kind)?;
+    let visa = library()?;
+    require(&visa.viGetAttribute)?;
+
+    // String attributes are at most as long as resource names, the u64s keep integers aligned
+    let mut buffer = [0u64; VI_FIND_BUFLEN as usize / 8];
+    unsafe {
+        let status = visa.viGetAttribute(object, attribute as _, buffer.as_mut_ptr() as _);
+        parse_vi_status(status)?;
+    }
+    let bytes: Vec<u8> = buffer.iter().flat_map(|word| word.to_ne_bytes()).collect();
+    let head = |len: usize| &bytes[..len];
+
+    let value = match kind {
+        AttributeKind::U8 => AttributeValue::U8(bytes[0]),
+        AttributeKind::U16 => AttributeValue::U16(u16::from_ne_bytes(head(2).try_into().unwrap())),
+        AttributeKind::U32 => AttributeValue::U32(u32::from_ne_bytes(head(4).try_into().unwrap())),
+        AttributeKind::U64 => AttributeValue::U64(u64::from_ne_bytes(head(8).try_into().unwrap())),
+        AttributeKind::I16 => AttributeValue::I16(i16::from_ne_bytes(head(2).try_into().unwrap())),
+        AttributeKind::I32 => AttributeValue::I32(i32::from_ne_bytes(head(4).try_into().unwrap())),
+        AttributeKind::Bool => {
+            AttributeValue::Bool(u16::from_ne_bytes(head(2).try_into().unwrap()) != VI_FALSE as _)
+        }
+        AttributeKind::String => {
+            let value = CStr::from_bytes_until_nul(&bytes)
+                .map_err(|_| Error::InvalidString)?
+                .to_str()
+                .map_err(|_| Error::InvalidString)?;
+            AttributeValue::String(value.to_owned())
+        }
+    };
+    Ok(value)
+}
+
+/// Sets the attribute `attribute` of a session or event context.
+pub(crate) fn set_vi_attribute(
+    object: ViObject,
+    attribute: u32,
+    value: &AttributeValue,
+) -> Result<()> {
+    let visa = library()?;
+    require(&visa.viSetAttribute)?;
+    // Keeps string values alive until the call returns
+    let string;
+    let state: ViAttrState = match value {
+        AttributeValue::U8(value) => *value as _,
+        AttributeValue::U16(value) => *value as _,
+        AttributeValue::U32(value) => *value as _,
+        AttributeValue::U64(value) => *value as _,
+        AttributeValue::I16(value) => *value as _,
+        AttributeValue::I32(value) => *value as _,
+        AttributeValue::Bool(true) => VI_TRUE as _,
+        AttributeValue::Bool(false) => VI_FALSE as _,
+        AttributeValue::String(value) => {
+            string = CString::from_str(value).map_err(|_| Error::InvalidString)?;
+            string.as_ptr() as usize as _
+        }
+    };
+
+    let status = unsafe {
+        let status = visa.viSetAttribute(object, attribute as _, state);
+        parse_vi_status(status)?
+    };
+    // The state is valid but the implementation ignored it
+    match status {
+        Status::AttributeStateNotSupported => {
+            Err(Error::Visa(VisaError::AttributeStateNotSupported))
+        }
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,407 @@
+mod rpc;
+#[cfg(feature = "test-servers")]
+mod server;
+
+use super::{Backend, DEFAULT_TIMEOUT, IoSettings, connect, write_chunks};
+use crate::{
+    attribute::{AttributeKind, AttributeValue},
+    error::{Error, Result, Status, VisaError},
+    instrument::{FlushMode, TriggerProtocol},
+    resource_manager::AccessMode,
+};
+use rpc::{Client, Decoder, Encoder, PORTMAPPER_PORT};
+use std::{
+    net::Shutdown,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+use tracing::debug;
+
+#[cfg(feature = "test-servers")]
+pub use server::*;
+
+pub(crate) const DEVICE_CORE: u32 = 0x0607AF;
+pub(crate) const DEVICE_ASYNC: u32 = 0x0607B0;
+pub(crate) const DEVICE_VERSION: u32 = 1;
+
+pub(crate) const DEVICE_ABORT: u32 = 1;
+pub(crate) const CREATE_LINK: u32 = 10;
+pub(crate) const DEVICE_WRITE: u32 = 11;
+pub(crate) const DEVICE_READ: u32 = 12;
+pub(crate) const DEVICE_READSTB: u32 = 13;
+pub(crate) const DEVICE_TRIGGER: u32 = 14;
+pub(crate) const DEVICE_CLEAR: u32 = 15;
+pub(crate) const DEVICE_REMOTE: u32 = 16;
+pub(crate) const DEVICE_LOCAL: u32 = 17;
+pub(crate) const DEVICE_LOCK: u32 = 18;
+pub(crate) const DEVICE_UNLOCK: u32 = 19;
+pub(crate) const DESTROY_LINK: u32 = 23;
+
+pub(crate) const FLAG_WAIT_LOCK: u32 = 0x01;
+pub(crate) const FLAG_END: u32 = 0x08;
+pub(crate) const FLAG_TERM_CHAR_SET: u32 = 0x80;
+
+#[cfg(feature = "test-servers")]
+pub(crate) const REASON_REQUEST_COUNT: i32 = 0x01;
+pub(crate) const REASON_TERM_CHAR: i32 = 0x02;
+pub(crate) const REASON_END: i32 = 0x04;
+
+pub(crate) const ERROR_NONE: i32 = 0;
+pub(crate) const ERROR_INVALID_LINK: i32 = 4;
+pub(crate) const ERROR_PARAMETER: i32 = 5;
+pub(crate) const ERROR_NOT_SUPPORTED: i32 = 8;
+pub(crate) const ERROR_LOCKED: i32 = 11;
+pub(crate) const ERROR_NO_LOCK: i32 = 12;
+pub(crate) const ERROR_IO_TIMEOUT: i32 = 15;
+pub(crate) const ERROR_ABORT: i32 = 23;
+
+/// Largest amount of data requested by a single `device_read`, keeping replies well below
+/// the largest record accepted.
+const MAX_READ_SIZE: usize = 1 << 20;
+/// Extra time given to the server to reply after the I/O timeout it was given expired.
+const REPLY_MARGIN: Duration = Duration::from_secs(1);
+
+/// Maps a VXI-11 `Device_ErrorCode` to the closest VISA error.
+fn check(error: i32) -> Result<()> {
+    let error = match error {
+        ERROR_NONE => return Ok(()),
+        1 | ERROR_PARAMETER => VisaError::InvalidParameter,
+        3 => VisaError::ResourceNotFound,
+        ERROR_INVALID_LINK => VisaError::InvalidObject,
+        ERROR_NOT_SUPPORTED => VisaError::OperationNotSupported,
+        9 => VisaError::Allocation,
+        ERROR_LOCKED => VisaError::ResourceLocked,
+        ERROR_NO_LOCK => VisaError::SessionNotLocked,
+        ERROR_IO_TIMEOUT => VisaError::Timeout,
+        21 => VisaError::InvalidResourceName,
+        ERROR_ABORT => VisaError::OperationAborted,
+        _ => VisaError::Io,
+    };
+    Err(Error::Visa(error))
+}
+
+fn millis(duration: Option<Duration>) -> u32 {
+    duration
+        .map(|duration| duration.as_millis().min(u32::MAX as _) as u32)
+        .unwrap_or(u32::MAX)
+}
+
+#[derive(Debug)]
+struct Core {
+    client: Client,
+    io: IoSettings,
+}
+
+impl Core {
+    fn call(&mut self, procedure: u32, arguments: Encoder) -> Result<Vec<u8>> {
+        let timeout = self.io.timeout.map(|timeout| timeout + REPLY_MARGIN);
+        self.client.call(procedure, &arguments.finish(), timeout)
+    }
+}
+
+/// Native VXI-11 client for `TCPIP::host[::device name]::INSTR` resources.
+///
+/// The core channel is looked up through the portmapper of the host, the abort
+/// channel is connected lazily the first time an operation is aborted.
+#[derive(Debug)]
+pub struct Vxi11 {
+    core: Mutex<Core>,
+    abort: Mutex<Option<Client>>,
+    host: String,
+    link: u32,
+    abort_port: u16,
+    max_receive_size: u32,
+    lock_timeout: AtomicU32,
+    closed: AtomicBool,
+}
+
+impl Vxi11 {
+    /// Connects to `device` on `host`, asking the portmapper on its standard port where the core channel is.
+    pub fn connect(host: &str, device: &str, timeout: Duration) -> Result<Self> {
+        Self::connect_with_portmapper(host, PORTMAPPER_PORT, device, timeout)
+    }
+
+    /// Connects to `device` on `host`, asking the portmapper listening on `portmapper_port` where the core channel is.
+    pub fn connect_with_portmapper(
+        host: &str,
+        portmapper_port: u16,
+        device: &str,
+        timeout: Duration,
+    ) -> Result<Self> {
+        let port = rpc::get_port(host, portmapper_port, DEVICE_CORE, DEVICE_VERSION, timeout)?;
+        let stream = connect(host, port, timeout)?;
+        let mut core = Core {
+            client: Client::new(stream, DEVICE_CORE, DEVICE_VERSION),
+            io: IoSettings::new(DEFAULT_TIMEOUT),
+        };
+
+        let reply = core.call(
+            CREATE_LINK,
+            Encoder::new()
+                .i32(std::process::id() as i32)
+                .bool(false)
+                .u32(0)
+                .string(device),
+        )?;
+        let mut reply = Decoder::new(&reply);
+        check(reply.i32()?)?;
+        let link = reply.u32()?;
+        let abort_port = reply.u32()? as u16;
+        let max_receive_size = reply.u32()?;
+        debug!("created link {} to {} on {}", link, device, host);
+
+        Ok(Self {
+            core: Mutex::new(core),
+            abort: Mutex::new(None),
+            host: host.to_owned(),
+            link,
+            abort_port,
+            // Some servers report 0, fall back to the minimum the specification allows
+            max_receive_size: max_receive_size.max(1024),
+            lock_timeout: AtomicU32::new(0),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    fn generic(&self, procedure: u32) -> Result<Vec<u8>> {
+        let mut core = self.core.lock().unwrap();
+        let io_timeout = millis(core.io.timeout);
+        core.call(
+            procedure,
+            Encoder::new()
+                .u32(self.link)
+                .u32(self.lock_flags())
+                .u32(self.lock_timeout.load(Ordering::Relaxed))
+                .u32(io_timeout),
+        )
+    }
+
+    fn lock_flags(&self) -> u32 {
+        match self.lock_timeout.load(Ordering::Relaxed) {
+            0 => 0,
+            _ => FLAG_WAIT_LOCK,
+        }
+    }
+
+    /// Reads the status byte of the device (`device_readstb`).
+    pub fn read_status_byte(&self) -> Result<u8> {
+        let reply = self.generic(DEVICE_READSTB)?;
+        let mut reply = Decoder::new(&reply);
+        check(reply.i32()?)?;
+        Ok(reply.u32()? as u8)
+    }
+
+    /// Sends a group execute trigger to the device (`device_trigger`).
+    pub fn trigger(&self) -> Result<()> {
+        let reply = self.generic(DEVICE_TRIGGER)?;
+        check(Decoder::new(&reply).i32()?)
+    }
+
+    /// Places the device in remote state (`device_remote`).
+    pub fn remote(&self) -> Result<()> {
+        let reply = self.generic(DEVICE_REMOTE)?;
+        check(Decoder::new(&reply).i32()?)
+    }
+
+    /// Places the device in local state (`device_local`).
+    pub fn local(&self) -> Result<()> {
+        let reply = self.generic(DEVICE_LOCAL)?;
+        check(Decoder::new(&reply).i32()?)
+    }
+
+    /// Aborts the operation in progress on the core channel through the abort channel.
+    pub fn abort(&self) -> Result<()> {
+        let mut abort = self.abort.lock().unwrap();
+        if abort.is_none() {
+            let stream = connect(&self.host, self.abort_port, DEFAULT_TIMEOUT)?;
+            *abort = Some(Client::new(stream, DEVICE_ASYNC, DEVICE_VERSION));
+        }
+
+        let client = abort.as_mut().unwrap();
+        let reply = client.call(
+            DEVICE_ABORT,
+            &Encoder::new().u32(self.link).finish(),
+            Some(DEFAULT_TIMEOUT),
+        )?;
+        check(Decoder::new(&reply).i32()?)
+    }
+
+    /// Sets how long operations wait for a lock held by another link, zero fails immediately.
+    pub fn set_lock_timeout(&self, timeout: Duration) {
+        self.lock_timeout
+            .store(millis(Some(timeout)), Ordering::Relaxed);
+    }
+
+    /// Sets the I/O timeout, `None` waits forever.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        self.core.lock().unwrap().io.timeout = timeout;
+    }
+}
+
+impl Backend for Vxi11 {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        let mut core = self.core.lock().unwrap();
+        let io_timeout = millis(core.io.timeout);
+        let lock_timeout = self.lock_timeout.load(Ordering::Relaxed);
+
+        // The device may accept only part of a chunk, the rest is sent again
+        write_chunks(buf, self.max_receive_size as _, |chunk, last| {
+            let mut flags = self.lock_flags();
+            if last && core.io.send_end {
+                flags |= FLAG_END;
+            }
+
+            let reply = core.call(
+                DEVICE_WRITE,
+                Encoder::new()
+                    .u32(self.link)
+                    .u32(io_timeout)
+                    .u32(lock_timeout)
+                    .u32(flags)
+                    .opaque(chunk),
+            )?;
+            let mut reply = Decoder::new(&reply);
+            check(reply.i32()?)?;
+            Ok(reply.u32()? as usize)
+        })
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut core = self.core.lock().unwrap();
+        let io_timeout = millis(core.io.timeout);
+        let mut count = 0;
+
+        loop {
+            let mut flags = self.lock_flags();
+            if core.io.term_char_enabled {
+                flags |= FLAG_TERM_CHAR_SET;
+            }
+            let term_char = core.io.term_char;
+            let request_size = (buf.len() - count).min(MAX_READ_SIZE);
+
+            let reply = core.call(
+                DEVICE_READ,
+                Encoder::new()
+                    .u32(self.link)
+                    .u32(request_size as u32)
+                    .u32(io_timeout)
+                    .u32(self.lock_timeout.load(Ordering::Relaxed))
+                    .u32(flags)
+                    .u32(term_char as u32),
+            )?;
+            let mut reply = Decoder::new(&reply);
+            check(reply.i32()?)?;
+            let reason = reply.i32()?;
+            let data = reply.opaque()?;
+
+            // More than was asked for can't be kept for the next read
+            if data.len() > request_size {
+                return Err(Error::Visa(VisaError::Io));
+            }
+            buf[count..count + data.len()].copy_from_slice(data);
+            count += data.len();
+
+            // The device may return a partial response without any reason, keep reading. Requests
+            // are capped, so the request count being reached only ends the read once `buf` is full.
+            if reason & REASON_END != 0 {
+                return Ok((count, Status::OperationCompleted));
+            } else if reason & REASON_TERM_CHAR != 0 {
+                return Ok((count, Status::TerminationCharacterRead));
+            } else if count == buf.len() {
+                return Ok((count, Status::BytesReadEqualInput));
+            } else if reason == 0 && data.is_empty() {
+                // Neither data nor a reason, reading again would only spin
+                return Err(Error::Visa(VisaError::Io));
+            }
+        }
+    }
+
+    fn flush(&self, _mode: FlushMode) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let reply = self.generic(DEVICE_CLEAR)?;
+        check(Decoder::new(&reply).i32()?)
+    }
+
+    fn read_status_byte(&self) -> Result<u8> {
+        Vxi11::read_status_byte(self)
+    }
+
+    fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        match protocol {
+            TriggerProtocol::Default => self.trigger(),
+            _ => Err(Error::Visa(VisaError::InvalidProtocol)),
+        }
+    }
+
+    fn lock(
+        &self,
+        mode: AccessMode,
+        timeout: Duration,
+        _key: Option<&str>,
+    ) -> Result<Option<String>> {
+        // VXI-11 only knows about exclusive locks
+        if mode.contains(AccessMode::SHARED_LOCK) {
+            return Err(Error::Visa(VisaError::InvalidLockType));
+        }
+
+        let lock_timeout = millis(Some(timeout));
+        let flags = match lock_timeout {
+            0 => 0,
+            _ => FLAG_WAIT_LOCK,
+        };
+
+        let mut core = self.core.lock().unwrap();
+        // Waiting for the lock happens on the server, give it enough time to reply
+        let timeout = core.io.timeout;
+        core.io.timeout =
+            timeout.map(|io_timeout| io_timeout.max(Duration::from_millis(lock_timeout as _)));
+        let reply = core.call(
+            DEVICE_LOCK,
+            Encoder::new().u32(self.link).u32(flags).u32(lock_timeout),
+        );
+        core.io.timeout = timeout;
+
+        check(Decoder::new(&reply?).i32()?)?;
+        Ok(None)
+    }
+
+    fn unlock(&self) -> Result<()> {
+        let mut core = self.core.lock().unwrap();
+        let reply = core.call(DEVICE_UNLOCK, Encoder::new().u32(self.link))?;
+        check(Decoder::new(&reply).i32()?)
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        self.core.lock().unwrap().io.get(attribute)
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        self.core.lock().unwrap().io.set(attribute, value)
+    }
+
+    fn close(&self) -> Result<()> {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+
+        let mut core = self.core.lock().unwrap();
+        let reply = core.call(DESTROY_LINK, Encoder::new().u32(self.link));
+        let _ = core.client.stream().shutdown(Shutdown::Both);
+        if let Some(abort) = self.abort.lock().unwrap().take() {
+            let _ = abort.stream().shutdown(Shutdown::Both);
+        }
+        check(Decoder::new(&reply?).i32()?)
+    }
+}
+
+impl Drop for Vxi11 {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
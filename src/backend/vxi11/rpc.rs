@@ -0,0 +1,386 @@
+//! Minimal ONC RPC (RFC 5531) over TCP with XDR (RFC 4506) encoding, as needed by VXI-11.
+
+use crate::{
+    backend::{connect, io_timeout, map_io_error, open_timeout},
+    error::{Error, Result, VisaError},
+};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+const CALL: u32 = 0;
+const REPLY: u32 = 1;
+const RPC_VERSION: u32 = 2;
+const MSG_ACCEPTED: u32 = 0;
+const SUCCESS: u32 = 0;
+const PROG_UNAVAIL: u32 = 1;
+const PROC_UNAVAIL: u32 = 3;
+#[cfg(feature = "test-servers")]
+const GARBAGE_ARGS: u32 = 4;
+const AUTH_NULL: u32 = 0;
+const LAST_FRAGMENT: u32 = 0x8000_0000;
+/// Largest record accepted, larger ones fail before anything is allocated for them.
+pub(crate) const MAX_RECORD_SIZE: usize = 1 << 24;
+
+pub(crate) const PORTMAPPER_PROGRAM: u32 = 100000;
+pub(crate) const PORTMAPPER_VERSION: u32 = 2;
+pub(crate) const PORTMAPPER_PORT: u16 = 111;
+pub(crate) const PMAPPROC_GETPORT: u32 = 3;
+pub(crate) const IPPROTO_TCP: u32 = 6;
+
+fn protocol_error() -> Error {
+    Error::Visa(VisaError::Io)
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn u32(mut self, value: u32) -> Self {
+        self.buf.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub(crate) fn i32(self, value: i32) -> Self {
+        self.u32(value as u32)
+    }
+
+    pub(crate) fn bool(self, value: bool) -> Self {
+        self.u32(value as u32)
+    }
+
+    pub(crate) fn opaque(mut self, value: &[u8]) -> Self {
+        self = self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value);
+        let padding = (4 - value.len() % 4) % 4;
+        self.buf.extend(std::iter::repeat_n(0, padding));
+        self
+    }
+
+    pub(crate) fn string(self, value: &str) -> Self {
+        self.opaque(value.as_bytes())
+    }
+
+    pub(crate) fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < count {
+            return Err(protocol_error());
+        }
+        let (value, rest) = self.buf.split_at(count);
+        self.buf = rest;
+        Ok(value)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let value = self.take(4)?;
+        Ok(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    #[cfg(feature = "test-servers")]
+    pub(crate) fn bool(&mut self) -> Result<bool> {
+        Ok(self.u32()? != 0)
+    }
+
+    pub(crate) fn opaque(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        let value = self.take(len)?;
+        self.take((4 - len % 4) % 4)?;
+        Ok(value)
+    }
+
+    #[cfg(feature = "test-servers")]
+    pub(crate) fn string(&mut self) -> Result<String> {
+        let value = self.opaque()?;
+        String::from_utf8(value.to_vec()).map_err(|_| Error::InvalidString)
+    }
+}
+
+/// Writes `message` as a single record.
+pub(crate) fn write_record(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<()> {
+    let mut record = Vec::with_capacity(message.len() + 4);
+    record.extend_from_slice(&(LAST_FRAGMENT | message.len() as u32).to_be_bytes());
+    record.extend_from_slice(message);
+    stream.write_all(&record)
+}
+
+/// Reads a record of at most `max_len` bytes, joining all of its fragments.
+pub(crate) fn read_record(stream: &mut TcpStream, max_len: usize) -> std::io::Result<Vec<u8>> {
+    let mut record = Vec::new();
+    loop {
+        let mut header = [0; 4];
+        stream.read_exact(&mut header)?;
+        let header = u32::from_be_bytes(header);
+        let len = (header & !LAST_FRAGMENT) as usize;
+        if len > max_len - record.len() {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "RPC record too long",
+            ));
+        }
+        let start = record.len();
+        record.resize(start + len, 0);
+        stream.read_exact(&mut record[start..])?;
+        if header & LAST_FRAGMENT != 0 {
+            return Ok(record);
+        }
+    }
+}
+
+/// A connection to a single RPC program.
+#[derive(Debug)]
+pub(crate) struct Client {
+    stream: TcpStream,
+    program: u32,
+    version: u32,
+    xid: u32,
+    /// Whether a record was only partly sent or received, leaving the stream out of step.
+    broken: bool,
+}
+
+impl Client {
+    pub(crate) fn new(stream: TcpStream, program: u32, version: u32) -> Self {
+        Self {
+            stream,
+            program,
+            version,
+            xid: std::process::id().rotate_left(16),
+            broken: false,
+        }
+    }
+
+    pub(crate) fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Calls `procedure` with the encoded `arguments`, returning the encoded results.
+    ///
+    /// `timeout` bounds how long to wait for the reply, `None` waits forever. A call that fails
+    /// partway through a record breaks the connection, later calls fail with a lost connection.
+    pub(crate) fn call(
+        &mut self,
+        procedure: u32,
+        arguments: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<Vec<u8>> {
+        if self.broken {
+            return Err(Error::Visa(VisaError::ConnectionLost));
+        }
+
+        self.xid = self.xid.wrapping_add(1);
+        let mut message = Encoder::new()
+            .u32(self.xid)
+            .u32(CALL)
+            .u32(RPC_VERSION)
+            .u32(self.program)
+            .u32(self.version)
+            .u32(procedure)
+            .u32(AUTH_NULL)
+            .opaque(&[])
+            .u32(AUTH_NULL)
+            .opaque(&[])
+            .finish();
+        message.extend_from_slice(arguments);
+
+        self.stream.set_write_timeout(io_timeout(timeout))?;
+        if let Err(error) = write_record(&mut self.stream, &message) {
+            self.broken = true;
+            return Err(map_io_error(error));
+        }
+
+        self.stream.set_read_timeout(io_timeout(timeout))?;
+        loop {
+            // Nothing is lost while waiting for the reply to start, a late reply is skipped by the next call
+            match self.stream.peek(&mut [0]) {
+                Ok(0) => return Err(Error::Visa(VisaError::ConnectionLost)),
+                Ok(_) => {}
+                Err(error) => return Err(map_io_error(error)),
+            }
+            let reply = match read_record(&mut self.stream, MAX_RECORD_SIZE) {
+                Ok(reply) => reply,
+                Err(error) => {
+                    self.broken = true;
+                    return Err(map_io_error(error));
+                }
+            };
+            let mut decoder = Decoder::new(&reply);
+            // Replies to calls that timed out earlier can still be in flight, skip them
+            if decoder.u32()? != self.xid {
+                continue;
+            }
+            if decoder.u32()? != REPLY || decoder.u32()? != MSG_ACCEPTED {
+                return Err(protocol_error());
+            }
+            decoder.u32()?;
+            decoder.opaque()?;
+            return match decoder.u32()? {
+                SUCCESS => Ok(decoder.buf.to_vec()),
+                PROG_UNAVAIL | PROC_UNAVAIL => Err(Error::Visa(VisaError::OperationNotSupported)),
+                _ => Err(protocol_error()),
+            };
+        }
+    }
+}
+
+/// Asks the portmapper at `host` for the TCP port of `program`.
+pub(crate) fn get_port(
+    host: &str,
+    portmapper_port: u16,
+    program: u32,
+    version: u32,
+    timeout: Duration,
+) -> Result<u16> {
+    let stream = connect(host, portmapper_port, timeout)?;
+    let mut client = Client::new(stream, PORTMAPPER_PROGRAM, PORTMAPPER_VERSION);
+    let arguments = Encoder::new()
+        .u32(program)
+        .u32(version)
+        .u32(IPPROTO_TCP)
+        .u32(0)
+        .finish();
+    let reply = client.call(PMAPPROC_GETPORT, &arguments, Some(open_timeout(timeout)))?;
+    match Decoder::new(&reply).u32()? {
+        0 => Err(Error::Visa(VisaError::ResourceNotFound)),
+        port => u16::try_from(port).map_err(|_| protocol_error()),
+    }
+}
+
+#[cfg(feature = "test-servers")]
+/// A decoded call received by a server.
+#[derive(Debug)]
+pub(crate) struct Call<'a> {
+    pub(crate) xid: u32,
+    pub(crate) program: u32,
+    pub(crate) procedure: u32,
+    pub(crate) arguments: Decoder<'a>,
+}
+
+#[cfg(feature = "test-servers")]
+impl<'a> Call<'a> {
+    pub(crate) fn decode(message: &'a [u8]) -> Result<Self> {
+        let mut decoder = Decoder::new(message);
+        let xid = decoder.u32()?;
+        if decoder.u32()? != CALL || decoder.u32()? != RPC_VERSION {
+            return Err(protocol_error());
+        }
+        let program = decoder.u32()?;
+        decoder.u32()?;
+        let procedure = decoder.u32()?;
+        decoder.u32()?;
+        decoder.opaque()?;
+        decoder.u32()?;
+        decoder.opaque()?;
+        Ok(Self {
+            xid,
+            program,
+            procedure,
+            arguments: decoder,
+        })
+    }
+}
+
+#[cfg(feature = "test-servers")]
+/// Encodes an accepted reply, `None` results meaning the procedure is unavailable.
+pub(crate) fn encode_reply(xid: u32, results: Option<Result<Vec<u8>>>) -> Vec<u8> {
+    let encoder = Encoder::new()
+        .u32(xid)
+        .u32(REPLY)
+        .u32(MSG_ACCEPTED)
+        .u32(AUTH_NULL)
+        .opaque(&[]);
+    match results {
+        Some(Ok(results)) => {
+            let mut message = encoder.u32(SUCCESS).finish();
+            message.extend_from_slice(&results);
+            message
+        }
+        Some(Err(_)) => encoder.u32(GARBAGE_ARGS).finish(),
+        None => encoder.u32(PROC_UNAVAIL).finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    fn pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        (client, listener.accept().unwrap().0)
+    }
+
+    #[test]
+    fn pads_opaque_data_to_four_bytes() {
+        let message = Encoder::new()
+            .opaque(b"abcde")
+            .string("xy")
+            .i32(-1)
+            .finish();
+        assert_eq!(message.len(), 4 + 8 + 4 + 4 + 4);
+
+        let mut decoder = Decoder::new(&message);
+        assert_eq!(decoder.opaque().unwrap(), b"abcde");
+        assert_eq!(decoder.string().unwrap(), "xy");
+        assert_eq!(decoder.i32().unwrap(), -1);
+        assert!(decoder.u32().is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_opaque_data() {
+        let message = Encoder::new().u32(8).u32(0).finish();
+        assert!(Decoder::new(&message).opaque().is_err());
+    }
+
+    #[test]
+    fn joins_record_fragments() {
+        let (mut client, mut server) = pair();
+        server.write_all(&3u32.to_be_bytes()).unwrap();
+        server.write_all(b"abc").unwrap();
+        server
+            .write_all(&(LAST_FRAGMENT | 2).to_be_bytes())
+            .unwrap();
+        server.write_all(b"de").unwrap();
+        assert_eq!(read_record(&mut client, MAX_RECORD_SIZE).unwrap(), b"abcde");
+
+        write_record(&mut server, b"fgh").unwrap();
+        assert_eq!(read_record(&mut client, MAX_RECORD_SIZE).unwrap(), b"fgh");
+    }
+
+    #[test]
+    fn rejects_records_longer_than_the_limit() {
+        let (mut client, mut server) = pair();
+        server.write_all(&3u32.to_be_bytes()).unwrap();
+        server.write_all(b"abc").unwrap();
+        server
+            .write_all(&(LAST_FRAGMENT | 3).to_be_bytes())
+            .unwrap();
+        let error = read_record(&mut client, 5).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
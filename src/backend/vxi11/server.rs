@@ -0,0 +1,440 @@
+use super::{
+    CREATE_LINK, DESTROY_LINK, DEVICE_ABORT, DEVICE_ASYNC, DEVICE_CLEAR, DEVICE_CORE, DEVICE_LOCAL,
+    DEVICE_LOCK, DEVICE_READ, DEVICE_READSTB, DEVICE_REMOTE, DEVICE_TRIGGER, DEVICE_UNLOCK,
+    DEVICE_WRITE, ERROR_ABORT, ERROR_INVALID_LINK, ERROR_IO_TIMEOUT, ERROR_LOCKED, ERROR_NO_LOCK,
+    ERROR_NONE, ERROR_NOT_SUPPORTED, FLAG_END, FLAG_TERM_CHAR_SET, FLAG_WAIT_LOCK, REASON_END,
+    REASON_REQUEST_COUNT, REASON_TERM_CHAR,
+    rpc::{
+        Call, Decoder, Encoder, MAX_RECORD_SIZE, PMAPPROC_GETPORT, PORTMAPPER_PROGRAM,
+        encode_reply, read_record, write_record,
+    },
+};
+use crate::error::Result;
+use std::{
+    collections::{HashSet, VecDeque},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Largest chunk of data accepted by a single `device_write`.
+const MAX_RECEIVE_SIZE: u32 = 4096;
+/// Status byte bit set while a response is waiting to be read.
+const MESSAGE_AVAILABLE: u8 = 0x10;
+
+type Handler = Box<dyn FnMut(&[u8]) -> Vec<u8> + Send>;
+
+struct Device {
+    handler: Handler,
+    /// Data written since the last END.
+    input: Vec<u8>,
+    /// Responses not yet read, each terminated by END.
+    output: VecDeque<Vec<u8>>,
+    links: HashSet<u32>,
+    next_link: u32,
+    lock: Option<u32>,
+    aborted: bool,
+    status_byte: u8,
+    triggers: usize,
+    clears: usize,
+}
+
+struct Shared {
+    device: Mutex<Device>,
+    changed: Condvar,
+    port: u16,
+}
+
+/// In-process VXI-11 server stand-in, for exercising clients without an instrument.
+///
+/// The portmapper, core and abort channels are all served on the same port. Every
+/// message written with END is passed to the handler, whose non-empty return value
+/// is queued as the response to the next reads.
+///
+/// ```no_run
+/// # use visa::*;
+/// # use std::time::Duration;
+/// let server = Vxi11Server::bind("127.0.0.1:0", |message| match message {
+///     b"*IDN?\n" => b"ACME,Model 1,1234,1.0\n".to_vec(),
+///     _ => vec![],
+/// })
+/// .unwrap();
+///
+/// let vxi11 = Vxi11::connect_with_portmapper("127.0.0.1", server.port(), "inst0", Duration::ZERO).unwrap();
+/// let instrument = Instrument::new(vxi11.into()).unwrap();
+/// ```
+pub struct Vxi11Server {
+    shared: Arc<Shared>,
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for Vxi11Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vxi11Server")
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Vxi11Server {
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        handler: impl FnMut(&[u8]) -> Vec<u8> + Send + 'static,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let shared = Arc::new(Shared {
+            device: Mutex::new(Device {
+                handler: Box::new(handler),
+                input: Vec::new(),
+                output: VecDeque::new(),
+                links: HashSet::new(),
+                next_link: 1,
+                lock: None,
+                aborted: false,
+                status_byte: 0,
+                triggers: 0,
+                clears: 0,
+            }),
+            changed: Condvar::new(),
+            port: address.port(),
+        });
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        {
+            let shared = shared.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::Acquire) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        let shared = shared.clone();
+                        thread::spawn(move || serve(stream, &shared));
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            shared,
+            address,
+            stopped,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Port the portmapper, core and abort channels are served on.
+    pub fn port(&self) -> u16 {
+        self.address.port()
+    }
+
+    /// Sets the status byte returned by `device_readstb`, the MAV bit is managed by the server.
+    pub fn set_status_byte(&self, status_byte: u8) {
+        self.shared.device.lock().unwrap().status_byte = status_byte;
+    }
+
+    /// Number of `device_trigger` calls received.
+    pub fn triggers(&self) -> usize {
+        self.shared.device.lock().unwrap().triggers
+    }
+
+    /// Number of `device_clear` calls received.
+    pub fn clears(&self) -> usize {
+        self.shared.device.lock().unwrap().clears
+    }
+}
+
+impl Drop for Vxi11Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        // Wake up the accept loop so it notices it has to stop
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+fn serve(mut stream: TcpStream, shared: &Shared) {
+    let mut links = vec![];
+    while let Ok(message) = read_record(&mut stream, MAX_RECORD_SIZE) {
+        let Ok(mut call) = Call::decode(&message) else {
+            break;
+        };
+        let results = match call.program {
+            PORTMAPPER_PROGRAM if call.procedure == PMAPPROC_GETPORT => {
+                Some(Ok(Encoder::new().u32(shared.port as u32).finish()))
+            }
+            DEVICE_CORE => core(shared, call.procedure, &mut call.arguments, &mut links),
+            DEVICE_ASYNC if call.procedure == DEVICE_ABORT => Some(abort(shared)),
+            _ => None,
+        };
+        if write_record(&mut stream, &encode_reply(call.xid, results)).is_err() {
+            break;
+        }
+    }
+
+    // Links die with the connection that created them
+    let mut device = shared.device.lock().unwrap();
+    for link in links {
+        destroy_link(&mut device, link);
+    }
+    shared.changed.notify_all();
+}
+
+fn abort(shared: &Shared) -> Result<Vec<u8>> {
+    shared.device.lock().unwrap().aborted = true;
+    shared.changed.notify_all();
+    Ok(Encoder::new().i32(ERROR_NONE).finish())
+}
+
+fn destroy_link(device: &mut Device, link: u32) {
+    device.links.remove(&link);
+    if device.lock == Some(link) {
+        device.lock = None;
+    }
+}
+
+/// Waits until `link` can access the device, as allowed by `flags` and `lock_timeout`.
+fn wait_lock<'a>(
+    shared: &'a Shared,
+    mut device: MutexGuard<'a, Device>,
+    link: u32,
+    flags: u32,
+    lock_timeout: u32,
+) -> std::result::Result<MutexGuard<'a, Device>, i32> {
+    if !device.links.contains(&link) {
+        return Err(ERROR_INVALID_LINK);
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(lock_timeout as _);
+    while device.lock.is_some_and(|owner| owner != link) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if flags & FLAG_WAIT_LOCK == 0 || remaining.is_zero() {
+            return Err(ERROR_LOCKED);
+        }
+        device = shared.changed.wait_timeout(device, remaining).unwrap().0;
+    }
+    Ok(device)
+}
+
+fn core(
+    shared: &Shared,
+    procedure: u32,
+    arguments: &mut Decoder,
+    links: &mut Vec<u32>,
+) -> Option<Result<Vec<u8>>> {
+    let result = match procedure {
+        CREATE_LINK => create_link(shared, arguments, links),
+        DEVICE_WRITE => device_write(shared, arguments),
+        DEVICE_READ => device_read(shared, arguments),
+        DEVICE_READSTB | DEVICE_TRIGGER | DEVICE_CLEAR | DEVICE_REMOTE | DEVICE_LOCAL => {
+            generic(shared, procedure, arguments)
+        }
+        DEVICE_LOCK => device_lock(shared, arguments),
+        DEVICE_UNLOCK => device_unlock(shared, arguments),
+        DESTROY_LINK => arguments.u32().map(|link| {
+            let mut device = shared.device.lock().unwrap();
+            destroy_link(&mut device, link);
+            links.retain(|other| *other != link);
+            shared.changed.notify_all();
+            Encoder::new().i32(ERROR_NONE).finish()
+        }),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn create_link(shared: &Shared, arguments: &mut Decoder, links: &mut Vec<u32>) -> Result<Vec<u8>> {
+    let _client_id = arguments.i32()?;
+    let lock_device = arguments.bool()?;
+    let lock_timeout = arguments.u32()?;
+    let _device = arguments.string()?;
+
+    let mut device = shared.device.lock().unwrap();
+    let link = device.next_link;
+    device.next_link += 1;
+    device.links.insert(link);
+    links.push(link);
+
+    let error = match lock_device {
+        true => match wait_lock(shared, device, link, FLAG_WAIT_LOCK, lock_timeout) {
+            Ok(mut device) => {
+                device.lock = Some(link);
+                ERROR_NONE
+            }
+            Err(error) => error,
+        },
+        false => ERROR_NONE,
+    };
+
+    Ok(Encoder::new()
+        .i32(error)
+        .u32(link)
+        .u32(shared.port as u32)
+        .u32(MAX_RECEIVE_SIZE)
+        .finish())
+}
+
+fn device_write(shared: &Shared, arguments: &mut Decoder) -> Result<Vec<u8>> {
+    let link = arguments.u32()?;
+    let _io_timeout = arguments.u32()?;
+    let lock_timeout = arguments.u32()?;
+    let flags = arguments.u32()?;
+    let data = arguments.opaque()?;
+
+    let device = shared.device.lock().unwrap();
+    let mut device = match wait_lock(shared, device, link, flags, lock_timeout) {
+        Ok(device) => device,
+        Err(error) => return Ok(Encoder::new().i32(error).u32(0).finish()),
+    };
+
+    device.input.extend_from_slice(data);
+    if flags & FLAG_END != 0 {
+        let input = std::mem::take(&mut device.input);
+        let response = (device.handler)(&input);
+        if !response.is_empty() {
+            device.output.push_back(response);
+            shared.changed.notify_all();
+        }
+    }
+
+    Ok(Encoder::new()
+        .i32(ERROR_NONE)
+        .u32(data.len() as u32)
+        .finish())
+}
+
+fn device_read(shared: &Shared, arguments: &mut Decoder) -> Result<Vec<u8>> {
+    let link = arguments.u32()?;
+    let request_size = arguments.u32()? as usize;
+    let io_timeout = arguments.u32()?;
+    let lock_timeout = arguments.u32()?;
+    let flags = arguments.u32()?;
+    let term_char = arguments.u32()? as u8;
+
+    let reply = |error: i32, reason: i32, data: &[u8]| {
+        Ok(Encoder::new().i32(error).i32(reason).opaque(data).finish())
+    };
+
+    let device = shared.device.lock().unwrap();
+    let mut device = match wait_lock(shared, device, link, flags, lock_timeout) {
+        Ok(device) => device,
+        Err(error) => return reply(error, 0, &[]),
+    };
+
+    device.aborted = false;
+    let deadline = Instant::now() + Duration::from_millis(io_timeout as _);
+    while device.output.is_empty() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if device.aborted {
+            device.aborted = false;
+            return reply(ERROR_ABORT, 0, &[]);
+        } else if remaining.is_zero() {
+            return reply(ERROR_IO_TIMEOUT, 0, &[]);
+        }
+        device = shared.changed.wait_timeout(device, remaining).unwrap().0;
+    }
+
+    let response = device.output.front_mut().unwrap();
+    let mut count = request_size.min(response.len());
+    let mut reason = 0;
+    if flags & FLAG_TERM_CHAR_SET != 0
+        && let Some(position) = response[..count].iter().position(|byte| *byte == term_char)
+    {
+        count = position + 1;
+        reason |= REASON_TERM_CHAR;
+    }
+    if count == request_size {
+        reason |= REASON_REQUEST_COUNT;
+    }
+
+    let data: Vec<u8> = response.drain(..count).collect();
+    if response.is_empty() {
+        device.output.pop_front();
+        reason |= REASON_END;
+    }
+    reply(ERROR_NONE, reason, &data)
+}
+
+fn generic(shared: &Shared, procedure: u32, arguments: &mut Decoder) -> Result<Vec<u8>> {
+    let link = arguments.u32()?;
+    let flags = arguments.u32()?;
+    let lock_timeout = arguments.u32()?;
+    let _io_timeout = arguments.u32()?;
+
+    let device = shared.device.lock().unwrap();
+    let mut device = match wait_lock(shared, device, link, flags, lock_timeout) {
+        Ok(device) => device,
+        Err(error) => {
+            let encoder = Encoder::new().i32(error);
+            return Ok(match procedure {
+                DEVICE_READSTB => encoder.u32(0),
+                _ => encoder,
+            }
+            .finish());
+        }
+    };
+
+    let encoder = Encoder::new().i32(ERROR_NONE);
+    let encoder = match procedure {
+        DEVICE_READSTB => {
+            let mut status_byte = device.status_byte;
+            if !device.output.is_empty() {
+                status_byte |= MESSAGE_AVAILABLE;
+            }
+            encoder.u32(status_byte as u32)
+        }
+        DEVICE_TRIGGER => {
+            device.triggers += 1;
+            encoder
+        }
+        DEVICE_CLEAR => {
+            device.clears += 1;
+            device.input.clear();
+            device.output.clear();
+            encoder
+        }
+        DEVICE_REMOTE | DEVICE_LOCAL => encoder,
+        _ => Encoder::new().i32(ERROR_NOT_SUPPORTED),
+    };
+    Ok(encoder.finish())
+}
+
+fn device_lock(shared: &Shared, arguments: &mut Decoder) -> Result<Vec<u8>> {
+    let link = arguments.u32()?;
+    let flags = arguments.u32()?;
+    let lock_timeout = arguments.u32()?;
+
+    let device = shared.device.lock().unwrap();
+    let error = match wait_lock(shared, device, link, flags, lock_timeout) {
+        Ok(mut device) => {
+            device.lock = Some(link);
+            ERROR_NONE
+        }
+        Err(error) => error,
+    };
+    Ok(Encoder::new().i32(error).finish())
+}
+
+fn device_unlock(shared: &Shared, arguments: &mut Decoder) -> Result<Vec<u8>> {
+    let link = arguments.u32()?;
+
+    let mut device = shared.device.lock().unwrap();
+    let error = match device.lock {
+        Some(owner) if owner == link => {
+            device.lock = None;
+            shared.changed.notify_all();
+            ERROR_NONE
+        }
+        _ => ERROR_NO_LOCK,
+    };
+    Ok(Encoder::new().i32(error).finish())
+}
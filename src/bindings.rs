@@ -596,20 +596,8 @@ pub const VI_ATTR_FIREWIRE_LOWER_CHIP_ID: u32 = 1073676788;
 pub const VI_ATTR_FIREWIRE_UPPER_CHIP_ID: u32 = 1073676789;
 pub const VI_FIREWIRE_DFLT_SPACE: u32 = 5;
 pub type va_list = *mut ::std::os::raw::c_char;
-unsafe extern "C" {
-    pub fn __va_start(arg1: *mut *mut ::std::os::raw::c_char, ...);
-}
 pub type __vcrt_bool = bool;
 pub type wchar_t = ::std::os::raw::c_ushort;
-unsafe extern "C" {
-    pub fn __security_init_cookie();
-}
-unsafe extern "C" {
-    pub fn __security_check_cookie(_StackCookie: usize);
-}
-unsafe extern "C" {
-    pub fn __report_gsfailure(_StackCookie: usize) -> !;
-}
 unsafe extern "C" {
     pub static mut __security_cookie: usize;
 }
@@ -713,31 +701,1102 @@ pub type ViHndlr = ::std::option::Option<
         userHandle: ViAddr,
     ) -> ViStatus,
 >;
-unsafe extern "C" {
-    pub fn viOpenDefaultRM(vi: ViPSession) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viFindRsrc(
+pub struct Visa {
+    __library: ::libloading::Library,
+    pub viOpenDefaultRM:
+        Result<unsafe extern "C" fn(vi: ViPSession) -> ViStatus, ::libloading::Error>,
+    pub viFindRsrc: Result<
+        unsafe extern "C" fn(
+            sesn: ViSession,
+            expr: ViConstString,
+            vi: ViPFindList,
+            retCnt: ViPUInt32,
+            desc: *mut ViChar,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viFindNext: Result<
+        unsafe extern "C" fn(vi: ViFindList, desc: *mut ViChar) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viParseRsrc: Result<
+        unsafe extern "C" fn(
+            rmSesn: ViSession,
+            rsrcName: ViConstRsrc,
+            intfType: ViPUInt16,
+            intfNum: ViPUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viParseRsrcEx: Result<
+        unsafe extern "C" fn(
+            rmSesn: ViSession,
+            rsrcName: ViConstRsrc,
+            intfType: ViPUInt16,
+            intfNum: ViPUInt16,
+            rsrcClass: *mut ViChar,
+            expandedUnaliasedName: *mut ViChar,
+            aliasIfExists: *mut ViChar,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOpen: Result<
+        unsafe extern "C" fn(
+            sesn: ViSession,
+            name: ViConstRsrc,
+            mode: ViAccessMode,
+            timeout: ViUInt32,
+            vi: ViPSession,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viClose: Result<unsafe extern "C" fn(vi: ViObject) -> ViStatus, ::libloading::Error>,
+    pub viSetAttribute: Result<
+        unsafe extern "C" fn(vi: ViObject, attrName: ViAttr, attrValue: ViAttrState) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viGetAttribute: Result<
+        unsafe extern "C" fn(
+            vi: ViObject,
+            attrName: ViAttr,
+            attrValue: *mut ::std::os::raw::c_void,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viStatusDesc: Result<
+        unsafe extern "C" fn(vi: ViObject, status: ViStatus, desc: *mut ViChar) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viTerminate: Result<
+        unsafe extern "C" fn(vi: ViObject, degree: ViUInt16, jobId: ViJobId) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viLock: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            lockType: ViAccessMode,
+            timeout: ViUInt32,
+            requestedKey: ViConstKeyId,
+            accessKey: *mut ViChar,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viUnlock: Result<unsafe extern "C" fn(vi: ViSession) -> ViStatus, ::libloading::Error>,
+    pub viEnableEvent: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            eventType: ViEventType,
+            mechanism: ViUInt16,
+            context: ViEventFilter,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viDisableEvent: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            eventType: ViEventType,
+            mechanism: ViUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viDiscardEvents: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            eventType: ViEventType,
+            mechanism: ViUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viWaitOnEvent: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            inEventType: ViEventType,
+            timeout: ViUInt32,
+            outEventType: ViPEventType,
+            outContext: ViPEvent,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viInstallHandler: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            eventType: ViEventType,
+            handler: ViHndlr,
+            userHandle: ViAddr,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viUninstallHandler: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            eventType: ViEventType,
+            handler: ViHndlr,
+            userHandle: ViAddr,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viRead: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViPBuf,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viReadAsync: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViPBuf,
+            cnt: ViUInt32,
+            jobId: ViPJobId,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viReadToFile: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            filename: ViConstString,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viWrite: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViConstBuf,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viWriteAsync: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViConstBuf,
+            cnt: ViUInt32,
+            jobId: ViPJobId,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viWriteFromFile: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            filename: ViConstString,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viAssertTrigger: Result<
+        unsafe extern "C" fn(vi: ViSession, protocol: ViUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viReadSTB: Result<
+        unsafe extern "C" fn(vi: ViSession, status: ViPUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viClear: Result<unsafe extern "C" fn(vi: ViSession) -> ViStatus, ::libloading::Error>,
+    pub viSetBuf: Result<
+        unsafe extern "C" fn(vi: ViSession, mask: ViUInt16, size: ViUInt32) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viFlush: Result<
+        unsafe extern "C" fn(vi: ViSession, mask: ViUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viBufWrite: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViConstBuf,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viBufRead: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViPBuf,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viPrintf: Result<
+        unsafe extern "C" fn(vi: ViSession, writeFmt: ViConstString, ...) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVPrintf: Result<
+        unsafe extern "C" fn(vi: ViSession, writeFmt: ViConstString, params: ViVAList) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viSPrintf: Result<
+        unsafe extern "C" fn(vi: ViSession, buf: ViPBuf, writeFmt: ViConstString, ...) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVSPrintf: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViPBuf,
+            writeFmt: ViConstString,
+            params: ViVAList,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viScanf: Result<
+        unsafe extern "C" fn(vi: ViSession, readFmt: ViConstString, ...) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVScanf: Result<
+        unsafe extern "C" fn(vi: ViSession, readFmt: ViConstString, params: ViVAList) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viSScanf: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViConstBuf,
+            readFmt: ViConstString,
+            ...
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVSScanf: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            buf: ViConstBuf,
+            readFmt: ViConstString,
+            params: ViVAList,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viQueryf: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            writeFmt: ViConstString,
+            readFmt: ViConstString,
+            ...
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVQueryf: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            writeFmt: ViConstString,
+            readFmt: ViConstString,
+            params: ViVAList,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn8: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val8: ViPUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut8: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val8: ViUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn16: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val16: ViPUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut16: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val16: ViUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn32: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val32: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut32: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val32: ViUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn64: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val64: ViPUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut64: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            val64: ViUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn8Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val8: ViPUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut8Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val8: ViUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn16Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val16: ViPUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut16Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val16: ViUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn32Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val32: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut32Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val32: ViUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viIn64Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val64: ViPUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viOut64Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            val64: ViUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn8: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf8: ViAUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut8: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf8: ViAUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn16: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf16: ViAUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut16: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf16: ViAUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn32: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf32: ViAUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut32: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf32: ViAUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn64: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf64: ViAUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut64: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress,
+            length: ViBusSize,
+            buf64: ViAUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn8Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf8: ViAUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut8Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf8: ViAUInt8,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn16Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf16: ViAUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut16Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf16: ViAUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn32Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf32: ViAUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut32Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf32: ViAUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveIn64Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf64: ViAUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveOut64Ex: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            space: ViUInt16,
+            offset: ViBusAddress64,
+            length: ViBusSize,
+            buf64: ViAUInt64,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMove: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            srcSpace: ViUInt16,
+            srcOffset: ViBusAddress,
+            srcWidth: ViUInt16,
+            destSpace: ViUInt16,
+            destOffset: ViBusAddress,
+            destWidth: ViUInt16,
+            srcLength: ViBusSize,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveAsync: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            srcSpace: ViUInt16,
+            srcOffset: ViBusAddress,
+            srcWidth: ViUInt16,
+            destSpace: ViUInt16,
+            destOffset: ViBusAddress,
+            destWidth: ViUInt16,
+            srcLength: ViBusSize,
+            jobId: ViPJobId,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveEx: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            srcSpace: ViUInt16,
+            srcOffset: ViBusAddress64,
+            srcWidth: ViUInt16,
+            destSpace: ViUInt16,
+            destOffset: ViBusAddress64,
+            destWidth: ViUInt16,
+            srcLength: ViBusSize,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMoveAsyncEx: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            srcSpace: ViUInt16,
+            srcOffset: ViBusAddress64,
+            srcWidth: ViUInt16,
+            destSpace: ViUInt16,
+            destOffset: ViBusAddress64,
+            destWidth: ViUInt16,
+            srcLength: ViBusSize,
+            jobId: ViPJobId,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMapAddress: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            mapSpace: ViUInt16,
+            mapOffset: ViBusAddress,
+            mapSize: ViBusSize,
+            access: ViBoolean,
+            suggested: ViAddr,
+            address: ViPAddr,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viUnmapAddress:
+        Result<unsafe extern "C" fn(vi: ViSession) -> ViStatus, ::libloading::Error>,
+    pub viMapAddressEx: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            mapSpace: ViUInt16,
+            mapOffset: ViBusAddress64,
+            mapSize: ViBusSize,
+            access: ViBoolean,
+            suggested: ViAddr,
+            address: ViPAddr,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viPeek8: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val8: ViPUInt8),
+        ::libloading::Error,
+    >,
+    pub viPoke8: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val8: ViUInt8),
+        ::libloading::Error,
+    >,
+    pub viPeek16: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val16: ViPUInt16),
+        ::libloading::Error,
+    >,
+    pub viPoke16: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val16: ViUInt16),
+        ::libloading::Error,
+    >,
+    pub viPeek32: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val32: ViPUInt32),
+        ::libloading::Error,
+    >,
+    pub viPoke32: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val32: ViUInt32),
+        ::libloading::Error,
+    >,
+    pub viPeek64: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val64: ViPUInt64),
+        ::libloading::Error,
+    >,
+    pub viPoke64: Result<
+        unsafe extern "C" fn(vi: ViSession, address: ViAddr, val64: ViUInt64),
+        ::libloading::Error,
+    >,
+    pub viMemAlloc: Result<
+        unsafe extern "C" fn(vi: ViSession, size: ViBusSize, offset: ViPBusAddress) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMemFree: Result<
+        unsafe extern "C" fn(vi: ViSession, offset: ViBusAddress) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMemAllocEx: Result<
+        unsafe extern "C" fn(vi: ViSession, size: ViBusSize, offset: ViPBusAddress64) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMemFreeEx: Result<
+        unsafe extern "C" fn(vi: ViSession, offset: ViBusAddress64) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viGpibControlREN: Result<
+        unsafe extern "C" fn(vi: ViSession, mode: ViUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viGpibControlATN: Result<
+        unsafe extern "C" fn(vi: ViSession, mode: ViUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viGpibSendIFC: Result<unsafe extern "C" fn(vi: ViSession) -> ViStatus, ::libloading::Error>,
+    pub viGpibCommand: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            cmd: ViConstBuf,
+            cnt: ViUInt32,
+            retCnt: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viGpibPassControl: Result<
+        unsafe extern "C" fn(vi: ViSession, primAddr: ViUInt16, secAddr: ViUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVxiCommandQuery: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            mode: ViUInt16,
+            cmd: ViUInt32,
+            response: ViPUInt32,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viAssertUtilSignal: Result<
+        unsafe extern "C" fn(vi: ViSession, line: ViUInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viAssertIntrSignal: Result<
+        unsafe extern "C" fn(vi: ViSession, mode: ViInt16, statusID: ViUInt32) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viMapTrigger: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            trigSrc: ViInt16,
+            trigDest: ViInt16,
+            mode: ViUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viUnmapTrigger: Result<
+        unsafe extern "C" fn(vi: ViSession, trigSrc: ViInt16, trigDest: ViInt16) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viUsbControlOut: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            bmRequestType: ViInt16,
+            bRequest: ViInt16,
+            wValue: ViUInt16,
+            wIndex: ViUInt16,
+            wLength: ViUInt16,
+            buf: ViConstBuf,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viUsbControlIn: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            bmRequestType: ViInt16,
+            bRequest: ViInt16,
+            wValue: ViUInt16,
+            wIndex: ViUInt16,
+            wLength: ViUInt16,
+            buf: ViPBuf,
+            retCnt: ViPUInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viPxiReserveTriggers: Result<
+        unsafe extern "C" fn(
+            vi: ViSession,
+            cnt: ViInt16,
+            trigBuses: ViAInt16,
+            trigLines: ViAInt16,
+            failureIndex: ViPInt16,
+        ) -> ViStatus,
+        ::libloading::Error,
+    >,
+    pub viVxiServantResponse: Result<
+        unsafe extern "C" fn(vi: ViSession, mode: ViInt16, resp: ViUInt32) -> ViStatus,
+        ::libloading::Error,
+    >,
+}
+impl Visa {
+    pub unsafe fn new<P>(path: P) -> Result<Self, ::libloading::Error>
+    where
+        P: AsRef<::std::ffi::OsStr>,
+    {
+        unsafe {
+            let library = ::libloading::Library::new(path)?;
+            Self::from_library(library)
+        }
+    }
+    pub unsafe fn from_library<L>(library: L) -> Result<Self, ::libloading::Error>
+    where
+        L: Into<::libloading::Library>,
+    {
+        unsafe {
+            let __library = library.into();
+            let viOpenDefaultRM = __library.get(b"viOpenDefaultRM\0").map(|sym| *sym);
+            let viFindRsrc = __library.get(b"viFindRsrc\0").map(|sym| *sym);
+            let viFindNext = __library.get(b"viFindNext\0").map(|sym| *sym);
+            let viParseRsrc = __library.get(b"viParseRsrc\0").map(|sym| *sym);
+            let viParseRsrcEx = __library.get(b"viParseRsrcEx\0").map(|sym| *sym);
+            let viOpen = __library.get(b"viOpen\0").map(|sym| *sym);
+            let viClose = __library.get(b"viClose\0").map(|sym| *sym);
+            let viSetAttribute = __library.get(b"viSetAttribute\0").map(|sym| *sym);
+            let viGetAttribute = __library.get(b"viGetAttribute\0").map(|sym| *sym);
+            let viStatusDesc = __library.get(b"viStatusDesc\0").map(|sym| *sym);
+            let viTerminate = __library.get(b"viTerminate\0").map(|sym| *sym);
+            let viLock = __library.get(b"viLock\0").map(|sym| *sym);
+            let viUnlock = __library.get(b"viUnlock\0").map(|sym| *sym);
+            let viEnableEvent = __library.get(b"viEnableEvent\0").map(|sym| *sym);
+            let viDisableEvent = __library.get(b"viDisableEvent\0").map(|sym| *sym);
+            let viDiscardEvents = __library.get(b"viDiscardEvents\0").map(|sym| *sym);
+            let viWaitOnEvent = __library.get(b"viWaitOnEvent\0").map(|sym| *sym);
+            let viInstallHandler = __library.get(b"viInstallHandler\0").map(|sym| *sym);
+            let viUninstallHandler = __library.get(b"viUninstallHandler\0").map(|sym| *sym);
+            let viRead = __library.get(b"viRead\0").map(|sym| *sym);
+            let viReadAsync = __library.get(b"viReadAsync\0").map(|sym| *sym);
+            let viReadToFile = __library.get(b"viReadToFile\0").map(|sym| *sym);
+            let viWrite = __library.get(b"viWrite\0").map(|sym| *sym);
+            let viWriteAsync = __library.get(b"viWriteAsync\0").map(|sym| *sym);
+            let viWriteFromFile = __library.get(b"viWriteFromFile\0").map(|sym| *sym);
+            let viAssertTrigger = __library.get(b"viAssertTrigger\0").map(|sym| *sym);
+            let viReadSTB = __library.get(b"viReadSTB\0").map(|sym| *sym);
+            let viClear = __library.get(b"viClear\0").map(|sym| *sym);
+            let viSetBuf = __library.get(b"viSetBuf\0").map(|sym| *sym);
+            let viFlush = __library.get(b"viFlush\0").map(|sym| *sym);
+            let viBufWrite = __library.get(b"viBufWrite\0").map(|sym| *sym);
+            let viBufRead = __library.get(b"viBufRead\0").map(|sym| *sym);
+            let viPrintf = __library.get(b"viPrintf\0").map(|sym| *sym);
+            let viVPrintf = __library.get(b"viVPrintf\0").map(|sym| *sym);
+            let viSPrintf = __library.get(b"viSPrintf\0").map(|sym| *sym);
+            let viVSPrintf = __library.get(b"viVSPrintf\0").map(|sym| *sym);
+            let viScanf = __library.get(b"viScanf\0").map(|sym| *sym);
+            let viVScanf = __library.get(b"viVScanf\0").map(|sym| *sym);
+            let viSScanf = __library.get(b"viSScanf\0").map(|sym| *sym);
+            let viVSScanf = __library.get(b"viVSScanf\0").map(|sym| *sym);
+            let viQueryf = __library.get(b"viQueryf\0").map(|sym| *sym);
+            let viVQueryf = __library.get(b"viVQueryf\0").map(|sym| *sym);
+            let viIn8 = __library.get(b"viIn8\0").map(|sym| *sym);
+            let viOut8 = __library.get(b"viOut8\0").map(|sym| *sym);
+            let viIn16 = __library.get(b"viIn16\0").map(|sym| *sym);
+            let viOut16 = __library.get(b"viOut16\0").map(|sym| *sym);
+            let viIn32 = __library.get(b"viIn32\0").map(|sym| *sym);
+            let viOut32 = __library.get(b"viOut32\0").map(|sym| *sym);
+            let viIn64 = __library.get(b"viIn64\0").map(|sym| *sym);
+            let viOut64 = __library.get(b"viOut64\0").map(|sym| *sym);
+            let viIn8Ex = __library.get(b"viIn8Ex\0").map(|sym| *sym);
+            let viOut8Ex = __library.get(b"viOut8Ex\0").map(|sym| *sym);
+            let viIn16Ex = __library.get(b"viIn16Ex\0").map(|sym| *sym);
+            let viOut16Ex = __library.get(b"viOut16Ex\0").map(|sym| *sym);
+            let viIn32Ex = __library.get(b"viIn32Ex\0").map(|sym| *sym);
+            let viOut32Ex = __library.get(b"viOut32Ex\0").map(|sym| *sym);
+            let viIn64Ex = __library.get(b"viIn64Ex\0").map(|sym| *sym);
+            let viOut64Ex = __library.get(b"viOut64Ex\0").map(|sym| *sym);
+            let viMoveIn8 = __library.get(b"viMoveIn8\0").map(|sym| *sym);
+            let viMoveOut8 = __library.get(b"viMoveOut8\0").map(|sym| *sym);
+            let viMoveIn16 = __library.get(b"viMoveIn16\0").map(|sym| *sym);
+            let viMoveOut16 = __library.get(b"viMoveOut16\0").map(|sym| *sym);
+            let viMoveIn32 = __library.get(b"viMoveIn32\0").map(|sym| *sym);
+            let viMoveOut32 = __library.get(b"viMoveOut32\0").map(|sym| *sym);
+            let viMoveIn64 = __library.get(b"viMoveIn64\0").map(|sym| *sym);
+            let viMoveOut64 = __library.get(b"viMoveOut64\0").map(|sym| *sym);
+            let viMoveIn8Ex = __library.get(b"viMoveIn8Ex\0").map(|sym| *sym);
+            let viMoveOut8Ex = __library.get(b"viMoveOut8Ex\0").map(|sym| *sym);
+            let viMoveIn16Ex = __library.get(b"viMoveIn16Ex\0").map(|sym| *sym);
+            let viMoveOut16Ex = __library.get(b"viMoveOut16Ex\0").map(|sym| *sym);
+            let viMoveIn32Ex = __library.get(b"viMoveIn32Ex\0").map(|sym| *sym);
+            let viMoveOut32Ex = __library.get(b"viMoveOut32Ex\0").map(|sym| *sym);
+            let viMoveIn64Ex = __library.get(b"viMoveIn64Ex\0").map(|sym| *sym);
+            let viMoveOut64Ex = __library.get(b"viMoveOut64Ex\0").map(|sym| *sym);
+            let viMove = __library.get(b"viMove\0").map(|sym| *sym);
+            let viMoveAsync = __library.get(b"viMoveAsync\0").map(|sym| *sym);
+            let viMoveEx = __library.get(b"viMoveEx\0").map(|sym| *sym);
+            let viMoveAsyncEx = __library.get(b"viMoveAsyncEx\0").map(|sym| *sym);
+            let viMapAddress = __library.get(b"viMapAddress\0").map(|sym| *sym);
+            let viUnmapAddress = __library.get(b"viUnmapAddress\0").map(|sym| *sym);
+            let viMapAddressEx = __library.get(b"viMapAddressEx\0").map(|sym| *sym);
+            let viPeek8 = __library.get(b"viPeek8\0").map(|sym| *sym);
+            let viPoke8 = __library.get(b"viPoke8\0").map(|sym| *sym);
+            let viPeek16 = __library.get(b"viPeek16\0").map(|sym| *sym);
+            let viPoke16 = __library.get(b"viPoke16\0").map(|sym| *sym);
+            let viPeek32 = __library.get(b"viPeek32\0").map(|sym| *sym);
+            let viPoke32 = __library.get(b"viPoke32\0").map(|sym| *sym);
+            let viPeek64 = __library.get(b"viPeek64\0").map(|sym| *sym);
+            let viPoke64 = __library.get(b"viPoke64\0").map(|sym| *sym);
+            let viMemAlloc = __library.get(b"viMemAlloc\0").map(|sym| *sym);
+            let viMemFree = __library.get(b"viMemFree\0").map(|sym| *sym);
+            let viMemAllocEx = __library.get(b"viMemAllocEx\0").map(|sym| *sym);
+            let viMemFreeEx = __library.get(b"viMemFreeEx\0").map(|sym| *sym);
+            let viGpibControlREN = __library.get(b"viGpibControlREN\0").map(|sym| *sym);
+            let viGpibControlATN = __library.get(b"viGpibControlATN\0").map(|sym| *sym);
+            let viGpibSendIFC = __library.get(b"viGpibSendIFC\0").map(|sym| *sym);
+            let viGpibCommand = __library.get(b"viGpibCommand\0").map(|sym| *sym);
+            let viGpibPassControl = __library.get(b"viGpibPassControl\0").map(|sym| *sym);
+            let viVxiCommandQuery = __library.get(b"viVxiCommandQuery\0").map(|sym| *sym);
+            let viAssertUtilSignal = __library.get(b"viAssertUtilSignal\0").map(|sym| *sym);
+            let viAssertIntrSignal = __library.get(b"viAssertIntrSignal\0").map(|sym| *sym);
+            let viMapTrigger = __library.get(b"viMapTrigger\0").map(|sym| *sym);
+            let viUnmapTrigger = __library.get(b"viUnmapTrigger\0").map(|sym| *sym);
+            let viUsbControlOut = __library.get(b"viUsbControlOut\0").map(|sym| *sym);
+            let viUsbControlIn = __library.get(b"viUsbControlIn\0").map(|sym| *sym);
+            let viPxiReserveTriggers = __library.get(b"viPxiReserveTriggers\0").map(|sym| *sym);
+            let viVxiServantResponse = __library.get(b"viVxiServantResponse\0").map(|sym| *sym);
+            Ok(Visa {
+                __library,
+                viOpenDefaultRM,
+                viFindRsrc,
+                viFindNext,
+                viParseRsrc,
+                viParseRsrcEx,
+                viOpen,
+                viClose,
+                viSetAttribute,
+                viGetAttribute,
+                viStatusDesc,
+                viTerminate,
+                viLock,
+                viUnlock,
+                viEnableEvent,
+                viDisableEvent,
+                viDiscardEvents,
+                viWaitOnEvent,
+                viInstallHandler,
+                viUninstallHandler,
+                viRead,
+                viReadAsync,
+                viReadToFile,
+                viWrite,
+                viWriteAsync,
+                viWriteFromFile,
+                viAssertTrigger,
+                viReadSTB,
+                viClear,
+                viSetBuf,
+                viFlush,
+                viBufWrite,
+                viBufRead,
+                viPrintf,
+                viVPrintf,
+                viSPrintf,
+                viVSPrintf,
+                viScanf,
+                viVScanf,
+                viSScanf,
+                viVSScanf,
+                viQueryf,
+                viVQueryf,
+                viIn8,
+                viOut8,
+                viIn16,
+                viOut16,
+                viIn32,
+                viOut32,
+                viIn64,
+                viOut64,
+                viIn8Ex,
+                viOut8Ex,
+                viIn16Ex,
+                viOut16Ex,
+                viIn32Ex,
+                viOut32Ex,
+                viIn64Ex,
+                viOut64Ex,
+                viMoveIn8,
+                viMoveOut8,
+                viMoveIn16,
+                viMoveOut16,
+                viMoveIn32,
+                viMoveOut32,
+                viMoveIn64,
+                viMoveOut64,
+                viMoveIn8Ex,
+                viMoveOut8Ex,
+                viMoveIn16Ex,
+                viMoveOut16Ex,
+                viMoveIn32Ex,
+                viMoveOut32Ex,
+                viMoveIn64Ex,
+                viMoveOut64Ex,
+                viMove,
+                viMoveAsync,
+                viMoveEx,
+                viMoveAsyncEx,
+                viMapAddress,
+                viUnmapAddress,
+                viMapAddressEx,
+                viPeek8,
+                viPoke8,
+                viPeek16,
+                viPoke16,
+                viPeek32,
+                viPoke32,
+                viPeek64,
+                viPoke64,
+                viMemAlloc,
+                viMemFree,
+                viMemAllocEx,
+                viMemFreeEx,
+                viGpibControlREN,
+                viGpibControlATN,
+                viGpibSendIFC,
+                viGpibCommand,
+                viGpibPassControl,
+                viVxiCommandQuery,
+                viAssertUtilSignal,
+                viAssertIntrSignal,
+                viMapTrigger,
+                viUnmapTrigger,
+                viUsbControlOut,
+                viUsbControlIn,
+                viPxiReserveTriggers,
+                viVxiServantResponse,
+            })
+        }
+    }
+    pub unsafe fn viOpenDefaultRM(&self, vi: ViPSession) -> ViStatus {
+        unsafe {
+            (self
+                .viOpenDefaultRM
+                .as_ref()
+                .expect("Expected function, got error."))(vi)
+        }
+    }
+    pub unsafe fn viFindRsrc(
+        &self,
         sesn: ViSession,
         expr: ViConstString,
         vi: ViPFindList,
         retCnt: ViPUInt32,
         desc: *mut ViChar,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viFindNext(vi: ViFindList, desc: *mut ViChar) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viParseRsrc(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viFindRsrc
+                .as_ref()
+                .expect("Expected function, got error."))(sesn, expr, vi, retCnt, desc)
+        }
+    }
+    pub unsafe fn viFindNext(&self, vi: ViFindList, desc: *mut ViChar) -> ViStatus {
+        unsafe {
+            (self
+                .viFindNext
+                .as_ref()
+                .expect("Expected function, got error."))(vi, desc)
+        }
+    }
+    pub unsafe fn viParseRsrc(
+        &self,
         rmSesn: ViSession,
         rsrcName: ViConstRsrc,
         intfType: ViPUInt16,
         intfNum: ViPUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viParseRsrcEx(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viParseRsrc
+                .as_ref()
+                .expect("Expected function, got error."))(
+                rmSesn, rsrcName, intfType, intfNum
+            )
+        }
+    }
+    pub unsafe fn viParseRsrcEx(
+        &self,
         rmSesn: ViSession,
         rsrcName: ViConstRsrc,
         intfType: ViPUInt16,
@@ -745,452 +1804,912 @@ unsafe extern "C" {
         rsrcClass: *mut ViChar,
         expandedUnaliasedName: *mut ViChar,
         aliasIfExists: *mut ViChar,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOpen(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viParseRsrcEx
+                .as_ref()
+                .expect("Expected function, got error."))(
+                rmSesn,
+                rsrcName,
+                intfType,
+                intfNum,
+                rsrcClass,
+                expandedUnaliasedName,
+                aliasIfExists,
+            )
+        }
+    }
+    pub unsafe fn viOpen(
+        &self,
         sesn: ViSession,
         name: ViConstRsrc,
         mode: ViAccessMode,
         timeout: ViUInt32,
         vi: ViPSession,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viClose(vi: ViObject) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viSetAttribute(vi: ViObject, attrName: ViAttr, attrValue: ViAttrState) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viGetAttribute(
+    ) -> ViStatus {
+        unsafe {
+            (self.viOpen.as_ref().expect("Expected function, got error."))(
+                sesn, name, mode, timeout, vi,
+            )
+        }
+    }
+    pub unsafe fn viClose(&self, vi: ViObject) -> ViStatus {
+        unsafe {
+            (self
+                .viClose
+                .as_ref()
+                .expect("Expected function, got error."))(vi)
+        }
+    }
+    pub unsafe fn viSetAttribute(
+        &self,
+        vi: ViObject,
+        attrName: ViAttr,
+        attrValue: ViAttrState,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viSetAttribute
+                .as_ref()
+                .expect("Expected function, got error."))(vi, attrName, attrValue)
+        }
+    }
+    pub unsafe fn viGetAttribute(
+        &self,
         vi: ViObject,
         attrName: ViAttr,
         attrValue: *mut ::std::os::raw::c_void,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viStatusDesc(vi: ViObject, status: ViStatus, desc: *mut ViChar) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viTerminate(vi: ViObject, degree: ViUInt16, jobId: ViJobId) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viLock(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viGetAttribute
+                .as_ref()
+                .expect("Expected function, got error."))(vi, attrName, attrValue)
+        }
+    }
+    pub unsafe fn viStatusDesc(
+        &self,
+        vi: ViObject,
+        status: ViStatus,
+        desc: *mut ViChar,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viStatusDesc
+                .as_ref()
+                .expect("Expected function, got error."))(vi, status, desc)
+        }
+    }
+    pub unsafe fn viTerminate(&self, vi: ViObject, degree: ViUInt16, jobId: ViJobId) -> ViStatus {
+        unsafe {
+            (self
+                .viTerminate
+                .as_ref()
+                .expect("Expected function, got error."))(vi, degree, jobId)
+        }
+    }
+    pub unsafe fn viLock(
+        &self,
         vi: ViSession,
         lockType: ViAccessMode,
         timeout: ViUInt32,
         requestedKey: ViConstKeyId,
         accessKey: *mut ViChar,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viUnlock(vi: ViSession) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viEnableEvent(
+    ) -> ViStatus {
+        unsafe {
+            (self.viLock.as_ref().expect("Expected function, got error."))(
+                vi,
+                lockType,
+                timeout,
+                requestedKey,
+                accessKey,
+            )
+        }
+    }
+    pub unsafe fn viUnlock(&self, vi: ViSession) -> ViStatus {
+        unsafe {
+            (self
+                .viUnlock
+                .as_ref()
+                .expect("Expected function, got error."))(vi)
+        }
+    }
+    pub unsafe fn viEnableEvent(
+        &self,
         vi: ViSession,
         eventType: ViEventType,
         mechanism: ViUInt16,
         context: ViEventFilter,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viDisableEvent(vi: ViSession, eventType: ViEventType, mechanism: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viDiscardEvents(vi: ViSession, eventType: ViEventType, mechanism: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viWaitOnEvent(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viEnableEvent
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, eventType, mechanism, context
+            )
+        }
+    }
+    pub unsafe fn viDisableEvent(
+        &self,
+        vi: ViSession,
+        eventType: ViEventType,
+        mechanism: ViUInt16,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viDisableEvent
+                .as_ref()
+                .expect("Expected function, got error."))(vi, eventType, mechanism)
+        }
+    }
+    pub unsafe fn viDiscardEvents(
+        &self,
+        vi: ViSession,
+        eventType: ViEventType,
+        mechanism: ViUInt16,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viDiscardEvents
+                .as_ref()
+                .expect("Expected function, got error."))(vi, eventType, mechanism)
+        }
+    }
+    pub unsafe fn viWaitOnEvent(
+        &self,
         vi: ViSession,
         inEventType: ViEventType,
         timeout: ViUInt32,
         outEventType: ViPEventType,
         outContext: ViPEvent,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viInstallHandler(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viWaitOnEvent
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi,
+                inEventType,
+                timeout,
+                outEventType,
+                outContext,
+            )
+        }
+    }
+    pub unsafe fn viInstallHandler(
+        &self,
         vi: ViSession,
         eventType: ViEventType,
         handler: ViHndlr,
         userHandle: ViAddr,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viUninstallHandler(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viInstallHandler
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, eventType, handler, userHandle
+            )
+        }
+    }
+    pub unsafe fn viUninstallHandler(
+        &self,
         vi: ViSession,
         eventType: ViEventType,
         handler: ViHndlr,
         userHandle: ViAddr,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viRead(vi: ViSession, buf: ViPBuf, cnt: ViUInt32, retCnt: ViPUInt32) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viReadAsync(vi: ViSession, buf: ViPBuf, cnt: ViUInt32, jobId: ViPJobId) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viReadToFile(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viUninstallHandler
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, eventType, handler, userHandle
+            )
+        }
+    }
+    pub unsafe fn viRead(
+        &self,
+        vi: ViSession,
+        buf: ViPBuf,
+        cnt: ViUInt32,
+        retCnt: ViPUInt32,
+    ) -> ViStatus {
+        unsafe {
+            (self.viRead.as_ref().expect("Expected function, got error."))(vi, buf, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viReadAsync(
+        &self,
+        vi: ViSession,
+        buf: ViPBuf,
+        cnt: ViUInt32,
+        jobId: ViPJobId,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viReadAsync
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, cnt, jobId)
+        }
+    }
+    pub unsafe fn viReadToFile(
+        &self,
         vi: ViSession,
         filename: ViConstString,
         cnt: ViUInt32,
         retCnt: ViPUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viWrite(vi: ViSession, buf: ViConstBuf, cnt: ViUInt32, retCnt: ViPUInt32) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viWriteAsync(vi: ViSession, buf: ViConstBuf, cnt: ViUInt32, jobId: ViPJobId)
-        -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viWriteFromFile(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viReadToFile
+                .as_ref()
+                .expect("Expected function, got error."))(vi, filename, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viWrite(
+        &self,
+        vi: ViSession,
+        buf: ViConstBuf,
+        cnt: ViUInt32,
+        retCnt: ViPUInt32,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viWrite
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viWriteAsync(
+        &self,
+        vi: ViSession,
+        buf: ViConstBuf,
+        cnt: ViUInt32,
+        jobId: ViPJobId,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viWriteAsync
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, cnt, jobId)
+        }
+    }
+    pub unsafe fn viWriteFromFile(
+        &self,
         vi: ViSession,
         filename: ViConstString,
         cnt: ViUInt32,
         retCnt: ViPUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viAssertTrigger(vi: ViSession, protocol: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viReadSTB(vi: ViSession, status: ViPUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viClear(vi: ViSession) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viSetBuf(vi: ViSession, mask: ViUInt16, size: ViUInt32) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viFlush(vi: ViSession, mask: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viBufWrite(vi: ViSession, buf: ViConstBuf, cnt: ViUInt32, retCnt: ViPUInt32)
-        -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viBufRead(vi: ViSession, buf: ViPBuf, cnt: ViUInt32, retCnt: ViPUInt32) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viPrintf(vi: ViSession, writeFmt: ViConstString, ...) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVPrintf(vi: ViSession, writeFmt: ViConstString, params: ViVAList) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viSPrintf(vi: ViSession, buf: ViPBuf, writeFmt: ViConstString, ...) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVSPrintf(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viWriteFromFile
+                .as_ref()
+                .expect("Expected function, got error."))(vi, filename, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viAssertTrigger(&self, vi: ViSession, protocol: ViUInt16) -> ViStatus {
+        unsafe {
+            (self
+                .viAssertTrigger
+                .as_ref()
+                .expect("Expected function, got error."))(vi, protocol)
+        }
+    }
+    pub unsafe fn viReadSTB(&self, vi: ViSession, status: ViPUInt16) -> ViStatus {
+        unsafe {
+            (self
+                .viReadSTB
+                .as_ref()
+                .expect("Expected function, got error."))(vi, status)
+        }
+    }
+    pub unsafe fn viClear(&self, vi: ViSession) -> ViStatus {
+        unsafe {
+            (self
+                .viClear
+                .as_ref()
+                .expect("Expected function, got error."))(vi)
+        }
+    }
+    pub unsafe fn viSetBuf(&self, vi: ViSession, mask: ViUInt16, size: ViUInt32) -> ViStatus {
+        unsafe {
+            (self
+                .viSetBuf
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mask, size)
+        }
+    }
+    pub unsafe fn viFlush(&self, vi: ViSession, mask: ViUInt16) -> ViStatus {
+        unsafe {
+            (self
+                .viFlush
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mask)
+        }
+    }
+    pub unsafe fn viBufWrite(
+        &self,
+        vi: ViSession,
+        buf: ViConstBuf,
+        cnt: ViUInt32,
+        retCnt: ViPUInt32,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viBufWrite
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viBufRead(
+        &self,
         vi: ViSession,
         buf: ViPBuf,
+        cnt: ViUInt32,
+        retCnt: ViPUInt32,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viBufRead
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viVPrintf(
+        &self,
+        vi: ViSession,
         writeFmt: ViConstString,
         params: ViVAList,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viScanf(vi: ViSession, readFmt: ViConstString, ...) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVScanf(vi: ViSession, readFmt: ViConstString, params: ViVAList) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viSScanf(vi: ViSession, buf: ViConstBuf, readFmt: ViConstString, ...) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVSScanf(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVPrintf
+                .as_ref()
+                .expect("Expected function, got error."))(vi, writeFmt, params)
+        }
+    }
+    pub unsafe fn viVSPrintf(
+        &self,
+        vi: ViSession,
+        buf: ViPBuf,
+        writeFmt: ViConstString,
+        params: ViVAList,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVSPrintf
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, writeFmt, params)
+        }
+    }
+    pub unsafe fn viVScanf(
+        &self,
         vi: ViSession,
-        buf: ViConstBuf,
         readFmt: ViConstString,
         params: ViVAList,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viQueryf(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVScanf
+                .as_ref()
+                .expect("Expected function, got error."))(vi, readFmt, params)
+        }
+    }
+    pub unsafe fn viVSScanf(
+        &self,
         vi: ViSession,
-        writeFmt: ViConstString,
+        buf: ViConstBuf,
         readFmt: ViConstString,
-        ...
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVQueryf(
+        params: ViVAList,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVSScanf
+                .as_ref()
+                .expect("Expected function, got error."))(vi, buf, readFmt, params)
+        }
+    }
+    pub unsafe fn viVQueryf(
+        &self,
         vi: ViSession,
         writeFmt: ViConstString,
         readFmt: ViConstString,
         params: ViVAList,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn8(vi: ViSession, space: ViUInt16, offset: ViBusAddress, val8: ViPUInt8) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut8(vi: ViSession, space: ViUInt16, offset: ViBusAddress, val8: ViUInt8) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn16(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVQueryf
+                .as_ref()
+                .expect("Expected function, got error."))(vi, writeFmt, readFmt, params)
+        }
+    }
+    pub unsafe fn viIn8(
+        &self,
+        vi: ViSession,
+        space: ViUInt16,
+        offset: ViBusAddress,
+        val8: ViPUInt8,
+    ) -> ViStatus {
+        unsafe {
+            (self.viIn8.as_ref().expect("Expected function, got error."))(vi, space, offset, val8)
+        }
+    }
+    pub unsafe fn viOut8(
+        &self,
+        vi: ViSession,
+        space: ViUInt16,
+        offset: ViBusAddress,
+        val8: ViUInt8,
+    ) -> ViStatus {
+        unsafe {
+            (self.viOut8.as_ref().expect("Expected function, got error."))(vi, space, offset, val8)
+        }
+    }
+    pub unsafe fn viIn16(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         val16: ViPUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut16(
+    ) -> ViStatus {
+        unsafe {
+            (self.viIn16.as_ref().expect("Expected function, got error."))(vi, space, offset, val16)
+        }
+    }
+    pub unsafe fn viOut16(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         val16: ViUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn32(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut16
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val16)
+        }
+    }
+    pub unsafe fn viIn32(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         val32: ViPUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut32(
+    ) -> ViStatus {
+        unsafe {
+            (self.viIn32.as_ref().expect("Expected function, got error."))(vi, space, offset, val32)
+        }
+    }
+    pub unsafe fn viOut32(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         val32: ViUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn64(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut32
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val32)
+        }
+    }
+    pub unsafe fn viIn64(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         val64: ViPUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut64(
+    ) -> ViStatus {
+        unsafe {
+            (self.viIn64.as_ref().expect("Expected function, got error."))(vi, space, offset, val64)
+        }
+    }
+    pub unsafe fn viOut64(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         val64: ViUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn8Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut64
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val64)
+        }
+    }
+    pub unsafe fn viIn8Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val8: ViPUInt8,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut8Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viIn8Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val8)
+        }
+    }
+    pub unsafe fn viOut8Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val8: ViUInt8,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn16Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut8Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val8)
+        }
+    }
+    pub unsafe fn viIn16Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val16: ViPUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut16Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viIn16Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val16)
+        }
+    }
+    pub unsafe fn viOut16Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val16: ViUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn32Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut16Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val16)
+        }
+    }
+    pub unsafe fn viIn32Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val32: ViPUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut32Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viIn32Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val32)
+        }
+    }
+    pub unsafe fn viOut32Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val32: ViUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viIn64Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut32Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val32)
+        }
+    }
+    pub unsafe fn viIn64Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val64: ViPUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viOut64Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viIn64Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val64)
+        }
+    }
+    pub unsafe fn viOut64Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         val64: ViUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn8(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viOut64Ex
+                .as_ref()
+                .expect("Expected function, got error."))(vi, space, offset, val64)
+        }
+    }
+    pub unsafe fn viMoveIn8(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf8: ViAUInt8,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut8(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn8
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf8
+            )
+        }
+    }
+    pub unsafe fn viMoveOut8(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf8: ViAUInt8,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn16(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut8
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf8
+            )
+        }
+    }
+    pub unsafe fn viMoveIn16(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf16: ViAUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut16(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn16
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf16
+            )
+        }
+    }
+    pub unsafe fn viMoveOut16(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf16: ViAUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn32(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut16
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf16
+            )
+        }
+    }
+    pub unsafe fn viMoveIn32(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf32: ViAUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut32(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn32
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf32
+            )
+        }
+    }
+    pub unsafe fn viMoveOut32(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf32: ViAUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn64(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut32
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf32
+            )
+        }
+    }
+    pub unsafe fn viMoveIn64(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf64: ViAUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut64(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn64
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf64
+            )
+        }
+    }
+    pub unsafe fn viMoveOut64(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress,
         length: ViBusSize,
         buf64: ViAUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn8Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut64
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf64
+            )
+        }
+    }
+    pub unsafe fn viMoveIn8Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf8: ViAUInt8,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut8Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn8Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf8
+            )
+        }
+    }
+    pub unsafe fn viMoveOut8Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf8: ViAUInt8,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn16Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut8Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf8
+            )
+        }
+    }
+    pub unsafe fn viMoveIn16Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf16: ViAUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut16Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn16Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf16
+            )
+        }
+    }
+    pub unsafe fn viMoveOut16Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf16: ViAUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn32Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut16Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf16
+            )
+        }
+    }
+    pub unsafe fn viMoveIn32Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf32: ViAUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut32Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn32Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf32
+            )
+        }
+    }
+    pub unsafe fn viMoveOut32Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf32: ViAUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveIn64Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut32Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf32
+            )
+        }
+    }
+    pub unsafe fn viMoveIn64Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf64: ViAUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveOut64Ex(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveIn64Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf64
+            )
+        }
+    }
+    pub unsafe fn viMoveOut64Ex(
+        &self,
         vi: ViSession,
         space: ViUInt16,
         offset: ViBusAddress64,
         length: ViBusSize,
         buf64: ViAUInt64,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMove(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveOut64Ex
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, space, offset, length, buf64
+            )
+        }
+    }
+    pub unsafe fn viMove(
+        &self,
         vi: ViSession,
         srcSpace: ViUInt16,
         srcOffset: ViBusAddress,
@@ -1199,10 +2718,15 @@ unsafe extern "C" {
         destOffset: ViBusAddress,
         destWidth: ViUInt16,
         srcLength: ViBusSize,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveAsync(
+    ) -> ViStatus {
+        unsafe {
+            (self.viMove.as_ref().expect("Expected function, got error."))(
+                vi, srcSpace, srcOffset, srcWidth, destSpace, destOffset, destWidth, srcLength,
+            )
+        }
+    }
+    pub unsafe fn viMoveAsync(
+        &self,
         vi: ViSession,
         srcSpace: ViUInt16,
         srcOffset: ViBusAddress,
@@ -1212,10 +2736,19 @@ unsafe extern "C" {
         destWidth: ViUInt16,
         srcLength: ViBusSize,
         jobId: ViPJobId,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveEx(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveAsync
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, srcSpace, srcOffset, srcWidth, destSpace, destOffset, destWidth, srcLength,
+                jobId,
+            )
+        }
+    }
+    pub unsafe fn viMoveEx(
+        &self,
         vi: ViSession,
         srcSpace: ViUInt16,
         srcOffset: ViBusAddress64,
@@ -1224,10 +2757,18 @@ unsafe extern "C" {
         destOffset: ViBusAddress64,
         destWidth: ViUInt16,
         srcLength: ViBusSize,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMoveAsyncEx(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveEx
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, srcSpace, srcOffset, srcWidth, destSpace, destOffset, destWidth, srcLength,
+            )
+        }
+    }
+    pub unsafe fn viMoveAsyncEx(
+        &self,
         vi: ViSession,
         srcSpace: ViUInt16,
         srcOffset: ViBusAddress64,
@@ -1237,10 +2778,19 @@ unsafe extern "C" {
         destWidth: ViUInt16,
         srcLength: ViBusSize,
         jobId: ViPJobId,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMapAddress(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMoveAsyncEx
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, srcSpace, srcOffset, srcWidth, destSpace, destOffset, destWidth, srcLength,
+                jobId,
+            )
+        }
+    }
+    pub unsafe fn viMapAddress(
+        &self,
         vi: ViSession,
         mapSpace: ViUInt16,
         mapOffset: ViBusAddress,
@@ -1248,13 +2798,26 @@ unsafe extern "C" {
         access: ViBoolean,
         suggested: ViAddr,
         address: ViPAddr,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viUnmapAddress(vi: ViSession) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMapAddressEx(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMapAddress
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, mapSpace, mapOffset, mapSize, access, suggested, address,
+            )
+        }
+    }
+    pub unsafe fn viUnmapAddress(&self, vi: ViSession) -> ViStatus {
+        unsafe {
+            (self
+                .viUnmapAddress
+                .as_ref()
+                .expect("Expected function, got error."))(vi)
+        }
+    }
+    pub unsafe fn viMapAddressEx(
+        &self,
         vi: ViSession,
         mapSpace: ViUInt16,
         mapOffset: ViBusAddress64,
@@ -1262,91 +2825,237 @@ unsafe extern "C" {
         access: ViBoolean,
         suggested: ViAddr,
         address: ViPAddr,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viPeek8(vi: ViSession, address: ViAddr, val8: ViPUInt8);
-}
-unsafe extern "C" {
-    pub fn viPoke8(vi: ViSession, address: ViAddr, val8: ViUInt8);
-}
-unsafe extern "C" {
-    pub fn viPeek16(vi: ViSession, address: ViAddr, val16: ViPUInt16);
-}
-unsafe extern "C" {
-    pub fn viPoke16(vi: ViSession, address: ViAddr, val16: ViUInt16);
-}
-unsafe extern "C" {
-    pub fn viPeek32(vi: ViSession, address: ViAddr, val32: ViPUInt32);
-}
-unsafe extern "C" {
-    pub fn viPoke32(vi: ViSession, address: ViAddr, val32: ViUInt32);
-}
-unsafe extern "C" {
-    pub fn viPeek64(vi: ViSession, address: ViAddr, val64: ViPUInt64);
-}
-unsafe extern "C" {
-    pub fn viPoke64(vi: ViSession, address: ViAddr, val64: ViUInt64);
-}
-unsafe extern "C" {
-    pub fn viMemAlloc(vi: ViSession, size: ViBusSize, offset: ViPBusAddress) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMemFree(vi: ViSession, offset: ViBusAddress) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMemAllocEx(vi: ViSession, size: ViBusSize, offset: ViPBusAddress64) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMemFreeEx(vi: ViSession, offset: ViBusAddress64) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viGpibControlREN(vi: ViSession, mode: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viGpibControlATN(vi: ViSession, mode: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viGpibSendIFC(vi: ViSession) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viGpibCommand(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMapAddressEx
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi, mapSpace, mapOffset, mapSize, access, suggested, address,
+            )
+        }
+    }
+    pub unsafe fn viPeek8(&self, vi: ViSession, address: ViAddr, val8: ViPUInt8) {
+        unsafe {
+            (self
+                .viPeek8
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val8)
+        }
+    }
+    pub unsafe fn viPoke8(&self, vi: ViSession, address: ViAddr, val8: ViUInt8) {
+        unsafe {
+            (self
+                .viPoke8
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val8)
+        }
+    }
+    pub unsafe fn viPeek16(&self, vi: ViSession, address: ViAddr, val16: ViPUInt16) {
+        unsafe {
+            (self
+                .viPeek16
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val16)
+        }
+    }
+    pub unsafe fn viPoke16(&self, vi: ViSession, address: ViAddr, val16: ViUInt16) {
+        unsafe {
+            (self
+                .viPoke16
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val16)
+        }
+    }
+    pub unsafe fn viPeek32(&self, vi: ViSession, address: ViAddr, val32: ViPUInt32) {
+        unsafe {
+            (self
+                .viPeek32
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val32)
+        }
+    }
+    pub unsafe fn viPoke32(&self, vi: ViSession, address: ViAddr, val32: ViUInt32) {
+        unsafe {
+            (self
+                .viPoke32
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val32)
+        }
+    }
+    pub unsafe fn viPeek64(&self, vi: ViSession, address: ViAddr, val64: ViPUInt64) {
+        unsafe {
+            (self
+                .viPeek64
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val64)
+        }
+    }
+    pub unsafe fn viPoke64(&self, vi: ViSession, address: ViAddr, val64: ViUInt64) {
+        unsafe {
+            (self
+                .viPoke64
+                .as_ref()
+                .expect("Expected function, got error."))(vi, address, val64)
+        }
+    }
+    pub unsafe fn viMemAlloc(
+        &self,
+        vi: ViSession,
+        size: ViBusSize,
+        offset: ViPBusAddress,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMemAlloc
+                .as_ref()
+                .expect("Expected function, got error."))(vi, size, offset)
+        }
+    }
+    pub unsafe fn viMemFree(&self, vi: ViSession, offset: ViBusAddress) -> ViStatus {
+        unsafe {
+            (self
+                .viMemFree
+                .as_ref()
+                .expect("Expected function, got error."))(vi, offset)
+        }
+    }
+    pub unsafe fn viMemAllocEx(
+        &self,
+        vi: ViSession,
+        size: ViBusSize,
+        offset: ViPBusAddress64,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMemAllocEx
+                .as_ref()
+                .expect("Expected function, got error."))(vi, size, offset)
+        }
+    }
+    pub unsafe fn viMemFreeEx(&self, vi: ViSession, offset: ViBusAddress64) -> ViStatus {
+        unsafe {
+            (self
+                .viMemFreeEx
+                .as_ref()
+                .expect("Expected function, got error."))(vi, offset)
+        }
+    }
+    pub unsafe fn viGpibControlREN(&self, vi: ViSession, mode: ViUInt16) -> ViStatus {
+        unsafe {
+            (self
+                .viGpibControlREN
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mode)
+        }
+    }
+    pub unsafe fn viGpibControlATN(&self, vi: ViSession, mode: ViUInt16) -> ViStatus {
+        unsafe {
+            (self
+                .viGpibControlATN
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mode)
+        }
+    }
+    pub unsafe fn viGpibSendIFC(&self, vi: ViSession) -> ViStatus {
+        unsafe {
+            (self
+                .viGpibSendIFC
+                .as_ref()
+                .expect("Expected function, got error."))(vi)
+        }
+    }
+    pub unsafe fn viGpibCommand(
+        &self,
         vi: ViSession,
         cmd: ViConstBuf,
         cnt: ViUInt32,
         retCnt: ViPUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viGpibPassControl(vi: ViSession, primAddr: ViUInt16, secAddr: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVxiCommandQuery(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viGpibCommand
+                .as_ref()
+                .expect("Expected function, got error."))(vi, cmd, cnt, retCnt)
+        }
+    }
+    pub unsafe fn viGpibPassControl(
+        &self,
+        vi: ViSession,
+        primAddr: ViUInt16,
+        secAddr: ViUInt16,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viGpibPassControl
+                .as_ref()
+                .expect("Expected function, got error."))(vi, primAddr, secAddr)
+        }
+    }
+    pub unsafe fn viVxiCommandQuery(
+        &self,
         vi: ViSession,
         mode: ViUInt16,
         cmd: ViUInt32,
         response: ViPUInt32,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viAssertUtilSignal(vi: ViSession, line: ViUInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viAssertIntrSignal(vi: ViSession, mode: ViInt16, statusID: ViUInt32) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viMapTrigger(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVxiCommandQuery
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mode, cmd, response)
+        }
+    }
+    pub unsafe fn viAssertUtilSignal(&self, vi: ViSession, line: ViUInt16) -> ViStatus {
+        unsafe {
+            (self
+                .viAssertUtilSignal
+                .as_ref()
+                .expect("Expected function, got error."))(vi, line)
+        }
+    }
+    pub unsafe fn viAssertIntrSignal(
+        &self,
+        vi: ViSession,
+        mode: ViInt16,
+        statusID: ViUInt32,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viAssertIntrSignal
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mode, statusID)
+        }
+    }
+    pub unsafe fn viMapTrigger(
+        &self,
         vi: ViSession,
         trigSrc: ViInt16,
         trigDest: ViInt16,
         mode: ViUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viUnmapTrigger(vi: ViSession, trigSrc: ViInt16, trigDest: ViInt16) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viUsbControlOut(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viMapTrigger
+                .as_ref()
+                .expect("Expected function, got error."))(vi, trigSrc, trigDest, mode)
+        }
+    }
+    pub unsafe fn viUnmapTrigger(
+        &self,
+        vi: ViSession,
+        trigSrc: ViInt16,
+        trigDest: ViInt16,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viUnmapTrigger
+                .as_ref()
+                .expect("Expected function, got error."))(vi, trigSrc, trigDest)
+        }
+    }
+    pub unsafe fn viUsbControlOut(
+        &self,
         vi: ViSession,
         bmRequestType: ViInt16,
         bRequest: ViInt16,
@@ -1354,10 +3063,24 @@ unsafe extern "C" {
         wIndex: ViUInt16,
         wLength: ViUInt16,
         buf: ViConstBuf,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viUsbControlIn(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viUsbControlOut
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi,
+                bmRequestType,
+                bRequest,
+                wValue,
+                wIndex,
+                wLength,
+                buf,
+            )
+        }
+    }
+    pub unsafe fn viUsbControlIn(
+        &self,
         vi: ViSession,
         bmRequestType: ViInt16,
         bRequest: ViInt16,
@@ -1366,17 +3089,55 @@ unsafe extern "C" {
         wLength: ViUInt16,
         buf: ViPBuf,
         retCnt: ViPUInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viPxiReserveTriggers(
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viUsbControlIn
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi,
+                bmRequestType,
+                bRequest,
+                wValue,
+                wIndex,
+                wLength,
+                buf,
+                retCnt,
+            )
+        }
+    }
+    pub unsafe fn viPxiReserveTriggers(
+        &self,
         vi: ViSession,
         cnt: ViInt16,
         trigBuses: ViAInt16,
         trigLines: ViAInt16,
         failureIndex: ViPInt16,
-    ) -> ViStatus;
-}
-unsafe extern "C" {
-    pub fn viVxiServantResponse(vi: ViSession, mode: ViInt16, resp: ViUInt32) -> ViStatus;
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viPxiReserveTriggers
+                .as_ref()
+                .expect("Expected function, got error."))(
+                vi,
+                cnt,
+                trigBuses,
+                trigLines,
+                failureIndex,
+            )
+        }
+    }
+    pub unsafe fn viVxiServantResponse(
+        &self,
+        vi: ViSession,
+        mode: ViInt16,
+        resp: ViUInt32,
+    ) -> ViStatus {
+        unsafe {
+            (self
+                .viVxiServantResponse
+                .as_ref()
+                .expect("Expected function, got error."))(vi, mode, resp)
+        }
+    }
 }
@@ -0,0 +1,304 @@
+use super::{
+    attribute::TermCharEnabled,
+    error::{Error, Result, Status},
+    instrument::Instrument,
+};
+use std::ops::Range;
+
+/// Byte order of the values in a binary block, IEEE 488.2 defaults to big endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ByteOrder {
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
+
+/// Length encoding of a binary block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum BlockLength {
+    /// `#<n><len><data>`, the length is sent ahead of the data.
+    #[default]
+    Definite,
+    /// `#0<data>`, the data lasts until the end of the message.
+    Indefinite,
+}
+
+/// Values a binary block can be decoded into, or encoded from.
+pub trait BlockValue: Copy {
+    /// Size of a value in bytes.
+    const SIZE: usize;
+
+    /// Decodes a value from exactly [`SIZE`](Self::SIZE) bytes.
+    fn from_bytes(bytes: &[u8], order: ByteOrder) -> Self;
+
+    /// Appends the encoded value to `buf`.
+    fn write_bytes(self, order: ByteOrder, buf: &mut Vec<u8>);
+}
+
+macro_rules! block_values {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl BlockValue for $type {
+                const SIZE: usize = size_of::<$type>();
+
+                fn from_bytes(bytes: &[u8], order: ByteOrder) -> Self {
+                    let bytes = bytes.try_into().unwrap();
+                    match order {
+                        ByteOrder::BigEndian => <$type>::from_be_bytes(bytes),
+                        ByteOrder::LittleEndian => <$type>::from_le_bytes(bytes),
+                    }
+                }
+
+                fn write_bytes(self, order: ByteOrder, buf: &mut Vec<u8>) {
+                    match order {
+                        ByteOrder::BigEndian => buf.extend_from_slice(&self.to_be_bytes()),
+                        ByteOrder::LittleEndian => buf.extend_from_slice(&self.to_le_bytes()),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+block_values!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl Instrument {
+    /// Reads an IEEE 488.2 arbitrary block, definite `#<n><len><data>` or indefinite `#0<data>`,
+    /// and decodes its data. Anything before the `#` and the terminator after the data are dropped.
+    ///
+    /// Indefinite blocks are read to END with the termination character disabled, only the newline
+    /// sent with END is dropped. Interfaces without END, such as TCP sockets, can't read them.
+    ///
+    /// ```no_run
+    /// # use visa::ByteOrder;
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// instrument.write("FORM:DATA INT,16;:FORM:BORD SWAP;:CURV?\n")?;
+    /// let samples = instrument.read_binary_block::<i16>(ByteOrder::LittleEndian)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_binary_block<T: BlockValue>(&mut self, order: ByteOrder) -> Result<Vec<T>> {
+        let (mut message, mut status) = self.read_message(false)?;
+        let data = loop {
+            match block_data(&message)? {
+                Some(BlockData::Definite(data)) => {
+                    // A termination character as the last data byte ended the read before the
+                    // terminator, which would otherwise be left for the next read
+                    if data.end == message.len() && status != Status::OperationCompleted {
+                        self.read_message(false)?;
+                    }
+                    break data;
+                }
+                Some(BlockData::Indefinite(mut data)) => {
+                    // Only END ends the data, a termination character in it ended the read early
+                    if status != Status::OperationCompleted {
+                        let instrument = self.with_attribute(TermCharEnabled(false))?;
+                        message.extend(instrument.read_message(false)?.0);
+                        data.end = message.len();
+                    }
+                    if message.ends_with(b"\n") {
+                        data.end -= 1;
+                    }
+                    break data;
+                }
+                None => {
+                    let (rest, rest_status) = self.read_message(false)?;
+                    message.extend(rest);
+                    status = rest_status;
+                }
+            }
+        };
+
+        let data = &message[data];
+        if data.len() % T::SIZE != 0 {
+            return Err(Error::InvalidBlock(format!(
+                "{} bytes of data for values of {} bytes",
+                data.len(),
+                T::SIZE
+            )));
+        }
+        Ok(data
+            .chunks_exact(T::SIZE)
+            .map(|value| T::from_bytes(value, order))
+            .collect())
+    }
+
+    /// Writes `command` followed by `values` as a block and a newline, as in
+    /// `instrument.write_binary_block("TRAC:DATA ", &points, ByteOrder::BigEndian, BlockLength::Definite)`.
+    pub fn write_binary_block<T: BlockValue>(
+        &mut self,
+        command: impl AsRef<[u8]>,
+        values: &[T],
+        order: ByteOrder,
+        length: BlockLength,
+    ) -> Result<()> {
+        let mut message = command.as_ref().to_vec();
+        encode_block(values, order, length, &mut message)?;
+        message.push(b'\n');
+        self.write(message)
+    }
+
+    /// Writes `buf` and reads the block of the response.
+    pub fn query_binary_block<T: BlockValue>(
+        &mut self,
+        buf: impl AsRef<[u8]>,
+        order: ByteOrder,
+    ) -> Result<Vec<T>> {
+        self.query_with(buf.as_ref(), |instrument| {
+            instrument.read_binary_block(order)
+        })
+    }
+}
+
+/// Appends `values` as a block to `message`.
+pub(crate) fn encode_block<T: BlockValue>(
+    values: &[T],
+    order: ByteOrder,
+    length: BlockLength,
+    message: &mut Vec<u8>,
+) -> Result<()> {
+    let data_length = values.len() * T::SIZE;
+    match length {
+        BlockLength::Definite => {
+            let digits = data_length.to_string();
+            if digits.len() > 9 {
+                return Err(Error::InvalidBlock(format!(
+                    "{} bytes of data don't fit a definite length",
+                    data_length
+                )));
+            }
+            message.push(b'#');
+            message.extend_from_slice(digits.len().to_string().as_bytes());
+            message.extend_from_slice(digits.as_bytes());
+        }
+        BlockLength::Indefinite => message.extend_from_slice(b"#0"),
+    }
+    message.reserve(data_length + 1);
+    for value in values {
+        value.write_bytes(order, message);
+    }
+    Ok(())
+}
+
+/// Where the data of a block lies in a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BlockData {
+    Definite(Range<usize>),
+    /// Everything after `#0` to the end of the message, the terminator included.
+    Indefinite(Range<usize>),
+}
+
+/// Where the data of the block in `message` lies, `None` if the message ends before it does.
+pub(crate) fn block_data(message: &[u8]) -> Result<Option<BlockData>> {
+    let start = message
+        .iter()
+        .position(|&byte| byte == b'#')
+        .ok_or_else(|| Error::InvalidBlock("missing #".into()))?;
+    let Some(&digits) = message.get(start + 1) else {
+        return Ok(None);
+    };
+    let digits = match digits {
+        b'0' => return Ok(Some(BlockData::Indefinite(start + 2..message.len()))),
+        b'1'..=b'9' => (digits - b'0') as usize,
+        digits => {
+            return Err(Error::InvalidBlock(format!(
+                "invalid length digit {:?}",
+                digits as char
+            )));
+        }
+    };
+
+    let Some(length) = message.get(start + 2..start + 2 + digits) else {
+        return Ok(None);
+    };
+    let length: usize = std::str::from_utf8(length)
+        .ok()
+        .and_then(|length| length.parse().ok())
+        .ok_or_else(|| {
+            Error::InvalidBlock(format!(
+                "invalid length {:?}",
+                String::from_utf8_lossy(length)
+            ))
+        })?;
+    let data = start + 2 + digits;
+    match message.len() >= data + length {
+        true => Ok(Some(BlockData::Definite(data..data + length))),
+        false => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_definite_block_data() {
+        assert_eq!(
+            block_data(b":CURV #14abcd\n").unwrap(),
+            Some(BlockData::Definite(9..13))
+        );
+        assert_eq!(
+            block_data(b"#10\n").unwrap(),
+            Some(BlockData::Definite(3..3))
+        );
+        assert_eq!(
+            block_data(b"#3012#\n\x00abcdefghij").unwrap(),
+            Some(BlockData::Definite(5..17))
+        );
+    }
+
+    #[test]
+    fn finds_indefinite_block_data() {
+        assert_eq!(
+            block_data(b"#0ab#1\n").unwrap(),
+            Some(BlockData::Indefinite(2..7))
+        );
+    }
+
+    #[test]
+    fn waits_for_the_rest_of_a_block() {
+        assert_eq!(block_data(b"#").unwrap(), None);
+        assert_eq!(block_data(b"#21").unwrap(), None);
+        assert_eq!(block_data(b"#15ab\n").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        for message in [&b"1,2,3\n"[..], b"#a5", b"#2x1ab"] {
+            assert!(matches!(block_data(message), Err(Error::InvalidBlock(_))));
+        }
+    }
+
+    #[test]
+    fn encodes_blocks() {
+        let mut message = vec![];
+        encode_block(
+            &[1u16, 2],
+            ByteOrder::BigEndian,
+            BlockLength::Definite,
+            &mut message,
+        )
+        .unwrap();
+        assert_eq!(message, b"#14\x00\x01\x00\x02");
+
+        message.clear();
+        encode_block(
+            &[1i16],
+            ByteOrder::LittleEndian,
+            BlockLength::Indefinite,
+            &mut message,
+        )
+        .unwrap();
+        assert_eq!(message, b"#0\x01\x00");
+
+        message.clear();
+        encode_block::<f64>(
+            &[],
+            ByteOrder::BigEndian,
+            BlockLength::Definite,
+            &mut message,
+        )
+        .unwrap();
+        assert_eq!(message, b"#10");
+    }
+}
@@ -1,4 +1,5 @@
-use super::bindings::*;
+use super::{attribute::AttributeKind, bindings::*, scpi::ScpiError};
+use std::path::PathBuf;
 use thiserror::Error;
 
 pub type Result<T> = core::result::Result<T, Error>;
@@ -15,6 +16,34 @@ pub enum Error {
     InvalidIdentification(String),
     #[error("Instrument not found")]
     InstrumentNotFound,
+    #[error("Resource {0} is not supported without the VISA library")]
+    UnsupportedResource(String),
+    #[error("VISA library not found, searched {0:?}")]
+    LibraryNotFound(Vec<PathBuf>),
+    #[error("Failed to load VISA library {0:?}: {1}")]
+    InvalidLibrary(PathBuf, String),
+    #[error("Another VISA library is already loaded from {0:?}")]
+    LibraryAlreadyLoaded(PathBuf),
+    #[error("Invalid simulation: {0}")]
+    InvalidSimulation(String),
+    #[error("Invalid transcript: {0}")]
+    InvalidTranscript(String),
+    #[error("Replay diverged from the transcript: {0}")]
+    ReplayDiverged(String),
+    #[error("Message longer than {0} bytes")]
+    MessageTooLong(usize),
+    #[error("Invalid binary block: {0}")]
+    InvalidBlock(String),
+    #[error("Attribute 0x{0:08X} holds {1} values, not {2}")]
+    AttributeKindMismatch(u32, AttributeKind, AttributeKind),
+    #[error("Invalid value {0:?}, expected {1}")]
+    InvalidValue(String, &'static str),
+    #[error("Invalid format string: {0}")]
+    InvalidFormat(String),
+    #[error("Response doesn't match the format: {0}")]
+    FormatMismatch(String),
+    #[error("Instrument reported {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Scpi(Vec<ScpiError>),
 }
 
 #[derive(Debug, Error, Clone, Copy, PartialEq, PartialOrd)]
@@ -484,17 +513,27 @@ impl VisaError {
             Self::NoListeners | Self::Parity | Self::Framing | Self::Overrun => ErrorKind::Other,
             Self::ConnectionLost => ErrorKind::BrokenPipe,
             Self::Io => std::io::Error::last_os_error().kind(),
-            e => ErrorKind::Other,
+            _ => ErrorKind::Other,
         };
 
         Error::new(kind, self)
     }
 }
 
+impl Error {
+    pub fn to_io_error(self) -> std::io::Error {
+        match self {
+            Self::Visa(error) => error.to_io_error(),
+            Self::Io(error) => error,
+            error => std::io::Error::other(error),
+        }
+    }
+}
+
 pub fn parse_vi_status(status: ViStatus) -> Result<Status> {
-    Status::try_from(status).map_err(|error| Error::Visa(error))
+    Status::try_from(status).map_err(Error::Visa)
 }
 
 pub fn parse_vi_status_to_io(status: ViStatus) -> std::io::Result<Status> {
-    Status::try_from(status).map_err(|error| error.to_io_error())
+    Status::try_from(status).map_err(VisaError::to_io_error)
 }
@@ -0,0 +1,183 @@
+use super::{
+    attribute::{Attribute, AttributeKind, AttributeValue},
+    backend::get_vi_attribute,
+    bindings::*,
+    error::{Error, Result, VisaError},
+    library::loaded_library,
+};
+use bitflags::bitflags;
+
+/// Types of events a session can receive, numbered like VISA's `VI_EVENT_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum EventType {
+    /// An asynchronous operation completed.
+    IoCompletion = VI_EVENT_IO_COMPLETION,
+    /// A trigger interrupt was received.
+    Trigger = VI_EVENT_TRIG,
+    /// The device requested service.
+    ServiceRequest = VI_EVENT_SERVICE_REQ,
+    /// The device was cleared.
+    Clear = VI_EVENT_CLEAR,
+    /// An error condition occurred during an operation, only delivered to handlers.
+    Exception = VI_EVENT_EXCEPTION,
+    /// The GPIB controller gained or lost controller-in-charge status.
+    GpibCic = VI_EVENT_GPIB_CIC,
+    /// The GPIB controller was addressed to talk.
+    GpibTalk = VI_EVENT_GPIB_TALK,
+    /// The GPIB controller was addressed to listen.
+    GpibListen = VI_EVENT_GPIB_LISTEN,
+    VxiVmeSysfail = VI_EVENT_VXI_VME_SYSFAIL,
+    VxiVmeSysreset = VI_EVENT_VXI_VME_SYSRESET,
+    VxiSigp = VI_EVENT_VXI_SIGP,
+    VxiVmeIntr = VI_EVENT_VXI_VME_INTR,
+    VxiDevCmd = VI_EVENT_VXI_DEV_CMD,
+    PxiIntr = VI_EVENT_PXI_INTR,
+    /// A client connected to a TCPIP server session.
+    TcpipConnect = VI_EVENT_TCPIP_CONNECT,
+    /// A vendor specific USB interrupt was received.
+    UsbIntr = VI_EVENT_USB_INTR,
+    /// A break was received on a serial port.
+    AsrlBreak = VI_EVENT_ASRL_BREAK,
+    AsrlCts = VI_EVENT_ASRL_CTS,
+    AsrlDsr = VI_EVENT_ASRL_DSR,
+    AsrlDcd = VI_EVENT_ASRL_DCD,
+    AsrlRi = VI_EVENT_ASRL_RI,
+    /// A character was received on a serial port.
+    AsrlChar = VI_EVENT_ASRL_CHAR,
+    /// The termination character was received on a serial port.
+    AsrlTermChar = VI_EVENT_ASRL_TERMCHAR,
+    /// Any enabled event, only valid when waiting for, disabling or discarding events.
+    AllEnabled = VI_ALL_ENABLED_EVENTS,
+}
+
+impl TryFrom<ViEventType> for EventType {
+    type Error = VisaError;
+
+    fn try_from(value: ViEventType) -> std::result::Result<Self, Self::Error> {
+        match value {
+            VI_EVENT_IO_COMPLETION => Ok(Self::IoCompletion),
+            VI_EVENT_TRIG => Ok(Self::Trigger),
+            VI_EVENT_SERVICE_REQ => Ok(Self::ServiceRequest),
+            VI_EVENT_CLEAR => Ok(Self::Clear),
+            VI_EVENT_EXCEPTION => Ok(Self::Exception),
+            VI_EVENT_GPIB_CIC => Ok(Self::GpibCic),
+            VI_EVENT_GPIB_TALK => Ok(Self::GpibTalk),
+            VI_EVENT_GPIB_LISTEN => Ok(Self::GpibListen),
+            VI_EVENT_VXI_VME_SYSFAIL => Ok(Self::VxiVmeSysfail),
+            VI_EVENT_VXI_VME_SYSRESET => Ok(Self::VxiVmeSysreset),
+            VI_EVENT_VXI_SIGP => Ok(Self::VxiSigp),
+            VI_EVENT_VXI_VME_INTR => Ok(Self::VxiVmeIntr),
+            VI_EVENT_VXI_DEV_CMD => Ok(Self::VxiDevCmd),
+            VI_EVENT_PXI_INTR => Ok(Self::PxiIntr),
+            VI_EVENT_TCPIP_CONNECT => Ok(Self::TcpipConnect),
+            VI_EVENT_USB_INTR => Ok(Self::UsbIntr),
+            VI_EVENT_ASRL_BREAK => Ok(Self::AsrlBreak),
+            VI_EVENT_ASRL_CTS => Ok(Self::AsrlCts),
+            VI_EVENT_ASRL_DSR => Ok(Self::AsrlDsr),
+            VI_EVENT_ASRL_DCD => Ok(Self::AsrlDcd),
+            VI_EVENT_ASRL_RI => Ok(Self::AsrlRi),
+            VI_EVENT_ASRL_CHAR => Ok(Self::AsrlChar),
+            VI_EVENT_ASRL_TERMCHAR => Ok(Self::AsrlTermChar),
+            VI_ALL_ENABLED_EVENTS => Ok(Self::AllEnabled),
+            _ => Err(VisaError::InvalidEvent),
+        }
+    }
+}
+
+bitflags! {
+    /// How events are delivered, queued for [`Instrument::wait_on_event`](crate::Instrument::wait_on_event)
+    /// or passed to handlers.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct EventMechanism: ViUInt16 {
+        const QUEUE = VI_QUEUE as _;
+        const HANDLER = VI_HNDLR as _;
+        /// Handlers aren't called, events are kept until the mechanism is switched to `HANDLER`.
+        const SUSPEND_HANDLER = VI_SUSPEND_HNDLR as _;
+        const ALL = VI_ALL_MECH as _;
+    }
+}
+
+/// A closure called with every event of the type it was installed for, once the
+/// [`EventMechanism::HANDLER`] mechanism is enabled. The event is only valid during the call.
+pub type EventHandler = Box<dyn FnMut(&Event) + Send>;
+
+/// Identifies an installed [`EventHandler`] to uninstall it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandlerId(pub u64);
+
+/// An event taken from the queue, the VISA event context it refers to is closed on drop.
+#[derive(Debug)]
+pub struct Event {
+    event_type: EventType,
+    context: Option<ViEvent>,
+}
+
+impl Event {
+    /// An event without a VISA event context, as delivered by native and custom backends.
+    pub fn new(event_type: EventType) -> Self {
+        Self {
+            event_type,
+            context: None,
+        }
+    }
+
+    pub(crate) fn from_context(event_type: EventType, context: ViEvent) -> Self {
+        Self {
+            event_type,
+            context: Some(context),
+        }
+    }
+
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    /// The VISA event context, `None` if the event wasn't delivered by the VISA library.
+    pub fn as_vi_event(&self) -> Option<ViEvent> {
+        self.context
+    }
+
+    /// Reads a typed attribute of the event, such as [`EventStatus`](crate::attribute::EventStatus).
+    pub fn get<A: Attribute>(&self) -> Result<A> {
+        A::from_value(self.get_attribute(A::ID, A::KIND)?)
+    }
+
+    /// Reads any attribute of the event by its `VI_ATTR_*` identifier, its values must be of type `kind`.
+    /// Attributes with a typed counterpart in [`attribute`](crate::attribute) fail with another `kind`.
+    pub fn get_attribute(&self, attribute: u32, kind: AttributeKind) -> Result<AttributeValue> {
+        let context = self
+            .context
+            .ok_or(Error::Visa(VisaError::AttributeNotSupported))?;
+        get_vi_attribute(context, attribute, kind)
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        // A context can only have been received through a loaded library
+        if let (Some(context), Some(visa)) = (self.context, loaded_library()) {
+            unsafe {
+                visa.viClose(context);
+            }
+        }
+    }
+}
+
+/// Checks that `event_type` and `mechanism` only ask for queued service requests, the only
+/// events native backends deliver.
+pub(crate) fn check_service_request(
+    event_type: EventType,
+    mechanism: EventMechanism,
+) -> Result<()> {
+    if !matches!(
+        event_type,
+        EventType::ServiceRequest | EventType::AllEnabled
+    ) {
+        return Err(Error::Visa(VisaError::InvalidEvent));
+    }
+    if !(mechanism - EventMechanism::QUEUE).is_empty() && mechanism != EventMechanism::ALL {
+        return Err(Error::Visa(VisaError::MechanismNotSupported));
+    }
+    Ok(())
+}
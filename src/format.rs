@@ -0,0 +1,1428 @@
+use super::{
+    ascii::AsciiValue,
+    block::{BlockData, BlockLength, BlockValue, ByteOrder, block_data, encode_block},
+    error::{Error, Result},
+    instrument::{FlushMode, Instrument},
+};
+use std::slice::Iter;
+
+/// An argument of [`Instrument::printf`], usually converted by the [`printf!`](crate::printf) macro.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatArg<'a> {
+    Int(i64),
+    Float(f64),
+    Char(char),
+    Str(&'a str),
+    /// Values of a `%,d` or `%,f` list, or of a `%b`, `%B` or `%y` block.
+    Array(FormatArray<'a>),
+}
+
+/// Values of a [`FormatArg::Array`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FormatArray<'a> {
+    I8(&'a [i8]),
+    U8(&'a [u8]),
+    I16(&'a [i16]),
+    U16(&'a [u16]),
+    I32(&'a [i32]),
+    U32(&'a [u32]),
+    I64(&'a [i64]),
+    U64(&'a [u64]),
+    F32(&'a [f32]),
+    F64(&'a [f64]),
+}
+
+macro_rules! format_ints {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl From<$type> for FormatArg<'_> {
+                fn from(value: $type) -> Self {
+                    Self::Int(value as i64)
+                }
+            }
+        )*
+    };
+}
+
+format_ints!(i8, u8, i16, u16, i32, u32, i64, isize, usize);
+
+impl From<f32> for FormatArg<'_> {
+    fn from(value: f32) -> Self {
+        Self::Float(value as f64)
+    }
+}
+
+impl From<f64> for FormatArg<'_> {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<char> for FormatArg<'_> {
+    fn from(value: char) -> Self {
+        Self::Char(value)
+    }
+}
+
+impl<'a> From<&'a str> for FormatArg<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a> From<&'a String> for FormatArg<'a> {
+    fn from(value: &'a String) -> Self {
+        Self::Str(value)
+    }
+}
+
+macro_rules! format_arrays {
+    ($($variant:ident($type:ty)),* $(,)?) => {
+        $(
+            impl<'a> From<&'a [$type]> for FormatArg<'a> {
+                fn from(value: &'a [$type]) -> Self {
+                    Self::Array(FormatArray::$variant(value))
+                }
+            }
+
+            impl<'a, const N: usize> From<&'a [$type; N]> for FormatArg<'a> {
+                fn from(value: &'a [$type; N]) -> Self {
+                    Self::Array(FormatArray::$variant(value))
+                }
+            }
+
+            impl<'a> From<&'a Vec<$type>> for FormatArg<'a> {
+                fn from(value: &'a Vec<$type>) -> Self {
+                    Self::Array(FormatArray::$variant(value))
+                }
+            }
+        )*
+    };
+}
+
+format_arrays!(
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+);
+
+impl FormatArray<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Self::I8(values) => values.len(),
+            Self::U8(values) => values.len(),
+            Self::I16(values) => values.len(),
+            Self::U16(values) => values.len(),
+            Self::I32(values) => values.len(),
+            Self::U32(values) => values.len(),
+            Self::I64(values) => values.len(),
+            Self::U64(values) => values.len(),
+            Self::F32(values) => values.len(),
+            Self::F64(values) => values.len(),
+        }
+    }
+
+    fn number(&self, index: usize) -> Result<Number> {
+        Ok(match self {
+            Self::I8(values) => Number::Int(values[index] as _),
+            Self::U8(values) => Number::Int(values[index] as _),
+            Self::I16(values) => Number::Int(values[index] as _),
+            Self::U16(values) => Number::Int(values[index] as _),
+            Self::I32(values) => Number::Int(values[index] as _),
+            Self::U32(values) => Number::Int(values[index] as _),
+            Self::I64(values) => Number::Int(values[index]),
+            Self::U64(values) => Number::Int(values[index].try_into().map_err(|_| {
+                Error::InvalidFormat(format!("{} doesn't fit an integer", values[index]))
+            })?),
+            Self::F32(values) => Number::Float(values[index] as _),
+            Self::F64(values) => Number::Float(values[index]),
+        })
+    }
+
+    /// Appends the first `count` values, as a block if `length` is set and raw otherwise.
+    fn encode(
+        &self,
+        spec: &Spec,
+        count: usize,
+        length: Option<BlockLength>,
+        message: &mut Vec<u8>,
+    ) -> Result<()> {
+        fn encode<T: BlockValue>(
+            values: &[T],
+            order: ByteOrder,
+            length: Option<BlockLength>,
+            message: &mut Vec<u8>,
+        ) -> Result<()> {
+            match length {
+                Some(length) => encode_block(values, order, length, message),
+                None => {
+                    for value in values {
+                        value.write_bytes(order, message);
+                    }
+                    Ok(())
+                }
+            }
+        }
+
+        let order = spec.order;
+        match (self, spec.size) {
+            (Self::I8(values), Size::Default) => encode(&values[..count], order, length, message),
+            (Self::U8(values), Size::Default) => encode(&values[..count], order, length, message),
+            (Self::I16(values), Size::Short) => encode(&values[..count], order, length, message),
+            (Self::U16(values), Size::Short) => encode(&values[..count], order, length, message),
+            (Self::I32(values), Size::Long) => encode(&values[..count], order, length, message),
+            (Self::U32(values), Size::Long) => encode(&values[..count], order, length, message),
+            (Self::I64(values), Size::LongLong) => encode(&values[..count], order, length, message),
+            (Self::U64(values), Size::LongLong) => encode(&values[..count], order, length, message),
+            (Self::F32(values), Size::Float32) => encode(&values[..count], order, length, message),
+            (Self::F64(values), Size::Float64) => encode(&values[..count], order, length, message),
+            _ => Err(Error::InvalidFormat(format!(
+                "{} doesn't take {}",
+                spec.text,
+                self.type_name()
+            ))),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::I8(_) => "i8 values",
+            Self::U8(_) => "u8 values",
+            Self::I16(_) => "i16 values",
+            Self::U16(_) => "u16 values",
+            Self::I32(_) => "i32 values",
+            Self::U32(_) => "u32 values",
+            Self::I64(_) => "i64 values",
+            Self::U64(_) => "u64 values",
+            Self::F32(_) => "f32 values",
+            Self::F64(_) => "f64 values",
+        }
+    }
+}
+
+/// A value read by [`Instrument::scanf`], converted to the caller's types by [`ScanValue`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scanned {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// Values of a `%,d` list.
+    Ints(Vec<i64>),
+    /// Values of a `%,f` list.
+    Floats(Vec<f64>),
+    /// Data of a `%b` block or of `%y`, elements of `size` bytes in `order`.
+    Block {
+        data: Vec<u8>,
+        size: usize,
+        order: ByteOrder,
+    },
+}
+
+/// Types a [`Scanned`] value converts into.
+pub trait ScanValue: Sized {
+    /// Converts `value`, giving it back if it doesn't fit.
+    fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned>;
+}
+
+impl ScanValue for Scanned {
+    fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+        Ok(value)
+    }
+}
+
+impl ScanValue for String {
+    fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+        match value {
+            Scanned::Str(value) => Ok(value),
+            value => Err(value),
+        }
+    }
+}
+
+impl ScanValue for char {
+    fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+        let mut chars = match &value {
+            Scanned::Str(text) => text.chars(),
+            _ => return Err(value),
+        };
+        match (chars.next(), chars.next()) {
+            (Some(char), None) => Ok(char),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Decodes the data of a block, whose elements must be the size of `T`.
+fn decode_block<T: BlockValue>(value: Scanned) -> std::result::Result<Vec<T>, Scanned> {
+    match value {
+        Scanned::Block { data, size, order } if size == T::SIZE => Ok(data
+            .chunks_exact(T::SIZE)
+            .map(|value| T::from_bytes(value, order))
+            .collect()),
+        value => Err(value),
+    }
+}
+
+macro_rules! scan_ints {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl ScanValue for $type {
+                fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+                    match value {
+                        Scanned::Int(number) => number.try_into().map_err(|_| value),
+                        value => Err(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+scan_ints!(i8, u8, i16, u16, i32, u32, i64, u64, isize, usize);
+
+macro_rules! scan_int_lists {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl ScanValue for Vec<$type> {
+                fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+                    match value {
+                        Scanned::Ints(numbers) => numbers
+                            .iter()
+                            .map(|&number| number.try_into())
+                            .collect::<std::result::Result<_, _>>()
+                            .map_err(|_| Scanned::Ints(numbers)),
+                        value => decode_block(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+scan_int_lists!(i8, u8, i16, u16, i32, u32, i64, u64);
+
+macro_rules! scan_floats {
+    ($($type:ty),* $(,)?) => {
+        $(
+            impl ScanValue for $type {
+                fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+                    match value {
+                        Scanned::Float(number) => Ok(number as _),
+                        Scanned::Int(number) => Ok(number as _),
+                        value => Err(value),
+                    }
+                }
+            }
+
+            impl ScanValue for Vec<$type> {
+                fn from_scanned(value: Scanned) -> std::result::Result<Self, Scanned> {
+                    match value {
+                        Scanned::Floats(numbers) => Ok(numbers.into_iter().map(|number| number as _).collect()),
+                        Scanned::Ints(numbers) => Ok(numbers.into_iter().map(|number| number as _).collect()),
+                        value => decode_block(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+scan_floats!(f32, f64);
+
+/// What [`Instrument::scanf`] fills, a tuple with a [`ScanValue`] for every conversion that
+/// isn't suppressed with `*`.
+pub trait FromScanned: Sized {
+    fn from_scanned(values: Vec<Scanned>) -> Result<Self>;
+}
+
+impl FromScanned for Vec<Scanned> {
+    fn from_scanned(values: Vec<Scanned>) -> Result<Self> {
+        Ok(values)
+    }
+}
+
+impl FromScanned for () {
+    fn from_scanned(values: Vec<Scanned>) -> Result<Self> {
+        match values.is_empty() {
+            true => Ok(()),
+            false => Err(Error::FormatMismatch(format!(
+                "{} values scanned for none",
+                values.len()
+            ))),
+        }
+    }
+}
+
+fn convert<T: ScanValue>(value: Scanned) -> Result<T> {
+    T::from_scanned(value)
+        .map_err(|value| Error::InvalidValue(format!("{:?}", value), std::any::type_name::<T>()))
+}
+
+macro_rules! scan_tuples {
+    ($(($($name:ident),+)),* $(,)?) => {
+        $(
+            impl<$($name: ScanValue),+> FromScanned for ($($name,)+) {
+                fn from_scanned(values: Vec<Scanned>) -> Result<Self> {
+                    let count = [$(stringify!($name)),+].len();
+                    if values.len() != count {
+                        return Err(Error::FormatMismatch(format!(
+                            "{} values scanned for {}",
+                            values.len(),
+                            count
+                        )));
+                    }
+                    let mut values = values.into_iter();
+                    Ok(($(convert::<$name>(values.next().unwrap())?,)+))
+                }
+            }
+        )*
+    };
+}
+
+scan_tuples!(
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F),
+    (A, B, C, D, E, F, G),
+    (A, B, C, D, E, F, G, H),
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Print,
+    Scan,
+}
+
+/// A width, precision or list length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Count {
+    Fixed(usize),
+    /// `*`, taken from the arguments.
+    Argument,
+    /// `#` or nothing after `,`, as many as there are.
+    Any,
+}
+
+/// Element size modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Size {
+    #[default]
+    Default,
+    /// `h`, 16 bits.
+    Short,
+    /// `l`, 32 bits.
+    Long,
+    /// `ll`, 64 bits.
+    LongLong,
+    /// `L`, for compatibility only.
+    LongDouble,
+    /// `z`, 32-bit floats.
+    Float32,
+    /// `Z`, 64-bit floats.
+    Float64,
+}
+
+impl Size {
+    fn bytes(self) -> usize {
+        match self {
+            Self::Default => 1,
+            Self::Short => 2,
+            Self::Long | Self::Float32 => 4,
+            Self::LongLong | Self::LongDouble | Self::Float64 => 8,
+        }
+    }
+}
+
+/// IEEE 488.2 numeric formats, `@1`, `@2`, `@3`, `@H`, `@Q` and `@B`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Notation {
+    Nr1,
+    Nr2,
+    Nr3,
+    Hexadecimal,
+    Octal,
+    Binary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+/// A conversion of a format string, such as `%,10lf`.
+#[derive(Debug, Clone, Default)]
+struct Spec {
+    /// The conversion as written, for errors.
+    text: String,
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    /// `%*d` of scans, read but not assigned.
+    suppress: bool,
+    width: Option<Count>,
+    /// Length of a `,` list.
+    array: Option<Count>,
+    precision: Option<Count>,
+    notation: Option<Notation>,
+    size: Size,
+    order: ByteOrder,
+    /// Characters of a `%[...]` scan, and whether they're excluded.
+    set: Option<(bool, Vec<char>)>,
+    conversion: char,
+}
+
+#[derive(Debug, Clone)]
+enum Piece {
+    Literal(String),
+    Conversion(Spec),
+}
+
+struct Cursor<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.position += char.len_utf8();
+        Some(char)
+    }
+
+    fn eat(&mut self, char: char) -> bool {
+        let eaten = self.peek() == Some(char);
+        if eaten {
+            self.position += char.len_utf8();
+        }
+        eaten
+    }
+
+    fn count(&mut self) -> Option<Count> {
+        if self.eat('*') {
+            return Some(Count::Argument);
+        }
+        let rest = &self.text[self.position..];
+        let digits = rest
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let count = rest[..digits].parse().ok()?;
+        self.position += digits;
+        Some(Count::Fixed(count))
+    }
+}
+
+fn parse_format(format: &str, direction: Direction) -> Result<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        literal.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("%%") {
+            literal.push('%');
+            rest = after;
+            continue;
+        }
+
+        let (spec, length) = parse_spec(rest, direction)?;
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+        }
+        pieces.push(Piece::Conversion(spec));
+        rest = &rest[length..];
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// Parses the conversion `directive` starts with, returning it and its length.
+fn parse_spec(directive: &str, direction: Direction) -> Result<(Spec, usize)> {
+    let mut cursor = Cursor {
+        text: directive,
+        position: 1,
+    };
+    let mut spec = Spec {
+        suppress: direction == Direction::Scan && cursor.eat('*'),
+        ..Spec::default()
+    };
+    while let Some(flag) = cursor.peek() {
+        match flag {
+            '-' => spec.left = true,
+            '+' => spec.plus = true,
+            ' ' => spec.space = true,
+            '#' => spec.alternate = true,
+            '0' => spec.zero = true,
+            _ => break,
+        }
+        cursor.bump();
+    }
+    spec.width = cursor.count();
+    if cursor.eat(',') {
+        spec.array = match cursor.eat('#') {
+            true => Some(Count::Any),
+            false => Some(cursor.count().unwrap_or(Count::Any)),
+        };
+    }
+    if cursor.eat('.') {
+        spec.precision = Some(cursor.count().unwrap_or(Count::Fixed(0)));
+    }
+
+    let invalid = |cursor: &Cursor| {
+        Error::InvalidFormat(format!(
+            "invalid conversion {:?}",
+            &directive[..cursor.position]
+        ))
+    };
+    loop {
+        if cursor.eat('@') {
+            spec.notation = Some(match cursor.bump() {
+                Some('1') => Notation::Nr1,
+                Some('2') => Notation::Nr2,
+                Some('3') => Notation::Nr3,
+                Some('H' | 'h') => Notation::Hexadecimal,
+                Some('Q' | 'q') => Notation::Octal,
+                Some('B') => Notation::Binary,
+                _ => return Err(invalid(&cursor)),
+            });
+        } else if cursor.eat('!') {
+            spec.order = match (cursor.bump(), cursor.bump()) {
+                (Some('o'), Some('b')) => ByteOrder::BigEndian,
+                (Some('o'), Some('l')) => ByteOrder::LittleEndian,
+                _ => return Err(invalid(&cursor)),
+            };
+        } else if cursor.eat('h') {
+            spec.size = Size::Short;
+        } else if cursor.eat('l') {
+            spec.size = match cursor.eat('l') {
+                true => Size::LongLong,
+                false => Size::Long,
+            };
+        } else if cursor.eat('L') {
+            spec.size = Size::LongDouble;
+        } else if cursor.eat('z') {
+            spec.size = Size::Float32;
+        } else if cursor.eat('Z') {
+            spec.size = Size::Float64;
+        } else {
+            break;
+        }
+    }
+
+    spec.conversion = cursor.bump().ok_or_else(|| invalid(&cursor))?;
+    let conversions = match direction {
+        Direction::Print => "diuoxXfFeEgGcsbBy",
+        Direction::Scan => "diuoxXfFeEgGcsbytTn[",
+    };
+    if !conversions.contains(spec.conversion) {
+        return Err(invalid(&cursor));
+    }
+    if direction == Direction::Scan {
+        // Scans take no arguments, `,*` reads a list of any length
+        if spec.width == Some(Count::Argument) || spec.precision == Some(Count::Argument) {
+            return Err(invalid(&cursor));
+        }
+        if spec.array == Some(Count::Argument) {
+            spec.array = Some(Count::Any);
+        }
+    }
+    if spec.conversion == '[' {
+        let excluded = cursor.eat('^');
+        let mut set = Vec::new();
+        // A leading `]` is part of the set
+        if cursor.eat(']') {
+            set.push(']');
+        }
+        loop {
+            match cursor.bump() {
+                Some(']') => break,
+                Some(char) => set.push(char),
+                None => return Err(invalid(&cursor)),
+            }
+        }
+        spec.set = Some((excluded, set));
+    }
+
+    spec.text = directive[..cursor.position].to_owned();
+    Ok((spec, cursor.position))
+}
+
+/// Formats `args` like VISA's `viSPrintf`, see [`Instrument::printf`] for the conversions.
+///
+/// ```
+/// use visa::{FormatArg, sprintf};
+///
+/// let points = [1, 2, 3];
+/// let message = sprintf("LIST %,#d;FREQ %@3d\n", &[FormatArg::from(&points), 1000.into()])?;
+/// assert_eq!(message, b"LIST 1,2,3;FREQ 1.000000E+03\n");
+/// # Ok::<(), visa::Error>(())
+/// ```
+pub fn sprintf(format: &str, args: &[FormatArg<'_>]) -> Result<Vec<u8>> {
+    let mut message = Vec::new();
+    let mut args = args.iter();
+    for piece in parse_format(format, Direction::Print)? {
+        match piece {
+            Piece::Literal(text) => message.extend_from_slice(text.as_bytes()),
+            Piece::Conversion(spec) => spec.print(&mut args, &mut message)?,
+        }
+    }
+    match args.len() {
+        0 => Ok(message),
+        count => Err(Error::InvalidFormat(format!(
+            "{} arguments left for {:?}",
+            count, format
+        ))),
+    }
+}
+
+/// Scans `response` like VISA's `viSScanf`, see [`Instrument::scanf`] for the conversions.
+/// `%T` reads up to a newline.
+pub fn sscanf<T: FromScanned>(format: &str, response: &[u8]) -> Result<T> {
+    let pieces = parse_format(format, Direction::Scan)?;
+    match scan(&pieces, response, b'\n')? {
+        Some(values) => T::from_scanned(values),
+        None => Err(Error::InvalidBlock("block longer than the response".into())),
+    }
+}
+
+impl Spec {
+    fn argument<'a>(&self, args: &mut Iter<'_, FormatArg<'a>>) -> Result<FormatArg<'a>> {
+        args.next()
+            .copied()
+            .ok_or_else(|| Error::InvalidFormat(format!("missing argument for {}", self.text)))
+    }
+
+    /// Resolves a count, taking it from the arguments for `*`.
+    fn resolve(
+        &self,
+        count: Option<Count>,
+        args: &mut Iter<'_, FormatArg>,
+    ) -> Result<Option<usize>> {
+        match count {
+            Some(Count::Fixed(count)) => Ok(Some(count)),
+            Some(Count::Argument) => match self.argument(args)? {
+                FormatArg::Int(count) if count >= 0 => Ok(Some(count as usize)),
+                arg => Err(Error::InvalidFormat(format!(
+                    "{:?} isn't a count for {}",
+                    arg, self.text
+                ))),
+            },
+            Some(Count::Any) | None => Ok(None),
+        }
+    }
+
+    fn mismatch(&self, arg: FormatArg, expected: &str) -> Error {
+        Error::InvalidFormat(format!("{} takes {}, got {:?}", self.text, expected, arg))
+    }
+
+    /// Takes the first `count` values of a slice, all of them for `None`.
+    fn array<'a>(
+        &self,
+        arg: FormatArg<'a>,
+        count: Option<usize>,
+    ) -> Result<(FormatArray<'a>, usize)> {
+        let FormatArg::Array(values) = arg else {
+            return Err(self.mismatch(arg, "a slice"));
+        };
+        let count = count.unwrap_or(values.len());
+        match count <= values.len() {
+            true => Ok((values, count)),
+            false => Err(Error::InvalidFormat(format!(
+                "{} takes {} values, got {}",
+                self.text,
+                count,
+                values.len()
+            ))),
+        }
+    }
+
+    fn print(&self, args: &mut Iter<'_, FormatArg>, message: &mut Vec<u8>) -> Result<()> {
+        let width = self.resolve(self.width, args)?;
+        let array = match self.array {
+            Some(count) => Some(self.resolve(Some(count), args)?),
+            None => None,
+        };
+        let precision = self.resolve(self.precision, args)?;
+        let arg = self.argument(args)?;
+
+        match self.conversion {
+            // The width counts the values of blocks
+            'b' | 'B' | 'y' => {
+                let (values, count) = self.array(arg, width)?;
+                let length = match self.conversion {
+                    'b' => Some(BlockLength::Definite),
+                    'B' => Some(BlockLength::Indefinite),
+                    _ => None,
+                };
+                values.encode(self, count, length, message)
+            }
+            'c' => {
+                let char = match arg {
+                    FormatArg::Char(char) => char,
+                    FormatArg::Int(code) => u8::try_from(code)
+                        .map(char::from)
+                        .map_err(|_| self.mismatch(arg, "a character"))?,
+                    arg => return Err(self.mismatch(arg, "a character")),
+                };
+                message.extend(self.pad("", &char.to_string(), width, false).as_bytes());
+                Ok(())
+            }
+            's' => {
+                let FormatArg::Str(text) = arg else {
+                    return Err(self.mismatch(arg, "a string"));
+                };
+                let text = match precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text.to_owned(),
+                };
+                message.extend(self.pad("", &text, width, false).as_bytes());
+                Ok(())
+            }
+            _ => match array {
+                Some(count) => {
+                    let (values, count) = self.array(arg, count)?;
+                    for index in 0..count {
+                        if index > 0 {
+                            message.push(b',');
+                        }
+                        let number = self.number(values.number(index)?, width, precision);
+                        message.extend(number.as_bytes());
+                    }
+                    Ok(())
+                }
+                None => {
+                    let number = match arg {
+                        FormatArg::Int(value) => Number::Int(value),
+                        FormatArg::Float(value) => Number::Float(value),
+                        arg => return Err(self.mismatch(arg, "a number")),
+                    };
+                    message.extend(self.number(number, width, precision).as_bytes());
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// Formats a number of a numeric conversion, IEEE 488.2 notations taking precedence.
+    fn number(&self, number: Number, width: Option<usize>, precision: Option<usize>) -> String {
+        let float = || match number {
+            Number::Int(value) => value as f64,
+            Number::Float(value) => value,
+        };
+        let integer = || match number {
+            Number::Int(value) => value,
+            Number::Float(value) => value.round() as i64,
+        };
+        let radix = |value: i64, radix: u32, prefix: &str, upper: bool| {
+            let magnitude = value.unsigned_abs();
+            let digits = match radix {
+                2 => format!("{:b}", magnitude),
+                8 => format!("{:o}", magnitude),
+                _ if upper => format!("{:X}", magnitude),
+                _ => format!("{:x}", magnitude),
+            };
+            (value < 0, format!("{}{}", prefix, digits))
+        };
+
+        let (negative, digits) = match (self.notation, self.conversion) {
+            (Some(Notation::Hexadecimal), _) => radix(integer(), 16, "#H", true),
+            (Some(Notation::Octal), _) => radix(integer(), 8, "#Q", true),
+            (Some(Notation::Binary), _) => radix(integer(), 2, "#B", true),
+            (Some(Notation::Nr1), _) | (None, 'd' | 'i' | 'u') => {
+                let value = integer();
+                let mut digits = value.unsigned_abs().to_string();
+                if let Some(precision) = precision {
+                    digits = format!("{:0>1$}", digits, precision);
+                }
+                (value < 0, digits)
+            }
+            (None, 'o') => radix(integer(), 8, if self.alternate { "0" } else { "" }, false),
+            (None, 'x') => radix(integer(), 16, if self.alternate { "0x" } else { "" }, false),
+            (None, 'X') => radix(integer(), 16, if self.alternate { "0X" } else { "" }, true),
+            (notation, conversion) => {
+                let value = float();
+                let upper = conversion.is_ascii_uppercase() || notation == Some(Notation::Nr3);
+                let precision = precision.unwrap_or(6);
+                let digits = match (notation, conversion) {
+                    _ if value.is_nan() => "nan".to_owned(),
+                    _ if value.is_infinite() => "inf".to_owned(),
+                    (Some(Notation::Nr3), _) | (None, 'e' | 'E') => {
+                        exponent(value.abs(), precision, upper)
+                    }
+                    (None, 'g' | 'G') => general(value.abs(), precision, upper, self.alternate),
+                    _ => {
+                        let mut digits = format!("{:.*}", precision, value.abs());
+                        if self.alternate && precision == 0 {
+                            digits.push('.');
+                        }
+                        digits
+                    }
+                };
+                let digits = match upper {
+                    true => digits.to_ascii_uppercase(),
+                    false => digits,
+                };
+                return self.pad(
+                    self.sign(value.is_sign_negative() && !value.is_nan()),
+                    &digits,
+                    width,
+                    value.is_finite(),
+                );
+            }
+        };
+        self.pad(self.sign(negative), &digits, width, precision.is_none())
+    }
+
+    fn sign(&self, negative: bool) -> &'static str {
+        match negative {
+            true => "-",
+            false if self.plus => "+",
+            false if self.space => " ",
+            false => "",
+        }
+    }
+
+    /// Pads `sign` and `digits` to `width`, with zeros after the sign if `zeros` are allowed.
+    fn pad(&self, sign: &str, digits: &str, width: Option<usize>, zeros: bool) -> String {
+        let length = sign.len() + digits.chars().count();
+        let padding = width.unwrap_or(0).saturating_sub(length);
+        match (self.left, self.zero && zeros) {
+            (true, _) => format!("{}{}{}", sign, digits, " ".repeat(padding)),
+            (false, true) => {
+                // After the radix prefix, if any
+                let prefix = match digits.as_bytes() {
+                    [b'#', _, ..] => 2,
+                    [b'0', b'x' | b'X', ..] => 2,
+                    _ => 0,
+                };
+                format!(
+                    "{}{}{}{}",
+                    sign,
+                    &digits[..prefix],
+                    "0".repeat(padding),
+                    &digits[prefix..]
+                )
+            }
+            (false, false) => format!("{}{}{}", " ".repeat(padding), sign, digits),
+        }
+    }
+}
+
+/// Formats a finite `value` like C's `%e`, `1.500000e+00`.
+pub(crate) fn exponent(value: f64, precision: usize, upper: bool) -> String {
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    format!(
+        "{}{}{}{:02}",
+        mantissa,
+        if upper { 'E' } else { 'e' },
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// Formats a non-negative `value` like C's `%g`, the shorter of `%f` and `%e` for `precision`
+/// significant digits.
+fn general(value: f64, precision: usize, upper: bool, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let rounded = format!("{:.*e}", precision - 1, value);
+    let exponent: i32 = rounded.split_once('e').unwrap().1.parse().unwrap();
+    let formatted = match exponent >= -4 && exponent < precision as i32 {
+        true => format!("{:.*}", (precision as i32 - 1 - exponent) as usize, value),
+        false => self::exponent(value, precision - 1, upper),
+    };
+    if alternate {
+        return formatted;
+    }
+
+    // Drops trailing zeros of the fraction, before the exponent if any
+    let (mantissa, exponent) = match formatted.find(['e', 'E']) {
+        Some(position) => formatted.split_at(position),
+        None => (formatted.as_str(), ""),
+    };
+    let mantissa = match mantissa.contains('.') {
+        true => mantissa.trim_end_matches('0').trim_end_matches('.'),
+        false => mantissa,
+    };
+    format!("{}{}", mantissa, exponent)
+}
+
+struct Input<'a> {
+    message: &'a [u8],
+    position: usize,
+}
+
+impl Input<'_> {
+    fn rest(&self) -> &[u8] {
+        &self.message[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .message
+            .get(self.position)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.position += 1;
+        }
+    }
+
+    fn mismatch(&self, expected: &str) -> Error {
+        let rest = self.rest();
+        Error::FormatMismatch(format!(
+            "expected {} at {:?}",
+            expected,
+            String::from_utf8_lossy(&rest[..rest.len().min(20)])
+        ))
+    }
+
+    /// Takes `length` bytes as text.
+    fn take_text(&mut self, length: usize) -> Result<String> {
+        let bytes = self
+            .rest()
+            .get(..length)
+            .ok_or_else(|| self.mismatch(&format!("{} bytes", length)))?;
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| Error::InvalidString)?
+            .to_owned();
+        self.position += length;
+        Ok(text)
+    }
+}
+
+/// Length of the float at the start of `rest`, such as `-1.5E+03`, `INF` or `NAN`.
+fn float_length(rest: &[u8]) -> usize {
+    let digits = |from: usize| {
+        rest[from..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    let mut length = usize::from(matches!(rest.first(), Some(b'+' | b'-')));
+    if rest.get(length).is_some_and(u8::is_ascii_alphabetic) {
+        let letters = rest[length..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphabetic())
+            .count();
+        return length + letters;
+    }
+
+    let integer = digits(length);
+    length += integer;
+    let mut fraction = 0;
+    if rest.get(length) == Some(&b'.') {
+        fraction = digits(length + 1);
+        length += 1 + fraction;
+    }
+    if integer + fraction == 0 {
+        return 0;
+    }
+    if matches!(rest.get(length), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(rest.get(length + 1), Some(b'+' | b'-')));
+        let exponent = digits(length + 1 + sign);
+        if exponent > 0 {
+            length += 1 + sign + exponent;
+        }
+    }
+    length
+}
+
+/// Length of the integer at the start of `rest` in `radix`, with an optional sign and prefix.
+fn integer_length(rest: &[u8], radix: u32, prefix: &[u8]) -> usize {
+    let mut length = usize::from(matches!(rest.first(), Some(b'+' | b'-')));
+    if rest[length..]
+        .get(..prefix.len())
+        .is_some_and(|start| !prefix.is_empty() && start.eq_ignore_ascii_case(prefix))
+    {
+        length += prefix.len();
+    }
+    length
+        + rest[length..]
+            .iter()
+            .take_while(|&&byte| (byte as char).is_digit(radix))
+            .count()
+}
+
+/// Scans `message`, `None` if it ends within a block.
+fn scan(pieces: &[Piece], message: &[u8], term_char: u8) -> Result<Option<Vec<Scanned>>> {
+    let mut input = Input {
+        message,
+        position: 0,
+    };
+    let mut values = Vec::new();
+    for piece in pieces {
+        match piece {
+            Piece::Literal(text) => {
+                for char in text.chars() {
+                    if char.is_whitespace() {
+                        input.skip_whitespace();
+                        continue;
+                    }
+                    let mut buf = [0; 4];
+                    let expected = char.encode_utf8(&mut buf).as_bytes();
+                    if !input.rest().starts_with(expected) {
+                        return Err(input.mismatch(&format!("{:?}", char)));
+                    }
+                    input.position += expected.len();
+                }
+            }
+            Piece::Conversion(spec) => {
+                let Some(value) = spec.scan(&mut input, term_char)? else {
+                    return Ok(None);
+                };
+                if !spec.suppress {
+                    values.push(value);
+                }
+            }
+        }
+    }
+    Ok(Some(values))
+}
+
+impl Spec {
+    fn scan(&self, input: &mut Input, term_char: u8) -> Result<Option<Scanned>> {
+        let width = match self.width {
+            Some(Count::Fixed(width)) => width,
+            _ => usize::MAX,
+        };
+        let limit = |input: &Input| input.rest().len().min(width);
+
+        let value = match self.conversion {
+            'n' => Scanned::Int(input.position as _),
+            'c' => {
+                let length = match self.width {
+                    Some(Count::Fixed(width)) => width,
+                    _ => 1,
+                };
+                if input.rest().len() < length {
+                    return Err(input.mismatch(&format!("{} characters", length)));
+                }
+                Scanned::Str(input.take_text(length)?)
+            }
+            't' => Scanned::Str(input.take_text(input.rest().len())?),
+            'T' => {
+                let length = match input.rest().iter().position(|&byte| byte == term_char) {
+                    Some(position) => position + 1,
+                    None => input.rest().len(),
+                };
+                Scanned::Str(input.take_text(length)?)
+            }
+            's' | '[' => {
+                if self.conversion == 's' {
+                    input.skip_whitespace();
+                }
+                let bytes = &input.rest()[..limit(input)];
+                // The width may end the text in the middle of a character, which isn't taken
+                let (text, invalid) = match std::str::from_utf8(bytes) {
+                    Ok(text) => (text, false),
+                    Err(error) => (
+                        std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap(),
+                        error.error_len().is_some(),
+                    ),
+                };
+                let length: usize = text
+                    .chars()
+                    .take_while(|&char| self.accepts(char))
+                    .map(char::len_utf8)
+                    .sum();
+                if invalid && length == text.len() {
+                    return Err(Error::InvalidString);
+                }
+                if length == 0 {
+                    return Err(input.mismatch(&format!("a string for {}", self.text)));
+                }
+                Scanned::Str(input.take_text(length)?)
+            }
+            'b' => {
+                input.skip_whitespace();
+                if !input.rest().starts_with(b"#") {
+                    return Err(input.mismatch(&format!("a block for {}", self.text)));
+                }
+                let data = match block_data(input.rest())? {
+                    Some(BlockData::Definite(data)) => data,
+                    Some(BlockData::Indefinite(data)) => {
+                        let rest = &input.rest()[data.clone()];
+                        data.start..data.end - rest.ends_with(b"\n") as usize
+                    }
+                    None => return Ok(None),
+                };
+                let value = Scanned::Block {
+                    data: input.rest()[data.clone()].to_vec(),
+                    size: self.size.bytes(),
+                    order: self.order,
+                };
+                input.position += data.end;
+                value
+            }
+            'y' => {
+                let Some(Count::Fixed(count)) = self.width else {
+                    return Err(Error::InvalidFormat(format!(
+                        "{} needs a count, such as %10y",
+                        self.text
+                    )));
+                };
+                let length = count * self.size.bytes();
+                if input.rest().len() < length {
+                    return Ok(None);
+                }
+                let value = Scanned::Block {
+                    data: input.rest()[..length].to_vec(),
+                    size: self.size.bytes(),
+                    order: self.order,
+                };
+                input.position += length;
+                value
+            }
+            _ => match self.array {
+                Some(count) => {
+                    let mut numbers = Vec::new();
+                    loop {
+                        numbers.push(self.scan_number(input, width)?);
+                        let full = count == Count::Fixed(numbers.len());
+                        if full || !input.rest().starts_with(b",") {
+                            break;
+                        }
+                        input.position += 1;
+                    }
+                    if let Count::Fixed(count) = count
+                        && numbers.len() != count
+                    {
+                        return Err(input.mismatch(&format!("{} values for {}", count, self.text)));
+                    }
+                    match self.is_float() {
+                        true => Scanned::Floats(
+                            numbers
+                                .into_iter()
+                                .map(|number| match number {
+                                    Number::Int(value) => value as f64,
+                                    Number::Float(value) => value,
+                                })
+                                .collect(),
+                        ),
+                        false => Scanned::Ints(
+                            numbers
+                                .into_iter()
+                                .map(|number| match number {
+                                    Number::Int(value) => value,
+                                    Number::Float(value) => value as i64,
+                                })
+                                .collect(),
+                        ),
+                    }
+                }
+                None => match self.scan_number(input, width)? {
+                    Number::Int(value) => Scanned::Int(value),
+                    Number::Float(value) => Scanned::Float(value),
+                },
+            },
+        };
+        Ok(Some(value))
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self.conversion, 'f' | 'F' | 'e' | 'E' | 'g' | 'G')
+    }
+
+    /// Whether `char` belongs to a `%s` or `%[` string.
+    fn accepts(&self, char: char) -> bool {
+        let Some((excluded, set)) = &self.set else {
+            return !char.is_whitespace();
+        };
+        let mut contained = false;
+        let mut index = 0;
+        while index < set.len() {
+            // `a-z` ranges, a `-` first or last is literal
+            if index + 2 < set.len() && set[index + 1] == '-' {
+                contained |= (set[index]..=set[index + 2]).contains(&char);
+                index += 3;
+            } else {
+                contained |= set[index] == char;
+                index += 1;
+            }
+        }
+        contained != *excluded
+    }
+
+    fn scan_number(&self, input: &mut Input, width: usize) -> Result<Number> {
+        input.skip_whitespace();
+        let rest = &input.rest()[..input.rest().len().min(width)];
+        let length = match self.conversion {
+            'x' | 'X' => integer_length(rest, 16, b"0x"),
+            'o' => integer_length(rest, 8, b""),
+            _ if self.is_float() => float_length(rest),
+            // IEEE 488.2 non-decimal numerics, or a decimal in any notation
+            _ => match rest.iter().position(|&byte| byte != b'+' && byte != b'-') {
+                Some(sign) if rest[sign] == b'#' => {
+                    sign + 2 + integer_length(rest.get(sign + 2..).unwrap_or_default(), 16, b"")
+                }
+                _ => float_length(rest),
+            },
+        };
+        let text = std::str::from_utf8(&rest[..length.min(rest.len())]).unwrap_or_default();
+
+        let number = match self.conversion {
+            'x' | 'X' | 'o' => {
+                let radix = if self.conversion == 'o' { 8 } else { 16 };
+                let (negative, digits) = match text.strip_prefix('-') {
+                    Some(digits) => (true, digits),
+                    None => (false, text.strip_prefix('+').unwrap_or(text)),
+                };
+                let digits = match radix {
+                    16 => digits
+                        .strip_prefix("0x")
+                        .or_else(|| digits.strip_prefix("0X"))
+                        .unwrap_or(digits),
+                    _ => digits,
+                };
+                i64::from_str_radix(digits, radix)
+                    .ok()
+                    .map(|value| Number::Int(if negative { -value } else { value }))
+            }
+            _ if self.is_float() => f64::parse_ascii(text).map(Number::Float),
+            _ => i64::parse_ascii(text).map(Number::Int).or_else(|| {
+                // Integers sent as `+1.00000E+00`
+                f64::parse_ascii(text)
+                    .filter(|value| value.fract() == 0.0)
+                    .map(|value| Number::Int(value as i64))
+            }),
+        };
+        match number {
+            Some(number) if !text.is_empty() => {
+                input.position += text.len();
+                Ok(number)
+            }
+            _ => Err(input.mismatch(&format!("a number for {}", self.text))),
+        }
+    }
+}
+
+impl Instrument {
+    /// Writes `args` formatted by `format` to the formatted I/O write buffer, like VISA's
+    /// `viPrintf`, and flushes it if `format` ends with a newline. Native backends have no
+    /// such buffer and send every call as a message.
+    ///
+    /// Conversions are `%[flags][width][,count][.precision][modifiers]conversion`:
+    /// - `%d`, `%i`, `%o`, `%x`, `%f`, `%e`, `%g`, `%c` and `%s` as in C, with `@1`, `@2` and
+    ///   `@3` writing numbers as IEEE 488.2 NR1, NR2 and NR3 and `@H`, `@Q` and `@B` as
+    ///   `#H1F`-like non-decimal numerics.
+    /// - `%,10d` or `%,10f` writes a `,` separated list of 10 values of a slice, `%,*d` takes
+    ///   the length from the arguments and `%,#d` writes the whole slice.
+    /// - `%b` writes a definite length block, `%B` an indefinite one and `%y` raw binary, of
+    ///   bytes or of `h` 16-bit, `l` 32-bit and `ll` 64-bit integers, or `z` 32-bit and `Z`
+    ///   64-bit floats. The width counts the values, `!ol` makes them little endian.
+    ///
+    /// Width, precision and counts given as `*` are taken from the arguments before the value.
+    ///
+    /// ```no_run
+    /// # use visa::printf;
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// let waveform: Vec<i16> = vec![0, 100, 200];
+    /// printf!(instrument, "SOUR:FREQ %@3f;:TRAC:DATA %hb\n", 1e3, &waveform)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn printf(&mut self, format: &str, args: &[FormatArg<'_>]) -> Result<()> {
+        let message = sprintf(format, args)?;
+        let mut rest = &message[..];
+        while !rest.is_empty() {
+            match self.backend().buffered_write(rest)? {
+                0 => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+                count => rest = &rest[count..],
+            }
+        }
+        if format.ends_with('\n') {
+            self.backend().flush(FlushMode::WRITE_BUF)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a message through the formatted I/O read buffer and scans it by `format`, like
+    /// VISA's `viScanf`, into a tuple of the values of every conversion.
+    ///
+    /// Conversions are those of [`printf`](Self::printf), reading numbers in any notation,
+    /// and:
+    /// - `%,10d` or `%,10f` reads a list of 10 values, `%,#d` one of any length.
+    /// - `%b` reads a definite or indefinite length block, `%10y` 10 values of raw binary.
+    /// - `%s` reads up to whitespace, `%[a-z]` a run of the given characters, `%t` the rest
+    ///   of the message and `%T` up to the termination character.
+    /// - `%n` gives the number of bytes read so far, and `%*d` reads without assigning.
+    ///
+    /// Whitespace in `format` matches any whitespace, other characters must match as is. The
+    /// rest of the message is dropped.
+    ///
+    /// ```no_run
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// instrument.write("MEAS:VOLT?;:SENS:RANG?\n")?;
+    /// let (voltage, range): (f64, u32) = instrument.scanf("%f;%d")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn scanf<T: FromScanned>(&self, format: &str) -> Result<T> {
+        let pieces = parse_format(format, Direction::Scan)?;
+        let term_char = match pieces
+            .iter()
+            .any(|piece| matches!(piece, Piece::Conversion(spec) if spec.conversion == 'T'))
+        {
+            true => self.term_char()?,
+            false => b'\n',
+        };
+
+        let (mut message, _) = self.read_message(true)?;
+        loop {
+            if let Some(values) = scan(&pieces, &message, term_char)? {
+                return T::from_scanned(values);
+            }
+            // A block held the termination character, which ended the read early
+            message.extend(self.read_message(true)?.0);
+        }
+    }
+
+    /// Writes with [`printf`](Self::printf) and reads the response with
+    /// [`scanf`](Self::scanf), like VISA's `viQueryf`.
+    pub fn queryf<T: FromScanned>(
+        &mut self,
+        write_format: &str,
+        args: &[FormatArg<'_>],
+        read_format: &str,
+    ) -> Result<T> {
+        self.printf(write_format, args)?;
+        self.scanf(read_format)
+    }
+}
+
+/// Writes formatted values with [`Instrument::printf`], converting every argument into a
+/// [`FormatArg`].
+///
+/// ```no_run
+/// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+/// let points = [0.0, 0.5, 1.0];
+/// visa::printf!(instrument, "LIST:VOLT %,#f;:LIST:COUN %d\n", &points, 10)?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! printf {
+    ($instrument:expr, $format:expr $(, $arg:expr)* $(,)?) => {
+        $instrument.printf($format, &[$($crate::FormatArg::from($arg)),*])
+    };
+}
@@ -1,20 +1,32 @@
 use super::{
-    Identification,
+    Identification, StatusByte,
+    attribute::{
+        SendEndEnabled, SuppressEndEnabled, TermChar, TermCharEnabled, Timeout, WritableAttribute,
+    },
     bindings::*,
-    error::{Error, Result, VisaError, parse_vi_status, parse_vi_status_to_io},
+    error::{Error, Result, Status, VisaError, parse_vi_status},
+    event::{Event, EventMechanism, EventType, HandlerId},
+    job::Outcome,
+    library::{library, require},
     resource_manager::AccessMode,
     session::Session,
 };
 use bitflags::bitflags;
 use std::{
-    ffi::{CStr, CString},
-    io::{BufRead, BufReader, Write},
-    ops::Deref,
-    str::FromStr,
+    collections::HashMap,
+    ffi::CStr,
+    io::Write,
+    ops::{Deref, DerefMut},
+    sync::Mutex,
     time::Duration,
 };
+use tracing::warn;
+
+/// Size of the chunks messages are read in unless set otherwise.
+const READ_CHUNK_SIZE: usize = 4096;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
     pub struct FlushMode: ViUInt16 {
         const READ_BUF = VI_READ_BUF as _;
         const READ_BUF_DISCARD = VI_READ_BUF_DISCARD as _;
@@ -27,10 +39,36 @@ bitflags! {
     }
 }
 
+/// Protocols of [`Instrument::assert_trigger`], VISA's `VI_TRIG_PROT_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[repr(u16)]
+pub enum TriggerProtocol {
+    /// The interface's software trigger, a group execute trigger on GPIB and VXI-11, a trigger
+    /// message on HiSLIP and USB488 and the word serial trigger command on VXI.
+    #[default]
+    Default = VI_TRIG_PROT_DEFAULT as _,
+    /// Asserts the VXI or PXI trigger line selected by `VI_ATTR_TRIG_ID`.
+    On = VI_TRIG_PROT_ON as _,
+    /// Deasserts the trigger line.
+    Off = VI_TRIG_PROT_OFF as _,
+    /// Pulses the trigger line, a SYNC pulse on VXI.
+    Sync = VI_TRIG_PROT_SYNC as _,
+    /// Reserves the PXI trigger line for the session.
+    Reserve = VI_TRIG_PROT_RESERVE as _,
+    Unreserve = VI_TRIG_PROT_UNRESERVE as _,
+}
+
 #[derive(Debug)]
 pub struct Instrument {
     inner: Session,
     pub identification: Identification,
+    /// Handlers installed through the instrument, uninstalled on drop.
+    handlers: Mutex<Vec<(EventType, HandlerId)>>,
+    /// Completions of asynchronous jobs seen while waiting for another one.
+    pub(crate) completions: Mutex<HashMap<ViJobId, Outcome>>,
+    read_chunk_size: usize,
+    max_read_length: Option<usize>,
+    check_errors: bool,
 }
 
 impl Deref for Instrument {
@@ -59,42 +97,20 @@ impl std::io::Read for Instrument {
 
 impl std::io::Write for &Instrument {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let mut ret_cnt: ViUInt32 = 0;
-        unsafe {
-            let status = viWrite(
-                self.as_vi_session(),
-                buf.as_ptr(),
-                buf.len() as _,
-                &mut ret_cnt as _,
-            );
-            parse_vi_status_to_io(status)?;
-        }
-
-        Ok(ret_cnt as _)
+        self.backend().write(buf).map_err(Error::to_io_error)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        unsafe {
-            let status = viFlush(self.as_vi_session(), FlushMode::IO_OUT_BUF.bits());
-            parse_vi_status_to_io(status)?;
-        }
-        Ok(())
+        self.backend()
+            .flush(FlushMode::IO_OUT_BUF)
+            .map_err(Error::to_io_error)
     }
 }
 
 impl std::io::Read for &Instrument {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let mut ret_cnt: ViUInt32 = 0;
-        unsafe {
-            let status = viRead(
-                self.as_vi_session(),
-                buf.as_mut_ptr(),
-                buf.len() as _,
-                &mut ret_cnt as _,
-            );
-            parse_vi_status_to_io(status)?;
-        }
-        Ok(ret_cnt as _)
+        let (count, _) = self.backend().read(buf).map_err(Error::to_io_error)?;
+        Ok(count)
     }
 }
 
@@ -108,41 +124,273 @@ impl Instrument {
                 serial_number: "".into(),
                 firmware_version: "".into(),
             },
+            handlers: Mutex::new(Vec::new()),
+            completions: Mutex::new(HashMap::new()),
+            read_chunk_size: READ_CHUNK_SIZE,
+            max_read_length: None,
+            check_errors: false,
         };
         let identification = instrument.query_identification()?;
         instrument.identification = identification;
         Ok(instrument)
     }
 
-    pub fn as_vi_session(&self) -> ViSession {
+    pub fn as_vi_session(&self) -> Option<ViSession> {
         self.inner.as_vi_session()
     }
 
+    /// Writes `buf`, then checks the error queue if [`check_errors`](Self::check_errors).
     pub fn write(&mut self, buf: impl AsRef<[u8]>) -> Result<()> {
         self.write_all(buf.as_ref())?;
+        if self.check_errors {
+            self.check_error_queue()?;
+        }
         Ok(())
     }
 
+    /// Reads a whole message, up to END or the termination character if it's enabled.
     pub fn read(&self) -> Result<String> {
-        let mut reader = BufReader::new(self);
-        let mut buf = String::new();
-        reader.read_line(&mut buf)?;
-        Ok(buf)
+        String::from_utf8(self.read_bytes()?).map_err(|_| Error::InvalidString)
+    }
+
+    /// Reads a whole message as is, up to END or the termination character if it's enabled.
+    ///
+    /// The message is read in chunks of [`read_chunk_size`](Self::read_chunk_size) bytes until
+    /// a read ends with anything but [`Status::BytesReadEqualInput`]. A message longer than
+    /// [`max_read_length`](Self::max_read_length) is read to its end and dropped.
+    pub fn read_bytes(&self) -> Result<Vec<u8>> {
+        self.read_message(false).map(|(message, _)| message)
+    }
+
+    /// Reads a whole message like [`read_bytes`](Self::read_bytes), through the formatted I/O
+    /// read buffer if `buffered`, along with the condition that ended it.
+    pub(crate) fn read_message(&self, buffered: bool) -> Result<(Vec<u8>, Status)> {
+        let read = |buf: &mut [u8]| match buffered {
+            true => self.backend().buffered_read(buf),
+            false => self.backend().read(buf),
+        };
+        let mut message = Vec::new();
+        loop {
+            let filled = message.len();
+            message.resize(filled + self.read_chunk_size, 0);
+            let (count, status) = read(&mut message[filled..])?;
+            message.truncate(filled + count);
+            let end = status != Status::BytesReadEqualInput;
+
+            match self.max_read_length {
+                Some(max) if message.len() > max => {
+                    if !end {
+                        let mut chunk = vec![0; self.read_chunk_size];
+                        while read(&mut chunk)?.1 == Status::BytesReadEqualInput {}
+                    }
+                    return Err(Error::MessageTooLong(max));
+                }
+                _ if end => return Ok((message, status)),
+                _ => (),
+            }
+        }
+    }
+
+    /// Reads exactly `count` bytes, whether or not messages end in between. A read returning
+    /// nothing fails with an [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof) I/O error.
+    pub fn read_exact(&self, count: usize) -> Result<Vec<u8>> {
+        let mut data = vec![0; count];
+        let mut filled = 0;
+        while filled < count {
+            let (read, _) = self.backend().read(&mut data[filled..])?;
+            if read == 0 {
+                return Err(Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    format!("read {} of {} bytes", filled, count),
+                )));
+            }
+            filled += read;
+        }
+        Ok(data)
     }
 
     pub fn query(&mut self, buf: impl AsRef<[u8]>) -> Result<String> {
-        self.write(buf)?;
-        let response = self.read()?;
-        Ok(response)
+        self.query_with(buf.as_ref(), |instrument| instrument.read())
+    }
+
+    /// Writes `buf` and reads the whole response as is.
+    pub fn query_bytes(&mut self, buf: impl AsRef<[u8]>) -> Result<Vec<u8>> {
+        self.query_with(buf.as_ref(), |instrument| instrument.read_bytes())
+    }
+
+    /// Writes `buf` and reads the response with `read`, then checks the error queue if
+    /// [`check_errors`](Self::check_errors). Checking before the read would interrupt the query.
+    pub(crate) fn query_with<T>(
+        &mut self,
+        buf: &[u8],
+        read: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        self.write_all(buf)?;
+        let response = read(self);
+        // An invalid query usually times out without a response
+        if self.check_errors && matches!(response, Ok(_) | Err(Error::Visa(VisaError::Timeout))) {
+            self.check_error_queue()?;
+        }
+        response
+    }
+
+    pub fn read_chunk_size(&self) -> usize {
+        self.read_chunk_size
+    }
+
+    /// Sets the size of the chunks [`read_bytes`](Self::read_bytes) reads messages in, at
+    /// least one byte.
+    pub fn set_read_chunk_size(&mut self, size: usize) {
+        self.read_chunk_size = size.max(1);
+    }
+
+    /// Length above which [`read_bytes`](Self::read_bytes) rejects a message, `None` by default.
+    pub fn max_read_length(&self) -> Option<usize> {
+        self.max_read_length
+    }
+
+    pub fn set_max_read_length(&mut self, length: Option<usize>) {
+        self.max_read_length = length;
+    }
+
+    /// Whether [`write`](Self::write) and [`query`](Self::query) drain the SCPI error queue
+    /// after every message and fail with [`Error::Scpi`] if it wasn't empty, `false` by default.
+    pub fn check_errors(&self) -> bool {
+        self.check_errors
+    }
+
+    pub fn set_check_errors(&mut self, enabled: bool) {
+        self.check_errors = enabled;
+    }
+
+    /// I/O timeout, `None` waits forever.
+    pub fn timeout(&self) -> Result<Option<Duration>> {
+        Ok(self.get::<Timeout>()?.0)
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        self.set(Timeout(timeout))
+    }
+
+    /// Character ending reads when [`term_char_enabled`](Self::term_char_enabled).
+    pub fn term_char(&self) -> Result<u8> {
+        Ok(self.get::<TermChar>()?.0)
+    }
+
+    pub fn set_term_char(&self, term_char: u8) -> Result<()> {
+        self.set(TermChar(term_char))
+    }
+
+    /// Whether reads end on the termination character, besides on END.
+    pub fn term_char_enabled(&self) -> Result<bool> {
+        Ok(self.get::<TermCharEnabled>()?.0)
+    }
+
+    pub fn set_term_char_enabled(&self, enabled: bool) -> Result<()> {
+        self.set(TermCharEnabled(enabled))
+    }
+
+    /// Whether END is sent with the last byte of every write.
+    pub fn send_end(&self) -> Result<bool> {
+        Ok(self.get::<SendEndEnabled>()?.0)
+    }
+
+    pub fn set_send_end(&self, send_end: bool) -> Result<()> {
+        self.set(SendEndEnabled(send_end))
+    }
+
+    /// Whether END is ignored by reads, which then only end on the termination character.
+    pub fn suppress_end(&self) -> Result<bool> {
+        Ok(self.get::<SuppressEndEnabled>()?.0)
+    }
+
+    pub fn set_suppress_end(&self, suppress_end: bool) -> Result<()> {
+        self.set(SuppressEndEnabled(suppress_end))
+    }
+
+    /// Sets `attribute` until the returned guard, through which the instrument stays usable,
+    /// is dropped and restores its previous value.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// let response = instrument
+    ///     .with_timeout(Some(Duration::from_secs(30)))?
+    ///     .query("MEAS?\n")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_attribute<A: WritableAttribute>(
+        &mut self,
+        attribute: A,
+    ) -> Result<AttributeOverride<'_, A>> {
+        let previous = self.get::<A>()?;
+        self.set(attribute)?;
+        Ok(AttributeOverride {
+            instrument: self,
+            previous,
+        })
+    }
+
+    pub fn with_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<AttributeOverride<'_, Timeout>> {
+        self.with_attribute(Timeout(timeout))
     }
 
-    pub fn status_description(&self, error: VisaError) -> Result<()> {
-        let mut buf = String::new();
+    pub fn with_term_char(&mut self, term_char: u8) -> Result<AttributeOverride<'_, TermChar>> {
+        self.with_attribute(TermChar(term_char))
+    }
+
+    /// Clears the device, discarding pending input and output. It also recovers a session left
+    /// waiting on a response that never comes, or in the middle of a message.
+    pub fn clear(&self) -> Result<()> {
+        self.backend().clear()
+    }
+
+    /// Triggers the device, as `*TRG` would without going through the message queue.
+    pub fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        self.backend().assert_trigger(protocol)
+    }
+
+    /// Serial polls the device for its status byte, without going through the message queue
+    /// like `*STB?` does. It also clears [`StatusByte::REQUEST_SERVICE`].
+    pub fn read_status_byte(&self) -> Result<StatusByte> {
+        Ok(StatusByte::from_bits_retain(
+            self.backend().read_status_byte()?,
+        ))
+    }
+
+    /// Flushes or discards the buffers selected by `mode`, such as the formatted I/O write
+    /// buffer of [`printf`](Self::printf) with [`FlushMode::WRITE_BUF`].
+    pub fn flush_buffers(&self, mode: FlushMode) -> Result<()> {
+        self.backend().flush(mode)
+    }
+
+    /// Sets the size of the buffers selected by `mask`, only [`FlushMode::READ_BUF`],
+    /// [`FlushMode::WRITE_BUF`], [`FlushMode::IO_IN_BUF`] and [`FlushMode::IO_OUT_BUF`] mean
+    /// anything here.
+    pub fn set_buffer_size(&self, mask: FlushMode, size: usize) -> Result<()> {
+        self.backend().set_buffer_size(mask, size)
+    }
+
+    pub fn status_description(&self, error: VisaError) -> Result<String> {
+        let session = self
+            .as_vi_session()
+            .ok_or(Error::Visa(VisaError::OperationNotSupported))?;
+        let visa = library()?;
+        require(&visa.viStatusDesc)?;
+        let mut buf = [0; 256];
         unsafe {
-            let status = viStatusDesc(self.as_vi_session(), error as _, buf.as_mut_ptr() as _);
+            let status = visa.viStatusDesc(session, error as _, buf.as_mut_ptr() as _);
             parse_vi_status(status)?;
         }
-        Ok(())
+        let description = CStr::from_bytes_until_nul(&buf)
+            .map_err(|_| Error::InvalidString)?
+            .to_string_lossy()
+            .into_owned();
+        Ok(description)
     }
 
     pub fn lock(
@@ -151,107 +399,149 @@ impl Instrument {
         timeout: Duration,
         key: Option<&str>,
     ) -> Result<Option<String>> {
-        match (mode & AccessMode::SHARED_LOCK).is_empty() {
-            true => unsafe {
-                let status = viLock(
-                    self.as_vi_session(),
-                    mode.bits(),
-                    timeout.as_millis() as _,
-                    VI_NULL as _,
-                    VI_NULL as _,
-                );
-                parse_vi_status(status)?;
-                Ok(None)
-            },
-            false => {
-                let key = match key {
-                    Some(key) => {
-                        let key = CString::from_str(key).map_err(|_| Error::InvalidString)?;
-                        Some(key)
-                    }
-                    None => None,
-                };
-
-                let mut access_key = [0; VI_FIND_BUFLEN as _];
-                unsafe {
-                    let status = viLock(
-                        self.as_vi_session(),
-                        mode.bits(),
-                        timeout.as_millis() as _,
-                        key.map(|key| key.as_ptr()).unwrap_or(VI_NULL as _),
-                        access_key.as_mut_ptr() as _,
-                    );
-                    parse_vi_status(status)?;
-                }
-
-                let access_key = CStr::from_bytes_with_nul(&access_key)
-                    .map_err(|_| Error::InvalidString)?
-                    .to_str()
-                    .map_err(|_| Error::InvalidString)?;
-                Ok(Some(access_key.to_owned()))
-            }
-        }
+        self.backend().lock(mode, timeout, key)
     }
 
     pub fn lock_exclusive(&self, timeout: Duration) -> Result<()> {
-        unsafe {
-            let status = viLock(
-                self.as_vi_session(),
-                AccessMode::EXCLUSIVE_LOCK.bits(),
-                timeout.as_millis() as _,
-                VI_NULL as _,
-                VI_NULL as _,
-            );
-            parse_vi_status(status)?;
-        }
+        self.backend()
+            .lock(AccessMode::EXCLUSIVE_LOCK, timeout, None)?;
         Ok(())
     }
 
     pub fn lock_shared(&self, timeout: Duration) -> Result<String> {
-        let mut access_key = [0; VI_FIND_BUFLEN as _];
-        unsafe {
-            let status = viLock(
-                self.as_vi_session(),
-                AccessMode::EXCLUSIVE_LOCK.bits(),
-                timeout.as_millis() as _,
-                VI_NULL as _,
-                access_key.as_mut_ptr() as _,
-            );
-            parse_vi_status(status)?;
-        }
-        let access_key = CStr::from_bytes_with_nul(&access_key)
-            .map_err(|_| Error::InvalidString)?
-            .to_str()
-            .map_err(|_| Error::InvalidString)?;
-        Ok(access_key.to_owned())
+        let access_key = self
+            .backend()
+            .lock(AccessMode::SHARED_LOCK, timeout, None)?
+            .ok_or(Error::InvalidString)?;
+        Ok(access_key)
     }
 
     pub fn lock_shared_with_key(&self, timeout: Duration, key: &str) -> Result<String> {
-        let key = CString::from_str(key).map_err(|_| Error::InvalidString)?;
+        let access_key = self
+            .backend()
+            .lock(AccessMode::SHARED_LOCK, timeout, Some(key))?
+            .ok_or(Error::InvalidString)?;
+        Ok(access_key)
+    }
 
-        let mut access_key = [0; VI_FIND_BUFLEN as _];
-        unsafe {
-            let status = viLock(
-                self.as_vi_session(),
-                AccessMode::EXCLUSIVE_LOCK.bits(),
-                timeout.as_millis() as _,
-                key.as_ptr(),
-                access_key.as_mut_ptr() as _,
-            );
-            parse_vi_status(status)?;
+    pub fn unlock(&self) -> Result<()> {
+        self.backend().unlock()
+    }
+
+    /// Starts delivering events of `event_type` through `mechanism`. Queued events are taken
+    /// with [`wait_on_event`](Self::wait_on_event).
+    pub fn enable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.backend().enable_event(event_type, mechanism)
+    }
+
+    pub fn disable_event(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.backend().disable_event(event_type, mechanism)
+    }
+
+    /// Drops the pending events of `event_type` delivered through `mechanism`.
+    pub fn discard_events(&self, event_type: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.backend().discard_events(event_type, mechanism)
+    }
+
+    /// Waits for a queued event of `event_type`, `None` waits forever.
+    ///
+    /// The event must have been enabled with [`EventMechanism::QUEUE`].
+    pub fn wait_on_event(&self, event_type: EventType, timeout: Option<Duration>) -> Result<Event> {
+        self.backend().wait_on_event(event_type, timeout)
+    }
+
+    /// Installs `handler` for events of `event_type`, called once the event is enabled with
+    /// [`EventMechanism::HANDLER`]. It runs on a thread of the VISA library, a panic is caught and
+    /// logged. The handler is uninstalled when the instrument is dropped at the latest.
+    ///
+    /// ```no_run
+    /// # use visa::{EventMechanism, EventType};
+    /// # fn example(instrument: &visa::Instrument) -> visa::Result<()> {
+    /// instrument.install_handler(EventType::ServiceRequest, |event| {
+    ///     println!("{:?}", event.event_type());
+    /// })?;
+    /// instrument.enable_event(EventType::ServiceRequest, EventMechanism::HANDLER)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn install_handler(
+        &self,
+        event_type: EventType,
+        handler: impl FnMut(&Event) + Send + 'static,
+    ) -> Result<HandlerId> {
+        let id = self
+            .backend()
+            .install_handler(event_type, Box::new(handler))?;
+        self.handlers.lock().unwrap().push((event_type, id));
+        Ok(id)
+    }
+
+    pub fn uninstall_handler(&self, event_type: EventType, id: HandlerId) -> Result<()> {
+        self.backend().uninstall_handler(event_type, id)?;
+        self.handlers
+            .lock()
+            .unwrap()
+            .retain(|handler| *handler != (event_type, id));
+        Ok(())
+    }
+
+    /// Waits for the device to request service, instead of polling `*STB?`.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use visa::{EventMechanism, EventType};
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// instrument.enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)?;
+    /// instrument.write("*ESE 1;*SRE 32;MEAS?;*OPC\n")?;
+    /// instrument.wait_for_service_request(Some(Duration::from_secs(10)))?;
+    /// let voltage = instrument.read()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait_for_service_request(&self, timeout: Option<Duration>) -> Result<Event> {
+        self.wait_on_event(EventType::ServiceRequest, timeout)
+    }
+}
+
+impl Drop for Instrument {
+    fn drop(&mut self) {
+        let handlers = std::mem::take(self.handlers.get_mut().unwrap());
+        for (event_type, id) in handlers {
+            // Fails if the session was closed first, which uninstalled them already
+            let _ = self.backend().uninstall_handler(event_type, id);
         }
-        let access_key = CStr::from_bytes_with_nul(&access_key)
-            .map_err(|_| Error::InvalidString)?
-            .to_str()
-            .map_err(|_| Error::InvalidString)?;
-        Ok(access_key.to_owned())
     }
+}
 
-    pub fn unlock(&self) -> Result<()> {
-        unsafe {
-            let status = viUnlock(self.as_vi_session());
-            parse_vi_status(status)?;
+/// An attribute set for a scope by [`Instrument::with_attribute`], the previous value is
+/// restored on drop.
+#[derive(Debug)]
+pub struct AttributeOverride<'a, A: WritableAttribute> {
+    instrument: &'a mut Instrument,
+    previous: A,
+}
+
+impl<A: WritableAttribute> Deref for AttributeOverride<'_, A> {
+    type Target = Instrument;
+
+    fn deref(&self) -> &Self::Target {
+        self.instrument
+    }
+}
+
+impl<A: WritableAttribute> DerefMut for AttributeOverride<'_, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.instrument
+    }
+}
+
+impl<A: WritableAttribute> Drop for AttributeOverride<'_, A> {
+    fn drop(&mut self) {
+        if let Err(error) = self
+            .instrument
+            .set_attribute(A::ID, &self.previous.to_value())
+        {
+            warn!("failed to restore attribute 0x{:08X}: {}", A::ID, error);
         }
-        Ok(())
     }
 }
@@ -0,0 +1,247 @@
+use super::{
+    attribute::{EventStatus, JobId, ReturnCount},
+    bindings::*,
+    error::{Error, Result, Status, VisaError, parse_vi_status},
+    event::{EventMechanism, EventType},
+    instrument::Instrument,
+    library::{library, require},
+};
+use std::time::{Duration, Instant};
+
+/// Outcome of an asynchronous operation, as its `VI_EVENT_IO_COMPLETION` event reported it.
+pub(crate) type Outcome = std::result::Result<Completion, VisaError>;
+
+/// How an asynchronous operation completed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Completion {
+    pub status: Status,
+    /// Number of bytes transferred, `VI_ATTR_RET_COUNT`.
+    pub count: usize,
+}
+
+/// A read or write running in the VISA library, started by [`Instrument::read_async`] or
+/// [`Instrument::write_async`].
+///
+/// The job owns the buffer the library reads into or writes from until the operation has
+/// completed. A job dropped before is terminated and waited for, if that fails the buffer is
+/// leaked rather than freed under the library.
+#[derive(Debug)]
+pub struct AsyncJob<'a> {
+    instrument: &'a Instrument,
+    id: ViJobId,
+    buffer: Vec<u8>,
+    outcome: Option<Outcome>,
+}
+
+impl Instrument {
+    /// Starts reading up to `count` bytes without blocking.
+    ///
+    /// ```no_run
+    /// # fn example(instrument: &visa::Instrument) -> visa::Result<()> {
+    /// let job = instrument.read_async(1 << 20)?;
+    /// // ...
+    /// let waveform = job.into_data()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_async(&self, count: usize) -> Result<AsyncJob<'_>> {
+        let session = self.start_async()?;
+        let visa = library()?;
+        require(&visa.viReadAsync)?;
+        let mut buffer = vec![0; count];
+        let mut id: ViJobId = 0;
+        unsafe {
+            let status = visa.viReadAsync(
+                session,
+                buffer.as_mut_ptr(),
+                buffer.len() as _,
+                &mut id as _,
+            );
+            parse_vi_status(status)?;
+        }
+        Ok(AsyncJob::new(self, id, buffer))
+    }
+
+    /// Starts writing `buf` without blocking.
+    pub fn write_async(&self, buf: impl Into<Vec<u8>>) -> Result<AsyncJob<'_>> {
+        let session = self.start_async()?;
+        let visa = library()?;
+        require(&visa.viWriteAsync)?;
+        let buffer = buf.into();
+        let mut id: ViJobId = 0;
+        unsafe {
+            let status =
+                visa.viWriteAsync(session, buffer.as_ptr(), buffer.len() as _, &mut id as _);
+            parse_vi_status(status)?;
+        }
+        Ok(AsyncJob::new(self, id, buffer))
+    }
+
+    fn start_async(&self) -> Result<ViSession> {
+        let session = self
+            .as_vi_session()
+            .ok_or(Error::Visa(VisaError::OperationNotSupported))?;
+        // Completions are taken from the queue, it must be enabled before the job starts
+        self.enable_event(EventType::IoCompletion, EventMechanism::QUEUE)?;
+        Ok(session)
+    }
+}
+
+impl<'a> AsyncJob<'a> {
+    fn new(instrument: &'a Instrument, id: ViJobId, buffer: Vec<u8>) -> Self {
+        Self {
+            instrument,
+            id,
+            buffer,
+            outcome: None,
+        }
+    }
+
+    pub fn id(&self) -> ViJobId {
+        self.id
+    }
+
+    /// Whether the job was seen completing by [`wait`](Self::wait).
+    pub fn is_complete(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Waits for the job to complete, `None` waits forever. Errors of the operation itself,
+    /// such as [`VisaError::OperationAborted`] once terminated, are returned again on every call.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> Result<Completion> {
+        if self.outcome.is_none() {
+            self.outcome = Some(self.wait_for_outcome(timeout)?);
+        }
+        self.outcome.unwrap().map_err(Error::Visa)
+    }
+
+    /// Asks the library to abort the job, which then completes with
+    /// [`VisaError::OperationAborted`].
+    pub fn terminate(&self) -> Result<()> {
+        let visa = library()?;
+        require(&visa.viTerminate)?;
+        let session = self
+            .instrument
+            .as_vi_session()
+            .ok_or(Error::Visa(VisaError::OperationNotSupported))?;
+        unsafe {
+            let status = visa.viTerminate(session, VI_NULL as _, self.id);
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    /// Waits for the job to complete and returns its buffer, only the bytes read for a read.
+    pub fn into_data(mut self) -> Result<Vec<u8>> {
+        let completion = self.wait(None)?;
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.truncate(completion.count);
+        Ok(buffer)
+    }
+
+    fn wait_for_outcome(&self, timeout: Option<Duration>) -> Result<Outcome> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // Waiting for another job may have seen this one complete
+            if let Some(outcome) = self.instrument.completions.lock().unwrap().remove(&self.id) {
+                return Ok(outcome);
+            }
+
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let event = self
+                .instrument
+                .wait_on_event(EventType::IoCompletion, remaining)?;
+            let id = event.get::<JobId>()?.0 as ViJobId;
+            let outcome = match Status::try_from(event.get::<EventStatus>()?.0 as ViStatus) {
+                Ok(status) => Ok(Completion {
+                    status,
+                    count: event.get::<ReturnCount>()?.0 as _,
+                }),
+                Err(error) => Err(error),
+            };
+            match id == self.id {
+                true => return Ok(outcome),
+                false => {
+                    self.instrument
+                        .completions
+                        .lock()
+                        .unwrap()
+                        .insert(id, outcome);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AsyncJob<'_> {
+    fn drop(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        let _ = self.terminate();
+        let timeout = self.instrument.timeout().ok().flatten();
+        if self.wait_for_outcome(timeout).is_err() {
+            // The library may still use the buffer
+            std::mem::forget(std::mem::take(&mut self.buffer));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{backend::Fake, session::Session};
+
+    fn open() -> Instrument {
+        Instrument::new(Session::from(Fake::new())).unwrap()
+    }
+
+    #[test]
+    fn refuses_sessions_not_opened_through_visa() {
+        let instrument = open();
+        assert!(matches!(
+            instrument.read_async(16),
+            Err(Error::Visa(VisaError::OperationNotSupported))
+        ));
+        assert!(matches!(
+            instrument.write_async(b"*IDN?\n".to_vec()),
+            Err(Error::Visa(VisaError::OperationNotSupported))
+        ));
+        // Refused before enabling completions
+        assert!(matches!(
+            instrument.wait_on_event(EventType::IoCompletion, None),
+            Err(Error::Visa(VisaError::SessionNotEnabled))
+        ));
+    }
+
+    #[test]
+    fn takes_completions_seen_waiting_for_other_jobs() {
+        let instrument = open();
+        let completion = Completion {
+            status: Status::OperationCompleted,
+            count: 3,
+        };
+        {
+            let mut completions = instrument.completions.lock().unwrap();
+            completions.insert(1, Ok(completion));
+            completions.insert(2, Err(VisaError::OperationAborted));
+        }
+
+        let mut read = AsyncJob::new(&instrument, 1, b"1.5\n\0\0".to_vec());
+        assert!(!read.is_complete());
+        assert_eq!(read.wait(Some(Duration::ZERO)).unwrap(), completion);
+        assert!(read.is_complete());
+        assert_eq!(read.into_data().unwrap(), b"1.5");
+
+        // Errors of the operation stick to the job
+        let mut aborted = AsyncJob::new(&instrument, 2, vec![0; 4]);
+        for _ in 0..2 {
+            assert!(matches!(
+                aborted.wait(None),
+                Err(Error::Visa(VisaError::OperationAborted))
+            ));
+        }
+        assert!(instrument.completions.lock().unwrap().is_empty());
+    }
+}
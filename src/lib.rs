@@ -1,14 +1,47 @@
+mod ascii;
+#[cfg(feature = "tokio")]
+mod async_instrument;
+pub mod attribute;
+mod backend;
+#[allow(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals,
+    clippy::all
+)]
 mod bindings;
+mod block;
 pub mod error;
+mod event;
+mod format;
 mod instrument;
+mod job;
+mod library;
+mod register;
+mod resource;
 mod resource_manager;
 mod scpi;
 mod session;
+mod status;
 
+pub use ascii::*;
+#[cfg(feature = "tokio")]
+pub use async_instrument::*;
+pub use attribute::{Attribute, AttributeKind, AttributeValue, WritableAttribute};
+pub use backend::*;
 #[allow(unused_imports)]
 use bindings::*;
+pub use block::*;
 pub use error::*;
+pub use event::*;
+pub use format::*;
 pub use instrument::*;
+pub use job::*;
+pub use library::*;
+pub use register::*;
+pub use resource::*;
 pub use resource_manager::*;
 pub use scpi::*;
 pub use session::*;
+pub use status::*;
@@ -0,0 +1,165 @@
+use super::{
+    bindings::Visa,
+    error::{Error, Result, VisaError},
+};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{debug, trace};
+
+/// Environment variable that overrides the location of the VISA library.
+pub const VISA_LIBRARY_ENV: &str = "VISA_LIBRARY";
+
+/// The loaded library and the location it was loaded from.
+static LIBRARY: Mutex<Option<(&'static Visa, PathBuf)>> = Mutex::new(None);
+
+#[cfg(target_os = "linux")]
+const DEFAULT_LOCATIONS: &[&str] = &[
+    // NI-VISA
+    "libvisa.so.0",
+    "libvisa.so",
+    "/usr/local/vxipnp/linux/lib64/libvisa.so",
+    // Keysight IO Libraries
+    "/opt/keysight/iolibs/libktvisa32.so.0",
+    "/opt/keysight/iolibs/libktvisa32.so",
+    // Rohde & Schwarz VISA
+    "librsvisa.so",
+    "/usr/lib/librsvisa.so",
+];
+
+#[cfg(target_os = "macos")]
+const DEFAULT_LOCATIONS: &[&str] = &[
+    // NI-VISA
+    "/Library/Frameworks/VISA.framework/VISA",
+    // Rohde & Schwarz VISA
+    "/Library/Frameworks/RsVisa.framework/RsVisa",
+];
+
+#[cfg(all(target_os = "windows", target_pointer_width = "64"))]
+const DEFAULT_LOCATIONS: &[&str] = &[
+    // IVI VISA shared components, dispatches to the installed vendor implementation
+    "visa64.dll",
+    r"C:\Windows\System32\visa64.dll",
+    // Keysight IO Libraries
+    "ktvisa64.dll",
+    // Rohde & Schwarz VISA
+    "RsVisa64.dll",
+];
+
+#[cfg(all(target_os = "windows", target_pointer_width = "32"))]
+const DEFAULT_LOCATIONS: &[&str] = &[
+    // IVI VISA shared components, dispatches to the installed vendor implementation
+    "visa32.dll",
+    r"C:\Windows\SysWOW64\visa32.dll",
+    // Keysight IO Libraries
+    "ktvisa32.dll",
+    // Rohde & Schwarz VISA
+    "RsVisa32.dll",
+];
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const DEFAULT_LOCATIONS: &[&str] = &[];
+
+/// Locations searched, in order, when no library has been loaded explicitly.
+///
+/// The path in [`VISA_LIBRARY_ENV`] comes first, followed by the usual
+/// install locations of NI-VISA, Keysight and Rohde & Schwarz.
+pub fn default_library_locations() -> Vec<PathBuf> {
+    env::var_os(VISA_LIBRARY_ENV)
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(DEFAULT_LOCATIONS.iter().map(PathBuf::from))
+        .collect()
+}
+
+/// Loads the VISA library at `path`.
+///
+/// Only one library can be loaded per process. Loading the one already loaded succeeds, any
+/// other fails with [`Error::LibraryAlreadyLoaded`].
+pub fn load_library(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    let mut library = LIBRARY.lock().unwrap();
+    match &*library {
+        Some((_, loaded)) if loaded == path => Ok(()),
+        Some((_, loaded)) => Err(Error::LibraryAlreadyLoaded(loaded.clone())),
+        None => {
+            *library = Some((open(path)?, path.to_owned()));
+            Ok(())
+        }
+    }
+}
+
+/// Returns whether a VISA library has been loaded.
+pub fn is_library_loaded() -> bool {
+    LIBRARY.lock().unwrap().is_some()
+}
+
+/// Returns the loaded VISA library, searching the default locations if none was loaded yet.
+pub(crate) fn library() -> Result<&'static Visa> {
+    let mut library = LIBRARY.lock().unwrap();
+    if let Some((library, _)) = *library {
+        return Ok(library);
+    }
+
+    let locations = default_library_locations();
+    for location in &locations {
+        match open(location) {
+            Ok(visa) => {
+                *library = Some((visa, location.clone()));
+                return Ok(visa);
+            }
+            Err(error) => trace!("skipping {}: {}", location.display(), error),
+        }
+    }
+
+    Err(Error::LibraryNotFound(locations))
+}
+
+/// Returns the loaded VISA library without attempting to load one.
+pub(crate) fn loaded_library() -> Option<&'static Visa> {
+    LIBRARY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(library, _)| *library)
+}
+
+/// Checks the library exports a function before it's called, the bindings panic on the
+/// optional ones a vendor left out.
+pub(crate) fn require<T>(function: &std::result::Result<T, libloading::Error>) -> Result<()> {
+    match function {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::Visa(VisaError::OperationNotSupported)),
+    }
+}
+
+fn open(path: &Path) -> Result<&'static Visa> {
+    let visa = unsafe { Visa::new(path) }
+        .map_err(|error| Error::InvalidLibrary(path.to_owned(), error.to_string()))?;
+
+    // Vendor libraries differ in the optional parts of the specification they export,
+    // but without the core of the resource and session operations the library is useless.
+    let missing = [
+        ("viOpenDefaultRM", visa.viOpenDefaultRM.is_err()),
+        ("viFindRsrc", visa.viFindRsrc.is_err()),
+        ("viFindNext", visa.viFindNext.is_err()),
+        ("viOpen", visa.viOpen.is_err()),
+        ("viClose", visa.viClose.is_err()),
+        ("viRead", visa.viRead.is_err()),
+        ("viWrite", visa.viWrite.is_err()),
+    ]
+    .into_iter()
+    .find_map(|(name, missing)| missing.then_some(name));
+
+    if let Some(name) = missing {
+        return Err(Error::InvalidLibrary(
+            path.to_owned(),
+            format!("missing symbol {}", name),
+        ));
+    }
+
+    debug!("loaded VISA library from {}", path.display());
+    Ok(Box::leak(Box::new(visa)))
+}
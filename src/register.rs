@@ -0,0 +1,521 @@
+use super::{
+    bindings::*,
+    error::{Error, Result, VisaError, parse_vi_status},
+    instrument::Instrument,
+    library::{library, require},
+};
+
+/// Address spaces of register based I/O, VISA's `VI_*_SPACE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum AddressSpace {
+    /// Memory of the controller, for moves between it and a device. Offsets in it are raw
+    /// addresses, so only [`Instrument::move_registers_unchecked`] accepts it.
+    Local = VI_LOCAL_SPACE as _,
+    /// VXI and VME A16 space.
+    A16 = VI_A16_SPACE as _,
+    /// VXI and VME A24 space.
+    A24 = VI_A24_SPACE as _,
+    /// VXI and VME A32 space.
+    A32 = VI_A32_SPACE as _,
+    /// VXI and VME A64 space.
+    A64 = VI_A64_SPACE as _,
+    /// PCI configuration space of a PXI module.
+    PxiConfig = VI_PXI_CFG_SPACE as _,
+    /// PCI base address registers of a PXI module.
+    PxiBar0 = VI_PXI_BAR0_SPACE as _,
+    PxiBar1 = VI_PXI_BAR1_SPACE as _,
+    PxiBar2 = VI_PXI_BAR2_SPACE as _,
+    PxiBar3 = VI_PXI_BAR3_SPACE as _,
+    PxiBar4 = VI_PXI_BAR4_SPACE as _,
+    PxiBar5 = VI_PXI_BAR5_SPACE as _,
+}
+
+/// Widths of register accesses, VISA's `VI_WIDTH_*`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u16)]
+pub enum RegisterWidth {
+    Bits8 = VI_WIDTH_8 as _,
+    Bits16 = VI_WIDTH_16 as _,
+    Bits32 = VI_WIDTH_32 as _,
+    Bits64 = VI_WIDTH_64 as _,
+}
+
+impl RegisterWidth {
+    /// Size of an access in bytes.
+    pub fn bytes(self) -> usize {
+        self as usize
+    }
+}
+
+mod sealed {
+    pub trait Sealed: Copy {
+        fn from_bits(bits: u64) -> Self;
+
+        fn into_bits(self) -> u64;
+    }
+}
+
+/// Integers registers are read and written as, accessed at their own width.
+pub trait RegisterValue: sealed::Sealed {
+    const WIDTH: RegisterWidth;
+}
+
+macro_rules! register_values {
+    ($($type:ty => $width:ident),* $(,)?) => {
+        $(
+            impl sealed::Sealed for $type {
+                fn from_bits(bits: u64) -> Self {
+                    bits as _
+                }
+
+                fn into_bits(self) -> u64 {
+                    self as _
+                }
+            }
+
+            impl RegisterValue for $type {
+                const WIDTH: RegisterWidth = RegisterWidth::$width;
+            }
+        )*
+    };
+}
+
+register_values!(
+    u8 => Bits8,
+    i8 => Bits8,
+    u16 => Bits16,
+    i16 => Bits16,
+    u32 => Bits32,
+    i32 => Bits32,
+    u64 => Bits64,
+    i64 => Bits64,
+);
+
+/// One side of [`Instrument::move_registers`], registers of `width` from `offset` in `space`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Registers {
+    pub space: AddressSpace,
+    pub offset: u64,
+    pub width: RegisterWidth,
+}
+
+impl Instrument {
+    /// Reads the register at `offset` in `space`, at the width of `T`. The offset must be
+    /// aligned to that width, and fit the bus addresses of the platform.
+    ///
+    /// ```no_run
+    /// # use visa::AddressSpace;
+    /// # fn example(instrument: &visa::Instrument) -> visa::Result<()> {
+    /// let id: u16 = instrument.read_register(AddressSpace::A16, 0)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_register<T: RegisterValue>(&self, space: AddressSpace, offset: u64) -> Result<T> {
+        self.read_register_with(space, offset, false)
+    }
+
+    /// Reads a register with `viIn*Ex`, taking 64-bit offsets on every platform.
+    pub fn read_register_ex<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+    ) -> Result<T> {
+        self.read_register_with(space, offset, true)
+    }
+
+    /// Writes the register at `offset` in `space`, at the width of `T`.
+    pub fn write_register<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        value: T,
+    ) -> Result<()> {
+        self.write_register_with(space, offset, value, false)
+    }
+
+    /// Writes a register with `viOut*Ex`, taking 64-bit offsets on every platform.
+    pub fn write_register_ex<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        value: T,
+    ) -> Result<()> {
+        self.write_register_with(space, offset, value, true)
+    }
+
+    /// Reads consecutive registers from `offset` in `space` into `values`, at the width of `T`.
+    ///
+    /// ```no_run
+    /// # use visa::AddressSpace;
+    /// # fn example(instrument: &visa::Instrument) -> visa::Result<()> {
+    /// let mut samples = vec![0u16; 1024];
+    /// instrument.move_in(AddressSpace::PxiBar1, 0x1000, &mut samples)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn move_in<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        values: &mut [T],
+    ) -> Result<()> {
+        self.move_in_with(space, offset, values, false)
+    }
+
+    /// Reads consecutive registers with `viMoveIn*Ex`, taking 64-bit offsets on every platform.
+    pub fn move_in_ex<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        values: &mut [T],
+    ) -> Result<()> {
+        self.move_in_with(space, offset, values, true)
+    }
+
+    /// Writes `values` to consecutive registers from `offset` in `space`.
+    pub fn move_out<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        values: &[T],
+    ) -> Result<()> {
+        self.move_out_with(space, offset, values, false)
+    }
+
+    /// Writes consecutive registers with `viMoveOut*Ex`, taking 64-bit offsets on every
+    /// platform.
+    pub fn move_out_ex<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        values: &[T],
+    ) -> Result<()> {
+        self.move_out_with(space, offset, values, true)
+    }
+
+    /// Copies `length` registers of the source width from `source` to `destination`, which
+    /// may be in another address space of the device. Neither side may be
+    /// [`AddressSpace::Local`] memory, see [`Instrument::move_registers_unchecked`].
+    pub fn move_registers(
+        &self,
+        source: Registers,
+        destination: Registers,
+        length: usize,
+    ) -> Result<()> {
+        check_space(source.space)?;
+        check_space(destination.space)?;
+        self.move_registers_with(source, destination, length, false)
+    }
+
+    /// Copies registers with `viMoveEx`, taking 64-bit offsets on every platform.
+    pub fn move_registers_ex(
+        &self,
+        source: Registers,
+        destination: Registers,
+        length: usize,
+    ) -> Result<()> {
+        check_space(source.space)?;
+        check_space(destination.space)?;
+        self.move_registers_with(source, destination, length, true)
+    }
+
+    /// Copies registers like [`Instrument::move_registers_ex`], but either side may be
+    /// [`AddressSpace::Local`] memory of the controller.
+    ///
+    /// # Safety
+    ///
+    /// The library reads and writes the controller's memory at the raw offsets of `Local`
+    /// sides, so they must address `length` registers of their width that are valid for
+    /// reads at the source and for writes at the destination, and not otherwise borrowed.
+    pub unsafe fn move_registers_unchecked(
+        &self,
+        source: Registers,
+        destination: Registers,
+        length: usize,
+    ) -> Result<()> {
+        self.move_registers_with(source, destination, length, true)
+    }
+
+    fn read_register_with<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        extended: bool,
+    ) -> Result<T> {
+        check_space(space)?;
+        check_offset(offset, T::WIDTH, extended)?;
+        let session = self.register_session()?;
+        let visa = library()?;
+        let space = space as ViUInt16;
+        let bits = unsafe {
+            macro_rules! read {
+                ($function:ident, $type:ty) => {{
+                    require(&visa.$function)?;
+                    let mut value: $type = 0;
+                    parse_vi_status(visa.$function(session, space, offset as _, &mut value as _))?;
+                    value as u64
+                }};
+            }
+            match (T::WIDTH, extended) {
+                (RegisterWidth::Bits8, false) => read!(viIn8, ViUInt8),
+                (RegisterWidth::Bits16, false) => read!(viIn16, ViUInt16),
+                (RegisterWidth::Bits32, false) => read!(viIn32, ViUInt32),
+                (RegisterWidth::Bits64, false) => read!(viIn64, ViUInt64),
+                (RegisterWidth::Bits8, true) => read!(viIn8Ex, ViUInt8),
+                (RegisterWidth::Bits16, true) => read!(viIn16Ex, ViUInt16),
+                (RegisterWidth::Bits32, true) => read!(viIn32Ex, ViUInt32),
+                (RegisterWidth::Bits64, true) => read!(viIn64Ex, ViUInt64),
+            }
+        };
+        Ok(T::from_bits(bits))
+    }
+
+    fn write_register_with<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        value: T,
+        extended: bool,
+    ) -> Result<()> {
+        check_space(space)?;
+        check_offset(offset, T::WIDTH, extended)?;
+        let session = self.register_session()?;
+        let visa = library()?;
+        let space = space as ViUInt16;
+        let offset = offset as _;
+        let bits = value.into_bits();
+        unsafe {
+            macro_rules! write {
+                ($function:ident) => {{
+                    require(&visa.$function)?;
+                    visa.$function(session, space, offset, bits as _)
+                }};
+            }
+            let status = match (T::WIDTH, extended) {
+                (RegisterWidth::Bits8, false) => write!(viOut8),
+                (RegisterWidth::Bits16, false) => write!(viOut16),
+                (RegisterWidth::Bits32, false) => write!(viOut32),
+                (RegisterWidth::Bits64, false) => write!(viOut64),
+                (RegisterWidth::Bits8, true) => write!(viOut8Ex),
+                (RegisterWidth::Bits16, true) => write!(viOut16Ex),
+                (RegisterWidth::Bits32, true) => write!(viOut32Ex),
+                (RegisterWidth::Bits64, true) => write!(viOut64Ex),
+            };
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn move_in_with<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        values: &mut [T],
+        extended: bool,
+    ) -> Result<()> {
+        check_space(space)?;
+        check_offset(offset, T::WIDTH, extended)?;
+        let session = self.register_session()?;
+        if values.is_empty() {
+            return Ok(());
+        }
+        let visa = library()?;
+        let space = space as ViUInt16;
+        let offset = offset as _;
+        let length = bus_size(values.len())?;
+        // Sealed values are integers of the width, laid out as the library expects
+        let buf = values.as_mut_ptr();
+        unsafe {
+            macro_rules! move_in {
+                ($function:ident) => {{
+                    require(&visa.$function)?;
+                    visa.$function(session, space, offset, length, buf.cast())
+                }};
+            }
+            let status = match (T::WIDTH, extended) {
+                (RegisterWidth::Bits8, false) => move_in!(viMoveIn8),
+                (RegisterWidth::Bits16, false) => move_in!(viMoveIn16),
+                (RegisterWidth::Bits32, false) => move_in!(viMoveIn32),
+                (RegisterWidth::Bits64, false) => move_in!(viMoveIn64),
+                (RegisterWidth::Bits8, true) => move_in!(viMoveIn8Ex),
+                (RegisterWidth::Bits16, true) => move_in!(viMoveIn16Ex),
+                (RegisterWidth::Bits32, true) => move_in!(viMoveIn32Ex),
+                (RegisterWidth::Bits64, true) => move_in!(viMoveIn64Ex),
+            };
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn move_out_with<T: RegisterValue>(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        values: &[T],
+        extended: bool,
+    ) -> Result<()> {
+        check_space(space)?;
+        check_offset(offset, T::WIDTH, extended)?;
+        let session = self.register_session()?;
+        if values.is_empty() {
+            return Ok(());
+        }
+        let visa = library()?;
+        let space = space as ViUInt16;
+        let offset = offset as _;
+        let length = bus_size(values.len())?;
+        // The library only reads from the buffer, despite its signature
+        let buf = values.as_ptr().cast_mut();
+        unsafe {
+            macro_rules! move_out {
+                ($function:ident) => {{
+                    require(&visa.$function)?;
+                    visa.$function(session, space, offset, length, buf.cast())
+                }};
+            }
+            let status = match (T::WIDTH, extended) {
+                (RegisterWidth::Bits8, false) => move_out!(viMoveOut8),
+                (RegisterWidth::Bits16, false) => move_out!(viMoveOut16),
+                (RegisterWidth::Bits32, false) => move_out!(viMoveOut32),
+                (RegisterWidth::Bits64, false) => move_out!(viMoveOut64),
+                (RegisterWidth::Bits8, true) => move_out!(viMoveOut8Ex),
+                (RegisterWidth::Bits16, true) => move_out!(viMoveOut16Ex),
+                (RegisterWidth::Bits32, true) => move_out!(viMoveOut32Ex),
+                (RegisterWidth::Bits64, true) => move_out!(viMoveOut64Ex),
+            };
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn move_registers_with(
+        &self,
+        source: Registers,
+        destination: Registers,
+        length: usize,
+        extended: bool,
+    ) -> Result<()> {
+        check_offset(source.offset, source.width, extended)?;
+        check_offset(destination.offset, destination.width, extended)?;
+        let session = self.register_session()?;
+        if length == 0 {
+            return Ok(());
+        }
+        let visa = library()?;
+        let length = bus_size(length)?;
+        let move_registers = match extended {
+            true => {
+                require(&visa.viMoveEx)?;
+                Visa::viMoveEx
+            }
+            false => {
+                require(&visa.viMove)?;
+                Visa::viMove
+            }
+        };
+        unsafe {
+            let status = move_registers(
+                visa,
+                session,
+                source.space as _,
+                source.offset as _,
+                source.width as _,
+                destination.space as _,
+                destination.offset as _,
+                destination.width as _,
+                length,
+            );
+            parse_vi_status(status)?;
+        }
+        Ok(())
+    }
+
+    fn register_session(&self) -> Result<ViSession> {
+        self.as_vi_session()
+            .ok_or(Error::Visa(VisaError::OperationNotSupported))
+    }
+}
+
+/// Checks `space` is on the device, the safe operations can't vouch for raw addresses of
+/// the controller's memory.
+fn check_space(space: AddressSpace) -> Result<()> {
+    match space {
+        AddressSpace::Local => Err(Error::Visa(VisaError::InvalidAddressSpace)),
+        _ => Ok(()),
+    }
+}
+
+/// Converts a count of registers to the library's size type.
+fn bus_size(length: usize) -> Result<ViBusSize> {
+    ViBusSize::try_from(length).map_err(|_| Error::Visa(VisaError::InvalidLength))
+}
+
+/// Checks `offset` is aligned to `width`, and fits a bus address unless `extended`.
+fn check_offset(offset: u64, width: RegisterWidth, extended: bool) -> Result<()> {
+    if !offset.is_multiple_of(width.bytes() as u64) {
+        return Err(Error::Visa(VisaError::OffsetNotAligned));
+    }
+    if !extended && ViBusAddress::try_from(offset).is_err() {
+        return Err(Error::Visa(VisaError::InvalidOffset));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_offsets_are_aligned_and_fit_a_bus_address() {
+        assert!(check_offset(3, RegisterWidth::Bits8, false).is_ok());
+        assert!(check_offset(6, RegisterWidth::Bits16, false).is_ok());
+        assert!(check_offset(0, RegisterWidth::Bits64, false).is_ok());
+        for (offset, width) in [
+            (1, RegisterWidth::Bits16),
+            (6, RegisterWidth::Bits32),
+            (12, RegisterWidth::Bits64),
+        ] {
+            for extended in [false, true] {
+                assert!(matches!(
+                    check_offset(offset, width, extended),
+                    Err(Error::Visa(VisaError::OffsetNotAligned))
+                ));
+            }
+        }
+
+        // Bus addresses are only 32 bits wide on some platforms, extended offsets never are
+        let beyond_32_bits = u32::MAX as u64 + 1;
+        match ViBusAddress::try_from(beyond_32_bits) {
+            Ok(_) => assert!(check_offset(beyond_32_bits, RegisterWidth::Bits8, false).is_ok()),
+            Err(_) => assert!(matches!(
+                check_offset(beyond_32_bits, RegisterWidth::Bits8, false),
+                Err(Error::Visa(VisaError::InvalidOffset))
+            )),
+        }
+        assert!(check_offset(u64::MAX - 7, RegisterWidth::Bits64, true).is_ok());
+    }
+
+    #[test]
+    fn refuses_the_local_space() {
+        assert!(matches!(
+            check_space(AddressSpace::Local),
+            Err(Error::Visa(VisaError::InvalidAddressSpace))
+        ));
+        for space in [AddressSpace::A16, AddressSpace::A64, AddressSpace::PxiBar0] {
+            assert!(check_space(space).is_ok());
+        }
+    }
+
+    #[test]
+    fn converts_register_counts_to_bus_sizes() {
+        assert_eq!(bus_size(0).unwrap(), 0);
+        assert_eq!(bus_size(1024).unwrap(), 1024);
+        if let Some(too_long) = (ViBusSize::MAX as usize).checked_add(1) {
+            assert!(matches!(
+                bus_size(too_long),
+                Err(Error::Visa(VisaError::InvalidLength))
+            ));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use super::error::{Error, Result, VisaError};
+use regex::Regex;
+use std::{fmt::Display, str::FromStr};
+
+/// A parsed VISA resource name.
+///
+/// Interface names and resource classes are case insensitive and the board number
+/// defaults to `0` when omitted, so `tcpip::10.0.0.2::5025::socket` and
+/// `TCPIP0::10.0.0.2::5025::SOCKET` parse to the same resource.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceName {
+    /// `GPIB[board]::primary address[::secondary address][::INSTR]`
+    Gpib {
+        board: u16,
+        primary_address: u8,
+        secondary_address: Option<u8>,
+    },
+    /// `VXI[board]::VXI logical address[::INSTR]`
+    Vxi { board: u16, logical_address: u16 },
+    /// `ASRL[board][::INSTR]`, where the board is either a number or a device path such as `/dev/ttyUSB0`
+    Asrl { port: String },
+    /// `TCPIP[board]::host address[::LAN device name][::INSTR]`
+    TcpipInstr {
+        board: u16,
+        host: String,
+        device_name: String,
+    },
+    /// `TCPIP[board]::host address::port::SOCKET`
+    TcpipSocket { board: u16, host: String, port: u16 },
+    /// `USB[board]::manufacturer ID::model code::serial number[::USB interface number][::INSTR]`
+    Usb {
+        board: u16,
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: String,
+        interface_number: Option<u8>,
+    },
+}
+
+impl ResourceName {
+    /// Board number of the interface the resource is attached to.
+    pub fn board(&self) -> u16 {
+        match self {
+            Self::Gpib { board, .. }
+            | Self::Vxi { board, .. }
+            | Self::TcpipInstr { board, .. }
+            | Self::TcpipSocket { board, .. }
+            | Self::Usb { board, .. } => *board,
+            Self::Asrl { port } => port.parse().unwrap_or(0),
+        }
+    }
+}
+
+/// The canonical, lowercase form of `resource`, so that names of the same resource compare equal.
+pub(crate) fn normalize(resource: &str) -> String {
+    resource
+        .parse::<ResourceName>()
+        .map(|resource| resource.to_string())
+        .unwrap_or_else(|_| resource.to_owned())
+        .to_ascii_lowercase()
+}
+
+/// Translates a VISA resource expression, as passed to `viFindRsrc`, into a regular expression.
+///
+/// `?` matches any character, `\` escapes the next one, while `[]`, `*`, `+`, `|` and
+/// `()` keep their usual meaning. Matching is case insensitive and covers the whole name.
+pub(crate) fn expression_regex(expression: &str) -> Result<Regex> {
+    let mut pattern = String::from("(?i)^(?:");
+    let mut chars = expression.chars();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) => pattern.push_str(&regex::escape(&escaped.to_string())),
+                None => return Err(Error::Visa(VisaError::InvalidExpression)),
+            },
+            '[' if !in_class => {
+                in_class = true;
+                pattern.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                pattern.push(c);
+            }
+            '^' | '-' if in_class => pattern.push(c),
+            '?' if !in_class => pattern.push('.'),
+            '*' | '+' | '|' | '(' | ')' if !in_class => pattern.push(c),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push_str(")$");
+
+    Regex::new(&pattern).map_err(|_| Error::Visa(VisaError::InvalidExpression))
+}
+
+fn invalid() -> Error {
+    Error::Visa(VisaError::InvalidResourceName)
+}
+
+fn parse_board(board: &str) -> Result<u16> {
+    match board {
+        "" => Ok(0),
+        board => board.parse().map_err(|_| invalid()),
+    }
+}
+
+fn parse_number<T: TryFrom<u64>>(number: &str) -> Result<T> {
+    let number = match number.strip_prefix("0x").or(number.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => number.parse(),
+    };
+    number
+        .ok()
+        .and_then(|number| T::try_from(number).ok())
+        .ok_or_else(invalid)
+}
+
+/// Strips the resource class from the last part, if it matches `class`.
+fn strip_class(parts: &mut Vec<&str>, class: &str) -> bool {
+    match parts.last() {
+        Some(last) if last.eq_ignore_ascii_case(class) => {
+            parts.pop();
+            true
+        }
+        _ => false,
+    }
+}
+
+impl FromStr for ResourceName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = s.trim().split("::").collect();
+        let interface = parts.remove(0);
+        let split = interface
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(interface.len());
+        let (kind, board) = interface.split_at(split);
+
+        match kind.to_ascii_uppercase().as_str() {
+            "GPIB" => {
+                strip_class(&mut parts, "INSTR");
+                match parts[..] {
+                    [primary] => Ok(Self::Gpib {
+                        board: parse_board(board)?,
+                        primary_address: parse_number(primary)?,
+                        secondary_address: None,
+                    }),
+                    [primary, secondary] => Ok(Self::Gpib {
+                        board: parse_board(board)?,
+                        primary_address: parse_number(primary)?,
+                        secondary_address: Some(parse_number(secondary)?),
+                    }),
+                    _ => Err(invalid()),
+                }
+            }
+            "VXI" => {
+                strip_class(&mut parts, "INSTR");
+                match parts[..] {
+                    [logical_address] => Ok(Self::Vxi {
+                        board: parse_board(board)?,
+                        logical_address: parse_number(logical_address)?,
+                    }),
+                    _ => Err(invalid()),
+                }
+            }
+            "ASRL" => {
+                strip_class(&mut parts, "INSTR");
+                match (parts.is_empty(), board) {
+                    (true, "") => Ok(Self::Asrl { port: "0".into() }),
+                    (true, port) => Ok(Self::Asrl { port: port.into() }),
+                    _ => Err(invalid()),
+                }
+            }
+            "TCPIP" => {
+                let board = parse_board(board)?;
+                if strip_class(&mut parts, "SOCKET") {
+                    return match parts[..] {
+                        [host, port] if !host.is_empty() => Ok(Self::TcpipSocket {
+                            board,
+                            host: host.into(),
+                            port: parse_number(port)?,
+                        }),
+                        _ => Err(invalid()),
+                    };
+                }
+
+                strip_class(&mut parts, "INSTR");
+                match parts[..] {
+                    [host] if !host.is_empty() => Ok(Self::TcpipInstr {
+                        board,
+                        host: host.into(),
+                        device_name: "inst0".into(),
+                    }),
+                    [host, device_name] if !host.is_empty() && !device_name.is_empty() => {
+                        Ok(Self::TcpipInstr {
+                            board,
+                            host: host.into(),
+                            device_name: device_name.into(),
+                        })
+                    }
+                    _ => Err(invalid()),
+                }
+            }
+            "USB" => {
+                strip_class(&mut parts, "INSTR");
+                let (vendor_id, product_id, serial_number, interface_number) = match parts[..] {
+                    [vendor_id, product_id, serial_number] => {
+                        (vendor_id, product_id, serial_number, None)
+                    }
+                    [vendor_id, product_id, serial_number, interface_number] => (
+                        vendor_id,
+                        product_id,
+                        serial_number,
+                        Some(parse_number(interface_number)?),
+                    ),
+                    _ => return Err(invalid()),
+                };
+                Ok(Self::Usb {
+                    board: parse_board(board)?,
+                    vendor_id: parse_number(vendor_id)?,
+                    product_id: parse_number(product_id)?,
+                    serial_number: serial_number.into(),
+                    interface_number,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for ResourceName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gpib {
+                board,
+                primary_address,
+                secondary_address: None,
+            } => write!(f, "GPIB{}::{}::INSTR", board, primary_address),
+            Self::Gpib {
+                board,
+                primary_address,
+                secondary_address: Some(secondary_address),
+            } => write!(
+                f,
+                "GPIB{}::{}::{}::INSTR",
+                board, primary_address, secondary_address
+            ),
+            Self::Vxi {
+                board,
+                logical_address,
+            } => write!(f, "VXI{}::{}::INSTR", board, logical_address),
+            Self::Asrl { port } => write!(f, "ASRL{}::INSTR", port),
+            Self::TcpipInstr {
+                board,
+                host,
+                device_name,
+            } => write!(f, "TCPIP{}::{}::{}::INSTR", board, host, device_name),
+            Self::TcpipSocket { board, host, port } => {
+                write!(f, "TCPIP{}::{}::{}::SOCKET", board, host, port)
+            }
+            Self::Usb {
+                board,
+                vendor_id,
+                product_id,
+                serial_number,
+                interface_number: None,
+            } => write!(
+                f,
+                "USB{}::0x{:04X}::0x{:04X}::{}::INSTR",
+                board, vendor_id, product_id, serial_number
+            ),
+            Self::Usb {
+                board,
+                vendor_id,
+                product_id,
+                serial_number,
+                interface_number: Some(interface_number),
+            } => write!(
+                f,
+                "USB{}::0x{:04X}::0x{:04X}::{}::{}::INSTR",
+                board, vendor_id, product_id, serial_number, interface_number
+            ),
+        }
+    }
+}
@@ -1,7 +1,12 @@
+#[cfg(feature = "sim")]
+use super::backend::Simulation;
 use super::{
+    backend::{self, Recorder, Replay},
     bindings::*,
-    error::{Error, Result, parse_vi_status},
+    error::{Error, Result, VisaError, parse_vi_status},
     instrument::Instrument,
+    library::{library, load_library},
+    resource::{expression_regex, normalize},
     session::Session,
 };
 use bitflags::bitflags;
@@ -9,9 +14,11 @@ use regex::Regex;
 use std::{
     collections::HashMap,
     ffi::{CStr, CString},
+    fmt::{self, Debug},
+    path::{Path, PathBuf},
     str::FromStr,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -30,34 +37,194 @@ bitflags! {
     }
 }
 
+/// Opens a session to a resource, as registered with [`ResourceManager::register_backend`].
+pub type OpenBackend = dyn Fn(&str, AccessMode, Duration) -> Result<Session> + Send + Sync;
+
+/// Resources accepted by `matches` are opened with `open`.
+struct Route {
+    expression: String,
+    matches: Box<dyn Fn(&str) -> bool + Send + Sync>,
+    /// Resources listed alongside the ones found by the VISA library or the native backends.
+    resources: Vec<String>,
+    open: Box<OpenBackend>,
+}
+
+impl Debug for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Route")
+            .field("expression", &self.expression)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug)]
 pub struct ResourceManager {
-    inner: Session,
+    /// The default resource manager session, `None` if the VISA library isn't used.
+    inner: Option<Session>,
     pub(crate) instruments: HashMap<String, Arc<Mutex<Instrument>>>,
+    routes: Vec<Route>,
+    /// Directory transcripts of newly opened resources are written to.
+    recording: Option<PathBuf>,
 }
 
 impl ResourceManager {
+    /// Opens the default resource manager.
+    ///
+    /// The VISA library is loaded on first use from the path in the `VISA_LIBRARY`
+    /// environment variable or one of the default vendor locations, failing with
+    /// [`Error::LibraryNotFound`] if none is present.
     pub fn new() -> Result<Self> {
         let mut session: ViSession = 0;
+        let visa = library()?;
         unsafe {
-            let status = viOpenDefaultRM(&mut session as _);
+            let status = visa.viOpenDefaultRM(&mut session as _);
             parse_vi_status(status)?;
             Ok(Self {
-                inner: Session::from_vi_session(session),
+                inner: Some(Session::from_vi_session(session)),
                 instruments: HashMap::new(),
+                routes: Vec::new(),
+                recording: None,
             })
         }
     }
 
+    /// Creates a resource manager that serves resources through the native backends only,
+    /// without loading the VISA library.
+    ///
+    /// Supported resources:
+    /// - `TCPIP[board]::host::port::SOCKET`
+    /// - `TCPIP[board]::host::hislip0[,port]::INSTR`, over HiSLIP
+    /// - `TCPIP[board]::host[::LAN device name]::INSTR`, over VXI-11 for any other device name
+    /// - `USB[board]::vendor::product::serial[::interface]::INSTR`, over USBTMC on Linux
+    /// - `ASRL[board]::INSTR` or `ASRL/dev/ttyUSB0::INSTR`, through termios on Unix
+    /// - `GPIB[board]::primary address[::secondary address]::INSTR`, through linux-gpib on Linux
+    pub fn native() -> Self {
+        Self {
+            inner: None,
+            instruments: HashMap::new(),
+            routes: Vec::new(),
+            recording: None,
+        }
+    }
+
+    /// Creates a resource manager that serves the resources of `simulation`, like
+    /// [`native`](Self::native) with the simulation registered.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use visa::{AccessMode, ResourceManager, Simulation};
+    ///
+    /// let simulation: Simulation = r#"
+    ///     [devices.dmm]
+    ///     dialogues = [{ q = "*IDN?", r = "ACME,DMM 1,1234,1.0" }]
+    ///
+    ///     [resources."TCPIP::sim::INSTR"]
+    ///     device = "dmm"
+    /// "#
+    /// .parse()?;
+    /// let mut rm = ResourceManager::simulated(simulation);
+    /// let instrument = rm.open("TCPIP::sim::INSTR", AccessMode::NO_LOCK, Duration::ZERO)?;
+    /// assert_eq!(instrument.lock().unwrap().identification.model, "DMM 1");
+    /// # Ok::<(), visa::Error>(())
+    /// ```
+    #[cfg(feature = "sim")]
+    pub fn simulated(simulation: Simulation) -> Self {
+        let mut resource_manager = Self::native();
+        resource_manager.register_simulation(simulation);
+        resource_manager
+    }
+
+    /// Opens the default resource manager of the VISA library at `path`.
+    pub fn with_library(path: impl AsRef<Path>) -> Result<Self> {
+        load_library(path)?;
+        Self::new()
+    }
+
     pub fn from_vi_session(session: ViSession) -> Self {
         Self {
-            inner: Session::from_vi_session(session),
+            inner: Some(Session::from_vi_session(session)),
             instruments: HashMap::new(),
+            routes: Vec::new(),
+            recording: None,
         }
     }
 
-    pub fn as_vi_session(&self) -> ViSession {
-        self.inner.as_vi_session()
+    pub fn as_vi_session(&self) -> Option<ViSession> {
+        self.inner.as_ref().and_then(Session::as_vi_session)
+    }
+
+    /// Opens the resources matching the VISA `expression` with `open` instead of the
+    /// VISA library or the native backends, the latest matching registration is used.
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use visa::{AccessMode, Fake, ResourceManager, Session};
+    ///
+    /// let mut rm = ResourceManager::native();
+    /// rm.register_backend("TCPIP?*::fake?*::INSTR", |_, _, _| Ok(Session::from(Fake::new())))?;
+    /// let instrument = rm.open("TCPIP0::fake1::INSTR", AccessMode::NO_LOCK, Duration::ZERO)?;
+    /// assert_eq!(instrument.lock().unwrap().identification.manufacturer, "Fake");
+    /// # Ok::<(), visa::Error>(())
+    /// ```
+    pub fn register_backend(
+        &mut self,
+        expression: &str,
+        open: impl Fn(&str, AccessMode, Duration) -> Result<Session> + Send + Sync + 'static,
+    ) -> Result<()> {
+        let regex = expression_regex(expression)?;
+        self.routes.push(Route {
+            expression: expression.to_owned(),
+            matches: Box::new(move |resource| regex.is_match(resource)),
+            resources: Vec::new(),
+            open: Box::new(open),
+        });
+        Ok(())
+    }
+
+    /// Serves the resources of `simulation` alongside the others, they're listed by
+    /// [`get_resources_with_expression`](Self::get_resources_with_expression) too.
+    #[cfg(feature = "sim")]
+    pub fn register_simulation(&mut self, simulation: Simulation) {
+        let matching = simulation.clone();
+        self.routes.push(Route {
+            expression: simulation.resources().join("|"),
+            matches: Box::new(move |resource| matching.contains(resource)),
+            resources: simulation.resources(),
+            open: Box::new(move |resource, _, _| Ok(Session::from(simulation.open(resource)?))),
+        });
+    }
+
+    /// Serves the resource `replay` was recorded from with it, so that a test can play a
+    /// recorded session back through the same code.
+    pub fn register_replay(&mut self, replay: Replay) -> Result<()> {
+        let resource = replay
+            .resource()
+            .ok_or_else(|| Error::InvalidTranscript("missing resource".into()))?
+            .to_owned();
+        let normalized = normalize(&resource);
+        self.routes.push(Route {
+            expression: resource.clone(),
+            matches: Box::new(move |resource| normalize(resource) == normalized),
+            resources: vec![resource],
+            open: Box::new(move |_, _, _| Ok(Session::from(replay.clone()))),
+        });
+        Ok(())
+    }
+
+    /// Records the exchange with every resource opened from now on to a transcript in
+    /// `directory`, named after the resource and when it was opened.
+    pub fn record_to(&mut self, directory: impl Into<PathBuf>) {
+        self.recording = Some(directory.into());
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recording = None;
+    }
+
+    /// Opens the resources matching the VISA `expression` with the native backends,
+    /// even if the VISA library serves the others.
+    pub fn use_native(&mut self, expression: &str) -> Result<()> {
+        self.register_backend(expression, backend::open)
     }
 
     pub fn open(
@@ -82,23 +249,12 @@ impl ResourceManager {
                 }
             }
             None => {
-                let c_resource = CString::from_str(resource).map_err(|_| Error::InvalidString)?;
-                let mut session: ViSession = 0;
-
-                unsafe {
-                    let status = viOpen(
-                        self.as_vi_session(),
-                        c_resource.as_ptr(),
-                        access_mode.bits(),
-                        timeout.as_millis() as _,
-                        &mut session as _,
-                    );
-                    parse_vi_status(status)?;
+                let mut session = self.open_session(resource, access_mode, timeout)?;
+                if let Some(directory) = &self.recording {
+                    let path = directory.join(transcript_name(resource));
+                    session = Session::from(Recorder::new(session, resource, path)?);
                 }
-
-                let instrument = Arc::new(Mutex::new(Instrument::new(Session::from_vi_session(
-                    session,
-                ))?));
+                let instrument = Arc::new(Mutex::new(Instrument::new(session)?));
 
                 self.instruments
                     .insert(resource.to_owned(), instrument.clone());
@@ -108,30 +264,85 @@ impl ResourceManager {
         }
     }
 
+    fn open_session(
+        &self,
+        resource: &str,
+        access_mode: AccessMode,
+        timeout: Duration,
+    ) -> Result<Session> {
+        if let Some(route) = self
+            .routes
+            .iter()
+            .rev()
+            .find(|route| (route.matches)(resource))
+        {
+            return (route.open)(resource, access_mode, timeout);
+        }
+
+        let Some(resource_manager) = self.as_vi_session() else {
+            return backend::open(resource, access_mode, timeout);
+        };
+
+        let c_resource = CString::from_str(resource).map_err(|_| Error::InvalidString)?;
+        let mut session: ViSession = 0;
+
+        let visa = library()?;
+        unsafe {
+            let status = visa.viOpen(
+                resource_manager,
+                c_resource.as_ptr(),
+                access_mode.bits(),
+                timeout.as_millis() as _,
+                &mut session as _,
+            );
+            parse_vi_status(status)?;
+        }
+
+        Ok(Session::from_vi_session(session))
+    }
+
     pub fn close(&mut self, resource: &str) -> Result<()> {
         let instrument = self.instruments.remove(resource);
 
         match instrument {
             Some(instrument) => {
                 let instrument = instrument.lock().unwrap();
-                unsafe {
-                    let status = viClose(instrument.as_vi_session());
-                    parse_vi_status(status)?;
-                    Ok(())
-                }
+                instrument.close()
             }
             None => Err(Error::InstrumentNotFound),
         }
     }
 
     pub fn get_resources_with_expression(&self, expression: &str) -> Result<Vec<String>> {
+        let mut resources = match self.find_resources(expression) {
+            Err(Error::Visa(VisaError::ResourceNotFound)) => vec![],
+            resources => resources?,
+        };
+
+        let regex = expression_regex(expression)?;
+        resources.extend(
+            self.routes
+                .iter()
+                .flat_map(|route| &route.resources)
+                .filter(|resource| regex.is_match(resource))
+                .cloned(),
+        );
+        Ok(resources)
+    }
+
+    fn find_resources(&self, expression: &str) -> Result<Vec<String>> {
+        let Some(resource_manager) = self.as_vi_session() else {
+            return backend::find(expression);
+        };
+
         let mut list: ViFindList = 0;
         let mut count: ViUInt32 = 0;
         let mut instrument_description = [0; VI_FIND_BUFLEN as _];
         let expression = CString::from_str(expression).map_err(|_| Error::InvalidString)?;
+        let visa = library()?;
         unsafe {
-            let status = viFindRsrc(
-                self.as_vi_session(),
+            let status = visa.viFindRsrc(
+                resource_manager,
                 expression.as_ptr(),
                 &mut list,
                 &mut count,
@@ -151,7 +362,7 @@ impl ResourceManager {
 
             for _ in 1..count {
                 unsafe {
-                    let status = viFindNext(list, instrument_description.as_mut_ptr() as _);
+                    let status = visa.viFindNext(list, instrument_description.as_mut_ptr() as _);
                     parse_vi_status(status)?;
                 }
 
@@ -214,3 +425,18 @@ impl ResourceManager {
         Err(Error::InstrumentNotFound)
     }
 }
+
+/// File name of a new transcript of `resource`.
+fn transcript_name(resource: &str) -> String {
+    let resource: String = resource
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    let opened = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}-{}.transcript", resource, opened.as_millis())
+}
@@ -1,5 +1,9 @@
-use super::{Error, Instrument, Result};
-use std::str::FromStr;
+use super::{AsciiValue, Error, Instrument, Result};
+use bitflags::bitflags;
+use std::{fmt, str::FromStr};
+
+/// Errors drained at most, in case an instrument never reports an empty queue.
+const MAX_DRAINED_ERRORS: usize = 256;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Hash)]
 pub struct Identification {
@@ -33,26 +37,130 @@ impl FromStr for Identification {
     }
 }
 
-// Implement all common SCPI commands
-impl Instrument {
-    pub fn query_identification(&mut self) -> Result<Identification> {
-        #[cfg(not(feature = "mock"))]
-        {
-            let response = self.query(b"*IDN?\n")?;
-            Ok(response
-                .parse()
-                .map_err(|error| Error::InvalidIdentification(error))?)
+/// Class of an [`ScpiError`], from the range of its code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScpiErrorKind {
+    /// -100 to -199, such as an undefined header, also sets [`StandardEventStatus::COMMAND_ERROR`].
+    Command,
+    /// -200 to -299, such as a parameter out of range, also sets
+    /// [`StandardEventStatus::EXECUTION_ERROR`].
+    Execution,
+    /// -300 to -399, also sets [`StandardEventStatus::DEVICE_DEPENDENT_ERROR`].
+    DeviceSpecific,
+    /// -400 to -499, such as a query interrupted by another message, also sets
+    /// [`StandardEventStatus::QUERY_ERROR`].
+    Query,
+    /// Any other code, the events from -500 to -899 and the instrument's own positive codes.
+    Other,
+}
+
+/// An entry of the SCPI error queue, as `SYSTem:ERRor?` reports it: `-113,"Undefined header;FOO"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScpiError {
+    pub code: i32,
+    pub message: String,
+    /// Details the instrument appended to the message after a `;`.
+    pub device_info: Option<String>,
+}
+
+impl ScpiError {
+    pub fn kind(&self) -> ScpiErrorKind {
+        match self.code {
+            -199..=-100 => ScpiErrorKind::Command,
+            -299..=-200 => ScpiErrorKind::Execution,
+            -399..=-300 => ScpiErrorKind::DeviceSpecific,
+            -499..=-400 => ScpiErrorKind::Query,
+            _ => ScpiErrorKind::Other,
         }
+    }
+}
 
-        #[cfg(feature = "mock")]
-        {
-            Ok(Identification {
-                manufacturer: "Fake Company Inc.".into(),
-                model: "Fake Deviceinator".into(),
-                serial_number: "3000".into(),
-                firmware_version: "2.40.69".into(),
-            })
+impl fmt::Display for ScpiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},\"{}", self.code, self.message)?;
+        if let Some(device_info) = &self.device_info {
+            write!(f, ";{}", device_info)?;
         }
+        write!(f, "\"")
+    }
+}
+
+impl FromStr for ScpiError {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || Error::InvalidValue(s.trim().to_owned(), "<code>,\"<message>\"");
+        let (code, message) = s.split_once(',').ok_or_else(invalid)?;
+        let code = code.trim().parse().map_err(|_| invalid())?;
+        let message = message.trim();
+        let message = message
+            .strip_prefix('"')
+            .and_then(|message| message.strip_suffix('"'))
+            .unwrap_or(message)
+            .replace("\"\"", "\"");
+        let (message, device_info) = match message.split_once(';') {
+            Some((message, device_info)) => (message.to_owned(), Some(device_info.to_owned())),
+            None => (message, None),
+        };
+
+        Ok(Self {
+            code,
+            message,
+            device_info,
+        })
+    }
+}
+
+bitflags! {
+    /// Standard Event Status Register, read with `*ESR?` and masked with `*ESE`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct StandardEventStatus: u8 {
+        /// Set by `*OPC` once pending operations completed.
+        const OPERATION_COMPLETE = 1 << 0;
+        const REQUEST_CONTROL = 1 << 1;
+        const QUERY_ERROR = 1 << 2;
+        const DEVICE_DEPENDENT_ERROR = 1 << 3;
+        const EXECUTION_ERROR = 1 << 4;
+        const COMMAND_ERROR = 1 << 5;
+        const USER_REQUEST = 1 << 6;
+        const POWER_ON = 1 << 7;
+    }
+}
+
+bitflags! {
+    /// Status Byte, read with `*STB?` or a serial poll and masked with `*SRE`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct StatusByte: u8 {
+        /// SCPI's error queue isn't empty.
+        const ERROR_QUEUE = 1 << 2;
+        /// SCPI's Questionable Status summary.
+        const QUESTIONABLE = 1 << 3;
+        /// A response is ready to be read, MAV.
+        const MESSAGE_AVAILABLE = 1 << 4;
+        /// An enabled event of the Standard Event Status Register is set, ESB.
+        const EVENT_STATUS = 1 << 5;
+        /// The device requests service, RQS, or MSS when read with `*STB?`.
+        const REQUEST_SERVICE = 1 << 6;
+        /// SCPI's Operation Status summary.
+        const OPERATION = 1 << 7;
+        // Bits 0 and 1 are device specific
+        const _ = !0;
+    }
+}
+
+/// Result of the `*TST?` self-test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelfTestResult {
+    Passed,
+    /// Failed with a device specific code.
+    Failed(i32),
+}
+
+// Implement all common SCPI commands
+impl Instrument {
+    pub fn query_identification(&mut self) -> Result<Identification> {
+        let response = self.query(b"*IDN?\n")?;
+        response.parse().map_err(Error::InvalidIdentification)
     }
 
     pub fn reset(&mut self) -> Result<()> {
@@ -60,9 +168,11 @@ impl Instrument {
         Ok(())
     }
 
-    pub fn query_self_test(&mut self) -> Result<()> {
-        let response = self.query(b"*TST?\n")?;
-        Ok(())
+    pub fn query_self_test(&mut self) -> Result<SelfTestResult> {
+        match self.query_value(b"*TST?\n")? {
+            0 => Ok(SelfTestResult::Passed),
+            code => Ok(SelfTestResult::Failed(code)),
+        }
     }
 
     pub fn operation_complete(&mut self) -> Result<()> {
@@ -70,9 +180,9 @@ impl Instrument {
         Ok(())
     }
 
-    pub fn query_operation_completed(&mut self) -> Result<()> {
-        let response = self.query(b"*OPC?\n")?;
-        Ok(())
+    /// Waits for pending operations to complete, which `*OPC?` answers with `1`.
+    pub fn query_operation_completed(&mut self) -> Result<bool> {
+        self.query_value(b"*OPC?\n")
     }
 
     pub fn wait_on_complete(&mut self) -> Result<()> {
@@ -85,33 +195,80 @@ impl Instrument {
         Ok(())
     }
 
-    pub fn event_status_enable(&mut self) -> Result<()> {
-        self.write("*ESE\n")?;
+    pub fn event_status_enable(&mut self, mask: StandardEventStatus) -> Result<()> {
+        self.write(format!("*ESE {}\n", mask.bits()))?;
         Ok(())
     }
 
-    pub fn query_event_status_enable(&mut self) -> Result<()> {
-        let response = self.query(b"*ESE?\n")?;
-        Ok(())
+    pub fn query_event_status_enable(&mut self) -> Result<StandardEventStatus> {
+        Ok(StandardEventStatus::from_bits_retain(
+            self.query_value(b"*ESE?\n")?,
+        ))
     }
 
-    pub fn query_event_status_register(&mut self) -> Result<()> {
-        let response = self.query(b"*ESR?\n")?;
-        Ok(())
+    /// Reads and clears the Standard Event Status Register.
+    pub fn query_event_status_register(&mut self) -> Result<StandardEventStatus> {
+        Ok(StandardEventStatus::from_bits_retain(
+            self.query_value(b"*ESR?\n")?,
+        ))
     }
 
-    pub fn service_request_enable(&mut self) -> Result<()> {
-        self.write("*SRE\n")?;
+    /// Sets which bits of the Status Byte request service, [`StatusByte::REQUEST_SERVICE`]
+    /// itself is ignored.
+    pub fn service_request_enable(&mut self, mask: StatusByte) -> Result<()> {
+        self.write(format!("*SRE {}\n", mask.bits()))?;
         Ok(())
     }
 
-    pub fn query_service_request_enable(&mut self) -> Result<()> {
-        let response = self.query(b"*SRE?\n")?;
-        Ok(())
+    pub fn query_service_request_enable(&mut self) -> Result<StatusByte> {
+        Ok(StatusByte::from_bits_retain(self.query_value(b"*SRE?\n")?))
     }
 
-    pub fn query_read_status_byte(&mut self) -> Result<()> {
-        let response = self.query(b"*STB?\n")?;
-        Ok(())
+    pub fn query_read_status_byte(&mut self) -> Result<StatusByte> {
+        Ok(StatusByte::from_bits_retain(self.query_value(b"*STB?\n")?))
+    }
+
+    /// Queries a single value, such as the `+0` of `*TST?`.
+    fn query_value<T: AsciiValue>(&mut self, buf: &[u8]) -> Result<T> {
+        let response = self.query(buf)?;
+        let response = response.trim();
+        T::parse_ascii(response)
+            .ok_or_else(|| Error::InvalidValue(response.to_owned(), std::any::type_name::<T>()))
+    }
+}
+
+impl Instrument {
+    /// Reads the SCPI error queue with `SYSTem:ERRor?` until it reports `0,"No error"`, oldest
+    /// error first.
+    ///
+    /// ```no_run
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// for error in instrument.drain_errors()? {
+    ///     eprintln!("{:?}: {}", error.kind(), error);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drain_errors(&mut self) -> Result<Vec<ScpiError>> {
+        let mut errors = Vec::new();
+        while errors.len() < MAX_DRAINED_ERRORS {
+            // Not through `query`, which may check the error queue itself
+            std::io::Write::write_all(self, b"SYST:ERR?\n")?;
+            let error: ScpiError = self.read()?.parse()?;
+            if error.code == 0 {
+                break;
+            }
+            errors.push(error);
+        }
+        Ok(errors)
+    }
+
+    /// Fails with [`Error::Scpi`] if the error queue isn't empty.
+    pub(crate) fn check_error_queue(&mut self) -> Result<()> {
+        let errors = self.drain_errors()?;
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Scpi(errors)),
+        }
     }
 }
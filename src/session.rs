@@ -1,25 +1,135 @@
-use super::{bindings::*, parse_vi_status};
+#[cfg(target_os = "linux")]
+use super::backend::Gpib;
+#[cfg(unix)]
+use super::backend::Serial;
+#[cfg(feature = "sim")]
+use super::backend::SimulatedDevice;
+use super::{
+    attribute::{
+        Attribute, AttributeKind, AttributeValue, WritableAttribute, check_kind, check_writable,
+    },
+    backend::{Backend, Fake, HiSlip, Recorder, Replay, UsbTmc, UsbTransport, VisaSession, Vxi11},
+    bindings::*,
+    error::Result,
+};
+use std::sync::Arc;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug)]
 pub struct Session {
-    inner: ViSession,
+    inner: Arc<dyn Backend>,
 }
 
 impl Session {
     pub fn from_vi_session(session: ViSession) -> Self {
-        Self { inner: session }
+        Self::from_backend(VisaSession::new(session))
     }
 
-    pub fn as_vi_session(&self) -> ViSession {
-        self.inner
+    /// Wraps any [`Backend`], such as a [`Fake`] or a custom transport.
+    pub fn from_backend(backend: impl Backend + 'static) -> Self {
+        Self {
+            inner: Arc::new(backend),
+        }
+    }
+
+    /// The underlying VISA session, `None` if the resource is served by a native backend.
+    pub fn as_vi_session(&self) -> Option<ViSession> {
+        self.inner.as_vi_session()
+    }
+
+    /// The implementation serving the resource.
+    pub fn backend(&self) -> &dyn Backend {
+        self.inner.as_ref()
+    }
+
+    /// The implementation serving the resource, for waits running apart from the session.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn shared_backend(&self) -> Arc<dyn Backend> {
+        self.inner.clone()
+    }
+
+    /// Reads a typed attribute, as in `session.get::<Timeout>()`.
+    pub fn get<A: Attribute>(&self) -> Result<A> {
+        A::from_value(self.inner.get_attribute(A::ID, A::KIND)?)
+    }
+
+    /// Sets a typed attribute, as in `session.set(TermChar(b'\n'))`, read only attributes can't be set.
+    pub fn set<A: WritableAttribute>(&self, attribute: A) -> Result<()> {
+        self.inner.set_attribute(A::ID, &attribute.to_value())
+    }
+
+    /// Reads any attribute by its `VI_ATTR_*` identifier, its values must be of type `kind`.
+    /// Attributes with a typed counterpart in [`attribute`](crate::attribute) fail with another `kind`.
+    pub fn get_attribute(&self, attribute: u32, kind: AttributeKind) -> Result<AttributeValue> {
+        check_kind(attribute, kind)?;
+        self.inner.get_attribute(attribute, kind)
+    }
+
+    /// Sets any attribute by its `VI_ATTR_*` identifier. Attributes with a typed counterpart
+    /// fail if it's read only or with a value of another type.
+    pub fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        check_writable(attribute, value)?;
+        self.inner.set_attribute(attribute, value)
+    }
+
+    /// Closes the session, any further operation on it fails.
+    pub fn close(&self) -> Result<()> {
+        self.inner.close()
     }
 }
 
-impl Drop for Session {
-    fn drop(&mut self) {
-        unsafe {
-            let status = viClose(self.as_vi_session());
-            let _ = parse_vi_status(status);
-        }
+impl From<Fake> for Session {
+    fn from(value: Fake) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+impl From<Recorder> for Session {
+    fn from(value: Recorder) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+impl From<Replay> for Session {
+    fn from(value: Replay) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+impl From<Vxi11> for Session {
+    fn from(value: Vxi11) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+impl From<HiSlip> for Session {
+    fn from(value: HiSlip) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+impl<T: UsbTransport + 'static> From<UsbTmc<T>> for Session {
+    fn from(value: UsbTmc<T>) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+#[cfg(unix)]
+impl From<Serial> for Session {
+    fn from(value: Serial) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<Gpib> for Session {
+    fn from(value: Gpib) -> Self {
+        Self::from_backend(value)
+    }
+}
+
+#[cfg(feature = "sim")]
+impl From<SimulatedDevice> for Session {
+    fn from(value: SimulatedDevice) -> Self {
+        Self::from_backend(value)
     }
 }
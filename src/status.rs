@@ -0,0 +1,108 @@
+use super::{
+    error::{Error, Result, VisaError},
+    event::{EventMechanism, EventType},
+    instrument::Instrument,
+    scpi::{StandardEventStatus, StatusByte},
+};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How [`Instrument::wait_for_status`] watches the status byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum StatusWait {
+    /// Waits for the service request event, then serial polls the status byte. The device must
+    /// request service for the awaited bits, see [`Instrument::service_request_enable`].
+    #[default]
+    ServiceRequest,
+    /// Serial polls the status byte every interval, for sessions without service requests.
+    SerialPoll(Duration),
+}
+
+impl Instrument {
+    /// Waits until the status byte has any bit of `mask` set, such as
+    /// [`StatusByte::MESSAGE_AVAILABLE`], and returns it. `None` waits forever.
+    ///
+    /// Waiting for service requests enables their queue and leaves it enabled.
+    pub fn wait_for_status(
+        &self,
+        mask: StatusByte,
+        wait: StatusWait,
+        timeout: Option<Duration>,
+    ) -> Result<StatusByte> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let remaining =
+            || deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        if wait == StatusWait::ServiceRequest {
+            self.enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)?;
+        }
+
+        loop {
+            if let StatusWait::ServiceRequest = wait {
+                self.wait_for_service_request(remaining())?;
+            }
+            let status_byte = self.read_status_byte()?;
+            if status_byte.intersects(mask) {
+                return Ok(status_byte);
+            }
+
+            if let StatusWait::SerialPoll(interval) = wait {
+                match remaining() {
+                    Some(remaining) if remaining.is_zero() => {
+                        return Err(Error::Visa(VisaError::Timeout));
+                    }
+                    Some(remaining) => thread::sleep(interval.min(remaining)),
+                    None => thread::sleep(interval),
+                }
+            }
+        }
+    }
+
+    /// Sends `command` followed by `*OPC` and waits until the operation it started has
+    /// completed, as the device reports with a service request. `None` waits forever.
+    ///
+    /// Unlike `*OPC?`, nothing is left pending on the bus while waiting. The Standard Event
+    /// Status Register is read, which clears it, and returned to check for errors.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # fn example(instrument: &mut visa::Instrument) -> visa::Result<()> {
+    /// let status = instrument.run_and_wait("INIT", Some(Duration::from_secs(30)))?;
+    /// assert!(!status.intersects(visa::StandardEventStatus::EXECUTION_ERROR));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_and_wait(
+        &mut self,
+        command: impl AsRef<[u8]>,
+        timeout: Option<Duration>,
+    ) -> Result<StandardEventStatus> {
+        self.run_and_wait_with(command, StatusWait::default(), timeout)
+    }
+
+    /// Like [`run_and_wait`](Self::run_and_wait), watching the status byte through `wait`.
+    ///
+    /// The `*ESE` and `*SRE` masks are set to report only the completion.
+    pub fn run_and_wait_with(
+        &mut self,
+        command: impl AsRef<[u8]>,
+        wait: StatusWait,
+        timeout: Option<Duration>,
+    ) -> Result<StandardEventStatus> {
+        self.event_status_enable(StandardEventStatus::OPERATION_COMPLETE)?;
+        self.service_request_enable(StatusByte::EVENT_STATUS)?;
+        // Clears a completion left from before, and the service request it made
+        self.query_event_status_register()?;
+        if wait == StatusWait::ServiceRequest {
+            self.enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)?;
+            self.discard_events(EventType::ServiceRequest, EventMechanism::QUEUE)?;
+        }
+
+        let mut message = command.as_ref().trim_ascii_end().to_vec();
+        message.extend_from_slice(b";*OPC\n");
+        self.write(message)?;
+        self.wait_for_status(StatusByte::EVENT_STATUS, wait, timeout)?;
+        self.query_event_status_register()
+    }
+}
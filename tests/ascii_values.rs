@@ -0,0 +1,99 @@
+use visa::*;
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &str)] = &[
+    ("READ?", "+1.234E+00,+1.235E+00,-5\n"),
+    ("HEAD?", ":CURV 1;2;3\n"),
+    ("BAD?", "1,x\n"),
+];
+
+fn parse<T: AsciiValue>(response: &str) -> Result<Vec<T>> {
+    parse_ascii_values(response, &AsciiFormat::default())
+}
+
+#[test]
+fn queries_separated_values() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert_eq!(
+        instrument.query_ascii_values::<f64>("READ?\n").unwrap(),
+        [1.234, 1.235, -5.0]
+    );
+    let format = AsciiFormat {
+        separator: ';',
+        strip_header: true,
+    };
+    assert_eq!(
+        instrument
+            .query_ascii_values_with::<u8>("HEAD?\n", &format)
+            .unwrap(),
+        [1, 2, 3]
+    );
+}
+
+#[test]
+fn parses_the_scpi_special_floats() {
+    let values = parse::<f64>("9.9E37, -9.9E+37,9.91E37,NAN,INF,ninf,+INF,-INF").unwrap();
+    assert_eq!(values[0], f64::INFINITY);
+    assert_eq!(values[1], f64::NEG_INFINITY);
+    assert!(values[2].is_nan() && values[3].is_nan());
+    assert_eq!(
+        values[4..],
+        [
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY
+        ]
+    );
+
+    let values = parse::<f32>("9.9E37,1.5").unwrap();
+    assert_eq!(values, [f32::INFINITY, 1.5]);
+}
+
+#[test]
+fn parses_non_decimal_integers() {
+    assert_eq!(
+        parse::<i32>("1,-2,#H1F,#b101,#Q17").unwrap(),
+        [1, -2, 31, 5, 15]
+    );
+}
+
+#[test]
+fn parses_booleans() {
+    assert_eq!(
+        parse::<bool>("1,0,ON,off,True,FALSE").unwrap(),
+        [true, false, true, false, true, false]
+    );
+}
+
+#[test]
+fn keeps_responses_without_a_header_whole() {
+    let format = AsciiFormat {
+        separator: ',',
+        strip_header: true,
+    };
+    assert_eq!(parse_ascii_values::<u8>("7\n", &format).unwrap(), [7]);
+}
+
+#[test]
+fn parses_empty_responses_into_no_values() {
+    assert!(parse::<f64>("\n").unwrap().is_empty());
+    assert!(parse::<f64>("").unwrap().is_empty());
+}
+
+#[test]
+fn names_the_values_that_fail_to_parse() {
+    let (mut instrument, _) = open(RESPONSES);
+    match instrument.query_ascii_values::<u32>("BAD?\n") {
+        Err(Error::InvalidValue(value, kind)) => {
+            assert_eq!(value, "x");
+            assert_eq!(kind, "u32");
+        }
+        result => panic!("expected an invalid value, got {:?}", result),
+    }
+    assert!(matches!(parse::<u8>("256"), Err(Error::InvalidValue(..))));
+    assert!(matches!(parse::<bool>("2"), Err(Error::InvalidValue(..))));
+    assert!(matches!(parse::<i8>("1,,2"), Err(Error::InvalidValue(..))));
+}
@@ -0,0 +1,152 @@
+#![cfg(feature = "tokio")]
+
+use std::{
+    task::{Context, Waker},
+    time::{Duration, Instant},
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use visa::*;
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &[u8])] = &[
+    ("MEAS?", b"1.5\n"),
+    ("CURV?", b"#15a\nb\0c\n"),
+    ("*OPC?", b"1\n"),
+];
+
+fn open_async() -> (AsyncInstrument, Fake) {
+    let (instrument, fake) = open(RESPONSES);
+    (AsyncInstrument::from(instrument), fake)
+}
+
+#[tokio::test]
+async fn runs_operations_on_the_blocking_pool() {
+    let (instrument, fake) = open_async();
+    assert_eq!(instrument.query("MEAS?\n").await.unwrap(), "1.5\n");
+    assert_eq!(
+        instrument.query_bytes("CURV?\n").await.unwrap(),
+        b"#15a\nb\0c\n"
+    );
+
+    instrument.write("CURV?\n").await.unwrap();
+    assert_eq!(
+        instrument
+            .read_binary_block::<u8>(ByteOrder::BigEndian)
+            .await
+            .unwrap(),
+        b"a\nb\0c"
+    );
+    instrument
+        .write_binary_block(
+            "TRAC ",
+            [1u16, 2],
+            ByteOrder::BigEndian,
+            BlockLength::Definite,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fake.written().last().unwrap(), b"TRAC #14\0\x01\0\x02\n");
+    assert!(matches!(
+        instrument.read().await,
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+
+    let timeout = instrument
+        .run_blocking(|instrument| instrument.timeout())
+        .await
+        .unwrap();
+    assert_eq!(timeout, Some(Duration::from_millis(2000)));
+}
+
+#[tokio::test]
+async fn reads_and_writes_as_a_stream() {
+    let (mut instrument, fake) = open_async();
+    instrument.write_all(b"MEAS?\n").await.unwrap();
+    instrument.flush().await.unwrap();
+    assert_eq!(fake.written().last().unwrap(), b"MEAS?\n");
+
+    // The rest of a read that didn't fit is returned next
+    let mut buf = [0; 2];
+    instrument.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"1.");
+    let mut buf = [0; 8];
+    let count = AsyncReadExt::read(&mut instrument, &mut buf).await.unwrap();
+    assert_eq!(&buf[..count], b"5\n");
+
+    // Errors are reported as I/O errors
+    assert_eq!(
+        AsyncReadExt::read(&mut instrument, &mut buf)
+            .await
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::TimedOut
+    );
+}
+
+#[tokio::test]
+async fn finishes_operations_whose_future_was_dropped() {
+    let (instrument, fake) = open_async();
+    // Started while the instrument is held, so it can't finish before it's dropped
+    let mut query = Box::pin(instrument.query("MEAS?\n"));
+    let held = instrument.instrument().lock().unwrap();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(query.as_mut().poll(&mut cx).is_pending());
+    drop(query);
+    drop(held);
+
+    // Each query still gets its own response
+    assert_eq!(instrument.query("*OPC?\n").await.unwrap(), "1\n");
+    while !fake.written().contains(&b"MEAS?\n".to_vec()) {
+        tokio::task::yield_now().await;
+    }
+    assert!(matches!(
+        instrument.read().await,
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[tokio::test]
+async fn waits_for_service_requests_without_holding_the_instrument() {
+    let (instrument, fake) = open_async();
+    instrument
+        .run_blocking(|instrument| {
+            instrument.enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+        })
+        .await
+        .unwrap();
+    let start = Instant::now();
+    assert!(matches!(
+        instrument
+            .wait_for_service_request(Some(Duration::from_millis(50)))
+            .await,
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    let wait = instrument.wait_for_service_request(Some(Duration::from_secs(5)));
+    let query = async {
+        let response = instrument.query("MEAS?\n").await;
+        fake.push_event(EventType::ServiceRequest);
+        response
+    };
+    let (event, response) = tokio::join!(wait, query);
+    assert_eq!(event.unwrap().event_type(), EventType::ServiceRequest);
+    assert_eq!(response.unwrap(), "1.5\n");
+
+    // A dropped wait stops, leaving the next request queued
+    let wait = instrument.wait_for_service_request(None);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(10), wait)
+            .await
+            .is_err()
+    );
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    fake.push_event(EventType::ServiceRequest);
+    let event = instrument
+        .run_blocking(|instrument| instrument.wait_for_service_request(None))
+        .await
+        .unwrap();
+    assert_eq!(event.event_type(), EventType::ServiceRequest);
+}
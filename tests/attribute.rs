@@ -0,0 +1,101 @@
+use std::time::Duration;
+use visa::{attribute::*, *};
+
+/// Identifier of an attribute without a typed counterpart.
+const USER_ATTRIBUTE: u32 = 0x3FFF_0001;
+
+fn open() -> (Session, Fake) {
+    let fake = Fake::new();
+    (Session::from(fake.clone()), fake)
+}
+
+#[test]
+fn reads_and_writes_typed_attributes() {
+    let (session, fake) = open();
+    assert_eq!(
+        session.get::<Timeout>().unwrap(),
+        Timeout(Some(Duration::from_millis(2000)))
+    );
+    session.set(Timeout(None)).unwrap();
+    session.set(TermChar(b'\r')).unwrap();
+    assert_eq!(session.get::<Timeout>().unwrap(), Timeout(None));
+    assert_eq!(session.get::<TermChar>().unwrap(), TermChar(b'\r'));
+    assert_eq!(
+        fake.attribute(TermChar::ID),
+        Some(AttributeValue::U8(b'\r'))
+    );
+}
+
+#[test]
+fn rejects_values_of_the_wrong_type() {
+    let (session, fake) = open();
+    assert!(matches!(
+        session.get_attribute(TermChar::ID, AttributeKind::Bool),
+        Err(Error::AttributeKindMismatch(
+            TermChar::ID,
+            AttributeKind::U8,
+            AttributeKind::Bool
+        ))
+    ));
+    assert!(matches!(
+        session.set_attribute(TermChar::ID, &AttributeValue::U32(10)),
+        Err(Error::AttributeKindMismatch(
+            TermChar::ID,
+            AttributeKind::U8,
+            AttributeKind::U32
+        ))
+    ));
+    assert_eq!(
+        fake.attribute(TermChar::ID),
+        Some(AttributeValue::U8(b'\n'))
+    );
+
+    // A backend answering with the wrong type fails the typed read
+    fake.set_attribute(TermCharEnabled::ID, &AttributeValue::U8(1))
+        .unwrap();
+    let error = session.get::<TermCharEnabled>().unwrap_err();
+    assert!(matches!(
+        error,
+        Error::AttributeKindMismatch(TermCharEnabled::ID, AttributeKind::Bool, AttributeKind::U8)
+    ));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Attribute 0x{:08X} holds bool values, not u8",
+            TermCharEnabled::ID
+        )
+    );
+}
+
+#[test]
+fn refuses_to_write_read_only_attributes() {
+    let (session, fake) = open();
+    for (id, value) in [
+        (
+            attribute::ResourceName::ID,
+            AttributeValue::String("TCPIP::x::INSTR".into()),
+        ),
+        (ResourceLockState::ID, AttributeValue::U32(0)),
+        (MemoryBase::ID, AttributeValue::U64(0)),
+    ] {
+        assert!(matches!(
+            session.set_attribute(id, &value),
+            Err(Error::Visa(VisaError::AttributeReadOnly))
+        ));
+        assert_eq!(fake.attribute(id), None);
+    }
+}
+
+#[test]
+fn leaves_attributes_without_a_typed_counterpart_to_the_backend() {
+    let (session, _) = open();
+    session
+        .set_attribute(USER_ATTRIBUTE, &AttributeValue::I16(-1))
+        .unwrap();
+    assert_eq!(
+        session
+            .get_attribute(USER_ATTRIBUTE, AttributeKind::U64)
+            .unwrap(),
+        AttributeValue::I16(-1)
+    );
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+use visa::{attribute::*, *};
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &[u8])] = &[
+    ("I16?", b"#14\x01\x02\xff\xfe\n"),
+    ("F32?", b":CURV #0\x00\x00\x80\x3f\n"),
+    ("ODD?", b"#13abc\n"),
+];
+
+#[derive(Debug, Default)]
+struct State {
+    output: VecDeque<Vec<u8>>,
+    term_char_enabled: bool,
+}
+
+/// Messages ending with END, whose reads also end on the termination character when it's
+/// enabled, like a GPIB or USBTMC device. Clones share the same messages.
+#[derive(Debug, Clone, Default)]
+struct Terminated(Arc<Mutex<State>>);
+
+impl Terminated {
+    fn push_output(&self, message: &[u8]) {
+        self.0.lock().unwrap().output.push_back(message.to_vec());
+    }
+}
+
+impl Backend for Terminated {
+    fn write(&self, buf: &[u8]) -> Result<usize> {
+        if buf == b"*IDN?\n" {
+            self.push_output(b"ACME,Model 1,1234,1.0\n");
+        }
+        Ok(buf.len())
+    }
+
+    fn read(&self, buf: &mut [u8]) -> Result<(usize, Status)> {
+        let mut state = self.0.lock().unwrap();
+        let term_char_enabled = state.term_char_enabled;
+        let message = state
+            .output
+            .front_mut()
+            .ok_or(Error::Visa(VisaError::Timeout))?;
+        let mut count = message.len().min(buf.len());
+        let mut status = Status::BytesReadEqualInput;
+        if term_char_enabled
+            && let Some(position) = message[..count].iter().position(|byte| *byte == b'\n')
+        {
+            count = position + 1;
+            status = Status::TerminationCharacterRead;
+        }
+        buf[..count].copy_from_slice(&message[..count]);
+        message.drain(..count);
+        if message.is_empty() {
+            state.output.pop_front();
+            status = Status::OperationCompleted;
+        }
+        Ok((count, status))
+    }
+
+    fn flush(&self, _mode: FlushMode) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.0.lock().unwrap().output.clear();
+        Ok(())
+    }
+
+    fn get_attribute(&self, attribute: u32, _kind: AttributeKind) -> Result<AttributeValue> {
+        match attribute {
+            TermCharEnabled::ID => {
+                Ok(TermCharEnabled(self.0.lock().unwrap().term_char_enabled).to_value())
+            }
+            _ => Err(Error::Visa(VisaError::AttributeNotSupported)),
+        }
+    }
+
+    fn set_attribute(&self, attribute: u32, value: &AttributeValue) -> Result<()> {
+        match attribute {
+            TermCharEnabled::ID => {
+                self.0.lock().unwrap().term_char_enabled =
+                    TermCharEnabled::from_value(value.clone())?.0;
+                Ok(())
+            }
+            _ => Err(Error::Visa(VisaError::AttributeNotSupported)),
+        }
+    }
+
+    fn close(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn decodes_definite_blocks_in_either_byte_order() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert_eq!(
+        instrument
+            .query_binary_block::<i16>("I16?\n", ByteOrder::BigEndian)
+            .unwrap(),
+        [0x0102, -2]
+    );
+    assert_eq!(
+        instrument
+            .query_binary_block::<u16>("I16?\n", ByteOrder::LittleEndian)
+            .unwrap(),
+        [0x0201, 0xfeff]
+    );
+    assert_eq!(
+        instrument
+            .query_binary_block::<i8>("I16?\n", ByteOrder::BigEndian)
+            .unwrap(),
+        [1, 2, -1, -2]
+    );
+}
+
+#[test]
+fn decodes_indefinite_blocks_after_a_header() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert_eq!(
+        instrument
+            .query_binary_block::<f32>("F32?\n", ByteOrder::LittleEndian)
+            .unwrap(),
+        [1.0]
+    );
+}
+
+#[test]
+fn rejects_data_not_made_of_whole_values() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert!(matches!(
+        instrument.query_binary_block::<i16>("ODD?\n", ByteOrder::BigEndian),
+        Err(Error::InvalidBlock(_))
+    ));
+}
+
+#[test]
+fn reads_definite_blocks_split_across_messages() {
+    let (mut instrument, fake) = open(RESPONSES);
+    fake.push_output("#2");
+    fake.push_output("04\x00\n");
+    fake.push_output("\x01\x02\n");
+    assert_eq!(
+        instrument
+            .read_binary_block::<u16>(ByteOrder::BigEndian)
+            .unwrap(),
+        [0x000a, 0x0102]
+    );
+}
+
+#[test]
+fn reads_the_terminator_after_definite_blocks_ending_with_a_newline() {
+    let backend = Terminated::default();
+    let mut instrument = Instrument::new(Session::from_backend(backend.clone())).unwrap();
+    instrument.set_term_char_enabled(true).unwrap();
+    backend.push_output(b"#12\x00\n\n");
+    backend.push_output(b"1\n");
+
+    assert_eq!(
+        instrument
+            .read_binary_block::<u8>(ByteOrder::BigEndian)
+            .unwrap(),
+        [0x00, 0x0a]
+    );
+    assert_eq!(instrument.read().unwrap(), "1\n");
+}
+
+#[test]
+fn reads_indefinite_blocks_to_end_past_newlines_in_the_data() {
+    let backend = Terminated::default();
+    let mut instrument = Instrument::new(Session::from_backend(backend.clone())).unwrap();
+    instrument.set_term_char_enabled(true).unwrap();
+    backend.push_output(b"#0\x00\n\x00\x0a\n");
+
+    assert_eq!(
+        instrument
+            .read_binary_block::<u16>(ByteOrder::BigEndian)
+            .unwrap(),
+        [0x000a, 0x000a]
+    );
+    assert!(instrument.term_char_enabled().unwrap());
+}
+
+#[test]
+fn writes_blocks() {
+    let (mut instrument, fake) = open(RESPONSES);
+    instrument
+        .write_binary_block(
+            "TRAC ",
+            &[1u16, 2],
+            ByteOrder::BigEndian,
+            BlockLength::Definite,
+        )
+        .unwrap();
+    assert_eq!(
+        fake.written().last().unwrap(),
+        b"TRAC #14\x00\x01\x00\x02\n"
+    );
+
+    instrument
+        .write_binary_block(
+            "TRAC ",
+            &[1.0f64],
+            ByteOrder::LittleEndian,
+            BlockLength::Indefinite,
+        )
+        .unwrap();
+    assert_eq!(
+        fake.written().last().unwrap(),
+        b"TRAC #0\x00\x00\x00\x00\x00\x00\xf0\x3f\n"
+    );
+}
+
+#[test]
+fn reads_back_written_blocks() {
+    let (mut instrument, fake) = open(RESPONSES);
+    let values: Vec<u8> = (0..=255).cycle().take(12_345).collect();
+    instrument
+        .write_binary_block("L ", &values, ByteOrder::BigEndian, BlockLength::Definite)
+        .unwrap();
+
+    let written = fake.written().last().unwrap().clone();
+    assert!(written.starts_with(b"L #512345"));
+    fake.push_output(&written[2..]);
+    assert_eq!(
+        instrument
+            .read_binary_block::<u8>(ByteOrder::BigEndian)
+            .unwrap(),
+        values
+    );
+}
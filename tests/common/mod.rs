@@ -0,0 +1,10 @@
+use visa::*;
+
+/// An instrument on a fake device answering the `(query, response)` pairs, and the fake itself.
+pub fn open<R: AsRef<[u8]>>(responses: &[(&str, R)]) -> (Instrument, Fake) {
+    let fake = Fake::new();
+    for (query, response) in responses {
+        fake.set_response(query, response);
+    }
+    (Instrument::new(Session::from(fake.clone())).unwrap(), fake)
+}
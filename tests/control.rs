@@ -0,0 +1,83 @@
+use visa::*;
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &str)] = &[("MEAS?", "1\n")];
+
+#[test]
+fn asserts_software_triggers() {
+    let (instrument, fake) = open(RESPONSES);
+    instrument
+        .assert_trigger(TriggerProtocol::default())
+        .unwrap();
+    instrument.assert_trigger(TriggerProtocol::Default).unwrap();
+    assert_eq!(fake.triggers(), 2);
+    // Trigger lines need a VXI or PXI session
+    assert!(matches!(
+        instrument.assert_trigger(TriggerProtocol::Sync),
+        Err(Error::Visa(VisaError::InvalidProtocol))
+    ));
+    assert_eq!(fake.triggers(), 2);
+    // Not a message
+    assert!(!fake.written().contains(&b"*TRG\n".to_vec()));
+}
+
+#[test]
+fn clears_pending_output() {
+    let (mut instrument, _) = open(RESPONSES);
+    instrument.write("MEAS?\n").unwrap();
+    instrument.clear().unwrap();
+    assert!(matches!(
+        instrument.read(),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "1\n");
+}
+
+#[test]
+fn serial_polls_the_status_byte() {
+    let (instrument, fake) = open(RESPONSES);
+    assert_eq!(instrument.read_status_byte().unwrap(), StatusByte::empty());
+    fake.set_status_byte(0x51);
+    let status = instrument.read_status_byte().unwrap();
+    assert_eq!(
+        status,
+        StatusByte::REQUEST_SERVICE
+            | StatusByte::MESSAGE_AVAILABLE
+            | StatusByte::from_bits_retain(1)
+    );
+    // Without a *STB? query
+    assert!(!fake.written().contains(&b"*STB?\n".to_vec()));
+}
+
+#[test]
+fn fails_once_closed() {
+    let (instrument, _) = open(RESPONSES);
+    instrument.close().unwrap();
+    assert!(matches!(
+        instrument.assert_trigger(TriggerProtocol::Default),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+    assert!(matches!(
+        instrument.clear(),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+    assert!(matches!(
+        instrument.read_status_byte(),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+}
+
+#[test]
+fn replays_triggers() {
+    let replay: Replay = "# visa transcript\n\
+        0.1 write \"*IDN?\\n\" -> 6\n\
+        0.2 read -> \"a,b,c,d\\n\" 0x00000000\n\
+        0.3 trigger 0x5\n"
+        .parse()
+        .unwrap();
+    let instrument = Instrument::new(Session::from_backend(replay.clone())).unwrap();
+    instrument.assert_trigger(TriggerProtocol::Sync).unwrap();
+    replay.finish().unwrap();
+}
@@ -0,0 +1,296 @@
+use std::time::Duration;
+use visa::*;
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &str)] = &[];
+
+/// A fresh directory for the transcripts of `test`.
+fn directory(test: &str) -> std::path::PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("visa-event-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[test]
+fn queues_enabled_events_only() {
+    let (instrument, fake) = open(RESPONSES);
+    assert!(matches!(
+        instrument.wait_for_service_request(None),
+        Err(Error::Visa(VisaError::SessionNotEnabled))
+    ));
+
+    // Events from before they were enabled are dropped
+    fake.push_event(EventType::ServiceRequest);
+    instrument
+        .enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+        .unwrap();
+    instrument
+        .enable_event(EventType::Trigger, EventMechanism::QUEUE)
+        .unwrap();
+    assert!(matches!(
+        instrument.wait_for_service_request(None),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+
+    fake.push_event(EventType::Trigger);
+    fake.push_event(EventType::ServiceRequest);
+    let event = instrument.wait_for_service_request(None).unwrap();
+    assert_eq!(event.event_type(), EventType::ServiceRequest);
+    assert!(event.as_vi_event().is_none());
+    assert!(matches!(
+        event.get::<attribute::EventStatus>(),
+        Err(Error::Visa(VisaError::AttributeNotSupported))
+    ));
+    // The trigger is still queued
+    assert_eq!(
+        instrument
+            .wait_on_event(EventType::AllEnabled, None)
+            .unwrap()
+            .event_type(),
+        EventType::Trigger
+    );
+}
+
+#[test]
+fn discards_and_disables_events() {
+    let (instrument, fake) = open(RESPONSES);
+    instrument
+        .enable_event(EventType::Trigger, EventMechanism::QUEUE)
+        .unwrap();
+    fake.push_event(EventType::Trigger);
+    instrument
+        .discard_events(EventType::Trigger, EventMechanism::ALL)
+        .unwrap();
+    assert!(matches!(
+        instrument.wait_on_event(EventType::Trigger, None),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+
+    instrument
+        .disable_event(EventType::AllEnabled, EventMechanism::ALL)
+        .unwrap();
+    fake.push_event(EventType::Trigger);
+    assert!(matches!(
+        instrument.wait_on_event(EventType::Trigger, None),
+        Err(Error::Visa(VisaError::SessionNotEnabled))
+    ));
+}
+
+#[test]
+fn rejects_invalid_events_and_mechanisms() {
+    let (instrument, _) = open(RESPONSES);
+    assert!(matches!(
+        instrument.enable_event(EventType::AllEnabled, EventMechanism::QUEUE),
+        Err(Error::Visa(VisaError::InvalidEvent))
+    ));
+    assert!(matches!(
+        instrument.enable_event(EventType::Trigger, EventMechanism::SUSPEND_HANDLER),
+        Err(Error::Visa(VisaError::MechanismNotSupported))
+    ));
+}
+
+#[test]
+fn waits_for_hislip_service_requests() {
+    let server = HiSlipServer::bind("127.0.0.1:0", |message| match message {
+        b"*IDN?\n" => b"ACME,Model 1,1234,1.0\n".to_vec(),
+        _ => vec![],
+    })
+    .unwrap();
+    let hislip = HiSlip::connect("127.0.0.1", server.port(), "hislip0", Duration::ZERO).unwrap();
+    let instrument = Instrument::new(hislip.into()).unwrap();
+    assert!(matches!(
+        instrument.enable_event(EventType::Trigger, EventMechanism::QUEUE),
+        Err(Error::Visa(VisaError::InvalidEvent))
+    ));
+
+    instrument
+        .enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+        .unwrap();
+    assert!(matches!(
+        instrument.wait_for_service_request(Some(Duration::from_millis(50))),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    server.request_service(0x40);
+    let event = instrument
+        .wait_for_service_request(Some(Duration::from_secs(2)))
+        .unwrap();
+    assert_eq!(event.event_type(), EventType::ServiceRequest);
+}
+
+#[test]
+fn replays_recorded_events() {
+    let directory = directory("replay");
+    let fake = Fake::new();
+    let backend = fake.clone();
+    let mut rm = ResourceManager::native();
+    rm.register_backend("TCPIP?*::fake::INSTR", move |_, _, _| {
+        Ok(Session::from(backend.clone()))
+    })
+    .unwrap();
+    rm.record_to(&directory);
+
+    let exercise = |rm: &mut ResourceManager, fake: Option<&Fake>| {
+        let instrument = rm
+            .open("TCPIP0::fake::INSTR", AccessMode::NO_LOCK, Duration::ZERO)
+            .unwrap();
+        let instrument = instrument.lock().unwrap();
+        instrument
+            .enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+            .unwrap();
+        if let Some(fake) = fake {
+            fake.push_event(EventType::ServiceRequest);
+        }
+        assert_eq!(
+            instrument
+                .wait_for_service_request(None)
+                .unwrap()
+                .event_type(),
+            EventType::ServiceRequest
+        );
+        instrument
+            .discard_events(EventType::AllEnabled, EventMechanism::ALL)
+            .unwrap();
+        instrument
+            .disable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+            .unwrap();
+    };
+    exercise(&mut rm, Some(&fake));
+    rm.close("TCPIP0::fake::INSTR").unwrap();
+
+    let path = std::fs::read_dir(&directory)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let replay = Replay::from_file(&path).unwrap();
+    let mut rm = ResourceManager::native();
+    rm.register_replay(replay.clone()).unwrap();
+    exercise(&mut rm, None);
+    rm.close("TCPIP0::fake::INSTR").unwrap();
+    replay.finish().unwrap();
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn calls_the_most_recent_handler_first() {
+    let (instrument, fake) = open(RESPONSES);
+    assert!(matches!(
+        instrument.enable_event(EventType::Trigger, EventMechanism::HANDLER),
+        Err(Error::Visa(VisaError::HandlerNotInstalled))
+    ));
+
+    let calls = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let handler = |name: &'static str| {
+        let calls = calls.clone();
+        move |event: &Event| calls.lock().unwrap().push((name, event.event_type()))
+    };
+    let first = instrument
+        .install_handler(EventType::Trigger, handler("first"))
+        .unwrap();
+    instrument
+        .install_handler(EventType::Trigger, handler("second"))
+        .unwrap();
+    // Not called before they're enabled
+    fake.push_event(EventType::Trigger);
+    assert!(calls.lock().unwrap().is_empty());
+
+    instrument
+        .enable_event(
+            EventType::Trigger,
+            EventMechanism::HANDLER | EventMechanism::QUEUE,
+        )
+        .unwrap();
+    fake.push_event(EventType::Trigger);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        [
+            ("second", EventType::Trigger),
+            ("first", EventType::Trigger)
+        ]
+    );
+    // Queued as well
+    instrument.wait_on_event(EventType::Trigger, None).unwrap();
+
+    instrument
+        .uninstall_handler(EventType::Trigger, first)
+        .unwrap();
+    assert!(matches!(
+        instrument.uninstall_handler(EventType::Trigger, first),
+        Err(Error::Visa(VisaError::HandlerNotInstalled))
+    ));
+    fake.push_event(EventType::Trigger);
+    assert_eq!(calls.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn uninstalls_handlers_when_dropped() {
+    let (instrument, fake) = open(RESPONSES);
+    let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = calls.clone();
+    let id = instrument
+        .install_handler(EventType::Trigger, move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        })
+        .unwrap();
+    instrument
+        .enable_event(EventType::Trigger, EventMechanism::HANDLER)
+        .unwrap();
+    drop(instrument);
+
+    assert!(matches!(
+        fake.uninstall_handler(EventType::Trigger, id),
+        Err(Error::Visa(VisaError::HandlerNotInstalled))
+    ));
+    fake.push_event(EventType::Trigger);
+    assert_eq!(calls.load(std::sync::atomic::Ordering::Relaxed), 0);
+}
+
+#[test]
+fn replays_recorded_handlers() {
+    let directory = directory("handlers");
+    let fake = Fake::new();
+    let backend = fake.clone();
+    let mut rm = ResourceManager::native();
+    rm.register_backend("TCPIP?*::fake::INSTR", move |_, _, _| {
+        Ok(Session::from(backend.clone()))
+    })
+    .unwrap();
+    rm.record_to(&directory);
+
+    let exercise = |rm: &mut ResourceManager| {
+        let instrument = rm
+            .open("TCPIP0::fake::INSTR", AccessMode::NO_LOCK, Duration::ZERO)
+            .unwrap();
+        let instrument = instrument.lock().unwrap();
+        let id = instrument
+            .install_handler(EventType::ServiceRequest, |_| {})
+            .unwrap();
+        instrument
+            .uninstall_handler(EventType::ServiceRequest, id)
+            .unwrap();
+        id
+    };
+    let recorded = exercise(&mut rm);
+    rm.close("TCPIP0::fake::INSTR").unwrap();
+
+    let path = std::fs::read_dir(&directory)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let replay = Replay::from_file(&path).unwrap();
+    let mut rm = ResourceManager::native();
+    rm.register_replay(replay.clone()).unwrap();
+    assert_eq!(exercise(&mut rm), recorded);
+    rm.close("TCPIP0::fake::INSTR").unwrap();
+    replay.finish().unwrap();
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
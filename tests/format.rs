@@ -0,0 +1,276 @@
+use visa::*;
+
+fn print(format: &str, args: &[FormatArg<'_>]) -> String {
+    String::from_utf8(sprintf(format, args).unwrap()).unwrap()
+}
+
+#[test]
+fn prints_integers() {
+    assert_eq!(
+        print(
+            "%d|%5d|%-5d|%05d|%+d|%x|%#X|%o|%*d",
+            &[
+                42.into(),
+                42.into(),
+                42.into(),
+                (-42).into(),
+                3.into(),
+                255.into(),
+                255.into(),
+                8.into(),
+                4.into(),
+                1.into()
+            ]
+        ),
+        "42|   42|42   |-0042|+3|ff|0XFF|10|   1"
+    );
+}
+
+#[test]
+fn prints_floats() {
+    assert_eq!(
+        print(
+            "%f|%.2f|%e|%E|%g|%g|%g|%.3g",
+            &[
+                1.5.into(),
+                2.345.into(),
+                1500.0.into(),
+                0.00012.into(),
+                100000.0.into(),
+                1e6.into(),
+                0.0001.into(),
+                1.23456.into()
+            ]
+        ),
+        "1.500000|2.35|1.500000e+03|1.200000E-04|100000|1e+06|0.0001|1.23"
+    );
+    assert_eq!(
+        print("%f %e", &[f64::INFINITY.into(), f64::NAN.into()]),
+        "inf nan"
+    );
+}
+
+#[test]
+fn prints_ieee_488_2_numerics() {
+    assert_eq!(
+        print(
+            "%@1d %@2f %@3f %@Hd %@Qd %@Bd",
+            &[
+                2.6.into(),
+                1.into(),
+                1.into(),
+                31.into(),
+                15.into(),
+                5.into()
+            ]
+        ),
+        "3 1.000000 1.000000E+00 #H1F #Q17 #B101"
+    );
+}
+
+#[test]
+fn prints_strings_and_characters() {
+    assert_eq!(
+        print(
+            "%s:%.2s:%5s:%c:%%",
+            &["ab".into(), "xyz".into(), "q".into(), 'z'.into()]
+        ),
+        "ab:xy:    q:z:%"
+    );
+}
+
+#[test]
+fn prints_comma_separated_arrays() {
+    assert_eq!(
+        print(
+            "%,3d;%,*d;%,#.1f",
+            &[
+                (&[1, 2, 3, 4]).into(),
+                2.into(),
+                (&vec![5u8, 6, 7]).into(),
+                (&[0.5f32, 1.0]).into()
+            ]
+        ),
+        "1,2,3;5,6;0.5,1.0"
+    );
+}
+
+#[test]
+fn prints_binary_blocks() {
+    assert_eq!(
+        sprintf("%hb", &[(&[1i16, 2]).into()]).unwrap(),
+        b"#14\0\x01\0\x02"
+    );
+    assert_eq!(
+        sprintf("%!olhb", &[(&[1i16]).into()]).unwrap(),
+        b"#12\x01\0"
+    );
+    assert_eq!(
+        sprintf("%2B", &[(&[1u8, 2, 3]).into()]).unwrap(),
+        b"#0\x01\x02"
+    );
+    assert_eq!(sprintf("%y", &[(&[7u8]).into()]).unwrap(), b"\x07");
+}
+
+#[test]
+fn rejects_invalid_formats_and_arguments() {
+    let invalid = |format: &str, args: &[FormatArg<'_>]| {
+        matches!(sprintf(format, args), Err(Error::InvalidFormat(_)))
+    };
+    assert!(invalid("%hb", &[(&[1u8]).into()]));
+    assert!(invalid("%d %d", &[1.into()]));
+    assert!(invalid("%d", &[1.into(), 2.into()]));
+    assert!(invalid("%q", &[]));
+    assert!(invalid("%", &[]));
+}
+
+#[test]
+fn scans_numbers_in_any_notation() {
+    let (voltage, range, unit): (f64, u32, String) =
+        sscanf("%f;%d %s", b"+1.5E+00;#H1F  volts\n").unwrap();
+    assert_eq!((voltage, range, unit.as_str()), (1.5, 31, "volts"));
+
+    let (count,): (i32,) = sscanf("%d", b"+1.00000E+01").unwrap();
+    assert_eq!(count, 10);
+    let (hex, octal): (u32, u8) = sscanf("%x %o", b"0x1f 17").unwrap();
+    assert_eq!((hex, octal), (31, 15));
+    let (infinity,): (f64,) = sscanf("%f", b"9.9E37").unwrap();
+    assert_eq!(infinity, f64::INFINITY);
+}
+
+#[test]
+fn scans_arrays_and_blocks() {
+    let (floats, integers): (Vec<f64>, Vec<i32>) = sscanf("%,#f;%,2d", b"1,2.5,-3;4,5").unwrap();
+    assert_eq!(floats, [1.0, 2.5, -3.0]);
+    assert_eq!(integers, [4, 5]);
+
+    let (block,): (Vec<i16>,) = sscanf("%hb", b"#14\0\x01\0\x02\n").unwrap();
+    assert_eq!(block, [1, 2]);
+    let (block,): (Vec<u16>,) = sscanf("%!olhb", b"#0\x01\0\n").unwrap();
+    assert_eq!(block, [1]);
+    let (raw,): (Vec<u8>,) = sscanf("%3y", b"abcd").unwrap();
+    assert_eq!(raw, b"abc");
+}
+
+#[test]
+fn scans_strings_and_positions() {
+    let (upper, rest): (String, String) = sscanf("%[A-Z]%t", b"ABCdef\n").unwrap();
+    assert_eq!((upper.as_str(), rest.as_str()), ("ABC", "def\n"));
+    let (character, position): (char, usize) = sscanf("%*d,%c%n", b"12,qz").unwrap();
+    assert_eq!((character, position), ('q', 4));
+    let (word,): (String,) = sscanf("%3s", b"abcdef").unwrap();
+    assert_eq!(word, "abc");
+
+    let values: Vec<Scanned> = sscanf("%d%s", b"1x").unwrap();
+    assert_eq!(values, [Scanned::Int(1), Scanned::Str("x".into())]);
+}
+
+#[test]
+fn fails_on_responses_that_dont_match() {
+    assert!(matches!(
+        sscanf::<(i32,)>("V%d", b"X1"),
+        Err(Error::FormatMismatch(_))
+    ));
+    assert!(matches!(
+        sscanf::<(i32,)>("%d", b"abc"),
+        Err(Error::FormatMismatch(_))
+    ));
+    assert!(matches!(
+        sscanf::<(i32, i32)>("%d", b"1"),
+        Err(Error::FormatMismatch(_))
+    ));
+    assert!(matches!(
+        sscanf::<(Vec<i32>,)>("%,3d", b"1,2"),
+        Err(Error::FormatMismatch(_))
+    ));
+    assert!(matches!(
+        sscanf::<(u8,)>("%d", b"300"),
+        Err(Error::InvalidValue(..))
+    ));
+}
+
+#[test]
+fn rejects_strings_that_arent_utf_8() {
+    for (format, message) in [
+        ("%s", &b"\xff\xff"[..]),
+        ("%s", b"ab\xff"),
+        ("%[^,]", b"a\xe9,b"),
+        ("%c", b"\xff"),
+        ("%t", b"a\xff\n"),
+    ] {
+        assert!(
+            matches!(
+                sscanf::<Vec<Scanned>>(format, message),
+                Err(Error::InvalidString)
+            ),
+            "{}",
+            format
+        );
+    }
+
+    // Bytes after the string don't matter, nor does a width splitting a character
+    let (word, rest): (String, Vec<u8>) = sscanf("%s %2y", b"ab \xff\xfe").unwrap();
+    assert_eq!((word.as_str(), rest.as_slice()), ("ab", &b"\xff\xfe"[..]));
+    let (text,): (String,) = sscanf("%3s", "aé".as_bytes()).unwrap();
+    assert_eq!(text, "aé");
+    let (text,): (String,) = sscanf("%2s", "aé".as_bytes()).unwrap();
+    assert_eq!(text, "a");
+}
+
+#[test]
+fn scans_what_it_prints() {
+    let message = sprintf(
+        "%d %x %@3f %s",
+        &[(-7).into(), 0xbeef.into(), 2.5.into(), "end".into()],
+    )
+    .unwrap();
+    let scanned: (i64, u32, f64, String) = sscanf("%d %x %f %s", &message).unwrap();
+    assert_eq!(scanned, (-7, 0xbeef, 2.5, "end".to_owned()));
+
+    let integers = [i32::MIN, -1, 0, 1, i32::MAX];
+    let floats = [-1.5e-9, 0.0, 0.125, 3.0e12];
+    let message = sprintf("%,#d;%,#e", &[(&integers).into(), (&floats).into()]).unwrap();
+    let scanned: (Vec<i32>, Vec<f64>) = sscanf("%,#d;%,#f", &message).unwrap();
+    assert_eq!(scanned, (integers.to_vec(), floats.to_vec()));
+
+    // Blocks holding the termination character in either byte order
+    let words = [0u16, 0x0a0d, 0xffff];
+    let message = sprintf("%hb;%!olhB", &[(&words).into(), (&words).into()]).unwrap();
+    let scanned: (Vec<u16>, Vec<u16>) = sscanf("%hb;%!olhb", &message).unwrap();
+    assert_eq!(scanned, (words.to_vec(), words.to_vec()));
+}
+
+#[test]
+fn writes_and_reads_formatted_messages() {
+    let fake = Fake::new()
+        .with_response("MEAS?", "1.5,2\n")
+        .with_response("TRAC?", "#16\0\x01\n\0\x02\0\x03\n");
+    let mut instrument = Instrument::new(Session::from(fake.clone())).unwrap();
+
+    printf!(instrument, "VOLT %@3f;CURR %d\n", 1.5, 2).unwrap();
+    assert_eq!(
+        fake.written().last().unwrap(),
+        b"VOLT 1.500000E+00;CURR 2\n"
+    );
+    let (voltage, current): (f64, i32) = instrument.queryf("MEAS?\n", &[], "%f,%d").unwrap();
+    assert_eq!((voltage, current), (1.5, 2));
+    // The newline in the block doesn't end it
+    let (trace,): (Vec<i16>,) = instrument.queryf("TRAC?\n", &[], "%hb").unwrap();
+    assert_eq!(trace, [1, 2560, 512]);
+}
+
+#[test]
+fn replays_formatted_io_through_the_buffers() {
+    let replay: Replay = "# visa transcript\n\
+        0.1 write \"*IDN?\\n\" -> 6\n\
+        0.2 read -> \"a,b,c,d\\n\" 0x00000000\n\
+        0.3 buffered_write \"X 1\\n\" -> 4\n\
+        0.4 flush 0x0002\n\
+        0.5 buffered_read -> \"7\\n\" 0x00000000\n"
+        .parse()
+        .unwrap();
+    let mut instrument = Instrument::new(Session::from_backend(replay.clone())).unwrap();
+    let (value,): (i32,) = instrument.queryf("X %d\n", &[1.into()], "%d").unwrap();
+    assert_eq!(value, 7);
+    replay.finish().unwrap();
+}
@@ -0,0 +1,39 @@
+#![cfg(target_os = "linux")]
+
+use std::time::Duration;
+use visa::*;
+
+#[test]
+fn rejects_addresses_out_of_range_before_loading_the_driver() {
+    for (primary_address, secondary_address) in [(31, None), (1, Some(31))] {
+        assert!(matches!(
+            Gpib::open(0, primary_address, secondary_address),
+            Err(Error::Visa(VisaError::InvalidResourceName))
+        ));
+    }
+    assert!(matches!(
+        ResourceManager::native().open("GPIB0::31::INSTR", AccessMode::NO_LOCK, Duration::ZERO),
+        Err(Error::Visa(VisaError::InvalidResourceName))
+    ));
+}
+
+#[test]
+fn parses_gpib_resource_names() {
+    let resource: ResourceName = "gpib::12::3::instr".parse().unwrap();
+    assert_eq!(
+        resource,
+        ResourceName::Gpib {
+            board: 0,
+            primary_address: 12,
+            secondary_address: Some(3),
+        }
+    );
+    assert_eq!(resource.to_string(), "GPIB0::12::3::INSTR");
+    assert_eq!(
+        "GPIB1::5::INSTR"
+            .parse::<ResourceName>()
+            .unwrap()
+            .to_string(),
+        "GPIB1::5::INSTR"
+    );
+}
@@ -0,0 +1,173 @@
+use std::{io::Write, thread, time::Duration};
+use visa::*;
+
+const IDN: &[u8] = b"ACME,Model 1,1234,1.0\n";
+
+/// Answers `*IDN?`, echoes `ECHO` commands as a run of `x` as long as the command, and
+/// answers nothing else.
+fn serve() -> HiSlipServer {
+    HiSlipServer::bind("127.0.0.1:0", |message| match message {
+        b"*IDN?\n" => IDN.to_vec(),
+        message if message.starts_with(b"ECHO") => {
+            let mut response = vec![b'x'; message.len() - 1];
+            response.push(b'\n');
+            response
+        }
+        _ => vec![],
+    })
+    .unwrap()
+}
+
+fn connect(server: &HiSlipServer) -> HiSlip {
+    HiSlip::connect("127.0.0.1", server.port(), "hislip0", Duration::ZERO).unwrap()
+}
+
+fn open(server: &HiSlipServer) -> Instrument {
+    Instrument::new(connect(server).into()).unwrap()
+}
+
+#[test]
+fn negotiates_the_session() {
+    let server = serve();
+    let hislip = connect(&server);
+    assert_eq!(hislip.server_version(), 0x0100);
+    assert!(!hislip.is_overlapped());
+
+    server.set_overlapped(true);
+    assert!(connect(&server).is_overlapped());
+}
+
+#[test]
+fn queries_the_identification_on_open() {
+    let server = serve();
+    let mut instrument = open(&server);
+    assert_eq!(instrument.identification.model, "Model 1");
+    assert_eq!(instrument.query("*IDN?\n").unwrap().as_bytes(), IDN);
+}
+
+#[test]
+fn writes_and_reads_messages_larger_than_a_chunk() {
+    let server = serve();
+    let mut instrument = open(&server);
+    let mut command = b"ECHO".to_vec();
+    command.extend(vec![b'y'; 10_000]);
+    command.push(b'\n');
+
+    let response = instrument.query_bytes(&command).unwrap();
+    assert_eq!(response.len(), command.len());
+    assert!(
+        response[..response.len() - 1]
+            .iter()
+            .all(|byte| *byte == b'x')
+    );
+}
+
+#[test]
+fn times_out_without_a_response() {
+    let server = serve();
+    let mut instrument = open(&server);
+    instrument
+        .set_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    assert!(matches!(
+        instrument.query("NOTHING\n"),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn sends_triggers_clears_and_status_queries() {
+    let server = serve();
+    let hislip = connect(&server);
+    server.set_status_byte(0x04);
+    assert_eq!(hislip.read_status_byte().unwrap(), 0x04);
+    hislip.trigger().unwrap();
+    hislip.remote_local(RemoteLocal::GoToLocal).unwrap();
+
+    let mut instrument = Instrument::new(hislip.into()).unwrap();
+    instrument.clear().unwrap();
+    assert_eq!(server.clears(), 1);
+    assert_eq!(server.triggers(), 1);
+    assert_eq!(instrument.query("*IDN?\n").unwrap().as_bytes(), IDN);
+}
+
+#[test]
+fn discards_the_pending_response_on_clear() {
+    let server = serve();
+    let instrument = open(&server);
+    (&instrument).write_all(b"*IDN?\n").unwrap();
+    instrument.clear().unwrap();
+    instrument
+        .set_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    assert!(matches!(
+        instrument.read(),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn waits_for_service_requests() {
+    let server = serve();
+    let hislip = connect(&server);
+    assert!(matches!(
+        hislip.wait_for_service_request(Some(Duration::from_millis(50))),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            server.request_service(0x40);
+        });
+        let status_byte = hislip
+            .wait_for_service_request(Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(status_byte, 0x40);
+    });
+}
+
+#[test]
+fn grants_exclusive_locks_to_one_session() {
+    let server = serve();
+    let owner = open(&server);
+    let other = open(&server);
+
+    owner.lock_exclusive(Duration::from_millis(100)).unwrap();
+    assert!(other.lock_exclusive(Duration::from_millis(50)).is_err());
+    owner.unlock().unwrap();
+    other.lock_exclusive(Duration::from_millis(100)).unwrap();
+    other.unlock().unwrap();
+    assert!(other.unlock().is_err());
+}
+
+#[test]
+fn shares_locks_with_the_same_key() {
+    let server = serve();
+    let first = open(&server);
+    let second = open(&server);
+
+    let key = first
+        .lock_shared_with_key(Duration::from_millis(100), "bench")
+        .unwrap();
+    assert_eq!(key, "bench");
+    second
+        .lock_shared_with_key(Duration::from_millis(100), "bench")
+        .unwrap();
+    assert!(second.lock_exclusive(Duration::from_millis(50)).is_err());
+    first.unlock().unwrap();
+    second.unlock().unwrap();
+}
+
+#[test]
+fn opens_through_the_native_resource_manager() {
+    let server = serve();
+    let resource = format!("TCPIP::127.0.0.1::hislip0,{}::INSTR", server.port());
+    let instrument = ResourceManager::native()
+        .open(&resource, AccessMode::NO_LOCK, Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(
+        instrument.lock().unwrap().identification.manufacturer,
+        "ACME"
+    );
+}
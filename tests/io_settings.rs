@@ -0,0 +1,147 @@
+use std::{
+    io::Write,
+    net::TcpListener,
+    thread,
+    time::{Duration, Instant},
+};
+use visa::{attribute::*, *};
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &str)] = &[("MEAS?", "1\n")];
+
+/// A socket instrument that sends its identification and then `lines`.
+fn open_socket(lines: &'static [u8]) -> Instrument {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(b"ACME,Model 1,1234,1.0\n").unwrap();
+        stream.write_all(lines).unwrap();
+        thread::sleep(Duration::from_secs(1));
+    });
+    let resource = format!("TCPIP::127.0.0.1::{}::SOCKET", port);
+    let instrument = ResourceManager::native()
+        .open(&resource, AccessMode::NO_LOCK, Duration::ZERO)
+        .unwrap();
+    // The resource manager is gone, leaving the only reference
+    std::sync::Arc::into_inner(instrument)
+        .unwrap()
+        .into_inner()
+        .unwrap()
+}
+
+#[test]
+fn starts_from_the_visa_defaults() {
+    let (instrument, _) = open(RESPONSES);
+    assert_eq!(
+        instrument.timeout().unwrap(),
+        Some(Duration::from_millis(2000))
+    );
+    assert_eq!(instrument.term_char().unwrap(), b'\n');
+    assert!(!instrument.term_char_enabled().unwrap());
+    assert!(instrument.send_end().unwrap());
+    assert!(!instrument.suppress_end().unwrap());
+}
+
+#[test]
+fn sets_the_session_attributes() {
+    let (instrument, fake) = open(RESPONSES);
+    instrument.set_timeout(None).unwrap();
+    instrument.set_term_char(b'\r').unwrap();
+    instrument.set_term_char_enabled(true).unwrap();
+    instrument.set_send_end(false).unwrap();
+
+    assert_eq!(instrument.timeout().unwrap(), None);
+    assert_eq!(fake.attribute(Timeout::ID), Some(Timeout(None).to_value()));
+    assert_eq!(
+        fake.attribute(TermChar::ID),
+        Some(AttributeValue::U8(b'\r'))
+    );
+    assert_eq!(
+        fake.attribute(TermCharEnabled::ID),
+        Some(AttributeValue::Bool(true))
+    );
+    assert_eq!(
+        fake.attribute(SendEndEnabled::ID),
+        Some(AttributeValue::Bool(false))
+    );
+}
+
+#[test]
+fn restores_overridden_attributes_on_drop() {
+    let (mut instrument, _) = open(RESPONSES);
+    {
+        let mut scoped = instrument
+            .with_timeout(Some(Duration::from_secs(30)))
+            .unwrap();
+        assert_eq!(scoped.timeout().unwrap(), Some(Duration::from_secs(30)));
+        assert_eq!(scoped.query("MEAS?\n").unwrap(), "1\n");
+    }
+    assert_eq!(
+        instrument.timeout().unwrap(),
+        Some(Duration::from_millis(2000))
+    );
+
+    instrument.set_term_char(b'\r').unwrap();
+    assert_eq!(
+        instrument
+            .with_term_char(b';')
+            .unwrap()
+            .term_char()
+            .unwrap(),
+        b';'
+    );
+    assert_eq!(instrument.term_char().unwrap(), b'\r');
+
+    instrument
+        .with_attribute(TermCharEnabled(true))
+        .unwrap()
+        .set_send_end(false)
+        .unwrap();
+    assert!(!instrument.term_char_enabled().unwrap());
+    assert!(!instrument.send_end().unwrap());
+}
+
+#[test]
+fn native_backends_end_reads_on_the_termination_character() {
+    let mut instrument = open_socket(b"one\ntwo\r");
+    assert!(instrument.term_char_enabled().unwrap());
+    assert_eq!(instrument.read().unwrap(), "one\n");
+    assert_eq!(
+        instrument.with_term_char(b'\r').unwrap().read().unwrap(),
+        "two\r"
+    );
+}
+
+#[test]
+fn native_backends_time_out_after_the_timeout() {
+    let mut instrument = open_socket(b"");
+    let start = Instant::now();
+    let result = instrument
+        .with_timeout(Some(Duration::from_millis(100)))
+        .unwrap()
+        .read();
+    assert!(matches!(result, Err(Error::Visa(VisaError::Timeout))));
+    assert!(start.elapsed() < Duration::from_millis(900));
+    assert_eq!(
+        instrument.timeout().unwrap(),
+        Some(Duration::from_millis(2000))
+    );
+}
+
+#[test]
+fn native_backends_refuse_to_suppress_end() {
+    let mut instrument = open_socket(b"");
+    assert!(matches!(
+        instrument.set_suppress_end(true),
+        Err(Error::Visa(VisaError::AttributeStateNotSupported))
+    ));
+    assert!(matches!(
+        instrument.with_attribute(SuppressEndEnabled(true)),
+        Err(Error::Visa(VisaError::AttributeStateNotSupported))
+    ));
+    instrument.set_suppress_end(false).unwrap();
+    assert!(!instrument.suppress_end().unwrap());
+}
@@ -0,0 +1,91 @@
+use std::io::Read;
+use visa::*;
+
+mod common;
+use common::open;
+
+const BINARY: &[u8] = b"\x00\xff\n\x01\n";
+const RESPONSES: &[(&str, &[u8])] = &[("BIN?", BINARY)];
+
+#[test]
+fn reads_binary_messages_to_the_end() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert_eq!(instrument.query_bytes("BIN?\n").unwrap(), BINARY);
+}
+
+#[test]
+fn reads_messages_in_chunks_of_any_size() {
+    let (mut instrument, _) = open(RESPONSES);
+    instrument.set_read_chunk_size(0);
+    assert_eq!(instrument.read_chunk_size(), 1);
+    assert_eq!(instrument.query_bytes("BIN?\n").unwrap(), BINARY);
+
+    instrument.set_read_chunk_size(BINARY.len());
+    assert_eq!(instrument.query_bytes("BIN?\n").unwrap(), BINARY);
+}
+
+#[test]
+fn rejects_messages_over_the_maximum_length_and_drops_them() {
+    let (mut instrument, fake) = open(RESPONSES);
+    instrument.set_read_chunk_size(2);
+    instrument.set_max_read_length(Some(4));
+    assert!(matches!(
+        instrument.query_bytes("BIN?\n"),
+        Err(Error::MessageTooLong(4))
+    ));
+
+    // The rest of the message was read and dropped
+    fake.push_output("ok\n");
+    assert_eq!(instrument.read().unwrap(), "ok\n");
+
+    instrument.set_max_read_length(Some(BINARY.len()));
+    assert_eq!(instrument.query_bytes("BIN?\n").unwrap(), BINARY);
+}
+
+#[test]
+fn reads_exact_counts_across_messages() {
+    let (instrument, fake) = open(RESPONSES);
+    fake.push_output("ab");
+    fake.push_output("cd");
+    assert_eq!(instrument.read_exact(3).unwrap(), b"abc");
+    assert_eq!(instrument.read_exact(1).unwrap(), b"d");
+    assert_eq!(instrument.read_exact(0).unwrap(), b"");
+    assert!(matches!(
+        instrument.read_exact(1),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn fails_exact_reads_that_return_nothing() {
+    let (instrument, fake) = open(RESPONSES);
+    fake.push_output("a");
+    fake.push_output("");
+    match instrument.read_exact(2) {
+        Err(Error::Io(error)) => assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof),
+        result => panic!("expected an unexpected EOF, got {:?}", result),
+    }
+}
+
+#[test]
+fn refuses_text_reads_of_invalid_utf8() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert!(matches!(
+        instrument.query("BIN?\n"),
+        Err(Error::InvalidString)
+    ));
+}
+
+#[test]
+fn reads_through_std_io() {
+    let (mut instrument, fake) = open(RESPONSES);
+    fake.push_output("abc");
+    let mut buf = [0; 2];
+    assert_eq!(Read::read(&mut instrument, &mut buf).unwrap(), 2);
+    assert_eq!(&buf, b"ab");
+
+    let mut rest = vec![];
+    let error = (&instrument).read_to_end(&mut rest).unwrap_err();
+    assert_eq!(rest, b"c");
+    assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+}
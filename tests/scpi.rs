@@ -0,0 +1,201 @@
+use visa::*;
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &str)] = &[
+    ("*TST?", "+0\n"),
+    ("*OPC?", "1\n"),
+    ("*ESR?", "+33\n"),
+    ("*STB?", "81\n"),
+    ("*SRE?", "32\n"),
+    ("*ESE?", "x\n"),
+];
+
+#[test]
+fn parses_the_identification() {
+    let (mut instrument, _) = open(RESPONSES);
+    let identification = instrument.query_identification().unwrap();
+    assert_eq!(identification, instrument.identification);
+    assert_eq!(identification.manufacturer, "Fake");
+
+    let identification: Identification = "Keysight, 34465A ,MY1,A.03.01-B,extra".parse().unwrap();
+    assert_eq!(identification.model, "34465A");
+    assert_eq!(identification.firmware_version, "A.03.01-B,extra");
+    assert!("ACME,Model 1".parse::<Identification>().is_err());
+}
+
+#[test]
+fn reports_the_self_test_result() {
+    let (mut instrument, fake) = open(RESPONSES);
+    assert_eq!(
+        instrument.query_self_test().unwrap(),
+        SelfTestResult::Passed
+    );
+    fake.set_response("*TST?", "-3\n");
+    assert_eq!(
+        instrument.query_self_test().unwrap(),
+        SelfTestResult::Failed(-3)
+    );
+}
+
+#[test]
+fn reads_the_status_registers() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert!(instrument.query_operation_completed().unwrap());
+    assert_eq!(
+        instrument.query_event_status_register().unwrap(),
+        StandardEventStatus::OPERATION_COMPLETE | StandardEventStatus::COMMAND_ERROR
+    );
+    let status = instrument.query_read_status_byte().unwrap();
+    assert_eq!(
+        status,
+        StatusByte::REQUEST_SERVICE
+            | StatusByte::MESSAGE_AVAILABLE
+            | StatusByte::from_bits_retain(1)
+    );
+    assert_eq!(status.bits(), 81);
+    assert_eq!(
+        instrument.query_service_request_enable().unwrap(),
+        StatusByte::EVENT_STATUS
+    );
+}
+
+#[test]
+fn rejects_malformed_values() {
+    let (mut instrument, _) = open(RESPONSES);
+    assert!(matches!(
+        instrument.query_event_status_enable(),
+        Err(Error::InvalidValue(..))
+    ));
+}
+
+#[test]
+fn writes_the_common_commands() {
+    let (mut instrument, fake) = open(RESPONSES);
+    instrument.reset().unwrap();
+    instrument.clear_status().unwrap();
+    instrument.operation_complete().unwrap();
+    instrument.wait_on_complete().unwrap();
+    instrument
+        .event_status_enable(StandardEventStatus::all())
+        .unwrap();
+    instrument
+        .service_request_enable(StatusByte::EVENT_STATUS | StatusByte::MESSAGE_AVAILABLE)
+        .unwrap();
+
+    let written = fake.written();
+    assert_eq!(
+        written[written.len() - 6..],
+        [
+            b"*RST\n".to_vec(),
+            b"*CLS\n".to_vec(),
+            b"*OPC\n".to_vec(),
+            b"*WAI\n".to_vec(),
+            b"*ESE 255\n".to_vec(),
+            b"*SRE 48\n".to_vec(),
+        ]
+    );
+}
+
+#[test]
+fn parses_error_queue_entries() {
+    let error: ScpiError = "-113,\"Undefined header;FOO:BAR\"".parse().unwrap();
+    assert_eq!(error.code, -113);
+    assert_eq!(error.message, "Undefined header");
+    assert_eq!(error.device_info.as_deref(), Some("FOO:BAR"));
+    assert_eq!(error.kind(), ScpiErrorKind::Command);
+    assert_eq!(error.to_string(), "-113,\"Undefined header;FOO:BAR\"");
+
+    let error: ScpiError = " +0,\"No error\"\n".parse().unwrap();
+    assert_eq!((error.code, error.message.as_str()), (0, "No error"));
+
+    let error: ScpiError = "-222,\"Value \"\"x\"\" out of range\"".parse().unwrap();
+    assert_eq!(error.message, "Value \"x\" out of range");
+    assert_eq!(error.device_info, None);
+    assert_eq!(error.kind(), ScpiErrorKind::Execution);
+}
+
+#[test]
+fn classifies_errors_by_code() {
+    let kind = |code: i32| {
+        ScpiError {
+            code,
+            message: String::new(),
+            device_info: None,
+        }
+        .kind()
+    };
+    assert_eq!(kind(-100), ScpiErrorKind::Command);
+    assert_eq!(kind(-199), ScpiErrorKind::Command);
+    assert_eq!(kind(-200), ScpiErrorKind::Execution);
+    assert_eq!(kind(-350), ScpiErrorKind::DeviceSpecific);
+    assert_eq!(kind(-410), ScpiErrorKind::Query);
+    assert_eq!(kind(-500), ScpiErrorKind::Other);
+    assert_eq!(kind(100), ScpiErrorKind::Other);
+}
+
+#[test]
+fn rejects_malformed_error_queue_entries() {
+    assert!(matches!(
+        "garbage".parse::<ScpiError>(),
+        Err(Error::InvalidValue(..))
+    ));
+    assert!(matches!(
+        "x,\"No error\"".parse::<ScpiError>(),
+        Err(Error::InvalidValue(..))
+    ));
+}
+
+#[test]
+fn drains_the_error_queue() {
+    let (mut instrument, fake) = open(RESPONSES);
+    fake.set_response("SYST:ERR?", "+0,\"No error\"\n");
+    assert!(instrument.drain_errors().unwrap().is_empty());
+
+    fake.push_output("-222,\"Data out of range\"\n");
+    fake.push_output("-113,\"Undefined header\"\n");
+    fake.push_output("0,\"No error\"\n");
+    let errors = instrument.drain_errors().unwrap();
+    assert_eq!(
+        errors.iter().map(|error| error.code).collect::<Vec<_>>(),
+        [-222, -113]
+    );
+}
+
+#[test]
+fn stops_draining_an_error_queue_that_never_empties() {
+    let (mut instrument, fake) = open(RESPONSES);
+    fake.set_response("SYST:ERR?", "-113,\"Undefined header\"\n");
+    assert_eq!(instrument.drain_errors().unwrap().len(), 256);
+}
+
+#[test]
+fn checks_the_error_queue_after_each_message() {
+    let (mut instrument, fake) = open(RESPONSES);
+    instrument.set_check_errors(true);
+    assert!(instrument.check_errors());
+    fake.push_output("-113,\"Undefined header\"\n");
+    fake.push_output("0,\"No error\"\n");
+    match instrument.write("FOO\n") {
+        Err(Error::Scpi(errors)) => assert_eq!(errors[0].code, -113),
+        result => panic!("expected SCPI errors, got {:?}", result),
+    }
+
+    fake.set_response("SYST:ERR?", "0,\"No error\"\n");
+    fake.set_response("GOOD?", "1\n");
+    instrument.write("CONF:VOLT\n").unwrap();
+    assert_eq!(instrument.query("GOOD?\n").unwrap(), "1\n");
+    assert_eq!(fake.written().last().unwrap(), b"SYST:ERR?\n");
+
+    // Invalid queries usually time out, the error queue tells why
+    assert!(matches!(
+        instrument.query("BAD?\n"),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    let written = fake.written();
+    assert_eq!(
+        written[written.len() - 2..],
+        [b"BAD?\n".to_vec(), b"SYST:ERR?\n".to_vec()]
+    );
+}
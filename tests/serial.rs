@@ -0,0 +1,245 @@
+#![cfg(target_os = "linux")]
+
+use std::{
+    ffi::CStr,
+    fs::File,
+    io::{Read, Write},
+    os::fd::{AsRawFd, FromRawFd},
+    time::Duration,
+};
+use visa::*;
+
+/// A pseudo terminal standing in for the device, the port opened on its slave side.
+struct Pty {
+    master: File,
+    /// Kept open so the slave's settings outlive the port.
+    slave: File,
+    path: String,
+}
+
+impl Pty {
+    fn new() -> Self {
+        let (mut master, mut slave) = (0, 0);
+        let mut name = [0; 64];
+        let result = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                name.as_mut_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(result, 0);
+        let path = unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned();
+        unsafe {
+            Self {
+                master: File::from_raw_fd(master),
+                slave: File::from_raw_fd(slave),
+                path,
+            }
+        }
+    }
+
+    fn open(&self, settings: SerialSettings) -> Serial {
+        let serial = Serial::open(&self.path, settings).unwrap();
+        serial.set_timeout(Some(Duration::from_secs(1)));
+        serial
+    }
+
+    fn termios(&self) -> libc::termios {
+        let mut termios = unsafe { std::mem::zeroed() };
+        assert_eq!(
+            unsafe { libc::tcgetattr(self.slave.as_raw_fd(), &mut termios) },
+            0
+        );
+        termios
+    }
+
+    /// Reads what the port sent.
+    fn received(&mut self, len: usize) -> Vec<u8> {
+        let mut buf = vec![0; len];
+        self.master.read_exact(&mut buf).unwrap();
+        buf
+    }
+}
+
+#[test]
+fn maps_the_line_settings_onto_termios() {
+    let pty = Pty::new();
+    let serial = pty.open(SerialSettings {
+        baud_rate: 115200,
+        parity: Parity::Odd,
+        stop_bits: StopBits::Two,
+        flow_control: FlowControl::RTS_CTS | FlowControl::XON_XOFF,
+        xon_char: 0x01,
+        ..Default::default()
+    });
+    let termios = pty.termios();
+    assert_eq!(unsafe { libc::cfgetospeed(&termios) }, libc::B115200);
+    assert_eq!(unsafe { libc::cfgetispeed(&termios) }, libc::B115200);
+    // Pseudo terminals always report 8 data bits without parity, the parity type still shows
+    assert_ne!(termios.c_cflag & libc::PARODD, 0);
+    assert_ne!(termios.c_cflag & libc::CSTOPB, 0);
+    assert_ne!(termios.c_cflag & libc::CRTSCTS, 0);
+    assert_eq!(
+        termios.c_iflag & (libc::IXON | libc::IXOFF),
+        libc::IXON | libc::IXOFF
+    );
+    assert_eq!(termios.c_cc[libc::VSTART], 0x01);
+    assert_eq!(termios.c_cc[libc::VSTOP], 0x13);
+
+    serial
+        .set_attribute(
+            attribute::AsrlParity::ID,
+            &AttributeValue::U16(Parity::Mark as u16),
+        )
+        .unwrap();
+    serial
+        .set_attribute(
+            attribute::AsrlFlowControl::ID,
+            &AttributeValue::U16(FlowControl::NONE.bits() as u16),
+        )
+        .unwrap();
+    let termios = pty.termios();
+    assert_eq!(
+        termios.c_cflag & (libc::PARODD | libc::CMSPAR),
+        libc::PARODD | libc::CMSPAR
+    );
+    assert_eq!(termios.c_cflag & libc::CRTSCTS, 0);
+    assert_eq!(termios.c_iflag & (libc::IXON | libc::IXOFF), 0);
+    assert_eq!(
+        serial
+            .get_attribute(attribute::AsrlParity::ID, AttributeKind::U16)
+            .unwrap(),
+        AttributeValue::U16(Parity::Mark as u16)
+    );
+}
+
+#[test]
+fn keeps_the_settings_the_port_doesnt_support() {
+    let pty = Pty::new();
+    let serial = pty.open(SerialSettings::default());
+    for settings in [
+        SerialSettings {
+            baud_rate: 12345,
+            ..Default::default()
+        },
+        SerialSettings {
+            stop_bits: StopBits::OneAndHalf,
+            ..Default::default()
+        },
+        SerialSettings {
+            flow_control: FlowControl::DTR_DSR,
+            ..Default::default()
+        },
+        SerialSettings {
+            end_in: SerialEnd::Break,
+            ..Default::default()
+        },
+    ] {
+        assert!(matches!(
+            serial.set_settings(settings),
+            Err(Error::Visa(VisaError::AttributeStateNotSupported))
+        ));
+    }
+    assert_eq!(serial.settings(), SerialSettings::default());
+    assert_eq!(unsafe { libc::cfgetospeed(&pty.termios()) }, libc::B9600);
+}
+
+#[test]
+fn ends_messages_as_configured_for_output() {
+    let mut pty = Pty::new();
+    let serial = pty.open(SerialSettings {
+        end_out: SerialEnd::TermChar,
+        ..Default::default()
+    });
+    serial.write(b"*RST").unwrap();
+    assert_eq!(pty.received(5), b"*RST\n");
+
+    serial
+        .set_settings(SerialSettings {
+            end_out: SerialEnd::LastBit,
+            ..Default::default()
+        })
+        .unwrap();
+    serial.write(b"AB").unwrap();
+    assert_eq!(pty.received(2), [b'A', b'B' | 0x80]);
+
+    // Turning END off leaves the message as it is
+    serial
+        .set_attribute(attribute::SendEndEnabled::ID, &AttributeValue::Bool(false))
+        .unwrap();
+    serial.write(b"AB").unwrap();
+    assert_eq!(pty.received(2), b"AB");
+}
+
+#[test]
+fn ends_reads_as_configured_for_input() {
+    let mut pty = Pty::new();
+    let serial = pty.open(SerialSettings::default());
+    let mut buf = [0; 16];
+    pty.master.write_all(b"1\n2\n").unwrap();
+    assert_eq!(
+        serial.read(&mut buf).unwrap(),
+        (2, Status::TerminationCharacterRead)
+    );
+    assert_eq!(
+        serial.read(&mut buf).unwrap(),
+        (2, Status::TerminationCharacterRead)
+    );
+
+    serial
+        .set_settings(SerialSettings {
+            end_in: SerialEnd::LastBit,
+            ..Default::default()
+        })
+        .unwrap();
+    pty.master
+        .write_all(&[b'a', b'\n', b'b' | 0x80, b'c'])
+        .unwrap();
+    assert_eq!(
+        serial.read(&mut buf).unwrap(),
+        (3, Status::OperationCompleted)
+    );
+    assert_eq!(buf[..3], [b'a', b'\n', b'b' | 0x80]);
+
+    serial
+        .set_settings(SerialSettings {
+            end_in: SerialEnd::None,
+            ..Default::default()
+        })
+        .unwrap();
+    pty.master.write_all(b"d\ne").unwrap();
+    assert_eq!(
+        serial.read(&mut buf[..4]).unwrap(),
+        (4, Status::BytesReadEqualInput)
+    );
+    assert_eq!(&buf[..4], b"cd\ne");
+}
+
+#[test]
+fn fails_once_closed() {
+    let pty = Pty::new();
+    let serial = pty.open(SerialSettings::default());
+    serial.close().unwrap();
+    assert!(matches!(
+        serial.write(b"*RST\n"),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+    assert!(matches!(
+        serial.get_attribute(attribute::AsrlBaud::ID, AttributeKind::U32),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+    serial.close().unwrap();
+}
+
+#[test]
+fn rejects_port_zero() {
+    assert!(matches!(
+        ResourceManager::native().open("ASRL0::INSTR", AccessMode::NO_LOCK, Duration::ZERO),
+        Err(Error::Visa(VisaError::InvalidResourceName))
+    ));
+}
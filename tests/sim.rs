@@ -0,0 +1,184 @@
+#![cfg(feature = "sim")]
+
+use std::time::{Duration, Instant};
+use visa::*;
+
+const DEVICE_FILE: &str = r#"
+[devices.dmm]
+dialogues = [
+    { q = "*IDN?", r = "ACME,DMM 1,1234,1.0" },
+    { q = "*RST" },
+    { q = "MEAS?", r = "+1.234E+00", delay = 0.1 },
+    { q = "SLOW?", r = "1", delay = 5.0 },
+]
+error = { response = "ERROR", queue = {} }
+
+[devices.dmm.properties.range]
+default = 10.0
+getter = { q = "RANG?", r = "{:.1f}" }
+setter = { q = "RANG {}" }
+specs = { min = 0.1, max = 1000.0 }
+
+[devices.dmm.properties.mode]
+default = "DC"
+getter = { q = "MODE?", r = "{}" }
+setter = { q = "MODE {}", r = "OK", e = "BAD" }
+specs = { valid = ["DC", "AC"] }
+
+[resources."TCPIP::sim::INSTR"]
+device = "dmm"
+
+[resources."ASRL1::INSTR"]
+device = "dmm"
+"#;
+
+fn open() -> Instrument {
+    let simulation: Simulation = DEVICE_FILE.parse().unwrap();
+    Instrument::new(simulation.open("TCPIP::sim::INSTR").unwrap().into()).unwrap()
+}
+
+fn invalid(device_file: &str) -> bool {
+    matches!(
+        device_file.parse::<Simulation>(),
+        Err(Error::InvalidSimulation(_))
+    )
+}
+
+#[test]
+fn lists_and_opens_the_simulated_resources() {
+    let simulation: Simulation = DEVICE_FILE.parse().unwrap();
+    let mut rm = ResourceManager::simulated(simulation);
+    assert_eq!(
+        rm.get_resources_with_expression("TCPIP?*").unwrap(),
+        ["TCPIP::sim::INSTR"]
+    );
+    // Native resources of the host are listed alongside
+    let resources = rm.get_resources_with_expression("?*INSTR").unwrap();
+    assert!(resources.iter().any(|resource| resource == "ASRL1::INSTR"));
+
+    // Names match however they're written
+    let instrument = rm
+        .open(
+            "tcpip0::sim::inst0::INSTR",
+            AccessMode::NO_LOCK,
+            Duration::ZERO,
+        )
+        .unwrap();
+    assert_eq!(
+        instrument.lock().unwrap().identification.manufacturer,
+        "ACME"
+    );
+}
+
+#[test]
+fn fails_to_open_unknown_resources() {
+    let simulation: Simulation = DEVICE_FILE.parse().unwrap();
+    assert!(matches!(
+        simulation.open("GPIB0::1::INSTR"),
+        Err(Error::Visa(VisaError::ResourceNotFound))
+    ));
+}
+
+#[test]
+fn answers_dialogues() {
+    let mut instrument = open();
+    assert_eq!(instrument.identification.model, "DMM 1");
+    instrument.write("*RST\n").unwrap();
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "+1.234E+00\n");
+}
+
+#[test]
+fn answers_each_message_of_a_write() {
+    let mut instrument = open();
+    instrument.write("*RST\nRANG?\n").unwrap();
+    assert_eq!(instrument.read().unwrap(), "10.0\n");
+}
+
+#[test]
+fn sets_and_gets_properties() {
+    let mut instrument = open();
+    assert_eq!(instrument.query("RANG?\n").unwrap(), "10.0\n");
+    instrument.write("RANG 100\n").unwrap();
+    assert_eq!(instrument.query("RANG?\n").unwrap(), "100.0\n");
+
+    assert_eq!(instrument.query("MODE AC\n").unwrap(), "OK\n");
+    assert_eq!(instrument.query("MODE?\n").unwrap(), "AC\n");
+}
+
+#[test]
+fn rejects_values_outside_the_specs() {
+    let mut instrument = open();
+    assert_eq!(instrument.query("RANG 5000\n").unwrap(), "ERROR\n");
+    assert_eq!(instrument.query("RANG?\n").unwrap(), "10.0\n");
+    assert_eq!(instrument.query("MODE XX\n").unwrap(), "BAD\n");
+    assert_eq!(instrument.query("MODE?\n").unwrap(), "DC\n");
+}
+
+#[test]
+fn queues_errors_in_order() {
+    let mut instrument = open();
+    assert_eq!(instrument.query("RANG 5000\n").unwrap(), "ERROR\n");
+    assert_eq!(instrument.query("FOO\n").unwrap(), "ERROR\n");
+    assert_eq!(
+        instrument.query("SYST:ERR?\n").unwrap(),
+        "-222,\"Data out of range\"\n"
+    );
+    assert_eq!(
+        instrument.query("SYST:ERR?\n").unwrap(),
+        "-100,\"Command error\"\n"
+    );
+    assert_eq!(instrument.query("SYST:ERR?\n").unwrap(), "0,\"No error\"\n");
+}
+
+#[test]
+fn keeps_properties_per_instance() {
+    let simulation: Simulation = DEVICE_FILE.parse().unwrap();
+    let mut first = Instrument::new(simulation.open("ASRL1::INSTR").unwrap().into()).unwrap();
+    let mut second = Instrument::new(simulation.open("ASRL1::INSTR").unwrap().into()).unwrap();
+    first.write("RANG 1\n").unwrap();
+    assert_eq!(second.query("RANG?\n").unwrap(), "10.0\n");
+}
+
+#[test]
+fn delays_responses() {
+    let mut instrument = open();
+    let start = Instant::now();
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "+1.234E+00\n");
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn times_out_on_responses_delayed_past_the_timeout() {
+    let simulation: Simulation = DEVICE_FILE.parse().unwrap();
+    let device = simulation.open("ASRL1::INSTR").unwrap();
+    device.set_timeout(Some(Duration::from_millis(50)));
+    device.write(b"SLOW?\n").unwrap();
+    let mut buf = [0; 16];
+    assert!(matches!(
+        device.read(&mut buf),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn times_out_right_away_without_a_pending_response() {
+    let mut instrument = open();
+    let start = Instant::now();
+    assert!(matches!(
+        instrument.query("*RST\n"),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn rejects_invalid_device_files() {
+    assert!(invalid("[resources.x]\ndevice = \"missing\""));
+    assert!(invalid("[devices.d]\nunknown = 1"));
+    assert!(invalid(
+        "[devices.d.properties.p]\ndefault = 1\nspecs = { type = \"str\" }"
+    ));
+    assert!(invalid(
+        "[devices.d]\ndialogues = [{ q = \"A?\", r = \"1\", delay = -1.0 }]"
+    ));
+}
@@ -0,0 +1,128 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use visa::*;
+
+const IDN: &[u8] = b"ACME,Model 1,1234,1.0\n";
+
+/// Serves line based commands on a local port, answering `*IDN?` and passing anything else
+/// to `respond`.
+fn serve(respond: fn(&str, &mut TcpStream)) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            thread::spawn(move || {
+                let reader = BufReader::new(stream.try_clone().unwrap());
+                for line in reader.lines() {
+                    let Ok(line) = line else { break };
+                    match line.as_str() {
+                        "*IDN?" => stream.write_all(IDN).unwrap(),
+                        command => respond(command, &mut stream),
+                    }
+                }
+            });
+        }
+    });
+    format!("TCPIP0::127.0.0.1::{}::SOCKET", port)
+}
+
+fn open(resource: &str) -> Arc<Mutex<Instrument>> {
+    ResourceManager::native()
+        .open(resource, AccessMode::NO_LOCK, Duration::from_secs(1))
+        .unwrap()
+}
+
+#[test]
+fn queries_the_identification_on_open() {
+    let instrument = open(&serve(|_, _| {}));
+    let instrument = instrument.lock().unwrap();
+    assert_eq!(instrument.identification.manufacturer, "ACME");
+    assert_eq!(instrument.identification.model, "Model 1");
+    assert_eq!(instrument.identification.serial_number, "1234");
+}
+
+#[test]
+fn reads_a_response_split_across_packets() {
+    let instrument = open(&serve(|_, stream| {
+        stream.write_all(b"1.0,").unwrap();
+        thread::sleep(Duration::from_millis(20));
+        stream.write_all(b"2.0\n").unwrap();
+    }));
+    let mut instrument = instrument.lock().unwrap();
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "1.0,2.0\n");
+}
+
+#[test]
+fn keeps_input_past_the_termination_character() {
+    let instrument = open(&serve(|_, stream| stream.write_all(b"1\n2\n").unwrap()));
+    let mut instrument = instrument.lock().unwrap();
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "1\n");
+    assert_eq!(instrument.read().unwrap(), "2\n");
+}
+
+#[test]
+fn ends_reads_on_the_configured_termination_character() {
+    let instrument = open(&serve(|_, stream| stream.write_all(b"1;2\n").unwrap()));
+    let mut instrument = instrument.lock().unwrap();
+    instrument.set_term_char(b';').unwrap();
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "1;");
+    instrument.set_term_char_enabled(false).unwrap();
+    assert_eq!(instrument.read_exact(2).unwrap(), b"2\n");
+}
+
+#[test]
+fn times_out_without_a_termination_character() {
+    let instrument = open(&serve(|_, stream| stream.write_all(b"partial").unwrap()));
+    let mut instrument = instrument.lock().unwrap();
+    instrument
+        .set_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    assert!(matches!(
+        instrument.query("MEAS?\n"),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn clear_discards_pending_input() {
+    let instrument = open(&serve(|_, stream| stream.write_all(b"stale\n").unwrap()));
+    let mut instrument = instrument.lock().unwrap();
+    instrument.write("MEAS?\n").unwrap();
+    thread::sleep(Duration::from_millis(50));
+    instrument.clear().unwrap();
+    assert_eq!(instrument.query("*IDN?\n").unwrap().as_bytes(), IDN);
+}
+
+#[test]
+fn fails_when_the_peer_closes() {
+    let instrument = open(&serve(|_, stream| {
+        stream.shutdown(std::net::Shutdown::Both).unwrap()
+    }));
+    let mut instrument = instrument.lock().unwrap();
+    assert!(matches!(
+        instrument.query("BYE\n"),
+        Err(Error::Visa(VisaError::ConnectionLost))
+    ));
+}
+
+#[test]
+fn leaves_status_descriptions_to_visa() {
+    let instrument = open(&serve(|_, _| {}));
+    let instrument = instrument.lock().unwrap();
+    assert!(matches!(
+        instrument.status_description(VisaError::Timeout),
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    ));
+}
+
+#[test]
+fn parses_socket_resource_names() {
+    let resource: ResourceName = "tcpip::example.com::5025::socket".parse().unwrap();
+    assert_eq!(resource.to_string(), "TCPIP0::example.com::5025::SOCKET");
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, Instant};
+use visa::*;
+
+mod common;
+use common::open;
+
+const RESPONSES: &[(&str, &str)] = &[("*ESR?", "+1\n")];
+
+#[test]
+fn runs_and_polls_until_the_operation_completed() {
+    let (mut instrument, fake) = open(RESPONSES);
+    fake.set_status_byte(0x60);
+    let status = instrument
+        .run_and_wait_with(
+            "INIT\n",
+            StatusWait::SerialPoll(Duration::from_millis(1)),
+            Some(Duration::from_secs(1)),
+        )
+        .unwrap();
+    assert_eq!(status, StandardEventStatus::OPERATION_COMPLETE);
+
+    let written = fake.written();
+    assert_eq!(
+        written[written.len() - 5..],
+        [
+            b"*ESE 1\n".to_vec(),
+            b"*SRE 32\n".to_vec(),
+            b"*ESR?\n".to_vec(),
+            b"INIT;*OPC\n".to_vec(),
+            b"*ESR?\n".to_vec(),
+        ]
+    );
+}
+
+#[test]
+fn times_out_polling_a_status_byte_that_never_changes() {
+    let (instrument, fake) = open(RESPONSES);
+    fake.set_status_byte(StatusByte::MESSAGE_AVAILABLE.bits());
+    let start = Instant::now();
+    assert!(matches!(
+        instrument.wait_for_status(
+            StatusByte::EVENT_STATUS,
+            StatusWait::SerialPoll(Duration::from_millis(10)),
+            Some(Duration::from_millis(50)),
+        ),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(50) && elapsed < Duration::from_millis(500));
+}
+
+#[test]
+fn waits_for_service_requests_with_the_awaited_bits() {
+    let (instrument, fake) = open(RESPONSES);
+    instrument
+        .enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+        .unwrap();
+
+    // A request for another reason is skipped
+    fake.set_status_byte(0x50);
+    fake.push_event(EventType::ServiceRequest);
+    assert!(matches!(
+        instrument.wait_for_status(StatusByte::EVENT_STATUS, StatusWait::ServiceRequest, None),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+
+    fake.set_status_byte(0x60);
+    fake.push_event(EventType::ServiceRequest);
+    assert_eq!(
+        instrument
+            .wait_for_status(StatusByte::EVENT_STATUS, StatusWait::ServiceRequest, None)
+            .unwrap(),
+        StatusByte::EVENT_STATUS | StatusByte::REQUEST_SERVICE
+    );
+}
+
+#[test]
+fn discards_service_requests_from_before_the_operation() {
+    let (mut instrument, fake) = open(RESPONSES);
+    instrument
+        .enable_event(EventType::ServiceRequest, EventMechanism::QUEUE)
+        .unwrap();
+    fake.set_status_byte(0x60);
+    fake.push_event(EventType::ServiceRequest);
+    assert!(matches!(
+        instrument.run_and_wait("INIT", Some(Duration::from_millis(50))),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn replays_serial_polls() {
+    let replay: Replay = "# visa transcript\n\
+        0.1 write \"*IDN?\\n\" -> 6\n\
+        0.2 read -> \"a,b,c,d\\n\" 0x00000000\n\
+        0.3 read_stb -> 0x60\n"
+        .parse()
+        .unwrap();
+    let instrument = Instrument::new(Session::from_backend(replay.clone())).unwrap();
+    assert_eq!(
+        instrument.read_status_byte().unwrap(),
+        StatusByte::EVENT_STATUS | StatusByte::REQUEST_SERVICE
+    );
+    replay.finish().unwrap();
+}
@@ -0,0 +1,136 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use visa::*;
+
+const BINARY: &[u8] = b"\x00\x01\"\\\xff\r\n";
+
+/// A fresh directory for the transcripts of `test`.
+fn directory(test: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("visa-transcript-{}-{}", test, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn fake() -> Fake {
+    Fake::new()
+        .with_response("MEAS?", "1.5\n")
+        .with_response("BIN?", BINARY)
+}
+
+/// Runs the same operations against a live or replayed instrument.
+fn exercise(instrument: &mut Instrument) {
+    assert_eq!(instrument.query("MEAS?\n").unwrap(), "1.5\n");
+    assert!(matches!(
+        instrument.read(),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    assert_eq!(instrument.query_bytes("BIN?\n").unwrap(), BINARY);
+    let key = instrument
+        .lock_shared_with_key(Duration::ZERO, "bench key")
+        .unwrap();
+    assert_eq!(key, "bench key");
+    instrument.unlock().unwrap();
+}
+
+fn record(directory: &Path) -> PathBuf {
+    let path = directory.join("fake.txt");
+    let recorder = Recorder::new(Session::from(fake()), "TCPIP0::fake::INSTR", &path).unwrap();
+    let mut instrument = Instrument::new(recorder.into()).unwrap();
+    exercise(&mut instrument);
+    drop(instrument);
+    path
+}
+
+#[test]
+fn replays_what_was_recorded() {
+    let directory = directory("replay");
+    let path = record(&directory);
+
+    let replay = Replay::from_file(&path).unwrap();
+    assert_eq!(replay.resource(), Some("TCPIP0::fake::INSTR"));
+    let mut instrument = Instrument::new(replay.clone().into()).unwrap();
+    assert!(replay.finish().is_err());
+    exercise(&mut instrument);
+    replay.finish().unwrap();
+    assert_eq!(replay.remaining(), 0);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn records_and_replays_through_the_resource_manager() {
+    let directory = directory("resource-manager");
+    let mut rm = ResourceManager::native();
+    rm.register_backend("TCPIP?*::fake::INSTR", |_, _, _| Ok(Session::from(fake())))
+        .unwrap();
+    rm.record_to(&directory);
+    let instrument = rm
+        .open("TCPIP0::fake::INSTR", AccessMode::NO_LOCK, Duration::ZERO)
+        .unwrap();
+    exercise(&mut instrument.lock().unwrap());
+    rm.close("TCPIP0::fake::INSTR").unwrap();
+
+    let path = std::fs::read_dir(&directory)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let replay = Replay::from_file(&path).unwrap();
+    let mut rm = ResourceManager::native();
+    rm.register_replay(replay.clone()).unwrap();
+    // Resource names match however they're written
+    let instrument = rm
+        .open("tcpip::fake::instr", AccessMode::NO_LOCK, Duration::ZERO)
+        .unwrap();
+    exercise(&mut instrument.lock().unwrap());
+    rm.close("tcpip::fake::instr").unwrap();
+    replay.finish().unwrap();
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn fails_when_the_replay_diverges() {
+    let directory = directory("diverge");
+    let replay = Replay::from_file(record(&directory)).unwrap();
+    replay.write(b"*IDN?\n").unwrap();
+    let mut buf = [0; 64];
+    replay.read(&mut buf).unwrap();
+    assert!(matches!(
+        replay.write(b"MEAS? \n"),
+        Err(Error::ReplayDiverged(_))
+    ));
+    assert!(matches!(replay.clear(), Err(Error::ReplayDiverged(_))));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn replays_recorded_errors() {
+    let replay: Replay = "# visa transcript\n# resource X\n0.1 read !! visa 0xBFFF0015\n"
+        .parse()
+        .unwrap();
+    let mut buf = [0; 16];
+    assert!(matches!(
+        replay.read(&mut buf),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    replay.finish().unwrap();
+}
+
+#[test]
+fn rejects_malformed_transcripts() {
+    assert!(matches!(
+        "0.1 bogus".parse::<Replay>(),
+        Err(Error::InvalidTranscript(_))
+    ));
+    assert!(matches!(
+        "0.1 write \"unterminated".parse::<Replay>(),
+        Err(Error::InvalidTranscript(_))
+    ));
+}
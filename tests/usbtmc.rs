@@ -0,0 +1,451 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use visa::*;
+
+const IDN: &[u8] = b"ACME,Model 1,1234,1.0\n";
+
+const DEV_DEP_MSG_OUT: u8 = 1;
+const REQUEST_DEV_DEP_MSG_IN: u8 = 2;
+const USB488_TRIGGER: u8 = 128;
+const INITIATE_ABORT_BULK_IN: u8 = 3;
+const INITIATE_CLEAR: u8 = 5;
+const CHECK_CLEAR_STATUS: u8 = 6;
+const GET_CAPABILITIES: u8 = 7;
+const READ_STATUS_BYTE: u8 = 128;
+const REN_CONTROL: u8 = 160;
+const LOCAL_LOCKOUT: u8 = 162;
+
+/// Largest bulk-IN transfer returned at once, so responses arrive in several transfers.
+const TRANSFER_SIZE: usize = 448;
+
+/// A USB488 interface answering `*IDN?` and `LONG?`, recording what it was sent.
+#[derive(Debug)]
+struct Device {
+    capabilities: [u8; 0x18],
+    has_interrupt_in: bool,
+    status_byte: u8,
+    /// Bulk-OUT transfers received.
+    bulk_out: Vec<Vec<u8>>,
+    /// Control requests received, with their values.
+    control: Vec<(u8, u16)>,
+    halts_cleared: Vec<Endpoint>,
+    /// Data of the current message, up to EOM.
+    message: Vec<u8>,
+    responses: VecDeque<Vec<u8>>,
+    /// Transfer prepared for bulk-IN.
+    bulk_in: VecDeque<u8>,
+    interrupt_in: VecDeque<[u8; 2]>,
+    /// Number of `CHECK_CLEAR_STATUS` requests answered as pending.
+    clear_pending: usize,
+    /// Whether bulk-IN transfers answer with the wrong `bTag`.
+    wrong_tag: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Mock(Arc<Mutex<Device>>);
+
+impl Mock {
+    fn new() -> Self {
+        let mut capabilities = [0; 0x18];
+        capabilities[0] = 0x01;
+        capabilities[5] = 0x01;
+        capabilities[14] = 0x07;
+        capabilities[15] = 0x0C;
+        Self(Arc::new(Mutex::new(Device {
+            capabilities,
+            has_interrupt_in: true,
+            status_byte: 0,
+            bulk_out: vec![],
+            control: vec![],
+            halts_cleared: vec![],
+            message: vec![],
+            responses: VecDeque::new(),
+            bulk_in: VecDeque::new(),
+            interrupt_in: VecDeque::new(),
+            clear_pending: 0,
+            wrong_tag: false,
+        })))
+    }
+
+    fn device(&self) -> std::sync::MutexGuard<'_, Device> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl Device {
+    fn respond(&mut self, tag: u8, len: usize, term_char: Option<u8>) {
+        let Some(mut response) = self.responses.pop_front() else {
+            return;
+        };
+        let mut count = len.min(response.len());
+        let mut attributes = 0;
+        if let Some(position) =
+            term_char.and_then(|term_char| response[..count].iter().position(|b| *b == term_char))
+        {
+            count = position + 1;
+            attributes |= 0x02;
+        }
+        let rest = response.split_off(count);
+        match rest.is_empty() {
+            true => attributes |= 0x01,
+            false => self.responses.push_front(rest),
+        }
+
+        let tag = if self.wrong_tag {
+            tag.wrapping_add(1)
+        } else {
+            tag
+        };
+        let mut transfer = vec![REQUEST_DEV_DEP_MSG_IN, tag, !tag, 0];
+        transfer.extend((response.len() as u32).to_le_bytes());
+        transfer.extend([attributes, 0, 0, 0]);
+        transfer.extend(response);
+        transfer.resize(transfer.len().next_multiple_of(4), 0);
+        self.bulk_in.extend(transfer);
+    }
+}
+
+impl UsbTransport for Mock {
+    fn bulk_out(&mut self, data: &[u8], _timeout: Option<Duration>) -> Result<usize> {
+        let mut device = self.device();
+        assert_eq!(data.len() % 4, 0, "transfers are padded to 4 bytes");
+        assert_eq!(data[1], !data[2], "bTagInverse matches bTag");
+        device.bulk_out.push(data.to_vec());
+
+        let size = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        match data[0] {
+            DEV_DEP_MSG_OUT => {
+                device.message.extend_from_slice(&data[12..12 + size]);
+                if data[8] & 0x01 != 0 {
+                    match std::mem::take(&mut device.message).as_slice() {
+                        b"*IDN?\n" => device.responses.push_back(IDN.to_vec()),
+                        b"LONG?\n" => {
+                            let mut response = vec![b'x'; 100_000];
+                            response.push(b'\n');
+                            device.responses.push_back(response);
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            REQUEST_DEV_DEP_MSG_IN => {
+                let term_char = (data[8] & 0x02 != 0).then_some(data[9]);
+                device.respond(data[1], size, term_char);
+            }
+            USB488_TRIGGER => (),
+            message_id => panic!("unexpected MsgID {}", message_id),
+        }
+        Ok(data.len())
+    }
+
+    fn bulk_in(&mut self, buf: &mut [u8], _timeout: Option<Duration>) -> Result<usize> {
+        let mut device = self.device();
+        if device.bulk_in.is_empty() {
+            return Err(Error::Visa(VisaError::Timeout));
+        }
+        let count = buf.len().min(device.bulk_in.len()).min(TRANSFER_SIZE);
+        for (byte, data) in buf.iter_mut().zip(device.bulk_in.drain(..count)) {
+            *byte = data;
+        }
+        Ok(count)
+    }
+
+    fn has_interrupt_in(&self) -> bool {
+        self.device().has_interrupt_in
+    }
+
+    fn interrupt_in(&mut self, buf: &mut [u8], _timeout: Option<Duration>) -> Result<usize> {
+        let packet = self
+            .device()
+            .interrupt_in
+            .pop_front()
+            .ok_or(Error::Visa(VisaError::Timeout))?;
+        buf[..2].copy_from_slice(&packet);
+        Ok(2)
+    }
+
+    fn control_in(
+        &mut self,
+        _recipient: Recipient,
+        request: u8,
+        value: u16,
+        buf: &mut [u8],
+        _timeout: Option<Duration>,
+    ) -> Result<usize> {
+        let mut device = self.device();
+        device.control.push((request, value));
+        buf.fill(0);
+        buf[0] = 0x01;
+        match request {
+            GET_CAPABILITIES => {
+                buf.copy_from_slice(&device.capabilities);
+                Ok(buf.len())
+            }
+            INITIATE_CLEAR => {
+                device.message.clear();
+                device.responses.clear();
+                device.bulk_in.clear();
+                Ok(1)
+            }
+            CHECK_CLEAR_STATUS if device.clear_pending > 0 => {
+                device.clear_pending -= 1;
+                buf[0] = 0x02;
+                Ok(2)
+            }
+            READ_STATUS_BYTE => {
+                buf[1] = value as u8;
+                match device.has_interrupt_in {
+                    true => {
+                        let status_byte = device.status_byte;
+                        device
+                            .interrupt_in
+                            .push_back([0x80 | value as u8, status_byte]);
+                    }
+                    false => buf[2] = device.status_byte,
+                }
+                Ok(3)
+            }
+            _ => Ok(buf.len()),
+        }
+    }
+
+    fn clear_halt(&mut self, endpoint: Endpoint) -> Result<()> {
+        self.device().halts_cleared.push(endpoint);
+        Ok(())
+    }
+}
+
+fn open() -> (UsbTmc<Mock>, Mock) {
+    let mock = Mock::new();
+    (UsbTmc::new(mock.clone()).unwrap(), mock)
+}
+
+#[test]
+fn parses_the_capabilities() {
+    let (usbtmc, _) = open();
+    let capabilities = usbtmc.capabilities();
+    assert!(capabilities.term_char);
+    assert!(capabilities.usb488 && capabilities.remote_local && capabilities.trigger);
+    assert!(capabilities.scpi && capabilities.service_request);
+    assert!(!capabilities.indicator_pulse);
+}
+
+#[test]
+fn frames_messages_with_incrementing_tags() {
+    let (usbtmc, mock) = open();
+    usbtmc.write(b"*RST\n").unwrap();
+    usbtmc.write(b"*CLS\n").unwrap();
+
+    let device = mock.device();
+    let [first, second] = device.bulk_out.as_slice() else {
+        panic!("expected two transfers, got {:?}", device.bulk_out);
+    };
+    assert_eq!(first[..4], [DEV_DEP_MSG_OUT, 1, !1, 0]);
+    assert_eq!(first[4..8], 5u32.to_le_bytes());
+    assert_eq!(first[8], 0x01);
+    assert_eq!(&first[12..], b"*RST\n\0\0\0");
+    assert_eq!(second[1], 2);
+}
+
+#[test]
+fn splits_long_writes_and_marks_only_the_last_transfer() {
+    let (usbtmc, mock) = open();
+    assert_eq!(usbtmc.write(&[b'a'; 100_000]).unwrap(), 100_000);
+
+    let device = mock.device();
+    let sizes: Vec<_> = device
+        .bulk_out
+        .iter()
+        .map(|transfer| u32::from_le_bytes(transfer[4..8].try_into().unwrap()))
+        .collect();
+    assert_eq!(sizes, [65_536, 34_464]);
+    assert_eq!(device.bulk_out[0][8], 0x00);
+    assert_eq!(device.bulk_out[1][8], 0x01);
+}
+
+#[test]
+fn leaves_out_the_end_of_message_when_send_end_is_disabled() {
+    let (usbtmc, mock) = open();
+    let session = Session::from(usbtmc);
+    session.set(attribute::SendEndEnabled(false)).unwrap();
+    session.backend().write(b"PART").unwrap();
+    assert_eq!(mock.device().bulk_out[0][8], 0x00);
+}
+
+#[test]
+fn reads_responses_spanning_several_transfers() {
+    let (usbtmc, _) = open();
+    let mut instrument = Instrument::new(usbtmc.into()).unwrap();
+    assert_eq!(instrument.identification.model, "Model 1");
+
+    let response = instrument.query_bytes("LONG?\n").unwrap();
+    assert_eq!(response.len(), 100_001);
+    assert_eq!(response.last(), Some(&b'\n'));
+}
+
+#[test]
+fn requests_reads_ending_on_the_termination_character() {
+    let (usbtmc, mock) = open();
+    let mut instrument = Instrument::new(usbtmc.into()).unwrap();
+    instrument.set_term_char(b',').unwrap();
+    instrument.set_term_char_enabled(true).unwrap();
+    assert_eq!(instrument.query("*IDN?\n").unwrap(), "ACME,");
+
+    let device = mock.device();
+    let request = device.bulk_out.last().unwrap();
+    assert_eq!(request[0], REQUEST_DEV_DEP_MSG_IN);
+    assert_eq!(request[8], 0x02);
+    assert_eq!(request[9], b',');
+}
+
+#[test]
+fn aborts_the_bulk_in_transfer_on_timeout() {
+    let (usbtmc, mock) = open();
+    let mut buf = [0; 16];
+    assert!(matches!(
+        usbtmc.read(&mut buf),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+    let device = mock.device();
+    let tag = device.bulk_out.last().unwrap()[1];
+    assert!(
+        device
+            .control
+            .contains(&(INITIATE_ABORT_BULK_IN, tag as u16))
+    );
+}
+
+#[test]
+fn rejects_responses_with_the_wrong_tag() {
+    let (usbtmc, mock) = open();
+    usbtmc.write(b"*IDN?\n").unwrap();
+    mock.device().wrong_tag = true;
+    let mut buf = [0; 64];
+    assert!(matches!(
+        usbtmc.read(&mut buf),
+        Err(Error::Visa(VisaError::Io))
+    ));
+}
+
+#[test]
+fn reads_the_status_byte_through_the_interrupt_endpoint() {
+    let (usbtmc, mock) = open();
+    {
+        let mut device = mock.device();
+        device.status_byte = 0x52;
+        device.interrupt_in.push_back([0x81, 0x40]);
+    }
+    assert_eq!(usbtmc.read_status_byte().unwrap(), 0x52);
+    assert_eq!(
+        usbtmc
+            .wait_for_service_request(Some(Duration::ZERO))
+            .unwrap(),
+        0x40
+    );
+
+    usbtmc.read_status_byte().unwrap();
+
+    let device = mock.device();
+    let tags: Vec<_> = device
+        .control
+        .iter()
+        .filter(|(request, _)| *request == READ_STATUS_BYTE)
+        .map(|(_, tag)| *tag)
+        .collect();
+    assert_eq!(tags, [2, 3]);
+}
+
+#[test]
+fn reads_the_status_byte_from_the_control_response_without_an_interrupt_endpoint() {
+    let mock = Mock::new();
+    mock.device().has_interrupt_in = false;
+    mock.device().status_byte = 0x10;
+    let usbtmc = UsbTmc::new(mock).unwrap();
+    assert_eq!(usbtmc.read_status_byte().unwrap(), 0x10);
+    assert!(matches!(
+        usbtmc.wait_for_service_request(Some(Duration::ZERO)),
+        Err(Error::Visa(VisaError::OperationNotSupported))
+    ));
+}
+
+#[test]
+fn times_out_waiting_for_service_requests() {
+    let (usbtmc, _) = open();
+    assert!(matches!(
+        usbtmc.wait_for_service_request(Some(Duration::from_millis(30))),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn sends_usb488_requests_the_interface_supports() {
+    let (usbtmc, mock) = open();
+    usbtmc.trigger().unwrap();
+    usbtmc
+        .remote_local(RemoteLocal::EnableRemoteLocalLockout)
+        .unwrap();
+    usbtmc.indicator_pulse().unwrap();
+
+    let device = mock.device();
+    assert_eq!(device.bulk_out[0][0], USB488_TRIGGER);
+    assert!(
+        device
+            .control
+            .ends_with(&[(REN_CONTROL, 1), (LOCAL_LOCKOUT, 0), (64, 0)])
+    );
+}
+
+#[test]
+fn refuses_usb488_requests_the_interface_lacks() {
+    let mock = Mock::new();
+    mock.device().capabilities[14] = 0;
+    let usbtmc = UsbTmc::new(mock).unwrap();
+    for result in [
+        usbtmc.trigger(),
+        usbtmc.remote_local(RemoteLocal::GoToLocal),
+        usbtmc.read_status_byte().map(|_| ()),
+    ] {
+        assert!(matches!(
+            result,
+            Err(Error::Visa(VisaError::OperationNotSupported))
+        ));
+    }
+}
+
+#[test]
+fn clears_until_the_interface_reports_success() {
+    let (usbtmc, mock) = open();
+    usbtmc.write(b"*IDN?\n").unwrap();
+    mock.device().clear_pending = 2;
+    usbtmc.clear().unwrap();
+
+    let device = mock.device();
+    let checks = device
+        .control
+        .iter()
+        .filter(|(request, _)| *request == CHECK_CLEAR_STATUS)
+        .count();
+    assert_eq!(checks, 3);
+    assert!(device.responses.is_empty());
+    assert_eq!(device.halts_cleared, [Endpoint::BulkOut]);
+}
+
+#[test]
+fn releases_the_interface_on_close() {
+    let (usbtmc, mock) = open();
+    usbtmc.close().unwrap();
+    // The session no longer holds the transport
+    assert_eq!(Arc::strong_count(&mock.0), 1);
+    assert!(matches!(
+        usbtmc.write(b"*IDN?\n"),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+    assert!(matches!(
+        usbtmc.discard_events(EventType::ServiceRequest, EventMechanism::QUEUE),
+        Err(Error::Visa(VisaError::InvalidObject))
+    ));
+    usbtmc.close().unwrap();
+}
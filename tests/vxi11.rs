@@ -0,0 +1,126 @@
+use std::{io::Write, time::Duration};
+use visa::*;
+
+const IDN: &[u8] = b"ACME,Model 1,1234,1.0\n";
+
+/// Answers `*IDN?`, echoes `ECHO` commands as a run of `x` as long as the command, and
+/// answers nothing else.
+fn serve() -> Vxi11Server {
+    Vxi11Server::bind("127.0.0.1:0", |message| match message {
+        b"*IDN?\n" => IDN.to_vec(),
+        message if message.starts_with(b"ECHO") => {
+            let mut response = vec![b'x'; message.len() - 1];
+            response.push(b'\n');
+            response
+        }
+        _ => vec![],
+    })
+    .unwrap()
+}
+
+fn connect(server: &Vxi11Server) -> Vxi11 {
+    Vxi11::connect_with_portmapper("127.0.0.1", server.port(), "inst0", Duration::ZERO).unwrap()
+}
+
+fn open(server: &Vxi11Server) -> Instrument {
+    Instrument::new(connect(server).into()).unwrap()
+}
+
+#[test]
+fn queries_the_identification_on_open() {
+    let server = serve();
+    let instrument = open(&server);
+    assert_eq!(instrument.identification.manufacturer, "ACME");
+    assert_eq!(instrument.identification.model, "Model 1");
+}
+
+#[test]
+fn writes_and_reads_messages_larger_than_a_chunk() {
+    let server = serve();
+    let mut instrument = open(&server);
+    let mut command = b"ECHO".to_vec();
+    command.extend(vec![b'y'; 10_000]);
+    command.push(b'\n');
+
+    let response = instrument.query_bytes(&command).unwrap();
+    assert_eq!(response.len(), command.len());
+    assert!(
+        response[..response.len() - 1]
+            .iter()
+            .all(|byte| *byte == b'x')
+    );
+    assert_eq!(response.last(), Some(&b'\n'));
+}
+
+#[test]
+fn reads_in_pieces_of_the_requested_size() {
+    let server = serve();
+    let instrument = open(&server);
+    (&instrument).write_all(b"*IDN?\n").unwrap();
+    assert_eq!(instrument.read_exact(4).unwrap(), b"ACME");
+    assert_eq!(instrument.read().unwrap().as_bytes(), &IDN[4..]);
+}
+
+#[test]
+fn times_out_without_a_response() {
+    let server = serve();
+    let mut instrument = open(&server);
+    instrument
+        .set_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+    assert!(matches!(
+        instrument.query("NOTHING\n"),
+        Err(Error::Visa(VisaError::Timeout))
+    ));
+}
+
+#[test]
+fn sends_triggers_clears_and_status_byte_reads() {
+    let server = serve();
+    let vxi11 = connect(&server);
+    server.set_status_byte(0x40);
+    assert_eq!(vxi11.read_status_byte().unwrap(), 0x40);
+    vxi11.trigger().unwrap();
+    vxi11.remote().unwrap();
+    vxi11.local().unwrap();
+    assert_eq!(server.triggers(), 1);
+
+    let instrument = Instrument::new(vxi11.into()).unwrap();
+    instrument.clear().unwrap();
+    assert_eq!(server.clears(), 1);
+}
+
+#[test]
+fn sets_the_message_available_bit_while_a_response_is_pending() {
+    let server = serve();
+    let vxi11 = connect(&server);
+    vxi11.write(b"*IDN?\n").unwrap();
+    assert_eq!(vxi11.read_status_byte().unwrap() & 0x10, 0x10);
+    let mut buf = [0; 64];
+    vxi11.read(&mut buf).unwrap();
+    assert_eq!(vxi11.read_status_byte().unwrap() & 0x10, 0);
+}
+
+#[test]
+fn aborts_without_a_pending_operation() {
+    let server = serve();
+    connect(&server).abort().unwrap();
+}
+
+#[test]
+fn refuses_other_links_while_locked() {
+    let server = serve();
+    let owner = open(&server);
+    let other = open(&server);
+
+    owner.lock_exclusive(Duration::from_millis(100)).unwrap();
+    assert!(matches!(
+        other.lock_exclusive(Duration::from_millis(50)),
+        Err(Error::Visa(VisaError::ResourceLocked))
+    ));
+    assert!((&other).write(b"*IDN?\n").is_err());
+
+    owner.unlock().unwrap();
+    (&other).write_all(b"*IDN?\n").unwrap();
+    assert_eq!(other.read().unwrap().as_bytes(), IDN);
+}